@@ -64,8 +64,8 @@ var ctx = canvas.getContext("2d");
 var tk;
 var img;
 function animationLoop(timestamp) {
-	tk(timestamp,locked,mx,my,rx,ry,scale,md,mu);
-	rx = ry = md = mu = 0;
+	tk(timestamp,locked,mx,my,rx,ry,scale,md,mu,wh,ku,kd,kl,kr,ks,kt,kv);
+	rx = ry = md = mu = wh = ks = 0;
 	ctx.putImageData(img, 0, 0);
 	requestAnimationFrame(animationLoop);
 }
@@ -125,6 +125,34 @@ function mouseUp() {
 	mu=1;
 }
 
+// Wheel steps, positive when scrolling up
+let wh = 0;
+function wheelCallback(e) {
+	wh -= Math.sign(e.deltaY);
+}
+
+// Held keys, space only counts the press
+let ku = 0, kd = 0, kl = 0, kr = 0, ks = 0, kt = 0, kv = 0;
+function key(e, down) {
+	switch (e.code) {
+		case "KeyW": ku = down; break;
+		case "KeyS": kd = down; break;
+		case "KeyA": kl = down; break;
+		case "KeyD": kr = down; break;
+		case "Space": if (down && !e.repeat) ks = 1; break;
+		case "Tab": kt = down; break;
+		case "KeyV": kv = down; break;
+		default: return;
+	}
+	e.preventDefault();
+}
+function keyDown(e) {
+	key(e, 1);
+}
+function keyUp(e) {
+	key(e, 0);
+}
+
 function lockError(e) {
 	changeCallback();
 }
@@ -139,6 +167,10 @@ document.addEventListener("mousemove", moveCallback, false);
 
 document.addEventListener("mousedown", mouseDown, false);
 document.addEventListener("mouseup", mouseUp, false);
+document.addEventListener("wheel", wheelCallback, false);
+
+document.addEventListener("keydown", keyDown, false);
+document.addEventListener("keyup", keyUp, false);
 
 document.addEventListener('pointerlockerror', lockError, false);
 document.addEventListener('mozpointerlockerror', lockError, false);
@@ -1,35 +1,30 @@
 use minifb::{Key, Window, WindowOptions, Scale, ScaleMode, MouseMode, MouseButton, KeyRepeat};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use std::fmt::Write;
+use std::ptr::addr_of_mut;
 
 use game_core::*;
-
-static mut STATE:DesktopState = DesktopState::new();
-
-struct DesktopState {
-	game:State,
-	rgba:[RGBA; SCREEN_SPACE as usize],
-	buffer:[u32; SCREEN_SPACE as usize],
+use game_core::canvas::{Canvas, Rect};
+
+static mut STATE:State = State::new();
+static mut IMAGE:[RGBA; SCREEN_SPACE as usize] = [RGBA::zeroed(); SCREEN_SPACE as usize];
+
+struct MinifbPlatform {
+	window:Window,
+	buffer:Box<[u32]>,
+	mouse_x:f32,
+	mouse_y:f32,
+	earliest_time:Instant,
+	/// Timings of the previous frame, drawn over the current one
+	timings:Timings,
+	last_present:Instant,
+	/// Where the editor saves to.
+	level_path:Option<String>,
 }
 
-impl DesktopState {
-	pub const fn new() -> DesktopState {
-		DesktopState {
-			game: State::new(),
-			rgba: [RGBA::zeroed(); SCREEN_SPACE as usize],
-			buffer: [0; SCREEN_SPACE as usize],
-		}
-	}
-	pub fn init(&mut self) {
-		self.game.init();
-	}
-}
-
-fn main() {
-	let state = unsafe { &mut STATE };
-	state.init();
-
-	let mut window = Box::new(
-		Window::new(
+impl MinifbPlatform {
+	fn new(level_path:Option<String>) -> MinifbPlatform {
+		let mut window = Window::new(
 			"Rust Window", SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize,
 			WindowOptions {
 				borderless: false,
@@ -37,71 +32,133 @@ fn main() {
 				resize: false,
 				scale: Scale::X1,
 				scale_mode: ScaleMode::UpperLeft,
+				..WindowOptions::default()
 			}
-		).unwrap()
-	);
-
-	window.limit_update_rate(None);
-
-	let earliest_time = Instant::now();
+		).unwrap();
+		window.limit_update_rate(None);
+
+		MinifbPlatform {
+			window,
+			buffer: vec![0; SCREEN_SPACE as usize].into_boxed_slice(),
+			mouse_x: 0f32,
+			mouse_y: 0f32,
+			earliest_time: Instant::now(),
+			timings: Timings::default(),
+			last_present: Instant::now(),
+			level_path,
+		}
+	}
+}
 
-	let mut mouse_x = 0.0;
-	let mut mouse_y = 0.0;
-	let mut dx;
-	let mut dy;
+impl Platform for MinifbPlatform {
+	fn time(&self) -> i32 {
+		let time_stamp = Instant::now().duration_since(self.earliest_time).as_millis() as u32;
+		(i32::MIN as i64 + time_stamp as i64) as i32
+	}
 
-	loop {
+	fn poll(&mut self, input:&mut Input) -> bool {
+		let window = &self.window;
 		if !window.is_open() || window.is_key_down(Key::Escape) {
-			break;
+			return false;
 		}
 
-		let before_tick = Instant::now();
-		let time_stamp = before_tick.duration_since(earliest_time).as_millis() as u32;
-		let time_stamp = (core::i32::MIN as i64 + time_stamp as i64) as i32;
+		input.up = window.is_key_down(Key::W);
+		input.down = window.is_key_down(Key::S);
+		input.left = window.is_key_down(Key::A);
+		input.right = window.is_key_down(Key::D);
+		input.space = window.is_key_pressed(Key::Space, KeyRepeat::No);
+		input.tab = window.is_key_down(Key::Tab);
+		input.view = window.is_key_down(Key::V);
 
-		let vku = window.is_key_down(Key::W);
-		let vkd = window.is_key_down(Key::S);
-		let vkl = window.is_key_down(Key::A);
-		let vkr = window.is_key_down(Key::D);
-		let vk_space = window.is_key_pressed(Key::Space, KeyRepeat::No);
-
-		let lmb = window.get_mouse_down(MouseButton::Left);
-		let rmb = window.get_mouse_down(MouseButton::Right);
+		input.lmb = window.get_mouse_down(MouseButton::Left);
+		input.rmb = window.get_mouse_down(MouseButton::Right);
 
 		if let Some((mx, my)) = window.get_mouse_pos(MouseMode::Pass) {
-			dx = mx - mouse_x;
-			dy = my - mouse_y;
-			mouse_x = mx;
-			mouse_y = my;
+			input.delta_x = mx - self.mouse_x;
+			input.delta_y = my - self.mouse_y;
+			self.mouse_x = mx;
+			self.mouse_y = my;
 		} else {
-			dx = 0.0;
-			dy = 0.0;
+			input.delta_x = 0f32;
+			input.delta_y = 0f32;
+		}
+		input.abs_x = self.mouse_x;
+		input.abs_y = self.mouse_y;
+		input.wheel = window.get_scroll_wheel().map_or(0f32, |(_, y)| y);
+		true
+	}
+
+	fn save_level(&mut self, text:&str) -> bool {
+		match &self.level_path {
+			Some(path) => std::fs::write(path, text).map_err(|error| eprintln!("Saving {:?}: {}", path, error)).is_ok(),
+			None => false,
 		}
+	}
 
-		state.game.tick(
-			time_stamp, false,
-			mouse_x, mouse_y, dx, dy,
-			1f32,
-			lmb, rmb,
-			vku, vkd, vkl, vkr, vk_space,
-			&mut state.rgba
-		);
+	fn present(&mut self, image:&mut [RGBA]) {
+		let before_present = Instant::now();
 
-		let before_rgb = Instant::now();
+		self.timings.draw(image);
+		convert_to_rgb(image, &mut self.buffer);
+		self.window.update_with_buffer(&self.buffer, SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize).unwrap();
 
-		convert_to_rgb(&state.rgba, &mut state.buffer);
+		let now = Instant::now();
+		self.timings = Timings {
+			internal: before_present.duration_since(self.last_present),
+			present:  now           .duration_since(before_present   ),
+			total:    now           .duration_since(self.last_present),
+		};
+		self.last_present = now;
+	}
+}
 
-		let before_window = Instant::now();
+fn main() {
+	// The scene is picked by name, e.g. `raycaster`
+	if let Some(name) = std::env::args().nth(1) {
+		match Scene::from_name(&name) {
+			Some(scene) => unsafe { (*addr_of_mut!(STATE)).set_scene(scene) },
+			None => eprintln!("Unknown scene {:?}, expected one of {:?}", name, Scene::ALL.iter().map(|scene| scene.name()).collect::<Vec<_>>()),
+		}
+	}
+	let level_path = unsafe { open_level(&mut *addr_of_mut!(STATE)) };
 
-		window.update_with_buffer(&state.buffer, SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize).unwrap();
+	let mut platform = MinifbPlatform::new(level_path);
+	unsafe { run(&mut platform, &mut *addr_of_mut!(STATE), &mut *addr_of_mut!(IMAGE)) };
+}
 
-		let now = Instant::now();
+/// In the editor the second argument is the level file, it is opened when it exists and saved to.
+fn open_level(state:&mut State) -> Option<String> {
+	if state.scene() != Scene::Editor {
+		return None;
+	}
+	let path = std::env::args().nth(2).unwrap_or_else(|| "editor.level".to_string());
+	if let Ok(text) = std::fs::read_to_string(&path) {
+		if let Err(error) = state.open_level(&text) {
+			eprintln!("{}:{}", path, error);
+		}
+	}
+	Some(path)
+}
 
-		println!("internal: {:>4}ms, rgb: {:>4}ms, window: {:>4}ms, total: {:>4}ms",
-			before_rgb   .duration_since(before_tick  ).as_millis(),
-			before_window.duration_since(before_rgb   ).as_millis(),
-			now          .duration_since(before_window).as_millis(),
-			now          .duration_since(before_tick  ).as_millis(),
+#[derive(Default)]
+struct Timings {
+	/// Input, game logic and rendering.
+	internal:Duration,
+	/// Conversion to the window format and the window update.
+	present:Duration,
+	total:Duration,
+}
+impl Timings {
+	fn draw(&self, image:&mut [RGBA]) {
+		let mut canvas = Canvas::screen(image);
+		let right = SCREEN_WIDTH as i32 - 4;
+		canvas.fill_rect(Rect::new(right - 104, 4, 104, 36), RGBA::rgba(0, 0, 0, 0xA0));
+		let fps = 1f32 / self.total.as_secs_f32().max(1e-3);
+		let _ = write!(canvas.text(right - 100, 8, RGBA::rgba(0xFF, 0xFF, 0x80, 0xFF), 1),
+			"internal: {:>4}ms\npresent:  {:>4}ms\nfps:      {:>6.0}",
+			self.internal.as_millis(),
+			self.present .as_millis(),
+			fps,
 		);
 	}
 }
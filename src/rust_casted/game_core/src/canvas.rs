@@ -0,0 +1,347 @@
+// Immediate-mode 2D drawing over an RGBA framebuffer, meant for HUDs, counters and menus drawn on top of a scene.
+// Everything is clipped against the clip rectangle, coordinates outside of the buffer are fine.
+
+pub mod font;
+
+use core::fmt;
+use crate::{RGBA, SCREEN_WIDTH, SCREEN_HEIGHT};
+use font::{glyph, GLYPH_WIDTH, GLYPH_HEIGHT, ADVANCE_X, ADVANCE_Y};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rect {
+	pub x:i32,
+	pub y:i32,
+	pub width:i32,
+	pub height:i32,
+}
+impl Rect {
+	#[inline(always)]
+	pub const fn new(x:i32, y:i32, width:i32, height:i32) -> Rect { Rect { x, y, width, height } }
+	#[inline(always)]
+	pub const fn right(&self) -> i32 { self.x + self.width }
+	#[inline(always)]
+	pub const fn bottom(&self) -> i32 { self.y + self.height }
+	#[inline(always)]
+	pub const fn is_empty(&self) -> bool { self.width <= 0 || self.height <= 0 }
+	#[inline(always)]
+	pub const fn contains(&self, x:i32, y:i32) -> bool {
+		x >= self.x && y >= self.y && x < self.right() && y < self.bottom()
+	}
+
+	/// Overlap of both rectangles, empty ones have zero width and height.
+	pub fn intersect(&self, other:&Rect) -> Rect {
+		let x = self.x.max(other.x);
+		let y = self.y.max(other.y);
+		let width = (self.right().min(other.right()) - x).max(0);
+		let height = (self.bottom().min(other.bottom()) - y).max(0);
+		Rect::new(x, y, width, height)
+	}
+}
+
+/// Borrowed row-major pixels to blit, `pixels` holds at least `width * height` of them.
+#[derive(Copy, Clone)]
+pub struct Image<'a> {
+	pub width:i32,
+	pub height:i32,
+	pub pixels:&'a [RGBA],
+}
+impl<'a> Image<'a> {
+	pub fn new(width:i32, height:i32, pixels:&'a [RGBA]) -> Image<'a> {
+		debug_assert!(pixels.len() >= (width * height) as usize);
+		Image { width, height, pixels }
+	}
+}
+
+pub struct Canvas<'a> {
+	pixels:&'a mut [RGBA],
+	width:i32,
+	height:i32,
+	clip:Rect,
+}
+
+impl<'a> Canvas<'a> {
+	pub fn new(pixels:&'a mut [RGBA], width:i32, height:i32) -> Canvas<'a> {
+		debug_assert!(pixels.len() >= (width * height) as usize);
+		Canvas { pixels, width, height, clip: Rect::new(0, 0, width, height) }
+	}
+
+	/// Canvas over the whole screen image the scenes render into.
+	#[inline(always)]
+	pub fn screen(pixels:&'a mut [RGBA]) -> Canvas<'a> {
+		Canvas::new(pixels, SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32)
+	}
+
+	#[inline(always)]
+	pub const fn width(&self) -> i32 { self.width }
+	#[inline(always)]
+	pub const fn height(&self) -> i32 { self.height }
+	#[inline(always)]
+	pub const fn clip(&self) -> Rect { self.clip }
+
+	/// Limits drawing to `clip`, it is kept inside of the buffer.
+	pub fn set_clip(&mut self, clip:Rect) {
+		self.clip = clip.intersect(&Rect::new(0, 0, self.width, self.height));
+	}
+	pub fn reset_clip(&mut self) {
+		self.clip = Rect::new(0, 0, self.width, self.height);
+	}
+
+	/// Blends `color` over the pixel by its alpha, nothing happens outside of the clip rectangle.
+	#[inline]
+	pub fn pixel(&mut self, x:i32, y:i32, color:RGBA) {
+		if self.clip.contains(x, y) {
+			let pixel = &mut self.pixels[(y * self.width + x) as usize];
+			*pixel = blend(*pixel, color);
+		}
+	}
+
+	pub fn fill_rect(&mut self, rect:Rect, color:RGBA) {
+		let rect = rect.intersect(&self.clip);
+		for y in rect.y..rect.bottom() {
+			let row = (y * self.width) as usize;
+			for pixel in &mut self.pixels[row + rect.x as usize..row + rect.right() as usize] {
+				*pixel = blend(*pixel, color);
+			}
+		}
+	}
+
+	/// One pixel wide outline along the inside of `rect`.
+	pub fn stroke_rect(&mut self, rect:Rect, color:RGBA) {
+		if rect.is_empty() {
+			return;
+		}
+		self.fill_rect(Rect::new(rect.x, rect.y, rect.width, 1), color);
+		if rect.height > 1 {
+			self.fill_rect(Rect::new(rect.x, rect.bottom() - 1, rect.width, 1), color);
+		}
+		if rect.height > 2 {
+			self.fill_rect(Rect::new(rect.x, rect.y + 1, 1, rect.height - 2), color);
+			if rect.width > 1 {
+				self.fill_rect(Rect::new(rect.right() - 1, rect.y + 1, 1, rect.height - 2), color);
+			}
+		}
+	}
+
+	/// Bresenham line, both end points included.
+	pub fn line(&mut self, x0:i32, y0:i32, x1:i32, y1:i32, color:RGBA) {
+		let dx = (x1 - x0).abs();
+		let dy = -(y1 - y0).abs();
+		let step_x = if x0 < x1 { 1 } else { -1 };
+		let step_y = if y0 < y1 { 1 } else { -1 };
+		let (mut x, mut y) = (x0, y0);
+		let mut error = dx + dy;
+		loop {
+			self.pixel(x, y, color);
+			if x == x1 && y == y1 {
+				break;
+			}
+			let error_2 = error * 2;
+			if error_2 >= dy {
+				error += dy;
+				x += step_x;
+			}
+			if error_2 <= dx {
+				error += dx;
+				y += step_y;
+			}
+		}
+	}
+
+	/// Draws the whole `image` with its top left corner at `x`, `y`.
+	#[inline(always)]
+	pub fn blit(&mut self, x:i32, y:i32, image:&Image) {
+		self.blit_region(x, y, image, Rect::new(0, 0, image.width, image.height));
+	}
+
+	/// Draws the part `source` of `image` with its top left corner at `x`, `y`, alpha blended.
+	pub fn blit_region(&mut self, x:i32, y:i32, image:&Image, source:Rect) {
+		let clipped = source.intersect(&Rect::new(0, 0, image.width, image.height));
+		// What is cut off the source moves the destination along with it
+		let (x, y) = (x + clipped.x - source.x, y + clipped.y - source.y);
+		let source = clipped;
+		let target = Rect::new(x, y, source.width, source.height).intersect(&self.clip);
+		for ty in target.y..target.bottom() {
+			let sy = source.y + ty - y;
+			for tx in target.x..target.right() {
+				let sx = source.x + tx - x;
+				let color = image.pixels[(sy * image.width + sx) as usize];
+				let pixel = &mut self.pixels[(ty * self.width + tx) as usize];
+				*pixel = blend(*pixel, color);
+			}
+		}
+	}
+
+	/// Draws `text` with the top left corner of the first glyph at `x`, `y`, every font pixel is `scale` pixels wide.
+	/// A new line starts again at `x`. Returns the x of where the next character would go.
+	pub fn draw_text(&mut self, x:i32, y:i32, text:&str, color:RGBA, scale:i32) -> i32 {
+		let (mut cx, mut cy) = (x, y);
+		for c in text.chars() {
+			if c == '\n' {
+				cx = x;
+				cy += ADVANCE_Y * scale;
+				continue;
+			}
+			self.draw_glyph(cx, cy, c, color, scale);
+			cx += ADVANCE_X * scale;
+		}
+		cx
+	}
+
+	fn draw_glyph(&mut self, x:i32, y:i32, c:char, color:RGBA, scale:i32) {
+		let bounds = Rect::new(x, y, GLYPH_WIDTH * scale, GLYPH_HEIGHT * scale);
+		if c == ' ' || bounds.intersect(&self.clip).is_empty() {
+			return;
+		}
+		for (row, bits) in glyph(c).iter().enumerate() {
+			for column in 0..GLYPH_WIDTH {
+				if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+					self.fill_rect(Rect::new(x + column * scale, y + row as i32 * scale, scale, scale), color);
+				}
+			}
+		}
+	}
+
+	/// Writer for `write!`, formats straight onto the canvas without allocating.
+	pub fn text<'c>(&'c mut self, x:i32, y:i32, color:RGBA, scale:i32) -> TextWriter<'c, 'a> {
+		TextWriter { canvas: self, left: x, x, y, color, scale }
+	}
+}
+
+/// Size `text` takes when drawn at `scale`, without the spacing after the last column and row.
+pub fn text_size(text:&str, scale:i32) -> (i32, i32) {
+	let (mut width, mut lines, mut column) = (0, 1, 0);
+	for c in text.chars() {
+		if c == '\n' {
+			lines += 1;
+			column = 0;
+		} else {
+			column += 1;
+			width = width.max(column);
+		}
+	}
+	if width == 0 {
+		return (0, lines * ADVANCE_Y * scale - scale);
+	}
+	(width * ADVANCE_X * scale - scale, lines * ADVANCE_Y * scale - scale)
+}
+
+/// Keeps the pen position between `write_str` calls, see `Canvas::text`.
+pub struct TextWriter<'c, 'a> {
+	canvas:&'c mut Canvas<'a>,
+	left:i32,
+	x:i32,
+	y:i32,
+	color:RGBA,
+	scale:i32,
+}
+impl fmt::Write for TextWriter<'_, '_> {
+	fn write_str(&mut self, s:&str) -> fmt::Result {
+		for (i, line) in s.split('\n').enumerate() {
+			if i > 0 {
+				self.x = self.left;
+				self.y += ADVANCE_Y * self.scale;
+			}
+			self.x = self.canvas.draw_text(self.x, self.y, line, self.color, self.scale);
+		}
+		Ok(())
+	}
+}
+
+/// Source over destination, the destination alpha accumulates coverage.
+#[inline(always)]
+fn blend(dst:RGBA, src:RGBA) -> RGBA {
+	match src.a {
+		0xFF => src,
+		0x00 => dst,
+		a => {
+			let a = a as u16;
+			let mix = |d:u8, s:u8| ((s as u16 * a + d as u16 * (0xFF - a) + 0x7F) / 0xFF) as u8;
+			RGBA::rgba(
+				mix(dst.r, src.r),
+				mix(dst.g, src.g),
+				mix(dst.b, src.b),
+				(a + dst.a as u16 * (0xFF - a) / 0xFF) as u8,
+			)
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use core::fmt::Write;
+
+	const W:i32 = 32;
+	const H:i32 = 16;
+	const WHITE:RGBA = RGBA::rgba(0xFF, 0xFF, 0xFF, 0xFF);
+
+	fn lit(pixels:&[RGBA]) -> usize {
+		pixels.iter().filter(|p| !p.is_zero()).count()
+	}
+
+	#[test]
+	fn drawing_is_clipped() {
+		let mut pixels = [RGBA::zeroed(); (W * H) as usize];
+		let mut canvas = Canvas::new(&mut pixels, W, H);
+		canvas.fill_rect(Rect::new(-10, -10, 15, 12), WHITE);
+		canvas.line(-100, 3, 100, 3, WHITE);
+		canvas.set_clip(Rect::new(20, 8, 100, 100));
+		canvas.fill_rect(Rect::new(0, 0, W, H), WHITE);
+		assert_eq!(canvas.clip(), Rect::new(20, 8, 12, 8));
+
+		assert_eq!(lit(&pixels), 5 * 2 + W as usize + 12 * 8);
+		assert!(!pixels[(W + 4) as usize].is_zero());
+		assert!(pixels[(W + 5) as usize].is_zero());
+		assert!(pixels[(7 * W + 19) as usize].is_zero());
+		assert!(!pixels[(15 * W + 31) as usize].is_zero());
+	}
+
+	#[test]
+	fn text_is_drawn_from_the_font() {
+		let mut pixels = [RGBA::zeroed(); (W * H) as usize];
+		let mut canvas = Canvas::new(&mut pixels, W, H);
+		assert_eq!(canvas.draw_text(1, 1, "I", WHITE, 1), 1 + ADVANCE_X);
+		write!(canvas.text(1 + ADVANCE_X, 1, WHITE, 1), "{}", 1).unwrap();
+		assert_eq!(text_size("ab\nc", 2), (2 * ADVANCE_X * 2 - 2, 2 * ADVANCE_Y * 2 - 2));
+
+		let expected:u32 = glyph('I').iter().chain(glyph('1').iter()).map(|row| row.count_ones()).sum();
+		assert_eq!(lit(&pixels), expected as usize);
+		// The top of 'I' is a bar spanning three columns
+		for x in 2..5 {
+			assert!(!pixels[(W + x) as usize].is_zero());
+		}
+	}
+
+	#[test]
+	fn blits_blend_by_alpha() {
+		let mut pixels = [RGBA::rgba(0, 0, 0xFF, 0xFF); (W * H) as usize];
+		let image = [RGBA::rgba(0xFF, 0, 0, 0xFF), RGBA::rgba(0xFF, 0, 0, 0x80), RGBA::zeroed(), RGBA::rgba(0, 0xFF, 0, 0xFF)];
+		let mut canvas = Canvas::new(&mut pixels, W, H);
+		canvas.blit(W - 1, H - 2, &Image::new(2, 2, &image));
+
+		assert_eq!(pixels[((H - 2) * W + W - 1) as usize].to_rgb32(), 0xFF0000);
+		assert_eq!(pixels[((H - 1) * W + W - 1) as usize].to_rgb32(), 0x0000FF);
+		assert_eq!(pixels[((H - 2) * W + W - 2) as usize].to_rgb32(), 0x0000FF);
+
+		let mut canvas = Canvas::new(&mut pixels, W, H);
+		canvas.blit_region(0, 0, &Image::new(2, 2, &image), Rect::new(1, 0, 1, 1));
+		let half = pixels[0];
+		assert_eq!((half.r, half.g, half.b, half.a), (0x80, 0, 0x7F, 0xFF));
+		assert_eq!(pixels[1].to_rgb32(), 0x0000FF);
+	}
+
+	#[test]
+	fn regions_outside_of_the_image_keep_their_place() {
+		let mut pixels = [RGBA::zeroed(); (W * H) as usize];
+		let image = [RGBA::rgba(0xFF, 0, 0, 0xFF), RGBA::rgba(0, 0xFF, 0, 0xFF), RGBA::rgba(0, 0, 0xFF, 0xFF), WHITE];
+		let mut canvas = Canvas::new(&mut pixels, W, H);
+		// The image starts one pixel into the region
+		canvas.blit_region(4, 4, &Image::new(2, 2, &image), Rect::new(-1, -1, 3, 3));
+
+		assert_eq!(lit(&pixels), 4);
+		assert_eq!(pixels[(5 * W + 5) as usize].to_rgb32(), 0xFF0000);
+		assert_eq!(pixels[(5 * W + 6) as usize].to_rgb32(), 0x00FF00);
+		assert_eq!(pixels[(6 * W + 5) as usize].to_rgb32(), 0x0000FF);
+		assert_eq!(pixels[(6 * W + 6) as usize].to_rgb32(), 0xFFFFFF);
+	}
+}
@@ -1,9 +1,12 @@
+use crate::Input;
+
 pub struct Controls {
 	is_pointer_locked: bool,
 	m_rounded_x: u16,
 	m_rounded_y: u16,
 	m_prec_x: f32,
 	m_prec_y: f32,
+	wheel: f32,
 	pub lmb:Key,
 	pub rmb:Key,
 	pub arrow_up:Key,
@@ -11,6 +14,8 @@ pub struct Controls {
 	pub arrow_left:Key,
 	pub arrow_right:Key,
 	pub space:Key,
+	pub tab:Key,
+	pub view:Key,
 }
 
 pub struct Key {
@@ -34,6 +39,7 @@ impl Controls {
 			m_rounded_y: screen_height / 2,
 			m_prec_x: screen_width as f32 / 2f32,
 			m_prec_y: screen_height as f32 / 2f32,
+			wheel: 0f32,
 
 			lmb:         Key::new(),
 			rmb:         Key::new(),
@@ -42,50 +48,36 @@ impl Controls {
 			arrow_left:  Key::new(),
 			arrow_right: Key::new(),
 			space:       Key::new(),
+			tab:         Key::new(),
+			view:        Key::new(),
 		}
 	}
 
-	pub fn input_loop(
-		&mut self,
-		locked_pointer:bool,
-		abs_x:f32,
-		abs_y:f32,
-		delta_x:f32,
-		delta_y:f32,
-		scale:f32,
-
-		lmb:bool,
-		rmb:bool,
-
-		vku: bool,
-		vkd: bool,
-		vkl: bool,
-		vkr: bool,
-		space:bool,
-
-		screen_width:u16, screen_height:u16,
-	) {
-		if !self.is_pointer_locked || !locked_pointer {
-			self.m_prec_x = abs_x / scale;
-			self.m_prec_y = abs_y / scale;
+	pub fn input_loop(&mut self, input:&Input, screen_width:u16, screen_height:u16) {
+		if !self.is_pointer_locked || !input.locked_pointer {
+			self.m_prec_x = input.abs_x / input.scale;
+			self.m_prec_y = input.abs_y / input.scale;
 		}
-		self.is_pointer_locked = locked_pointer;
+		self.is_pointer_locked = input.locked_pointer;
 		if self.is_pointer_locked {
-			self.m_prec_x += delta_x / scale;
-			self.m_prec_y += delta_y / scale;
+			self.m_prec_x += input.delta_x / input.scale;
+			self.m_prec_y += input.delta_y / input.scale;
 		}
 		//m_prec_x = m_prec_x.clamp(0f32, SCREEN_WIDTH  as f32);
 		//m_prec_y = m_prec_y.clamp(0f32, SCREEN_HEIGHT as f32);
 		self.m_rounded_x = core::cmp::min((self.m_prec_x+0.5f32) as u16, screen_width  - 1);
 		self.m_rounded_y = core::cmp::min((self.m_prec_y+0.5f32) as u16, screen_height - 1);
+		self.wheel = input.wheel;
 
-		self.lmb        .update(lmb);
-		self.rmb        .update(rmb);
-		self.arrow_up   .update(vku);
-		self.arrow_down .update(vkd);
-		self.arrow_left .update(vkl);
-		self.arrow_right.update(vkr);
-		self.space      .update(space);
+		self.lmb        .update(input.lmb);
+		self.rmb        .update(input.rmb);
+		self.arrow_up   .update(input.up);
+		self.arrow_down .update(input.down);
+		self.arrow_left .update(input.left);
+		self.arrow_right.update(input.right);
+		self.space      .update(input.space);
+		self.tab        .update(input.tab);
+		self.view       .update(input.view);
 	}
 
 	// Cursor
@@ -94,4 +86,6 @@ impl Controls {
 	#[inline(always)] pub const fn pointer_y        (&self) -> u16 { self.m_rounded_y }
 	#[inline(always)] pub const fn pointer_precise_x(&self) -> f32 { self.m_prec_x    }
 	#[inline(always)] pub const fn pointer_precise_y(&self) -> f32 { self.m_prec_y    }
+	/// Wheel movement since the previous input, positive when scrolled up / away from the user.
+	#[inline(always)] pub const fn wheel            (&self) -> f32 { self.wheel       }
 }
\ No newline at end of file
@@ -1,14 +1,6 @@
 // TODO: watch for update https://github.com/rust-lang/rust/issues/29596
 // #![no_std] // TODO:
-#![feature(const_fn)]
-#![feature(const_if_match)]
-#![feature(const_loop)]
-#![feature(const_mut_refs)]
-#![feature(const_extern_fn)]
-#![feature(core_intrinsics)] // likely / unlikely branch predictions
-#![feature(clamp)]
-#![feature(tau_constant)]
-#![feature(test)]
+#![cfg_attr(test, feature(test))]
 
 // #[panic_handler]
 // fn panic(_info: &core::panic::PanicInfo) -> ! {
@@ -17,92 +9,144 @@
 
 // ==== DISPlAY ====
 
-// mod profile;
+mod profile;
 mod timing;
 mod controls;
+pub mod canvas;
+pub mod platform;
 // mod trig;
 // mod vector;
-// mod sft_renderer;
+pub mod sft_renderer;
 // mod shader1;
 // mod shader2;
 mod minecraft4k;
-//mod rcl;
-//mod demo1;
+mod rcl;
 
 use timing::*;
 use controls::Controls;
-// use sft_renderer::SoftwareRenderer;
+pub use platform::{Input, Platform, run};
+use sft_renderer::SoftwareRenderer;
 // use shader1::Shader1;
 // use shader2::Shader1;
 use minecraft4k::Shader1;
-//use demo1::Demo1;
+use rcl::demo1::Demo1;
+use rcl::editor::Editor;
 
 pub const SCREEN_WIDTH:u16 = minecraft4k::w as u16 * 4;
 pub const SCREEN_HEIGHT:u16 = minecraft4k::h as u16 * 4;
 pub const SCREEN_SPACE:u32 = SCREEN_WIDTH as u32 * SCREEN_HEIGHT as u32;
 
+/// What `State` renders, hosts pick it before running the game.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Scene {
+	Minecraft4k,
+	/// Textured, lit model drawn by the software rasterizer.
+	SoftwareRenderer,
+	/// The raycastlib demo.
+	Raycaster,
+	/// Level editor for the raycaster.
+	Editor,
+}
+impl Scene {
+	pub const ALL:[Scene; 4] = [Scene::Minecraft4k, Scene::SoftwareRenderer, Scene::Raycaster, Scene::Editor];
+
+	pub const fn name(self) -> &'static str {
+		match self {
+			Scene::Minecraft4k => "minecraft4k",
+			Scene::SoftwareRenderer => "software",
+			Scene::Raycaster => "raycaster",
+			Scene::Editor => "editor",
+		}
+	}
+
+	pub fn from_name(name:&str) -> Option<Scene> {
+		Scene::ALL.iter().copied().find(|scene| scene.name() == name)
+	}
+}
+
 pub struct State {
 	timing:Timing,
 	controls:Controls,
-//	software_renderer:SoftwareRenderer,
-//	demo:Demo1,
+	scene:Scene,
+	software_renderer:SoftwareRenderer,
+	demo:Demo1,
+	editor:Editor,
 	shader:Shader1,
 }
 
+impl Default for State {
+	fn default() -> State { State::new() }
+}
+
 impl State {
 	#[inline(always)]
 	pub const fn new() -> State {
 		State {
 			timing: Timing::new(),
 			controls: Controls::new(SCREEN_WIDTH, SCREEN_HEIGHT),
-//			software_renderer: SoftwareRenderer::new(),
-//			demo: Demo1::new(),
+			scene: Scene::Minecraft4k,
+			software_renderer: SoftwareRenderer::new(),
+			demo: Demo1::new(),
+			editor: Editor::new(),
 			shader:Shader1::new(),
 		}
 	}
 
+	#[inline(always)]
+	pub fn scene(&self) -> Scene { self.scene }
+
+	/// Takes effect with the next `init`.
+	#[inline(always)]
+	pub fn set_scene(&mut self, scene:Scene) {
+		self.scene = scene;
+	}
+
 	#[inline(always)]
 	pub fn init(&mut self) {
-//		self.software_renderer.init();
-//		self.demo.main();
-		self.shader.init();
+		match self.scene {
+			Scene::Minecraft4k => self.shader.init(),
+			Scene::SoftwareRenderer => self.software_renderer.init(),
+			Scene::Raycaster => self.demo.main(),
+			Scene::Editor => self.editor.init(),
+		}
+	}
+
+	/// Level the editor starts with instead of demo1, in the text format of `rcl::rcl_level`.
+	pub fn open_level(&mut self, text:&str) -> Result<(), String> {
+		self.editor.open(text).map_err(|error| error.to_string())
+	}
+
+	/// Text of the level the editor saved since the previous call, for the host to store.
+	pub fn take_saved_level(&mut self) -> Option<String> {
+		self.editor.takeSaved()
+	}
+
+	/// Tells the editor whether the host stored the level.
+	pub fn level_saved(&mut self, saved:bool) {
+		self.editor.setSaved(saved);
+	}
+
+	/// The pointer is captured while dragging, so it can not leave the window and keeps reporting movement.
+	/// Not in the editor, which paints with absolute positions.
+	#[inline(always)]
+	pub fn wants_pointer_lock(&self) -> bool {
+		self.scene != Scene::Editor && self.controls.lmb.is_pressed()
 	}
 
 	#[inline(always)]
-	pub fn tick(
-		&mut self,
-		time_stamp:i32,
-		locked_pointer:bool,
-		abs_x:f32, abs_y:f32,
-		delta_x:f32, delta_y:f32,
-		scale:f32,
-		lmb:bool, rmb:bool,
-		vk_u:bool, vk_d:bool, vk_l:bool, vk_r:bool, vk_space:bool,
-		image:&mut [RGBA],
-	) {
-		self.controls.input_loop(
-			locked_pointer,
-			abs_x, abs_y,
-			delta_x, delta_y,
-			scale,
-
-			lmb,
-			rmb,
-
-			vk_u,
-			vk_d,
-			vk_l,
-			vk_r,
-			vk_space,
-
-			SCREEN_WIDTH, SCREEN_HEIGHT
-		);
+	pub fn tick(&mut self, time_stamp:i32, input:&Input, image:&mut [RGBA]) {
+		self.controls.input_loop(input, SCREEN_WIDTH, SCREEN_HEIGHT);
 		let time = self.timing.timing_loop(time_stamp);
 		if let Some(time) = time {
-//			self.demo.update(image, &time, &self.controls);
-//			self.software_renderer.render(image, time.tick_number(), &self.controls);
-			self.shader.handle_event(&self.controls);
-			self.shader.render(image, time.tick_number(), &self.controls);
+			match self.scene {
+				Scene::Minecraft4k => {
+					self.shader.handle_event(&self.controls);
+					self.shader.render(image, time.tick_number(), &self.controls);
+				},
+				Scene::SoftwareRenderer => self.software_renderer.render(image, time.tick_number(), &self.controls),
+				Scene::Raycaster => self.demo.update(image, &time, &self.controls),
+				Scene::Editor => self.editor.update(image, &time, &self.controls),
+			}
 		}
 	}
 }
@@ -124,6 +168,7 @@ pub struct RGBA {
 }
 impl RGB {
 	#[inline]
+	#[allow(clippy::self_named_constructors)]
 	pub const fn rgb(r:u8, g:u8, b:u8) -> RGB {
 		RGB { r, g, b }
 	}
@@ -132,6 +177,7 @@ impl RGB {
 }
 impl RGBA {
 	#[inline]
+	#[allow(clippy::self_named_constructors)]
 	pub const fn rgba(r:u8, g:u8, b:u8, a:u8) -> RGBA { RGBA { r, g, b, a } }
 	#[inline(always)]
 	pub const fn zeroed() -> RGBA { RGBA::rgba(0, 0, 0, 0) }
@@ -1,3 +1,8 @@
+// Line by line port of the Java original, its names and quirks are kept so the two can be
+// compared side by side.
+#![allow(non_upper_case_globals, non_snake_case, dead_code, unused_assignments)]
+#![allow(clippy::precedence, clippy::identity_op, clippy::unnecessary_cast)]
+
 use crate::RGBA;
 use crate::controls::*;
 use tiny_lib::trig::PI;
@@ -79,7 +84,7 @@ impl Shader1 {
 	// public void run() {
 	pub fn init(&mut self) {
 		// Random localRandom = new Random();
-		let localRandom = unsafe { &mut tiny_lib::prng::GLOBAL_PRNG };
+		let localRandom = unsafe { &mut *core::ptr::addr_of_mut!(tiny_lib::prng::GLOBAL_PRNG) };
 		// BufferedImage localBufferedImage = new BufferedImage(214, 120, 1);
 		// localRandom.setSeed(18295169L);
 		// NOOP
@@ -0,0 +1,159 @@
+// What every front-end (minifb, SDL2, the wasm exports) hands over to the game, what it has to provide and the loop running it.
+
+use crate::{RGBA, State, SCREEN_WIDTH, SCREEN_HEIGHT};
+
+/// Raw input of one frame, as the host sees it. Pointer coordinates are in window pixels, `scale` converts them to
+/// screen pixels. `Controls` turns it into button states.
+#[derive(Copy, Clone)]
+pub struct Input {
+	pub locked_pointer:bool,
+	pub abs_x:f32,
+	pub abs_y:f32,
+	/// Pointer movement since the last frame, only used while the pointer is locked.
+	pub delta_x:f32,
+	pub delta_y:f32,
+	pub scale:f32,
+	/// Wheel steps since the last frame, positive when scrolled up.
+	pub wheel:f32,
+	pub lmb:bool,
+	pub rmb:bool,
+	pub up:bool,
+	pub down:bool,
+	pub left:bool,
+	pub right:bool,
+	pub space:bool,
+	pub tab:bool,
+	/// Cycles through the debug views of the software renderer.
+	pub view:bool,
+}
+
+impl Default for Input {
+	fn default() -> Input { Input::new() }
+}
+
+impl Input {
+	pub const fn new() -> Input {
+		Input {
+			locked_pointer: false,
+			abs_x: 0f32,
+			abs_y: 0f32,
+			delta_x: 0f32,
+			delta_y: 0f32,
+			scale: 1f32,
+			wheel: 0f32,
+			lmb: false,
+			rmb: false,
+			up: false,
+			down: false,
+			left: false,
+			right: false,
+			space: false,
+			tab: false,
+			view: false,
+		}
+	}
+}
+
+/// Window, input, time and presentation of a front-end, `run` drives the game with it.
+pub trait Platform {
+	/// Milliseconds since an arbitrary point, allowed to wrap around.
+	fn time(&self) -> i32;
+	/// Pumps the window events and updates `input`, returns false once the window should close.
+	/// Fields the host cannot report are left alone, `run` takes care of `scale` and `locked_pointer`.
+	fn poll(&mut self, input:&mut Input) -> bool;
+	/// New size of the drawable area in pixels, when it changed since the previous call. Hosts show the frame
+	/// `window_scale` times enlarged at the top left corner, pointer coordinates are mapped back the same way.
+	fn resized(&mut self) -> Option<(u32, u32)> { None }
+	/// Captures the pointer, it then moves without bounds and only deltas are reported. Returns whether it is locked now,
+	/// hosts which cannot lock keep the default.
+	fn set_pointer_lock(&mut self, _locked:bool) -> bool { false }
+	/// Stores a level saved in the editor, returns whether it was stored. Hosts without storage keep the default.
+	fn save_level(&mut self, _text:&str) -> bool { false }
+	/// Shows a `SCREEN_WIDTH` x `SCREEN_HEIGHT` frame, the host may draw its own overlays on top of it first.
+	fn present(&mut self, image:&mut [RGBA]);
+}
+
+/// Largest whole scale of the screen that fits into a `width` x `height` window, at least 1.
+pub fn window_scale(width:u32, height:u32) -> f32 {
+	let scale = core::cmp::min(width / SCREEN_WIDTH as u32, height / SCREEN_HEIGHT as u32);
+	core::cmp::max(scale, 1) as f32
+}
+
+/// Runs the game until the platform closes. Hosts which are driven by callbacks, like the wasm exports,
+/// call `State::tick` themselves instead.
+pub fn run<P:Platform>(platform:&mut P, state:&mut State, image:&mut [RGBA]) {
+	state.init();
+
+	let mut input = Input::new();
+	while platform.poll(&mut input) {
+		if let Some((width, height)) = platform.resized() {
+			input.scale = window_scale(width, height);
+		}
+
+		state.tick(platform.time(), &input, image);
+
+		if let Some(text) = state.take_saved_level() {
+			let saved = platform.save_level(&text);
+			state.level_saved(saved);
+		}
+
+		let lock = state.wants_pointer_lock();
+		if lock != input.locked_pointer {
+			input.locked_pointer = platform.set_pointer_lock(lock);
+		}
+
+		platform.present(image);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::SCREEN_SPACE;
+
+	/// Plays back a fixed amount of frames, dragging in the middle of them.
+	struct Playback {
+		frames:u32,
+		presented:u32,
+		locked:bool,
+		lock_requests:u32,
+	}
+	impl Platform for Playback {
+		fn time(&self) -> i32 { self.presented as i32 * 10 }
+		fn poll(&mut self, input:&mut Input) -> bool {
+			input.lmb = self.presented >= 2 && self.presented < 5;
+			self.presented < self.frames
+		}
+		fn resized(&mut self) -> Option<(u32, u32)> {
+			if self.presented == 0 { Some((SCREEN_WIDTH as u32 * 2 + 100, SCREEN_HEIGHT as u32 * 3)) } else { None }
+		}
+		fn set_pointer_lock(&mut self, locked:bool) -> bool {
+			self.lock_requests += 1;
+			self.locked = locked;
+			locked
+		}
+		fn present(&mut self, image:&mut [RGBA]) {
+			assert_eq!(image.len(), SCREEN_SPACE as usize);
+			self.presented += 1;
+		}
+	}
+
+	#[test]
+	fn run_drives_the_platform_until_it_closes() {
+		assert_eq!(window_scale(SCREEN_WIDTH as u32 * 2 + 100, SCREEN_HEIGHT as u32 * 3), 2f32);
+		assert_eq!(window_scale(10, 10), 1f32);
+
+		// The game is too large for the test thread stack
+		std::thread::Builder::new().stack_size(1 << 28).spawn(|| {
+			let mut state = Box::new(State::new());
+			let mut image = vec![RGBA::zeroed(); SCREEN_SPACE as usize];
+			let mut platform = Playback { frames: 8, presented: 0, locked: false, lock_requests: 0 };
+			run(&mut platform, &mut state, &mut image);
+
+			assert_eq!(platform.presented, 8);
+			// Locked while dragging, released afterwards
+			assert_eq!(platform.lock_requests, 2);
+			assert!(!platform.locked);
+		}).unwrap().join().unwrap();
+	}
+}
@@ -1,13 +1,15 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
 const RCL_PROFILE:bool = false;
 
 pub struct Profiler {
-	calls:u32
+	calls:AtomicU32
 }
 
 impl Profiler {
-	pub fn call(&mut self) {
+	pub fn call(&self) {
 		if RCL_PROFILE {
-			self.calls.overflowing_add(1);
+			self.calls.fetch_add(1, Ordering::Relaxed);
 		}
 	}
 }
@@ -15,14 +17,16 @@ impl Profiler {
 macro_rules! profile {
 	( $( $p:ident ),+ ) => {
 		$(
-			pub static mut $p:Profiler = Profiler { calls: 0 };
+			#[allow(non_upper_case_globals)] // named after the profiled C functions
+			pub static $p:Profiler = Profiler { calls: AtomicU32::new(0) };
 		)+
 
+		#[allow(dead_code)]
 		fn print_profile() {
 			if RCL_PROFILE {
 //				println!("profile fn calls:\n");
 //				$(
-//					println!("  {}: {}\n", stringify!($p), unsafe { &$p }.calls);
+//					println!("  {}: {}\n", stringify!($p), $p.calls.load(Ordering::Relaxed));
 //				)+
 			}
 		}
@@ -1,101 +1,80 @@
 use crate::rcl::*;
-use crate::rcl_my_settings::*;
-use crate::rcl_general::*;
-use crate::timing::Time;
+use crate::rcl::rcl_general::*;
+use crate::rcl::rcl_level::*;
+use crate::rcl::rcl_sprites::*;
+use crate::rcl::rcl_doors::*;
+use crate::rcl::rcl_lights::*;
+use crate::timing::{Time, TARGET_FPS};
 use crate::controls::Controls;
 use crate::RGBA;
 
-/**
+/*
   Raycasting demo 1 for Pokitto.
   This demo is a showoff of various features (movement, textures, sprites, fog, ...). The performance can be lower with all the features on.
 */
 
-// TODO: Was commented out
-/// turns off wall textures
-const COMPUTE_WALL_TEXCOORDS:u8 = 0;
-
 // TODO: Was commented out
 /// turns on full X-resolution
 const SUBSAMPLE:u8 = 1;
 
-/// turns on floor texture (a lot slower)
-const RCL_COMPUTE_FLOOR_TEXCOORDS:u8 = 1;
-
 // redefine some parameters
-const PLAYER_SPEED:RCL_Unit = (RCL_UNITS_PER_SQUARE * 2 * SPEED_MULTIPLIER);
-const GRAVITY_ACCELERATION:RCL_Unit = (RCL_UNITS_PER_SQUARE * SPEED_MULTIPLIER);
-const PLAYER_JUMP_SPEED:RCL_Unit = 400;
-const RCL_CAMERA_COLL_HEIGHT_BELOW:RCL_Unit = ((3 * RCL_UNITS_PER_SQUARE) / 2);
-const RCL_HORIZONTAL_FOV:RCL_Unit = (RCL_UNITS_PER_SQUARE / 5);
-
-const TEXTURE_W:u8 = 32;
-const TEXTURE_H:u8 = 32;
+const SPEED_MULTIPLIER:RCL_Unit = 1;
+
+pub(crate) const DEMO1_CONFIG:RCL_Config = RCL_Config {
+  horizontalFov: RCL_UNITS_PER_SQUARE / 5,
+  cameraCollHeightBelow: (3 * RCL_UNITS_PER_SQUARE) / 2,
+  // floor and ceiling textures and the sky (a lot slower)
+  computeFloorTexCoords: true,
+  computeCeilingTexCoords: true,
+  ..GENERAL_CONFIG
+};
+
+const DEMO1_PLAYER_CONFIG:PlayerConfig = PlayerConfig {
+  speed: RCL_UNITS_PER_SQUARE * 2 * SPEED_MULTIPLIER,
+  jumpSpeed: 400,
+  gravity: RCL_UNITS_PER_SQUARE * SPEED_MULTIPLIER,
+  ..GENERAL_PLAYER_CONFIG
+};
+
+/// Distant things fade into a haze of the sky's color.
+pub(crate) const DEMO1_FOG:RclFog = RclFog::new(RGBA::rgba(150, 175, 205, 0xFF), 3 * RCL_UNITS_PER_SQUARE, 12 * RCL_UNITS_PER_SQUARE);
+const TORCH_LIGHT_RADIUS:RCL_Unit = 5 * RCL_UNITS_PER_SQUARE / 2;
 
 // #include "general.hpp"
 
-const LEVEL_X_RES:u8 = 29;
-const LEVEL_Y_RES:u8 = 21;
+pub(crate) const DEMO1_LEVEL:&str = include_str!("../../res/demo1.level");
 
 pub(crate) struct Demo1 {
   player:Player,
   runReleased:bool, // helper for detecting switching between walk/run
+  useReleased:bool,
   general: RCL_General,
   renderer: RCL_Renderer_Global,
-  screen: Screen,
+  level: Demo1Level,
+  sprites:RclSprites,
+  doors:RclDoors,
 }
 
 impl Demo1 {
   pub const fn new() -> Demo1 {
     Demo1 {
-      player: Player::new(),
+      player: Player::withConfig(DEMO1_PLAYER_CONFIG),
       runReleased: false,
+      useReleased: false,
       general: RCL_General::new(),
-      renderer: RCL_Renderer_Global::new(),
-      screen: Screen::new(),
+      renderer: RCL_Renderer_Global::new(DEMO1_CONFIG),
+      level: Demo1Level::new(),
+      sprites: RclSprites::new(),
+      doors: RclDoors::new(),
     }
   }
 
   fn draw(&mut self, time:&Time) {
-    self.renderer.RCL_renderComplex(&mut self.general, self.player.mCamera.clone(),Some(textureAt));
-
-    let mut previousDepth:RCL_Unit = 0;
-
-    // draw sprites
-
-    for i in 0..SPRITES {
-      // use Chebyshew distance instead Euclidean, it's faster
-      if
-        RCL_absVal(sprites[i as usize].mPosition.x - self.player.mCamera.position.x) > SPRITE_MAX_DISTANCE ||
-        RCL_absVal(sprites[i as usize].mPosition.y - self.player.mCamera.position.y) > SPRITE_MAX_DISTANCE
-      {
-        continue;
-      }
-
-      let pos = RCL_mapToScreen(sprites[i as usize].mPosition, sprites[i as usize].mHeight, &self.player.mCamera);
-
-      if pos.depth > 0 { // is in front of camera?{
-        let mut image = sprites[i as usize].mImage;
-
-        // animate torch
-        if core::ptr::eq(image, &spriteTorch1) && (time.tick_number() >> 2) % 2 == 0 {
-          image = &spriteTorch2;
-        }
-
-        self.general.drawSpriteSquare(&mut self.screen,
-          image,pos.position.x as i16 * SUBSAMPLE as i16,
-          pos.position.y as i16,
-          pos.depth,
-          RCL_perspectiveScale(sprites[i as usize].mPixelSize,pos.depth) as u16,distanceToIntensity(pos.depth)
-        );
-      }
-
-      // trick: sort the sprites by distance with bubble sort as we draw - the order will be correct in a few frames
-      if i != 0 && pos.depth > previousDepth {
-        core::mem::swap(&mut sprites[i as usize], &mut sprites[(i-1) as usize]);
-      }
+    self.renderer.RCL_renderComplex(&mut self.general, self.player.mCamera.clone(), &RclDoorMap::new(&self.level, &self.doors));
 
-      previousDepth = pos.depth;
-    }
+    let level = &self.level;
+    self.sprites.draw(&mut self.general, self.renderer.config(), &self.player.mCamera, time.tick_number(),
+      |general, position, depth| spriteShade(general, level, position, depth));
 
     // uncomment for debugging camera
 
@@ -113,26 +92,33 @@ impl Demo1 {
   }
 
   pub fn main(&mut self) {
-    self.general.initGeneral();
+    self.general.initGeneral(self.renderer.config());
     self.general.defaultConstraints.maxHits = 8;
     self.general.defaultConstraints.maxSteps = 15;
 
-    self.player.setPositionSquare(12,6);
-    self.player.mCamera.direction = -1 * RCL_UNITS_PER_SQUARE / 4;
+    self.level = Demo1Level::load();
+    placeSprites(&self.level.level, &mut self.sprites);
+    placeLights(&self.level.level, &mut self.general.lights);
+    self.general.lights.fog = DEMO1_FOG;
+    self.doors.place(&self.level, &self.level.level.doors);
+
+    self.player.setPosition(self.level.level.startPosition.x, self.level.level.startPosition.y);
+    self.player.mCamera.direction = self.level.level.startDirection;
   }
 
   pub fn update(&mut self, image:&mut [RGBA], time:&Time, controls:&Controls) {
-    unsafe { FC = time.tick_number() as u8 };
+    self.level.liftFrame = time.tick_number() as u8;
+    self.general.lights.update(time.tick_number());
 
     self.draw(time);
 
-    self.screen.project(image);
+    self.general.screen.project(image);
 
     let mut moveDirection:i16 = 0;
     let mut shearDirection:i16 = 0;
     let mut rotationDirection:i16 = 0;
 
-    if controls.c.is_pressed() {
+    if controls.tab.is_pressed() {
       if self.runReleased {
         self.player.mRunning = !self.player.mRunning;
         self.runReleased = false;
@@ -141,7 +127,18 @@ impl Demo1 {
       self.runReleased = true;
     }
 
-    let mut strafe = controls.a.is_pressed();
+    // the left button uses the door in front
+    if controls.lmb.is_pressed() {
+      if self.useReleased {
+        self.doors.activate(&self.player.mCamera);
+        self.useReleased = false;
+      }
+    } else {
+      self.useReleased = true;
+    }
+    self.doors.update(time.ticks().get(), &self.player.mCamera);
+
+    let strafe = controls.rmb.is_pressed();
 
     if controls.arrow_up.is_pressed() {
       if !strafe {
@@ -171,149 +168,46 @@ impl Demo1 {
       }
     }
 
-    let dt = time.tick_number();
-    self.player.update(&mut self.renderer, moveDirection,strafe,rotationDirection,controls.space.is_pressed(), shearDirection,floorHeightAt,ceilingHeightAt,true, dt);
+    // milliseconds since the previous update
+    let dt = time.ticks().get() * 1000 / TARGET_FPS as u32;
+    self.player.update(&mut self.renderer, &RclDoorMap::new(&self.level, &self.doors), moveDirection,strafe,rotationDirection,controls.space.is_pressed(), shearDirection,true, dt);
   }
 }
 
-const SPRITES:u8 = 17;
-const SPRITE_MAX_DISTANCE:RCL_Unit = 5 * RCL_UNITS_PER_SQUARE;
-
-const sprites:[Sprite; SPRITES as usize] = placeSprites();
-
-const fn addSpriteHeightFract(sprite:&mut Sprite, f:RCL_Unit) {
-  sprite.mHeight += RCL_UNITS_PER_SQUARE / f;
-}
-const fn placeSprites() -> [Sprite; SPRITES as usize] {
-  let mut ret = [
-    Sprite::new(&spriteStatue,10,5 ,1,100),
-    Sprite::new(&spriteStatue,14,5 ,1,100),
-    Sprite::new(&spriteNPC   ,15,19,1,200),
-    Sprite::new(&spriteTree  ,8 ,2 ,1,300),
-    Sprite::new(&spriteTree  ,20,5 ,1,300),
-    Sprite::new(&spriteTree  ,26,18,1,300),
-    Sprite::new(&spriteTree  ,16,12,1,300),
-    Sprite::new(&spriteGrass ,27,16,0,100),
-    Sprite::new(&spriteGrass ,6 ,6 ,0,150),
-    Sprite::new(&spriteGrass ,8 ,5 ,0,120),
-    Sprite::new(&spriteGrass ,17,6 ,0,150),
-    Sprite::new(&spriteBarrel,12,16,0,120),
-    Sprite::new(&spriteBarrel,27,5 ,0,120),
-    Sprite::new(&spriteTorch1,11,9 ,2,120),
-    Sprite::new(&spriteTorch1,13,9 ,2,120),
-    Sprite::new(&spriteTorch1,14,19,2,120),
-    Sprite::new(&spriteTorch1,1 ,19,3,120),
-  ];
-  addSpriteHeightFract(&mut ret[0],8);
-  addSpriteHeightFract(&mut ret[1],8);
-  addSpriteHeightFract(&mut ret[3],4);
-  ret[3].mPosition.y -= RCL_UNITS_PER_SQUARE / 2;
-  addSpriteHeightFract(&mut ret[4],4);
-  addSpriteHeightFract(&mut ret[5],2);
-  addSpriteHeightFract(&mut ret[6],4);
-  addSpriteHeightFract(&mut ret[7],8);
-  addSpriteHeightFract(&mut ret[8],8);
-  addSpriteHeightFract(&mut ret[9],8);
-  addSpriteHeightFract(&mut ret[10],8);
-  addSpriteHeightFract(&mut ret[11],4);
-  addSpriteHeightFract(&mut ret[12],4);
-  ret[13].mPosition.y += RCL_UNITS_PER_SQUARE / 3;
-  ret[14].mPosition.y += RCL_UNITS_PER_SQUARE / 3;
-  ret[15].mPosition.y += RCL_UNITS_PER_SQUARE / 3;
-  ret[16].mPosition.y += RCL_UNITS_PER_SQUARE / 3;
-
-  return ret;
+/// Replaces `sprites` with the sprites of the level, with the images of this demo.
+pub(crate) fn placeSprites(level:&RclLevel, sprites:&mut RclSprites) {
+  sprites.clear();
+  for placement in &level.sprites {
+    sprites.add(Sprite {
+      mImages: SPRITE_IMAGES[placement.image],
+      mPosition: placement.position,
+      mHeight: placement.height,
+      mPixelSize: placement.pixelSize,
+      mDirection: 0,
+    });
+  }
 }
 
-/// For each level square says the texture index.
-const levelTexture:[u8;29*21] = [
-// 0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28
-   1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 3, 0, 3, 3, 2, 2, 2, 2, // 0   20
-   1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 1, 1, 1, 2, // 1   19
-   1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 2, // 2   18
-   1, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 1, 1, 1, 2, // 3   17
-   1, 0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 2, // 4   16
-   1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 1, 1, 1, 2, // 5   15
-   1, 0, 1, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, // 6   14
-   1, 0, 1, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 2, // 7   13
-   1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, // 8   12
-   1, 1, 1, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, // 9   11
-   1, 1, 1, 1, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, // 10  10
-   1, 1, 1, 1, 0, 0, 3, 3, 3, 3, 0, 0, 0, 0, 0, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, // 11  9
-   1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, // 12  8
-   1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 3, 3, 3, 3, 3, // 13  7
-   1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, // 14  6
-   1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 3, 3, 3, 3, 3, // 15  5
-   1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 3, 3, 3, 3, 3, // 16  4
-   1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 3, 3, 3, 3, 3, // 17  3
-   1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 3, 3, 3, 3, 3, // 18  2
-   1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 3, 3, 3, 3, 3, // 19  1
-   1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, // 20  0
-];
-
-const XO:i8 = -15;
-
-/// For each level square says the floor height.
-const levelFloor:[i8;29*21] = [
-// 0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28
-  40,40,40,40,40,40,40,40,48,40,48,40,40,40,40,40,40,48,40,48,40,48,40,48,48,24,24,26,28, // 0  20
-  40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,40, 2, 2, 2,40,32,32,32,32,32,32,32,48, 2, 2, 2,26, // 1  19
-  40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,40, 2, 2, 2,40, 0, 0, 0, 0, 0,32,32,40, 2, 2, 2,26, // 2  18
-  40,16,12, 8, 4, 0,48, 0, 0, 0, 0, 0,24, 2,24, 8,24, 0, 0, 9, 9, 0,28,32,48, 2, 2, 2,24, // 3  17
-  40,20,48,48,48,48,48, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 9, 0,24,32,40, 0, 0, 0,24, // 4  16
-  40,24,48,40,40,40,40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,20,32,48, 0, 0, 0,24, // 5  15
-  40,28,32,32,32,32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 8,12,16,32,40, 0, 0, 0,24, // 6  14
-  40,32,32,32,32,32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,32,32,32,32,32,32,48, 0, 0, 0,24, // 7  13
-  40, 0,48,40,40,40,40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,32,-3,-8,-8,-5,-2, 0, 0, 0, 0,24, // 8  12
-  40, 0,-3,-8,-8,-8,32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,32,-3,-8,-8, 0, 0, 0, 0, 0, 0,24, // 9  11
-  40, 0,-6,-8,-8,-8,32, 0, 0, 0,48,48, 0,48,48, 0, 0,36,32,36,-8,-8, 0, 0, 0, 0, 0, 0,24, // 10 10
-  40, 0,48,-8,-8,-8,32,32,32,32,40, 1, 0, 1,40,32,32,32,32,32,-8,-8, 0, 0, 0, 0, 0, 0,24, // 11 9
-  40, 0,48,-8,-8,-8,-8,-8,XO,XO,XO, 0, 0, 0,XO,XO,-8,36,32,36,-8,-8, 0, 0, 0, 0, 0, 0,24, // 12 8
-  40, 0,48,-8,-8,-8,-8,-8,XO,XO,XO, 0, 0, 0,XO,XO,-8,-8,-8,-8,-8,-8, 0, 0, 0, 0, 0, 0,24, // 13 7
-  40, 0,48, 0,-2,-2, 0,-8,XO,XO,XO, 0, 0, 0,XO,XO, 0, 0, 0, 0, 0, 0, 0, 0,24,24,10,24,24, // 14 6
-  40, 0, 0, 0,-2,-2, 0, 0, 0, 0, 8, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,24, 0, 0, 0,24, // 15 5
-  40, 0, 0, 0,-2,-2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,24, 0, 0, 0,24, // 16 4
-  40,24,48,-2,-2,-2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,24, // 17 3
-   0,24,48,48,-2,-2,-2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,24, // 18 2
-   0,24,48,48,48,-2,-2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,24, 0, 0, 0,24, // 19 1
-   0,24,24,24,32,32,32,27,24,27,30,34,36,38,36,34,36,34,34,32,32,33,37,39,24,24,24,24,24  // 20 0
-];
+/// Replaces `lights` with a flickering light at each torch of the level.
+pub(crate) fn placeLights(level:&RclLevel, lights:&mut RclLights) {
+  lights.clear();
+  for placement in level.sprites.iter().filter(|placement| SPRITE_NAMES[placement.image] == "torch") {
+    lights.add(RclLight::flickering(placement.position, TORCH_LIGHT_RADIUS, 6, 2));
+  }
+}
 
-// TODO: personal: wtf is this?
-// const heightLevelColors:[u8;8] = [ HUE(10), HUE(0) - 3, HUE(4), HUE(1) ];
-
-// #undef XO
-
-const XT:i8 = 127; // helper to keep a big number two-characters, for formatting
-
-/// For each level square says the ceiling height.
-const levelCeiling:[i8;29*21] = [
-// 0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28
-  40,40,40,40,40,40,40,XT,XT,XT,XT,36,40,40,40,40,40,36,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 0  20
-  40,50,50,50,45,40,20,XT,XT,XT,XT,36,40,30,30,30,XT,36,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 1  19
-  40,50,50,50,45,40,20,XT,XT,XT,XT,36,40,30,30,30,XT,36,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 2  18
-  40,50,50,50,45,40,48,XT,XT,XT,XT,36,24,24,24,24,24,36,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 3  17
-  40,50,48,48,48,48,47,XT,XT,XT,XT,36,36,36,36,36,36,36,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 4  16
-  40,50,48,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 5  15
-  40,50,48,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 6  14
-  40,50,48,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 7  13
-  40,50,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 8  12
-  40,40,16,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 9  11
-  40,30,16,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 10 10
-  40,25,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 11 9
-  40,20,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT, // 12 8
-  40,18,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,24,24,24,24,24,24, // 13 7
-  40,18,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,24,24,24,24,24,24, // 14 6
-  40,18,16,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,24,24,25,27,29,24, // 15 5
-  40,18,16,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,24,24,25,27,29,24, // 16 4
-  40,24,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,24,24,25,27,29,24, // 17 3
-  XT,24,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,24,24,25,27,29,24, // 18 2
-  XT,24,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,24,24,25,27,29,24, // 19 1
-  XT,24,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,XT,24,24,24,24,24,24  // 20 0
+/// Sprite images by the names the level file uses.
+pub(crate) const SPRITE_NAMES:[&str;6] = [ "statue", "npc", "tree", "grass", "barrel", "torch" ];
+pub(crate) const SPRITE_IMAGES:[RclSpriteImages;6] = [
+  RclSpriteImages::still(&[RclImage::Palette(&spriteStatue)]),
+  RclSpriteImages::still(&[RclImage::Palette(&spriteNPC)]),
+  RclSpriteImages::still(&[RclImage::Palette(&spriteTree)]),
+  RclSpriteImages::still(&[RclImage::Palette(&spriteGrass)]),
+  RclSpriteImages::still(&[RclImage::Palette(&spriteBarrel)]),
+  // the torch flickers
+  RclSpriteImages::animated(&[RclImage::Palette(&spriteTorch2), RclImage::Palette(&spriteTorch1)], 4),
 ];
 
-// #undef XT
-
 const SPRITE_ARRAY_LENGTH:usize = 2 + 32 * 32;
 
 const texture1:[u8;2+32*32] = [ 32, 32 // width, height
@@ -1208,93 +1102,147 @@ const spriteTorch2:[u8;2+32*32] = [ 32, 32 // width, height
   ,0x8f,0x8f
 ];
 
-const textures:[&[u8];5] = [ &texture1, &texture2, &texture3, &texture4, &texture5 ];
+pub(crate) const textures:[RclImage;5] = [
+  RclImage::Palette(&texture1), RclImage::Palette(&texture2), RclImage::Palette(&texture3),
+  RclImage::Palette(&texture4), RclImage::Palette(&texture5),
+];
 
-// #define
-#[inline(always)]
-fn isWithinMap(x:i16, y:i16) -> Option<u16> {
-  if
-    x >= 0 && x < LEVEL_X_RES as i16 &&
-    y >= 0 && y < LEVEL_Y_RES as i16
-  {
-    Some((LEVEL_Y_RES - y as u8 - 1) as u16 * LEVEL_X_RES as u16 + x as u16)
-  } else {
-    None
+const SKY_WIDTH:usize = 128;
+const SKY_HEIGHT:usize = 32;
+
+/// Sky panorama, seen twice around the view: a gradient from the top down to
+/// the horizon with clouds of interpolated noise, wrapping around horizontally.
+static SKY_IMAGE:[u8; 4 + SKY_WIDTH * SKY_HEIGHT * 4] = skyImage();
+
+static SKY_TEXTURE:RclImage = RclImage::Rgba(&SKY_IMAGE);
+
+const fn skyImage() -> [u8; 4 + SKY_WIDTH * SKY_HEIGHT * 4] {
+  const CELL:usize = 16;
+  const fn noise(x:usize, y:usize) -> u32 {
+    let h = ((x % (SKY_WIDTH / CELL)) as u32).wrapping_mul(73856093) ^ (y as u32).wrapping_mul(19349663);
+    (h ^ (h >> 13)).wrapping_mul(0x5bd1e995) >> 24
   }
-}
 
-fn textureAt(x:i16, y:i16) -> RCL_Unit {
-  match isWithinMap(x, y) {
-    Some(index) => levelTexture[index as usize] as RCL_Unit,
-    None => 0,
+  let mut data = [0u8; 4 + SKY_WIDTH * SKY_HEIGHT * 4];
+  data[1] = SKY_WIDTH as u8;
+  data[3] = SKY_HEIGHT as u8;
+
+  let mut y = 0;
+  while y < SKY_HEIGHT {
+    let mut x = 0;
+    while x < SKY_WIDTH {
+      let (cx, cy, fx, fy) = (x / CELL, y / (CELL / 2), (x % CELL) as u32, (y % (CELL / 2)) as u32);
+      let top = noise(cx, cy) * (CELL as u32 - fx) + noise(cx + 1, cy) * fx;
+      let bottom = noise(cx, cy + 1) * (CELL as u32 - fx) + noise(cx + 1, cy + 1) * fx;
+      let cloud = (top * (CELL as u32 / 2 - fy) + bottom * fy) / (CELL as u32 * CELL as u32 / 2);
+      let cloud = if cloud > 140 { (cloud - 140) * 2 } else { 0 };
+
+      let t = (y * 255 / (SKY_HEIGHT - 1)) as u32;
+      let blue = [40 + 130 * t / 255, 80 + 120 * t / 255, 170 + 60 * t / 255];
+
+      let i = 4 + (y * SKY_WIDTH + x) * 4;
+      let mut c = 0;
+      while c < 3 {
+        data[i + c] = ((blue[c] * (255 - cloud) + 240 * cloud) / 255) as u8;
+        c += 1;
+      }
+      data[i + 3] = 0xFF;
+      x += 1;
+    }
+    y += 1;
   }
+  data
 }
 
-static mut FC:u8 = 0;
+/// The level of the demo, with a lift that moves with the frame count.
+pub(crate) struct Demo1Level {
+  pub level:RclLevel,
+  pub liftFrame:u8,
+}
 
-pub fn floorHeightAt(x:i16, y:i16) -> RCL_Unit {
-  if x == 6 && (y == 13 || y == 14) { // moving lift
-    RCL_absVal(-(((unsafe { FC } % 64) + 32) as RCL_Unit * (RCL_UNITS_PER_SQUARE / 8)) as RCL_Unit)
-  } else {
-    match isWithinMap(x, y) {
-      Some(index) => levelFloor[index as usize] as RCL_Unit * (RCL_UNITS_PER_SQUARE / 8),
-      None => 0,
-    }
+impl Demo1Level {
+  pub const fn new() -> Demo1Level {
+    Demo1Level { level: RclLevel::new(), liftFrame: 0 }
+  }
+
+  pub fn load() -> Demo1Level {
+    let level = RclLevel::parse(DEMO1_LEVEL, &SPRITE_NAMES, textures.len() as u8)
+      .unwrap_or_else(|error| panic!("demo1.level:{}", error));
+    Demo1Level { level, liftFrame: 0 }
   }
 }
 
-pub fn ceilingHeightAt(x:i16, y:i16) -> RCL_Unit {
-  match isWithinMap(x, y) {
-    Some(index) => levelCeiling[index as usize] as RCL_Unit * (RCL_UNITS_PER_SQUARE / 8),
-    None => 127 * (RCL_UNITS_PER_SQUARE / 8),
+impl RclMap for Demo1Level {
+  fn floorHeight(&self, x:i16, y:i16) -> RCL_Unit {
+    if x == 6 && (y == 13 || y == 14) { // moving lift
+      RCL_absVal(-(((self.liftFrame % 64) + 32) as RCL_Unit * (RCL_UNITS_PER_SQUARE / 8)) as RCL_Unit)
+    } else {
+      self.level.floorHeight(x, y)
+    }
   }
+
+  fn hasCeiling(&self) -> bool { self.level.hasCeiling() }
+
+  fn ceilingHeight(&self, x:i16, y:i16) -> RCL_Unit { self.level.ceilingHeight(x, y) }
+
+  fn texture(&self, x:i16, y:i16) -> RCL_Unit { self.level.texture(x, y) }
+
+  fn floorTexture(&self, x:i16, y:i16) -> RCL_Unit { self.level.floorTexture(x, y) }
+
+  fn ceilingTexture(&self, x:i16, y:i16) -> RCL_Unit { self.level.ceilingTexture(x, y) }
+
+  fn isSky(&self, x:i16, y:i16) -> bool { self.level.isSky(x, y) }
+
+  fn ambientLight(&self, x:i16, y:i16) -> RCL_Unit { self.level.ambientLight(x, y) }
+
+  fn hasDoors(&self) -> bool { self.level.hasDoors() }
 }
 
-#[inline]
-fn distanceToIntensity(distance:RCL_Unit) -> i8 {
-  (8 - distance / (RCL_UNITS_PER_SQUARE / 2)) as i8
+/// Lighting of a sprite, by the square it stands in like the pixels around it.
+pub(crate) fn spriteShade(general:&RCL_General, map:&dyn RclMap, position:RCL_Vector2D, depth:RCL_Unit) -> RclShade {
+  let light = map.ambientLight(
+    RCL_divRoundDown(position.x, RCL_UNITS_PER_SQUARE) as i16,
+    RCL_divRoundDown(position.y, RCL_UNITS_PER_SQUARE) as i16
+  );
+  general.lights.shade(light, position, depth)
 }
 
 // Function for drawing a single pixel (like a fragment shader in OpenGL). Bottleneck => should be as fast as possible.
 #[inline]
 pub fn pixelFunc(general:&mut RCL_General, pixel:&RCL_PixelInfo) {
-  if pixel.position.y == MIDDLE_ROW as RCL_Unit {
+  if pixel.position.y == (general.screen.height() / 2) as RCL_Unit {
     if let Some(p) = general.zBuffer.get_mut(pixel.position.x as usize){
       *p = pixel.depth;
     }
   }
 
-  let mut color:u8;
+  let mut color:RGBA;
 
-  let mut intensity = distanceToIntensity(pixel.depth);
+  let mut light = pixel.light;
+  // where the pixel is in the level, for the lights around
+  let position;
 
   if pixel.isWall {
     if pixel.hit.direction == 0 || pixel.hit.direction == 2 {
-      intensity -= 2;
+      light -= 2;
     }
 
     color = if RCL_COMPUTE_WALL_TEXCOORDS {
-      sampleImage(&textures[pixel.hit.type_ as usize], pixel.texCoords.x, pixel.texCoords.y)
+      textures[pixel.hit.type_ as usize].sample(pixel.texCoords.x, pixel.texCoords.y)
     } else {
-      textures[pixel.hit.type_ as usize][2]
+      textures[pixel.hit.type_ as usize].pixel(0, 0)
     };
+    position = pixel.hit.position;
+  } else if pixel.isSky {
+    // neither lit nor in the fog
+    color = SKY_TEXTURE.sample(pixel.texCoords.x * 2, pixel.texCoords.y);
+    general.screen.drawPixel(pixel.position.x as i16, pixel.position.y as i16, color);
+    return;
   } else {
-    color =
-      if !pixel.isFloor {
-        18
-      } else {
-        if RCL_COMPUTE_FLOOR_TEXCOORDS == 1 {
-          if pixel.height == RCL_FLOOR_TEXCOORDS_HEIGHT {
-            sampleImage(&texture5, pixel.texCoords.x / 2, pixel.texCoords.y / 2)
-          } else {
-            HUE(1)
-          }
-        } else {
-          HUE(10)
-        }
-      };
+    color = textures[pixel.texture as usize].sample(pixel.texCoords.x, pixel.texCoords.y);
+    position = pixel.texCoords;
   }
-  color = addIntensity(color,intensity);
+  color = general.lights.shade(light, position, pixel.depth).apply(color);
 
-  putSubsampledPixel(&mut general.screenBuffer, pixel.position.x as u8, pixel.position.y as u8,color);
+  general.screen.drawPixel(pixel.position.x as i16, pixel.position.y as i16, color);
 }
\ No newline at end of file
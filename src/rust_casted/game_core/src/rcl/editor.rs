@@ -0,0 +1,483 @@
+// Level editor for the raycaster: a top-down grid of the level on the left, painted with the mouse, and a live view
+// of it on the right, walked with the arrows like demo1. Saved levels go to the host, see `Platform::save_level`.
+
+use core::fmt::Write;
+use crate::canvas::{Canvas, Rect};
+use crate::controls::Controls;
+use crate::timing::{Time, TARGET_FPS};
+use crate::{RGBA, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::rcl::*;
+use crate::rcl::rcl_general::*;
+use crate::rcl::rcl_level::*;
+use crate::rcl::rcl_sprites::*;
+use crate::rcl::demo1::{self, textures, DEMO1_CONFIG, SPRITE_NAMES, SPRITE_IMAGES};
+
+const PANEL_WIDTH:i32 = SCREEN_WIDTH as i32 / 2;
+/// The view is scaled up by the largest whole factor that fits.
+const PREVIEW:Rect = Rect::new(PANEL_WIDTH, 0, PANEL_WIDTH, SCREEN_HEIGHT as i32 / 2);
+const TOOLBAR:Rect = Rect::new(PANEL_WIDTH + 8, PREVIEW.y + PREVIEW.height + 8, PANEL_WIDTH - 16, SCREEN_HEIGHT as i32 / 2 - 16);
+
+const BUTTON_WIDTH:i32 = 76;
+const BUTTON_HEIGHT:i32 = 16;
+const LESS:Rect = Rect::new(TOOLBAR.x, TOOLBAR.y + 24, BUTTON_HEIGHT, BUTTON_HEIGHT);
+const MORE:Rect = Rect::new(TOOLBAR.x + 160, TOOLBAR.y + 24, BUTTON_HEIGHT, BUTTON_HEIGHT);
+const SAVE:Rect = Rect::new(TOOLBAR.x + TOOLBAR.width - BUTTON_WIDTH, TOOLBAR.y + 24, BUTTON_WIDTH, BUTTON_HEIGHT);
+
+const BACKGROUND:RGBA = RGBA::rgba(0x20, 0x20, 0x28, 0xFF);
+const BUTTON:RGBA = RGBA::rgba(0x40, 0x40, 0x50, 0xFF);
+const SELECTED:RGBA = RGBA::rgba(0x60, 0x60, 0xA0, 0xFF);
+const TEXT:RGBA = RGBA::rgba(0xE0, 0xE0, 0xE0, 0xFF);
+const SKY_COLOR:RGBA = RGBA::rgba(0x50, 0x90, 0xE0, 0xFF);
+const START_COLOR:RGBA = RGBA::rgba(0xFF, 0xE0, 0x40, 0xFF);
+
+/// Size of new sprites, the demo ones are between 100 and 300.
+const SPRITE_PIXEL_SIZE:RCL_Unit = 120;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Tool {
+	Floor,
+	Ceiling,
+	Texture,
+	Sprite,
+	Start,
+}
+
+impl Tool {
+	const ALL:[Tool; 5] = [Tool::Floor, Tool::Ceiling, Tool::Texture, Tool::Sprite, Tool::Start];
+
+	const fn name(self) -> &'static str {
+		match self {
+			Tool::Floor => "floor",
+			Tool::Ceiling => "ceiling",
+			Tool::Texture => "texture",
+			Tool::Sprite => "sprite",
+			Tool::Start => "start",
+		}
+	}
+
+	const fn button(index:usize) -> Rect {
+		Rect::new(TOOLBAR.x + index as i32 * (BUTTON_WIDTH + 4), TOOLBAR.y, BUTTON_WIDTH, BUTTON_HEIGHT)
+	}
+}
+
+/// Gray getting lighter with the height, in eighths of a square.
+fn heightColor(height:i8) -> RGBA {
+	let value = (64 + height as i32 * 2).clamp(0, 0xFF) as u8;
+	RGBA::rgba(value, value, value, 0xFF)
+}
+
+pub(crate) struct Editor {
+	level:RclLevel,
+	/// Placed again when the level sprites change.
+	sprites:RclSprites,
+	player:Player,
+	general:RCL_General,
+	renderer:RCL_Renderer_Global,
+	tool:Tool,
+	// What the tools paint
+	floor:i8,
+	ceiling:i8,
+	texture:u8,
+	image:usize,
+	lmbWasPressed:bool,
+	rmbWasPressed:bool,
+	/// Text of the level to save, until the host takes it.
+	saved:Option<String>,
+	status:&'static str,
+	/// Average colors of the textures and the sprite images, for the grid.
+	textureColors:Vec<RGBA>,
+	spriteColors:Vec<RGBA>,
+}
+
+impl Editor {
+	pub const fn new() -> Editor {
+		Editor {
+			level: RclLevel::new(),
+			sprites: RclSprites::new(),
+			player: Player::new(),
+			general: RCL_General::new(),
+			renderer: RCL_Renderer_Global::new(DEMO1_CONFIG),
+			tool: Tool::Floor,
+			floor: HEIGHT_STEPS as i8,
+			ceiling: 3 * HEIGHT_STEPS as i8,
+			texture: 1,
+			image: 0,
+			lmbWasPressed: false,
+			rmbWasPressed: false,
+			saved: None,
+			status: "",
+			textureColors: Vec::new(),
+			spriteColors: Vec::new(),
+		}
+	}
+
+	/// Starts with the demo1 level, unless one was opened before.
+	pub fn init(&mut self) {
+		self.general.initGeneral(self.renderer.config());
+		self.general.defaultConstraints.maxHits = 8;
+		self.general.defaultConstraints.maxSteps = 15;
+		self.general.lights.fog = demo1::DEMO1_FOG;
+		self.textureColors = textures.iter().map(|texture| texture.averageColor()).collect();
+		self.spriteColors = SPRITE_IMAGES.iter().map(|images| images.images[0].averageColor()).collect();
+
+		if self.level.width == 0 {
+			self.level = demo1::Demo1Level::load().level;
+		}
+		self.restart();
+	}
+
+	pub fn open(&mut self, text:&str) -> Result<(), RclLevelError> {
+		self.level = RclLevel::parse(text, &SPRITE_NAMES, textures.len() as u8)?;
+		self.restart();
+		Ok(())
+	}
+
+	/// The level text once it was saved, for the host to store.
+	pub fn takeSaved(&mut self) -> Option<String> {
+		self.saved.take()
+	}
+
+	pub fn setSaved(&mut self, saved:bool) {
+		self.status = if saved { "saved" } else { "saving is not supported here" };
+	}
+
+	/// Sprites and torch lights of the level for the preview.
+	fn placeSprites(&mut self) {
+		demo1::placeSprites(&self.level, &mut self.sprites);
+		demo1::placeLights(&self.level, &mut self.general.lights);
+	}
+
+	/// Puts the preview camera at the start of the level.
+	fn restart(&mut self) {
+		self.placeSprites();
+		self.player = Player::new();
+		self.player.setPosition(self.level.startPosition.x, self.level.startPosition.y);
+		self.player.mCamera.direction = self.level.startDirection;
+	}
+
+	pub fn update(&mut self, image:&mut [RGBA], time:&Time, controls:&Controls) {
+		let pointer = (controls.pointer_x() as i32, controls.pointer_y() as i32);
+		let clicked = controls.lmb.is_pressed() && !self.lmbWasPressed;
+		let rightClicked = controls.rmb.is_pressed() && !self.rmbWasPressed;
+		self.lmbWasPressed = controls.lmb.is_pressed();
+		self.rmbWasPressed = controls.rmb.is_pressed();
+
+		let steps = controls.wheel().round() as i32;
+		if steps != 0 {
+			self.adjust(steps);
+		}
+
+		if clicked {
+			self.clickToolbar(pointer);
+		}
+		if let Some((x, y, position)) = self.squareAt(pointer) {
+			self.edit(x, y, position, controls.lmb.is_pressed(), clicked, rightClicked);
+		}
+
+		self.walk(time, controls);
+		self.draw(image, time, pointer);
+	}
+
+	fn adjust(&mut self, steps:i32) {
+		match self.tool {
+			Tool::Floor => self.floor = (self.floor as i32 + steps).clamp(-64, 64) as i8,
+			// Above the highest ceiling is the sky
+			Tool::Ceiling => self.ceiling = (self.ceiling as i32 + steps).clamp(-64, SKY as i32) as i8,
+			Tool::Texture => self.texture = (self.texture as i32 + steps).rem_euclid(textures.len() as i32) as u8,
+			Tool::Sprite => self.image = (self.image as i32 + steps).rem_euclid(SPRITE_NAMES.len() as i32) as usize,
+			Tool::Start => {},
+		}
+	}
+
+	fn clickToolbar(&mut self, (x, y):(i32, i32)) {
+		for (i, &tool) in Tool::ALL.iter().enumerate() {
+			if Tool::button(i).contains(x, y) {
+				self.tool = tool;
+			}
+		}
+		if LESS.contains(x, y) {
+			self.adjust(-1);
+		} else if MORE.contains(x, y) {
+			self.adjust(1);
+		} else if SAVE.contains(x, y) {
+			self.saved = Some(self.level.toText(&SPRITE_NAMES));
+			self.status = "saving";
+		}
+	}
+
+	/// Size of a square in pixels and the top left corner of the grid, centered in the left half.
+	fn grid(&self) -> (i32, i32, i32) {
+		let (width, height) = (self.level.width.max(1) as i32, self.level.height.max(1) as i32);
+		let cell = (PANEL_WIDTH / width).min(SCREEN_HEIGHT as i32 / height).max(1);
+		(cell, (PANEL_WIDTH - cell * width) / 2, (SCREEN_HEIGHT as i32 - cell * height) / 2)
+	}
+
+	/// Square under the pointer and the position of the pointer in the level.
+	fn squareAt(&self, (x, y):(i32, i32)) -> Option<(i16, i16, RCL_Vector2D)> {
+		let (cell, left, top) = self.grid();
+		let (width, height) = (self.level.width as i32 * cell, self.level.height as i32 * cell);
+		let (x, y) = (x - left, y - top);
+		if x < 0 || y < 0 || x >= width || y >= height {
+			return None;
+		}
+		// The pixel center, the level y grows upwards
+		let position = RCL_Vector2D {
+			x: (2 * x + 1) as RCL_Unit * RCL_UNITS_PER_SQUARE / (2 * cell) as RCL_Unit,
+			y: (2 * (height - y) - 1) as RCL_Unit * RCL_UNITS_PER_SQUARE / (2 * cell) as RCL_Unit,
+		};
+		Some(((x / cell) as i16, (self.level.height as i32 - 1 - y / cell) as i16, position))
+	}
+
+	/// Left paints the square with the value of the tool, right picks the value of the square.
+	fn edit(&mut self, x:i16, y:i16, position:RCL_Vector2D, held:bool, clicked:bool, rightClicked:bool) {
+		let i = match self.level.index(x, y) {
+			Some(i) => i,
+			None => return,
+		};
+		match self.tool {
+			Tool::Floor if held => self.level.floor[i] = self.floor,
+			Tool::Floor if rightClicked => self.floor = self.level.floor[i],
+			Tool::Ceiling if held => self.level.ceiling[i] = self.ceiling,
+			Tool::Ceiling if rightClicked => self.ceiling = self.level.ceiling[i],
+			Tool::Texture if held => self.level.texture[i] = self.texture,
+			Tool::Texture if rightClicked => self.texture = self.level.texture[i],
+			Tool::Sprite if clicked => {
+				self.level.sprites.push(RclSpritePlacement {
+					image: self.image,
+					position,
+					// Standing on the floor
+					height: self.level.floorHeight(x, y) + RCL_UNITS_PER_SQUARE / 2,
+					pixelSize: SPRITE_PIXEL_SIZE,
+				});
+				self.placeSprites();
+			},
+			// Removes the nearest sprite within half a square
+			Tool::Sprite if rightClicked => {
+				let nearest = self.level.sprites.iter()
+					.map(|sprite| RCL_Vector2D::dist(sprite.position, position, RCL_DistApprox::None))
+					.enumerate()
+					.filter(|&(_, distance)| distance < RCL_UNITS_PER_SQUARE / 2)
+					.min_by_key(|&(_, distance)| distance);
+				if let Some((nearest, _)) = nearest {
+					self.level.sprites.remove(nearest);
+					self.placeSprites();
+				}
+			},
+			// Looking where the preview camera looks
+			Tool::Start if clicked => {
+				let degrees = (RCL_wrap(self.player.mCamera.direction, RCL_UNITS_PER_SQUARE) * 360 + RCL_UNITS_PER_SQUARE / 2) / RCL_UNITS_PER_SQUARE;
+				self.level.startPosition = position;
+				self.level.startDirection = RCL_degreesToUnitsAngle(degrees as i16);
+				self.restart();
+			},
+			_ => {},
+		}
+	}
+
+	fn walk(&mut self, time:&Time, controls:&Controls) {
+		let moveDirection =
+			if controls.arrow_up.is_pressed() { 1 } else if controls.arrow_down.is_pressed() { -1 } else { 0 };
+		let rotationDirection =
+			if controls.arrow_right.is_pressed() { 1 } else if controls.arrow_left.is_pressed() { -1 } else { 0 };
+
+		// milliseconds since the previous update
+		let dt = time.ticks().get() * 1000 / TARGET_FPS as u32;
+		self.player.update(&mut self.renderer, &self.level, moveDirection, false, rotationDirection, controls.space.is_pressed(), 0, true, dt);
+	}
+
+	fn draw(&mut self, image:&mut [RGBA], time:&Time, pointer:(i32, i32)) {
+		self.general.lights.update(time.tick_number());
+		self.renderer.RCL_renderComplex(&mut self.general, self.player.mCamera.clone(), &self.level);
+		let level = &self.level;
+		self.sprites.draw(&mut self.general, self.renderer.config(), &self.player.mCamera, time.tick_number(),
+			|general, position, depth| demo1::spriteShade(general, level, position, depth));
+		self.general.screen.projectInto(image, PREVIEW);
+
+		let mut canvas = Canvas::screen(image);
+		canvas.fill_rect(Rect::new(0, 0, PANEL_WIDTH, SCREEN_HEIGHT as i32), BACKGROUND);
+		canvas.fill_rect(Rect::new(PANEL_WIDTH, PREVIEW.bottom(), PANEL_WIDTH, SCREEN_HEIGHT as i32 - PREVIEW.bottom()), BACKGROUND);
+		self.drawGrid(&mut canvas, pointer);
+		self.drawToolbar(&mut canvas);
+	}
+
+	fn drawGrid(&self, canvas:&mut Canvas, pointer:(i32, i32)) {
+		let (cell, left, top) = self.grid();
+
+		for row in 0..self.level.height as i32 {
+			for x in 0..self.level.width as i32 {
+				let i = (row * self.level.width as i32 + x) as usize;
+				let color = match self.tool {
+					Tool::Ceiling if self.level.ceiling[i] == SKY => SKY_COLOR,
+					Tool::Ceiling => heightColor(self.level.ceiling[i]),
+					Tool::Texture => self.textureColors[self.level.texture[i] as usize],
+					_ => heightColor(self.level.floor[i]),
+				};
+				// The background shows through as the grid lines
+				canvas.fill_rect(Rect::new(left + x * cell, top + row * cell, cell - 1, cell - 1), color);
+			}
+		}
+
+		let height = self.level.height as i32 * cell;
+		let toScreen = |position:RCL_Vector2D| (
+			left + position.x * cell / RCL_UNITS_PER_SQUARE,
+			top + height - position.y * cell / RCL_UNITS_PER_SQUARE,
+		);
+
+		for sprite in &self.level.sprites {
+			let (x, y) = toScreen(sprite.position);
+			canvas.fill_rect(Rect::new(x - 3, y - 3, 6, 6), RGBA::rgba(0, 0, 0, 0xFF));
+			canvas.fill_rect(Rect::new(x - 2, y - 2, 4, 4), self.spriteColors[sprite.image]);
+		}
+
+		// Start and the preview camera, with where they look
+		for &(position, direction, color) in &[
+			(self.level.startPosition, self.level.startDirection, START_COLOR),
+			(self.player.mCamera.position, self.player.mCamera.direction, TEXT),
+		] {
+			let (x, y) = toScreen(position);
+			let look = RCL_angleToDirection(direction);
+			canvas.stroke_rect(Rect::new(x - 3, y - 3, 7, 7), color);
+			canvas.line(x, y, x + look.x * cell / RCL_UNITS_PER_SQUARE, y - look.y * cell / RCL_UNITS_PER_SQUARE, color);
+		}
+
+		if let Some((x, y, _)) = self.squareAt(pointer) {
+			let row = self.level.height as i32 - 1 - y as i32;
+			canvas.stroke_rect(Rect::new(left + x as i32 * cell - 1, top + row * cell - 1, cell + 1, cell + 1), TEXT);
+		}
+	}
+
+	fn drawToolbar(&self, canvas:&mut Canvas) {
+		let button = |canvas:&mut Canvas, rect:Rect, text:&str, selected:bool| {
+			canvas.fill_rect(rect, if selected { SELECTED } else { BUTTON });
+			canvas.draw_text(rect.x + 4, rect.y + 5, text, TEXT, 1);
+		};
+
+		for (i, &tool) in Tool::ALL.iter().enumerate() {
+			button(canvas, Tool::button(i), tool.name(), tool == self.tool);
+		}
+		button(canvas, LESS, "-", false);
+		button(canvas, MORE, "+", false);
+		button(canvas, SAVE, "save", false);
+
+		let mut value = canvas.text(LESS.right() + 8, LESS.y + 5, TEXT, 1);
+		let _ = match self.tool {
+			Tool::Floor => write!(value, "height {}", self.floor),
+			Tool::Ceiling if self.ceiling == SKY => write!(value, "sky"),
+			Tool::Ceiling => write!(value, "height {}", self.ceiling),
+			Tool::Texture => write!(value, "texture {}", self.texture),
+			Tool::Sprite => write!(value, "{}", SPRITE_NAMES[self.image]),
+			Tool::Start => write!(value, "click to place"),
+		};
+
+		let _ = write!(canvas.text(TOOLBAR.x, TOOLBAR.y + 56, TEXT, 1),
+			"{}\n\nlmb: paint, place    rmb: pick, remove\nwheel, -, +: change the value\narrows: walk    space: jump\n\nheights are in eighths of a square",
+			self.status,
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{State, Scene, Input, SCREEN_SPACE};
+
+	const LEVEL:&str = "
+size = 4 3
+start = 0.5 0.5 0
+[floor]
+0 0 0 0
+0 0 0 0
+0 0 0 0
+[ceiling]
+- - - -
+16 16 16 16
+16 16 16 16
+[texture]
+0 0 0 0
+0 0 0 0
+0 0 0 0
+";
+
+	/// Ticks `state` with the pointer at `x`, `y`, 10ms after the previous tick.
+	fn tick(state:&mut State, frame:&mut i32, x:i32, y:i32, lmb:bool, rmb:bool, image:&mut [RGBA]) {
+		let mut input = Input::new();
+		input.abs_x = x as f32;
+		input.abs_y = y as f32;
+		input.lmb = lmb;
+		input.rmb = rmb;
+		state.tick(*frame * 10, &input, image);
+		*frame += 1;
+	}
+
+	fn click(state:&mut State, frame:&mut i32, x:i32, y:i32, right:bool, image:&mut [RGBA]) {
+		tick(state, frame, x, y, !right, right, image);
+		tick(state, frame, x, y, false, false, image);
+	}
+
+	#[test]
+	fn paints_places_and_saves() {
+		// The game is too large for the test thread stack
+		std::thread::Builder::new().stack_size(1 << 28).spawn(|| {
+			let mut state = Box::new(State::new());
+			let mut image = vec![RGBA::zeroed(); SCREEN_SPACE as usize];
+			let mut frame = 0;
+			state.set_scene(Scene::Editor);
+			assert!(state.open_level("size = 4").is_err());
+			state.open_level(LEVEL).unwrap();
+			state.init();
+			// The second tick has the time stamp of the first, which already counted a whole tick
+			for _ in 0..2 {
+				tick(&mut state, &mut frame, 0, 0, false, false, &mut image);
+			}
+
+			// Center of the square x, y
+			let (cell, left, top) = state.editor.grid();
+			let at = |x:i32, y:i32| (left + x * cell + cell / 2, top + (2 - y) * cell + cell / 2);
+			let center = |rect:Rect| (rect.x + rect.width / 2, rect.y + rect.height / 2);
+
+			let (x, y) = at(3, 2);
+			click(&mut state, &mut frame, x, y, false, &mut image);
+			assert_eq!(state.editor.level.floorHeight(3, 2), RCL_UNITS_PER_SQUARE);
+			assert!(image[(y as usize) * SCREEN_WIDTH as usize + x as usize].to_rgb32() != BACKGROUND.to_rgb32());
+			// Something is rendered in the preview
+			let preview = PREVIEW.y as usize * SCREEN_WIDTH as usize + PREVIEW.x as usize + PREVIEW.width as usize / 2;
+			assert!(image[preview..preview + 100].iter().any(|c| c.to_rgb32() != 0));
+
+			// Picks the sky and paints it
+			let (bx, by) = center(Tool::button(1));
+			click(&mut state, &mut frame, bx, by, false, &mut image);
+			let (x, y) = at(0, 2);
+			click(&mut state, &mut frame, x, y, true, &mut image);
+			let (x, y) = at(1, 0);
+			click(&mut state, &mut frame, x, y, false, &mut image);
+			assert_eq!(state.editor.level.ceiling[state.editor.level.index(1, 0).unwrap()], SKY);
+
+			// Places two sprites and removes one
+			let (bx, by) = center(Tool::button(3));
+			click(&mut state, &mut frame, bx, by, false, &mut image);
+			let (mx, my) = center(MORE);
+			click(&mut state, &mut frame, mx, my, false, &mut image);
+			for &(x, y) in &[at(2, 1), at(0, 1)] {
+				click(&mut state, &mut frame, x, y, false, &mut image);
+			}
+			let (x, y) = at(0, 1);
+			click(&mut state, &mut frame, x, y, true, &mut image);
+			assert_eq!(state.editor.level.sprites.len(), 1);
+			assert_eq!(state.editor.sprites.len(), 1);
+
+			assert!(state.take_saved_level().is_none());
+			let (sx, sy) = center(SAVE);
+			click(&mut state, &mut frame, sx, sy, false, &mut image);
+			let saved = state.take_saved_level().expect("saved level");
+			state.level_saved(true);
+			assert_eq!(state.editor.status, "saved");
+
+			let level = RclLevel::parse(&saved, &SPRITE_NAMES, textures.len() as u8).unwrap();
+			assert_eq!(level.floorHeight(3, 2), RCL_UNITS_PER_SQUARE);
+			assert_eq!(level.ceiling[level.index(1, 0).unwrap()], SKY);
+			let sprite = level.sprites[0];
+			assert_eq!(SPRITE_NAMES[sprite.image], "npc");
+			assert_eq!((sprite.position.x / RCL_UNITS_PER_SQUARE, sprite.position.y / RCL_UNITS_PER_SQUARE), (2, 1));
+		}).unwrap().join().unwrap();
+	}
+}
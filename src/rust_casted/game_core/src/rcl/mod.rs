@@ -1,3 +1,7 @@
+#![allow(non_snake_case, non_camel_case_types, non_upper_case_globals)] // keeps the names of the C original
+// The library is ported whole: its unused parts, argument lists and doc layout stay as in the C original
+#![allow(dead_code, clippy::too_many_arguments, clippy::tabs_in_doc_comments)]
+
 /*
   raycastlib (RCL) - Small C header-only raycasting library for embedded and
   low performance computers, such as Arduino. Only uses integer math and stdint
@@ -34,62 +38,42 @@
 
 // #include <stdint.h>
 
+mod rcl_switch;
+pub mod rcl_general;
+pub mod rcl_level;
+pub mod rcl_sprites;
+pub mod rcl_doors;
+pub mod rcl_lights;
+pub mod demo1;
+pub mod editor;
+
 const HITS_ARRAY_LIMIT:u8 = 16;
 
-const CAMERA_RESOLUTION_X:u16 = 320; // 20
-const CAMERA_RESOLUTION_Y:u16 = 200; // 15
-const _RCL_camResYLimit:u16 = CAMERA_RESOLUTION_Y - 1;
-const halfResY:u16 = CAMERA_RESOLUTION_Y / 2;
+// Largest resolution a `RCL_Config` may pick, the buffers are allocated for it
+pub const RCL_MAX_RESOLUTION_X:u16 = crate::SCREEN_WIDTH / 2;
+pub const RCL_MAX_RESOLUTION_Y:u16 = crate::SCREEN_HEIGHT / 2;
 
 use crate::profile;
 use std::fmt::{Display, Formatter, Error};
 use tiny_lib::util::NonZeroSignum;
-use crate::rcl_switch::*;
-use crate::rcl_general::RCL_General;
+use self::rcl_switch::*;
+use self::rcl_general::RCL_General;
 
 const RCL_RAYCAST_TINY:bool = false; // Turns on super efficient version of this library. Only use if neccesarry, looks ugly. Also not done yet.
 
 // Smallest spatial unit, there is RCL_UNITS_PER_SQUARE units in a square's length. This effectively serves the purpose of a fixed-point arithmetic.
-pub(crate) type RCL_Unit = i32; // i16 if not RCL_RAYCAST_TINY
-type RCL_Unit_unsigned = u32; // u16 if not RCL_RAYCAST_TINY
-const RCL_INFINITY:RCL_Unit = if RCL_RAYCAST_TINY { 2000000000 } else { 30000 };
-pub(crate) const RCL_UNITS_PER_SQUARE:RCL_Unit = if RCL_RAYCAST_TINY { 1024 } else { 32 }; // Number of RCL_Units in a side of a spatial square.
-const RCL_USE_DIST_APPROX:u8 =
-	if RCL_RAYCAST_TINY {
-		0 // What distance approximation to use:
-		// 0: none (compute full Euclidean distance)
-		// 1: accurate approximation
-		// 2: octagonal approximation (LQ)
-	} else {
-		2
-	}
-;
+pub(crate) type RCL_Unit = i32; // i16 if RCL_RAYCAST_TINY
+type RCL_Unit_unsigned = u32; // u16 if RCL_RAYCAST_TINY
+const RCL_INFINITY:RCL_Unit = if RCL_RAYCAST_TINY { 30000 } else { 2000000000 };
+pub(crate) const RCL_UNITS_PER_SQUARE:RCL_Unit = if RCL_RAYCAST_TINY { 32 } else { 1024 }; // Number of RCL_Units in a side of a spatial square.
 
 pub const RCL_COMPUTE_WALL_TEXCOORDS:bool = true;
 
-const RCL_COMPUTE_FLOOR_TEXCOORDS:bool = false;
-
-pub const RCL_FLOOR_TEXCOORDS_HEIGHT:RCL_Unit = 0;
-// If RCL_COMPUTE_FLOOR_TEXCOORDS == 1, this says for what height level the texture coords will be computed for (for simplicity/performance only one level is allowed).
-
 const RCL_USE_COS_LUT:u8 = 0; // type of look up table for cos function: 0: none (compute) 1: 64 items 2: 128 items
 
-const RCL_RECTILINEAR:bool = true; // Whether to use rectilinear perspective (normally used), or curvilinear perspective (fish eye).
-
-const RCL_TEXTURE_VERTICAL_STRETCH:u8 = 1; // Whether textures should be stretched to wall height (possibly slightly slower if on).
-
-const RCL_COMPUTE_FLOOR_DEPTH:bool = true; // Whether depth should be computed for floor pixels - turns this off if not needed.
-
-const  RCL_COMPUTE_CEILING_DEPTH:bool = true; // As RCL_COMPUTE_FLOOR_DEPTH but for ceiling.
-
 const RCL_ROLL_TEXTURE_COORDS:bool = true; // Says whether rolling doors should also roll the texture coordinates along (mostly desired for doors).
 
-const RCL_VERTICAL_FOV:RCL_Unit = RCL_UNITS_PER_SQUARE / 2;
-const RCL_HORIZONTAL_FOV:RCL_Unit = RCL_UNITS_PER_SQUARE / 4;
-const RCL_HORIZONTAL_FOV_HALF:RCL_Unit = RCL_HORIZONTAL_FOV / 2;
-
 const RCL_CAMERA_COLL_RADIUS:RCL_Unit = RCL_UNITS_PER_SQUARE / 4;
-const RCL_CAMERA_COLL_HEIGHT_BELOW:RCL_Unit = RCL_UNITS_PER_SQUARE;
 const RCL_CAMERA_COLL_HEIGHT_ABOVE:RCL_Unit = RCL_UNITS_PER_SQUARE / 3;
 const RCL_CAMERA_COLL_STEP_HEIGHT:RCL_Unit = RCL_UNITS_PER_SQUARE / 2;
 
@@ -101,6 +85,123 @@ const RCL_HORIZON_DEPTH:RCL_Unit = 11 * RCL_UNITS_PER_SQUARE;
 
 const RCL_VERTICAL_DEPTH_MULTIPLY:RCL_Unit = 2; // Defines a multiplier of height difference when approximating floor/ceil depth.
 
+/// What distance approximation to use.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RCL_DistApprox {
+	/// Compute full Euclidean distance.
+	None,
+	Accurate,
+	/// Octagonal approximation (LQ).
+	Octagonal,
+}
+
+/// Renderer settings, the `#define`s of the C library which a level or demo can pick at runtime.
+#[derive(Copy, Clone, Debug)]
+pub struct RCL_Config {
+	/// Rendered pixels, up to RCL_MAX_RESOLUTION_X x RCL_MAX_RESOLUTION_Y.
+	pub resolutionX:u16,
+	pub resolutionY:u16,
+	/// Angle in RCL_Units, RCL_UNITS_PER_SQUARE is a full turn.
+	pub horizontalFov:RCL_Unit,
+	/// Not an actual angle, just linear approximation.
+	pub verticalFov:RCL_Unit,
+	/// Whether to use rectilinear perspective (normally used), or curvilinear perspective (fish eye).
+	pub rectilinear:bool,
+	pub distApprox:RCL_DistApprox,
+	/// Whether depth should be computed for floor pixels - turn this off if not needed.
+	pub computeFloorDepth:bool,
+	pub computeCeilingDepth:bool,
+	/// Whether texture coordinates should be computed for floor pixels, at every
+	/// floor height (a lot slower).
+	pub computeFloorTexCoords:bool,
+	/// Same for ceiling pixels, these also tell the sky apart.
+	pub computeCeilingTexCoords:bool,
+	/// Whether textures should be stretched to wall height (possibly slightly slower if on).
+	pub textureVerticalStretch:bool,
+	/// How far below the camera its collision box reaches, steps lower than that are walked down.
+	pub cameraCollHeightBelow:RCL_Unit,
+}
+
+impl RCL_Config {
+	/// Defaults of the C library at a quarter of the game screen, `Screen::project` scales it up.
+	pub const fn new() -> RCL_Config {
+		RCL_Config {
+			resolutionX: crate::SCREEN_WIDTH / 4,
+			resolutionY: crate::SCREEN_HEIGHT / 4,
+			horizontalFov: RCL_UNITS_PER_SQUARE / 4,
+			verticalFov: RCL_UNITS_PER_SQUARE / 2,
+			rectilinear: true,
+			distApprox: if RCL_RAYCAST_TINY { RCL_DistApprox::None } else { RCL_DistApprox::Octagonal },
+			computeFloorDepth: true,
+			computeCeilingDepth: true,
+			computeFloorTexCoords: false,
+			computeCeilingTexCoords: false,
+			textureVerticalStretch: true,
+			cameraCollHeightBelow: RCL_UNITS_PER_SQUARE,
+		}
+	}
+
+	/// Limits the resolution to what the buffers can hold.
+	pub const fn clamped(mut self) -> RCL_Config {
+		if self.resolutionX > RCL_MAX_RESOLUTION_X { self.resolutionX = RCL_MAX_RESOLUTION_X; }
+		if self.resolutionY > RCL_MAX_RESOLUTION_Y { self.resolutionY = RCL_MAX_RESOLUTION_Y; }
+		if self.resolutionX < 1 { self.resolutionX = 1; }
+		if self.resolutionY < 2 { self.resolutionY = 2; }
+		self
+	}
+
+	/// Computes the change in size of an object due to perspective.
+	pub fn perspectiveScale(&self, originalSize:RCL_Unit, distance:RCL_Unit) -> RCL_Unit {
+		profile::RCL_perspectiveScale.call();
+
+		if distance != 0 {
+			(originalSize * RCL_UNITS_PER_SQUARE) / RCL_nonZero((self.verticalFov * 2 * distance) / RCL_UNITS_PER_SQUARE)
+		} else {
+			0
+		}
+	}
+
+	fn perspectiveScaleInverse(&self, originalSize:RCL_Unit, scaledSize:RCL_Unit) -> RCL_Unit {
+		if scaledSize != 0 {
+			(originalSize * RCL_UNITS_PER_SQUARE + RCL_UNITS_PER_SQUARE / 2) /
+				// ^ take the middle
+				RCL_nonZero((self.verticalFov * 2 * scaledSize) / RCL_UNITS_PER_SQUARE)
+		} else {
+			RCL_INFINITY
+		}
+	}
+
+	// Maps a single point in the world to the screen (2D position + depth).
+	pub fn mapToScreen(&self, worldPosition:RCL_Vector2D, height:RCL_Unit, camera:&RCL_Camera) -> RCL_PixelInfo {
+		let mut result:RCL_PixelInfo = RCL_PixelInfo::zeroed();
+
+		let mut toPoint:RCL_Vector2D = RCL_Vector2D {
+			x: worldPosition.x - camera.position.x,
+			y: worldPosition.y - camera.position.y
+		};
+
+		let middleColumn = self.resolutionX / 2;
+
+		// rotate the point
+
+		let cos = RCL_cosInt(camera.direction);
+		let sin = RCL_sinInt(camera.direction);
+
+		let tmp = toPoint.x;
+
+		toPoint.x = (toPoint.x * cos - toPoint.y * sin) / RCL_UNITS_PER_SQUARE;
+		toPoint.y = (tmp * sin + toPoint.y * cos) / RCL_UNITS_PER_SQUARE;
+
+		result.depth = toPoint.x;
+
+		result.position.x = middleColumn as RCL_Unit + (-toPoint.y * middleColumn as RCL_Unit) / RCL_nonZero(result.depth);
+
+		result.position.y = (self.resolutionY / 2) as RCL_Unit - (self.resolutionY as RCL_Unit * self.perspectiveScale(height - camera.height,result.depth)) / RCL_UNITS_PER_SQUARE + camera.shear as RCL_Unit;
+
+		result
+	}
+}
+
 /// To prevent zero divisions.
 // TODO: NonZeroU8 and so on
 fn RCL_nonZero(v:RCL_Unit) -> RCL_Unit {
@@ -117,11 +218,12 @@ fn RCL_zeroClamp(x:RCL_Unit) -> RCL_Unit {
 		0
 	}
 }
+// Branch hints are internal to the standard library, so these are no-ops like the C version without __builtin_expect
 fn RCL_likely(cond:bool) -> bool {
-	unsafe { core::intrinsics::likely(cond) }
+	cond
 }
 fn RCL_unlikely(cond:bool) -> bool {
-	unsafe { core::intrinsics::unlikely(cond) }
+	cond
 }
 
 /// Position in 2D space.
@@ -133,60 +235,59 @@ pub struct RCL_Vector2D {
 impl RCL_Vector2D {
 	pub const ZERO:RCL_Vector2D = RCL_Vector2D { x: 0, y: 0 };
 
-	fn len(self) -> RCL_Unit {
-		unsafe { profile::RCL_len.call(); }
+	fn len(self, approx:RCL_DistApprox) -> RCL_Unit {
+		profile::RCL_len.call();
 
-		return RCL_Vector2D::dist(RCL_Vector2D::ZERO, self);
+		RCL_Vector2D::dist(RCL_Vector2D::ZERO, self, approx)
 	}
 
 	/// Normalizes given vector to have RCL_UNITS_PER_SQUARE length.
-	fn normalize(self) -> RCL_Vector2D {
-		unsafe { profile::RCL_normalize.call(); }
+	fn normalize(self, approx:RCL_DistApprox) -> RCL_Vector2D {
+		profile::RCL_normalize.call();
 
-		let l = RCL_nonZero(self.len());
+		let l = RCL_nonZero(self.len(approx));
 
-		return RCL_Vector2D {
+		RCL_Vector2D {
 			x: (self.x * RCL_UNITS_PER_SQUARE) / l,
 			y: (self.y * RCL_UNITS_PER_SQUARE) / l,
-		};
+		}
 	}
 
 	/// Computes a cos of an angle between two vectors.
-	fn angleCos(mut v1:RCL_Vector2D, mut v2:RCL_Vector2D) -> RCL_Unit {
-		unsafe { profile::RCL_vectorsAngleCos.call(); }
+	fn angleCos(mut v1:RCL_Vector2D, mut v2:RCL_Vector2D, approx:RCL_DistApprox) -> RCL_Unit {
+		profile::RCL_vectorsAngleCos.call();
 
-		v1 = v1.normalize();
-		v2 = v2.normalize();
+		v1 = v1.normalize(approx);
+		v2 = v2.normalize(approx);
 
-		return (v1.x * v2.x + v1.y * v2.y) / RCL_UNITS_PER_SQUARE;
+		(v1.x * v2.x + v1.y * v2.y) / RCL_UNITS_PER_SQUARE
 	}
 
-	fn dist(p1:RCL_Vector2D, p2:RCL_Vector2D) -> RCL_Unit {
-		unsafe { profile::RCL_dist.call(); }
+	fn dist(p1:RCL_Vector2D, p2:RCL_Vector2D, approx:RCL_DistApprox) -> RCL_Unit {
+		profile::RCL_dist.call();
 
 		let mut dx:RCL_Unit = p2.x - p1.x;
 		let mut dy:RCL_Unit = p2.y - p1.y;
 
-		if RCL_USE_DIST_APPROX == 2 {
+		if approx == RCL_DistApprox::Octagonal {
 			// octagonal approximation
 
 			dx = RCL_absVal(dx);
 			dy = RCL_absVal(dy);
 
-			return if dy > dx {
+			if dy > dx {
 				dx / 2 + dy
 			} else {
 				dy / 2 + dx
-			};
-		} else if RCL_USE_DIST_APPROX == 1 {
+			}
+		} else if approx == RCL_DistApprox::Accurate {
 			// more accurate approximation
 
 			// dx = ((dx < 0) * 2 - 1) * dx;
 			// dy = ((dy < 0) * 2 - 1) * dy;
-			// dx = if dx < 0 { 1 } else { -1 } * dx;
-			// dy = if dy < 0 { 1 } else { -1 } * dy;
-			if dx > 0 { dx *= -1; }
-			if dy > 0 { dy *= -1; }
+			// ^ this makes both negative and the result with them, a and b are meant to be the larger and the smaller leg
+			dx = RCL_absVal(dx);
+			dy = RCL_absVal(dy);
 
 			let a;
 			let b;
@@ -204,12 +305,12 @@ impl RCL_Vector2D {
 				result -= (5 * a) / 128;
 			}
 
-			return result;
+			result
 		} else {
 			dx = dx * dx;
 			dy = dy * dy;
 
-			return RCL_sqrtInt(dx + dy) as RCL_Unit;
+			RCL_sqrtInt(dx + dy) as RCL_Unit
 		}
 	}
 }
@@ -269,8 +370,9 @@ impl RCL_HitResult {
 	fn _RCL_makeInfiniteHit(&mut self, ray:&RCL_Ray) {
 		self.distance = RCL_UNITS_PER_SQUARE * RCL_UNITS_PER_SQUARE;
 		// ^ horizon is at infinity, but we can't use too big infinity (RCL_INFINITY) because it would overflow in the following mult.
-		self.position.x = (ray.direction.x * self.distance) / RCL_UNITS_PER_SQUARE;
-		self.position.y = (ray.direction.y * self.distance) / RCL_UNITS_PER_SQUARE;
+		self.position.x = ray.start.x + (ray.direction.x * self.distance) / RCL_UNITS_PER_SQUARE;
+		self.position.y = ray.start.y + (ray.direction.y * self.distance) / RCL_UNITS_PER_SQUARE;
+		// ^ along the ray like the other hits, so the horizon's floor texture coordinates follow it
 
 		self.direction = 0;
 		self.textureCoord = 0;
@@ -278,6 +380,18 @@ impl RCL_HitResult {
 		self.doorRoll = 0;
 		self.type_ = 0;
 	}
+
+	/// Whether the hit is on the part of a rolling door that is rolled away, so the ray goes on through it.
+	#[inline]
+	fn _RCL_isUnrolled(&self) -> bool {
+		let texCoordMod:RCL_Unit = self.textureCoord % RCL_UNITS_PER_SQUARE;
+
+		if self.doorRoll >= 0 {
+			self.doorRoll > texCoordMod
+		} else {
+			texCoordMod > RCL_UNITS_PER_SQUARE + self.doorRoll
+		}
+	}
 }
 impl Display for RCL_HitResult {
 	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
@@ -294,8 +408,7 @@ impl Display for RCL_HitResult {
 pub struct RCL_Camera {
   pub position:RCL_Vector2D,
   pub direction:RCL_Unit,
-  pub resolution:RCL_Vector2D,
-	// from -camera.resolution.y to +camera.resolution.y
+	// from -resolutionY to +resolutionY of the config, the resolution is set by `RCL_Config`
   pub shear:i16, /// Shear offset in pixels (0 => no shear), can simulate looking up/down.
 	pub height:RCL_Unit,
 }
@@ -305,8 +418,6 @@ impl RCL_Camera {
 		self.position.x = 0;
 		self.position.y = 0;
 		self.direction = 0;
-//		self.resolution.x = 20;
-//		self.resolution.y = 15;
 		self.shear = 0;
 		self.height = RCL_UNITS_PER_SQUARE;
 	}
@@ -319,7 +430,6 @@ impl Display for RCL_Camera {
 		writeln!(f, "  height: {}", self.height)?;
 		writeln!(f, "  direction: {}", self.direction)?;
 		writeln!(f, "  shear: {}", self.shear)
-		// writeln!(f, "  resolution: {} x {}", self.resolution.x, self.resolution.y);
 	}
 }
 
@@ -343,6 +453,17 @@ pub struct RCL_PixelInfo {
 	pub hit:RCL_HitResult,
 	/// Normalized (0 to RCL_UNITS_PER_SQUARE - 1) texture coordinates.
 	pub texCoords:RCL_Vector2D,
+	/// Only for floor and ceiling pixels with texture coordinates, the map's
+	/// floor or ceiling texture of the square the pixel shows.
+	pub texture:RCL_Unit,
+	/// Only for ceiling pixels with texture coordinates, whether they show the
+	/// sky. Its texture coordinates go around the view (x, RCL_UNITS_PER_SQUARE
+	/// is a full turn) and down from the top of the screen (y, 0) to the horizon
+	/// (y, RCL_UNITS_PER_SQUARE - 1).
+	pub isSky:bool,
+	/// Ambient light of the square lighting the pixel (see RclMap::ambientLight),
+	/// the one a floor or ceiling pixel shows or the one in front of a wall.
+	pub light:RCL_Unit,
 }
 
 impl RCL_PixelInfo {
@@ -357,6 +478,9 @@ impl RCL_PixelInfo {
 			height: 0,
 			hit: RCL_HitResult::zeroed(),
 			texCoords: RCL_Vector2D::ZERO,
+			texture: 0,
+			isSky: false,
+			light: 0,
 		}
 	}
 }
@@ -387,19 +511,65 @@ impl RCL_RayConstraints {
 }
 
 /**
-  Function used to retrieve some information about cells of the rendered scene.
-  It should return a characteristic of given square as an integer (e.g. square
-  height, texture index, ...) - between squares that return different numbers
-  there is considered to be a collision.
+  Level data of the rendered scene, one value per square. It takes the place
+  of the array functions of the C original (floorHeightFunc, ceilingHeightFunc,
+  typeFunc and rollFunc). The map is only borrowed for a render or a camera
+  move, so it can live on the heap, be edited between frames and keep its own
+  state.
 
-  This function should be as fast as possible as it will typically be called
+  These methods should be as fast as possible as they will typically be called
   very often.
 */
-pub(crate) type RCL_ArrayFunction = fn(x:i16, y:i16) -> RCL_Unit;
-/*
-  TODO: maybe array functions should be replaced by defines of funtion names
-  like with pixelFunc? Could be more efficient than function pointers.
+pub trait RclMap {
+	/// Floor height of the square in RCL_Units, walls are squares with a high floor.
+	fn floorHeight(&self, x:i16, y:i16) -> RCL_Unit;
+
+	/// Whether the map has a ceiling at all, without one no ceiling is rendered
+	/// and the camera doesn't collide with it.
+	fn hasCeiling(&self) -> bool { false }
+
+	/// Ceiling height of the square in RCL_Units, only read if `hasCeiling`.
+	fn ceilingHeight(&self, _x:i16, _y:i16) -> RCL_Unit { RCL_INFINITY }
+
+	/// Type of the square that ends up in the hit results, e.g. its texture index.
+	fn texture(&self, _x:i16, _y:i16) -> RCL_Unit { 0 }
+
+	/// Floor texture of the square, given to floor pixels with texture coordinates.
+	fn floorTexture(&self, _x:i16, _y:i16) -> RCL_Unit { 0 }
+
+	/// Ceiling texture of the square, given to ceiling pixels with texture
+	/// coordinates.
+	fn ceilingTexture(&self, _x:i16, _y:i16) -> RCL_Unit { 0 }
+
+	/// Whether the square is open to the sky, its ceiling pixels then show the
+	/// sky instead of a ceiling texture. A map without a ceiling is all sky.
+	fn isSky(&self, _x:i16, _y:i16) -> bool { false }
+
+	/// Ambient light of the square for the pixel function, e.g. intensity steps
+	/// to brighten or darken it by.
+	fn ambientLight(&self, _x:i16, _y:i16) -> RCL_Unit { 0 }
+
+	/// Whether the map has rolling doors, only then `doorRoll` is read.
+	fn hasDoors(&self) -> bool { false }
+
+	/// Door roll of the square in RCL_Units (0 = no roll, RCL_UNITS_PER_SQUARE =
+	/// full roll right, -RCL_UNITS_PER_SQUARE = full roll left). The camera
+	/// doesn't move into a partly rolled door, a fully rolled one is passed by
+	/// its floor and ceiling heights like any square.
+	fn doorRoll(&self, _x:i16, _y:i16) -> RCL_Unit { 0 }
+
+	/// Free data of the square for the map's own use (flags, light, ...), the
+	/// renderer doesn't read it.
+	fn metadata(&self, _x:i16, _y:i16) -> u32 { 0 }
+}
+
+/**
+  Function used to tell the squares of a map apart for the ray caster. It
+  should return a characteristic of given square as an integer (e.g. square
+  height) - between squares that return different numbers there is considered
+  to be a collision.
 */
+pub(crate) type RCL_ArrayFunction = fn(map:&dyn RclMap, x:i16, y:i16) -> RCL_Unit;
 
 /**
   Function that renders a single pixel at the display. It is handed an info
@@ -409,12 +579,12 @@ pub(crate) type RCL_ArrayFunction = fn(x:i16, y:i16) -> RCL_Unit;
   very often.
 */
 pub type RCL_PixelFunction = fn(general:&mut RCL_General, info:&RCL_PixelInfo);
-type RCL_ColumnFunction = fn(&mut RCL_Renderer_Global, general:&mut RCL_General, hits:&[RCL_HitResult], x:u16, ray:RCL_Ray);
+type RCL_ColumnFunction = fn(&mut RCL_Renderer_Global, general:&mut RCL_General, map:&dyn RclMap, hits:&[RCL_HitResult], x:u16, ray:RCL_Ray);
 
 //=============================================================================
 // privates
 
-fn _RCL_UNUSED<T>(what:T) {}
+fn _RCL_UNUSED<T>(_what:T) {}
 
 const CLT_SIZE:usize =
 	if RCL_USE_COS_LUT == 1 {
@@ -428,7 +598,7 @@ const CLT_SIZE:usize =
 	} else {
 		0
 	};
-const cosLUT:&'static [RCL_Unit] =
+const cosLUT:&[RCL_Unit] =
 	if RCL_USE_COS_LUT == 1 {
 		if RCL_RAYCAST_TINY {
 			&[ 16,14,11,6,0,-6,-11,-14,-15,-14,-11,-6,0,6,11,14 ]
@@ -456,7 +626,7 @@ const cosLUT:&'static [RCL_Unit] =
 	};
 
 pub fn RCL_clamp(value:RCL_Unit, valueMin:RCL_Unit, valueMax:RCL_Unit) -> RCL_Unit {
-	unsafe { profile::RCL_clamp.call(); }
+	profile::RCL_clamp.call();
 
 	debug_assert!(valueMin <= valueMax);
 
@@ -473,34 +643,34 @@ pub fn RCL_clamp(value:RCL_Unit, valueMin:RCL_Unit, valueMax:RCL_Unit) -> RCL_Un
 
 #[inline]
 pub fn RCL_absVal(value:RCL_Unit) -> RCL_Unit {
-	unsafe { profile::RCL_absVal.call(); }
+	profile::RCL_absVal.call();
 	// return value * (((value >= 0) << 1) - 1);
 	// TODO:
-	return value.abs()
+	value.abs()
 }
 
 /// Like mod, but behaves differently for negative values.
 #[inline]
 pub fn RCL_wrap(value:RCL_Unit, mod_:RCL_Unit) -> RCL_Unit {
-	unsafe { profile::RCL_wrap.call(); }
+	profile::RCL_wrap.call();
 	let cmp:RCL_Unit = if value < 0 { 1 } else { 0 };
-	return cmp * mod_ + (value % mod_) - cmp;
+	cmp * mod_ + (value % mod_) - cmp
 }
 
 /// Performs division, rounding down, NOT towards zero.
 #[inline]
 fn RCL_divRoundDown(value:RCL_Unit, divisor:RCL_Unit) -> RCL_Unit{
-	unsafe { profile::RCL_divRoundDown.call(); }
+	profile::RCL_divRoundDown.call();
 
-	return value / divisor - (if value >= 0 { 0 } else { 1 });
+	value / divisor - (if value >= 0 { 0 } else { 1 })
 }
 
 /// Bhaskara's cosine approximation formula
 // #define
 fn trigHelper(x:RCL_Unit) -> RCL_Unit { // TODO: X's type is yet unknown
-	(RCL_UNITS_PER_SQUARE *
+	RCL_UNITS_PER_SQUARE *
 		(RCL_UNITS_PER_SQUARE / 2 * RCL_UNITS_PER_SQUARE / 2 - 4 * x * x) /
-		(RCL_UNITS_PER_SQUARE / 2 * RCL_UNITS_PER_SQUARE / 2 +     x * x))
+		(RCL_UNITS_PER_SQUARE / 2 * RCL_UNITS_PER_SQUARE / 2 +     x * x)
 }
 
 /**
@@ -511,27 +681,27 @@ Cos function.
 				RCL_UNITS_PER_SQUARE)
 */
 fn RCL_cosInt(input:RCL_Unit) -> RCL_Unit {
-	unsafe { profile::RCL_cosInt.call(); }
+	profile::RCL_cosInt.call();
 
 	let input = RCL_wrap(input,RCL_UNITS_PER_SQUARE);
 
 	if RCL_USE_COS_LUT == 1 {
-		return if RCL_RAYCAST_TINY {
+		if RCL_RAYCAST_TINY {
 			cosLUT[input as usize]
 		} else {
 			cosLUT[(input / 16) as usize]
 		}
 	} else if RCL_USE_COS_LUT == 2 {
-		return cosLUT[(input / 8) as usize];
+		cosLUT[(input / 8) as usize]
 	} else {
 		if input < RCL_UNITS_PER_SQUARE / 4 {
-			return trigHelper(input);
+			trigHelper(input)
 		} else if input < RCL_UNITS_PER_SQUARE / 2 {
-			return -1 * trigHelper(RCL_UNITS_PER_SQUARE / 2 - input);
+			-trigHelper(RCL_UNITS_PER_SQUARE / 2 - input)
 		} else if input < 3 * RCL_UNITS_PER_SQUARE / 4 {
-			return -1 * trigHelper(input - RCL_UNITS_PER_SQUARE / 2);
+			-trigHelper(input - RCL_UNITS_PER_SQUARE / 2)
 		} else {
-			return trigHelper(RCL_UNITS_PER_SQUARE - input);
+			trigHelper(RCL_UNITS_PER_SQUARE - input)
 		}
 	}
 }
@@ -539,20 +709,20 @@ fn RCL_cosInt(input:RCL_Unit) -> RCL_Unit {
 // #undef trigHelper
 
 fn RCL_sinInt(input:RCL_Unit) -> RCL_Unit {
-	return RCL_cosInt(input - RCL_UNITS_PER_SQUARE / 4);
+	RCL_cosInt(input - RCL_UNITS_PER_SQUARE / 4)
 }
 
 pub fn RCL_angleToDirection(angle:RCL_Unit) -> RCL_Vector2D {
-	unsafe { profile::RCL_angleToDirection.call() };
+	profile::RCL_angleToDirection.call();
 
-	return RCL_Vector2D {
+	RCL_Vector2D {
 		x:      RCL_cosInt(angle),
-		y: -1 * RCL_sinInt(angle),
-	};
+		y: -RCL_sinInt(angle),
+	}
 }
 
 fn RCL_sqrtInt(value:RCL_Unit) -> RCL_Unit_unsigned {
-	unsafe { profile::RCL_sqrtInt.call() };
+	profile::RCL_sqrtInt.call();
 
 	let mut result:RCL_Unit_unsigned = 0;
 	let mut a:RCL_Unit_unsigned = value as RCL_Unit_unsigned;
@@ -565,95 +735,35 @@ fn RCL_sqrtInt(value:RCL_Unit) -> RCL_Unit_unsigned {
 	while b != 0 {
 		if a >= result + b {
 			a -= result + b;
-			result = result +  2 * b;
+			result += 2 * b;
 		}
 
 		b >>= 2;
 		result >>= 1;
 	}
 
-	return result;
+	result
 }
 
 #[inline]
 fn RCL_pointIsLeftOfRay(point:RCL_Vector2D, ray:RCL_Ray) -> bool {
-	unsafe { profile::RCL_pointIsLeftOfRay.call(); }
+	profile::RCL_pointIsLeftOfRay.call();
 
 	let dX = point.x - ray.start.x;
 	let dY = point.y - ray.start.y;
-	return (ray.direction.x * dY - ray.direction.y * dX) > 0;
+	(ray.direction.x * dY - ray.direction.y * dX) > 0
 	// ^ Z component of cross-product
 }
 
 
 ///  Converts an angle in whole degrees to an angle in RCL_Units that this library uses.
 fn RCL_degreesToUnitsAngle(degrees:i16) -> RCL_Unit {
-	return (degrees as RCL_Unit * RCL_UNITS_PER_SQUARE) / 360;
-}
-
-/// Computes the change in size of an object due to perspective.
-pub fn RCL_perspectiveScale(originalSize:RCL_Unit, distance:RCL_Unit) -> RCL_Unit {
-	unsafe { profile::RCL_perspectiveScale.call(); }
-
-	return if distance != 0 {
-		(originalSize * RCL_UNITS_PER_SQUARE) / ((RCL_VERTICAL_FOV * 2 * distance) / RCL_UNITS_PER_SQUARE)
-	} else {
-		0
-	}
-}
-
-fn RCL_perspectiveScaleInverse(originalSize:RCL_Unit, scaledSize:RCL_Unit) -> RCL_Unit {
-	if scaledSize != 0 {
-		(originalSize * RCL_UNITS_PER_SQUARE + RCL_UNITS_PER_SQUARE / 2) /
-			// ^ take the middle
-			((RCL_VERTICAL_FOV * 2 * scaledSize) / RCL_UNITS_PER_SQUARE)
-	} else {
-		RCL_INFINITY
-	}
-}
-
-// Maps a single point in the world to the screen (2D position + depth).
-pub fn RCL_mapToScreen(worldPosition:RCL_Vector2D, height:RCL_Unit, camera:&RCL_Camera) -> RCL_PixelInfo {
-	let mut result:RCL_PixelInfo = RCL_PixelInfo {
-		position: RCL_Vector2D::ZERO,
-		isWall: false,
-		isFloor: false,
-		isHorizon: false,
-		depth: 0,
-		wallHeight: 0,
-		height: 0,
-		hit: RCL_HitResult::zeroed(),
-		texCoords: RCL_Vector2D::ZERO,
-	};
-
-	let mut toPoint:RCL_Vector2D = RCL_Vector2D {
-		x: worldPosition.x - camera.position.x,
-		y: worldPosition.y - camera.position.y
-	};
-
-	let middleColumn = CAMERA_RESOLUTION_X / 2;
-
-	// rotate the point
-
-	let cos = RCL_cosInt(camera.direction);
-	let sin = RCL_sinInt(camera.direction);
-
-	let tmp = toPoint.x;
-
-	toPoint.x = (toPoint.x * cos - toPoint.y * sin) / RCL_UNITS_PER_SQUARE;
-	toPoint.y = (tmp * sin + toPoint.y * cos) / RCL_UNITS_PER_SQUARE;
-
-	result.depth = toPoint.x;
-
-	result.position.x = middleColumn as RCL_Unit + (-1 * toPoint.y * middleColumn as RCL_Unit) / RCL_nonZero(result.depth);
-
-	result.position.y = halfResY as RCL_Unit - (CAMERA_RESOLUTION_Y as RCL_Unit * RCL_perspectiveScale(height - camera.height,result.depth)) / RCL_UNITS_PER_SQUARE + camera.shear as RCL_Unit;
-
-	return result;
+	(degrees as RCL_Unit * RCL_UNITS_PER_SQUARE) / 360
 }
 
 // global helper variables, for precomputing stuff etc.
 pub struct RCL_Renderer_Global {
+	config:RCL_Config,
 	_RCL_camera:RCL_Camera,
 	_RCL_horizontalDepthStep:RCL_Unit,
 	_RCL_startFloorHeight:RCL_Unit,
@@ -662,17 +772,16 @@ pub struct RCL_Renderer_Global {
 	_RCL_fHorizontalDepthStart:i16,
 	_RCL_cHorizontalDepthStart:i16,
 	_RCL_cameraHeightScreen:i16,
-	_RCL_rollFunction:Option<RCL_ArrayFunction>, // says door rolling
-	_RCL_floorPixelDistances:Option<[RCL_Unit; CAMERA_RESOLUTION_Y as usize]>,
+	_RCL_floorPixelDistances:Option<[RCL_Unit; RCL_MAX_RESOLUTION_Y as usize]>,
 }
 
 impl RCL_Renderer_Global {
-	pub const fn new() -> RCL_Renderer_Global {
+	pub const fn new(config:RCL_Config) -> RCL_Renderer_Global {
 		RCL_Renderer_Global {
+			config: config.clamped(),
 			_RCL_camera: RCL_Camera {
 				position: RCL_Vector2D::ZERO,
 				direction: 0,
-				resolution: RCL_Vector2D::ZERO,
 				shear: 0,
 				height: 0,
 			},
@@ -683,24 +792,29 @@ impl RCL_Renderer_Global {
 			_RCL_fHorizontalDepthStart: 0,
 			_RCL_cHorizontalDepthStart: 0,
 			_RCL_cameraHeightScreen: 0,
-			_RCL_rollFunction: None,
 			_RCL_floorPixelDistances: None,
 		}
 	}
 
+	#[inline(always)]
+	pub fn config(&self) -> &RCL_Config { &self.config }
+
+	pub fn setConfig(&mut self, config:RCL_Config) {
+		self.config = config.clamped();
+	}
+
 	/**
 		Casts a single ray and returns a list of collisions.
 
-		@param ray ray to be cast, if RCL_RECTILINEAR != 0 then the computed hit
+		@param ray ray to be cast, if the config is rectilinear then the computed hit
 					 distance is divided by the ray direction vector length (to correct
 					 the fish eye effect)
+		@param map map whose squares are hit, its texture and door roll are stored
+					 in the hit results
 		@param arrayFunc function that will be used to determine collisions (hits)
 					 with the ray (squares for which this function returns different values
 					 are considered to have a collision between them), this will typically
 					 be a function returning floor height
-		@param typeFunc optional (can be 0) function - if provided, it will be used
-					 to mark the hit result with the number returned by this function
-					 (it can be e.g. a texture index)
 		@param hitResults array in which the hit results will be stored (has to be
 					 preallocated with at space for at least as many hit results as
 					 maxHits specified with the constraints parameter)
@@ -711,13 +825,13 @@ impl RCL_Renderer_Global {
 	fn RCL_castRayMultiHit(
 		&mut self,
 		ray:RCL_Ray,
+		map:&dyn RclMap,
 		arrayFunc:RCL_ArrayFunction,
-		typeFunc:Option<RCL_ArrayFunction>,
 		hitResults:&mut [RCL_HitResult],
 		hitResultsLen:&mut u8,
 		constraints:RCL_RayConstraints
 	) {
-		unsafe { profile::RCL_castRayMultiHit.call(); }
+		profile::RCL_castRayMultiHit.call();
 
 		assert!(!hitResults.is_empty()); // Should prevent runtime checking
 
@@ -729,14 +843,14 @@ impl RCL_Renderer_Global {
 
 		*hitResultsLen = 0;
 
-		let mut squareType:RCL_Unit = arrayFunc(currentSquare.x as i16, currentSquare.y as i16);
+		let mut squareType:RCL_Unit = arrayFunc(map, currentSquare.x as i16, currentSquare.y as i16);
 
 		// DDA variables
 		let mut nextSideDist = RCL_Vector2D::ZERO; // dist. from start to the next side in given axis
 		let mut step = RCL_Vector2D::ZERO; // -1 or 1 for each axis
 		let mut stepHorizontal:bool = false; // whether the last step was hor. or vert.
 
-		let dirVecLengthNorm = ray.direction.len() * RCL_UNITS_PER_SQUARE;
+		let dirVecLengthNorm = ray.direction.len(self.config.distApprox) * RCL_UNITS_PER_SQUARE;
 
 		let delta = RCL_Vector2D {
 			x: RCL_absVal(dirVecLengthNorm / RCL_nonZero(ray.direction.x)),
@@ -769,8 +883,8 @@ impl RCL_Renderer_Global {
 		let rayDirYRecip = RECIP_SCALE / RCL_nonZero(ray.direction.y);
 		// ^ we precompute reciprocals to avoid divisions in the loop
 
-		for i in 0..constraints.maxSteps {
-			let currentType:RCL_Unit = arrayFunc(currentSquare.x as i16, currentSquare.y as i16);
+		for _ in 0..constraints.maxSteps {
+			let currentType:RCL_Unit = arrayFunc(map, currentSquare.x as i16, currentSquare.y as i16);
 
 			if RCL_unlikely(currentType != squareType) {
 				// collision
@@ -796,7 +910,7 @@ impl RCL_Renderer_Global {
 					// avoid division by multiplying with reciprocal
 					h.position.y = ray.start.y + ((ray.direction.y * diff) * rayDirXRecip) / RECIP_SCALE;
 
-					if RCL_RECTILINEAR {
+					if self.config.rectilinear {
 						// Here we compute the fish eye corrected distance (perpendicular to
 						// the projection plane) as the Euclidean distance divided by the length
 						// of the ray direction vector. This can be computed without actually
@@ -819,30 +933,28 @@ impl RCL_Renderer_Global {
 
 					h.position.x = ray.start.x + ((ray.direction.x * diff) * rayDirYRecip) / RECIP_SCALE;
 
-					if RCL_RECTILINEAR {
+					if self.config.rectilinear {
 						h.distance = (((h.position.y - ray.start.y) / 4) * RCL_UNITS_PER_SQUARE * rayDirYRecip) / (RECIP_SCALE / 4); // "^ / 4" is here to prevent overflow
 					}
 				}
 
-				if !RCL_RECTILINEAR {
-					h.distance = RCL_Vector2D::dist(h.position, ray.start);
+				if !self.config.rectilinear {
+					h.distance = RCL_Vector2D::dist(h.position, ray.start, self.config.distApprox);
 				}
 
-				if let Some(typeFunc) = typeFunc {
-					h.type_ = typeFunc(currentSquare.x as i16, currentSquare.y as i16);
-				}
+				h.type_ = map.texture(currentSquare.x as i16, currentSquare.y as i16);
 
 				if RCL_COMPUTE_WALL_TEXCOORDS {
 					h.textureCoord = match h.direction {
-						0 => RCL_wrap(-1 * h.position.x,RCL_UNITS_PER_SQUARE),
+						0 => RCL_wrap(-h.position.x,RCL_UNITS_PER_SQUARE),
 						1 => RCL_wrap(h.position.y,RCL_UNITS_PER_SQUARE),
 						2 => RCL_wrap(h.position.x,RCL_UNITS_PER_SQUARE),
-						3 => RCL_wrap(-1 * h.position.y,RCL_UNITS_PER_SQUARE),
+						3 => RCL_wrap(-h.position.y,RCL_UNITS_PER_SQUARE),
 						_ => 0,
 					};
 
-					if let Some(roll_function) = self._RCL_rollFunction {
-						h.doorRoll = roll_function(currentSquare.x as i16, currentSquare.y as i16);
+					if map.hasDoors() {
+						h.doorRoll = map.doorRoll(currentSquare.x as i16, currentSquare.y as i16);
 
 						if h.direction == 0 || h.direction == 1 {
 							h.doorRoll *= -1;
@@ -881,8 +993,8 @@ impl RCL_Renderer_Global {
 		Simple-interface function to cast a single ray.
 		@return          The first collision result.
 	*/
-	fn RCL_castRay(&mut self, ray:RCL_Ray, arrayFunc:RCL_ArrayFunction) -> RCL_HitResult {
-		unsafe { profile::RCL_castRay.call(); }
+	fn RCL_castRay(&mut self, ray:RCL_Ray, map:&dyn RclMap, arrayFunc:RCL_ArrayFunction) -> RCL_HitResult {
+		profile::RCL_castRay.call();
 
 		let mut result = [RCL_HitResult::zeroed()];
 		let mut RCL_len = 0;
@@ -891,7 +1003,7 @@ impl RCL_Renderer_Global {
 			maxHits: 1,
 		};
 
-		self.RCL_castRayMultiHit(ray,arrayFunc,None,&mut result,&mut RCL_len,c);
+		self.RCL_castRayMultiHit(ray,map,arrayFunc,&mut result,&mut RCL_len,c);
 
 		let mut result = result[0];
 
@@ -899,18 +1011,18 @@ impl RCL_Renderer_Global {
 			result.distance = -1;
 		}
 
-		return result;
+		result
 	}
 
 	/// Casts rays for given camera view and for each hit calls a user provided function.
 	fn RCL_castRaysMultiHit(
 		&mut self, general: &mut RCL_General, cam:&RCL_Camera,
+		map:&dyn RclMap,
 		arrayFunc:RCL_ArrayFunction,
-		typeFunction:Option<RCL_ArrayFunction>,
 		columnFunc:RCL_ColumnFunction,
 	) {
-		let dir1 = RCL_angleToDirection(cam.direction - RCL_HORIZONTAL_FOV_HALF);
-		let dir2 = RCL_angleToDirection(cam.direction + RCL_HORIZONTAL_FOV_HALF);
+		let dir1 = RCL_angleToDirection(cam.direction - self.config.horizontalFov / 2);
+		let dir2 = RCL_angleToDirection(cam.direction + self.config.horizontalFov / 2);
 
 		let dX = dir2.x - dir1.x;
 		let dY = dir2.y - dir1.y;
@@ -926,17 +1038,17 @@ impl RCL_Renderer_Global {
 		let mut currentDX:RCL_Unit = 0;
 		let mut currentDY:RCL_Unit = 0;
 
-		for i in 0..CAMERA_RESOLUTION_X {
+		for i in 0..self.config.resolutionX {
 			// Here by linearly interpolating the direction vector its length changes,
 			// which in result achieves correcting the fish eye effect (computing
 			// perpendicular distance).
 
-			r.direction.x = dir1.x + currentDX / CAMERA_RESOLUTION_X as RCL_Unit;
-			r.direction.y = dir1.y + currentDY / CAMERA_RESOLUTION_Y as RCL_Unit;
+			r.direction.x = dir1.x + currentDX / self.config.resolutionX as RCL_Unit;
+			r.direction.y = dir1.y + currentDY / self.config.resolutionX as RCL_Unit;
 
-			self.RCL_castRayMultiHit(r, arrayFunc,typeFunction,&mut hits,&mut hitCount, general.defaultConstraints);
+			self.RCL_castRayMultiHit(r, map, arrayFunc,&mut hits,&mut hitCount, general.defaultConstraints);
 
-			columnFunc(self, general, &hits[0..hitCount as usize], i, r);
+			columnFunc(self, general, map, &hits[0..hitCount as usize], i, r);
 
 			currentDX += dX;
 			currentDY += dY;
@@ -944,25 +1056,29 @@ impl RCL_Renderer_Global {
 	}
 
 	/// Helper function that determines intersection with both ceiling and floor.
-	fn _RCL_floorCeilFunction(x:i16, y:i16) -> RCL_Unit {
-		let f = floorHeightFunction(x, y);
+	fn _RCL_floorCeilFunction(map:&dyn RclMap, x:i16, y:i16) -> RCL_Unit {
+		if map.hasDoors() && map.doorRoll(x, y) != 0 {
+			return -1 - ((x & 0x00FF) | ((y & 0x00FF) << 8)) as RCL_Unit;
+			// ^ tells rolled doors apart from the walls around them, rays going through a door hit them
+		}
 
-		match ceilingHeightFunc {
-			None => f,
-			Some(chf) => {
-				let c = chf(x, y);
+		let f = map.floorHeight(x, y);
 
-				if !RCL_RAYCAST_TINY {
-					((f & 0x0000ffff) << 16) | (c & 0x0000ffff)
-				} else {
-					((f & 0x00ff) << 8) | (c & 0x00ff)
-				}
-			},
+		if !map.hasCeiling() {
+			f
+		} else {
+			let c = map.ceilingHeight(x, y);
+
+			if !RCL_RAYCAST_TINY {
+				((f & 0x0000ffff) << 16) | (c & 0x0000ffff)
+			} else {
+				((f & 0x00ff) << 8) | (c & 0x00ff)
+			}
 		}
 	}
 
-	fn _floorHeightNotZeroFunction(x:i16, y:i16) -> RCL_Unit {
-		if floorHeightFunction(x, y) == 0 {
+	fn _floorHeightNotZeroFunction(map:&dyn RclMap, x:i16, y:i16) -> RCL_Unit {
+		if map.floorHeight(x, y) == 0 {
 			0
 		} else {
 			RCL_nonZero(((x & 0x00FF) | ((y & 0x00FF) << 8)) as RCL_Unit)
@@ -970,15 +1086,15 @@ impl RCL_Renderer_Global {
 		}
 	}
 
-	fn RCL_adjustDistance(distance:RCL_Unit, camera:&RCL_Camera, ray:&RCL_Ray) -> RCL_Unit {
+	fn RCL_adjustDistance(&self, distance:RCL_Unit, camera:&RCL_Camera, ray:&RCL_Ray) -> RCL_Unit {
 		/* FIXME/TODO: The adjusted (=orthogonal, camera-space) distance could
 			 possibly be computed more efficiently by not computing Euclidean
 			 distance at all, but rather compute the distance of the collision
 			 point from the projection plane (line). */
 
-		let result = (distance * RCL_Vector2D::angleCos(RCL_angleToDirection(camera.direction), ray.direction)) / RCL_UNITS_PER_SQUARE;
+		let result = (distance * RCL_Vector2D::angleCos(RCL_angleToDirection(camera.direction), ray.direction, self.config.distApprox)) / RCL_UNITS_PER_SQUARE;
 
-		return RCL_nonZero(result);
+		RCL_nonZero(result)
 				// ^ prevent division by zero
 	}
 
@@ -987,6 +1103,7 @@ impl RCL_Renderer_Global {
 	fn _RCL_drawHorizontalColumn(
 		&mut self,
 		general:&mut RCL_General,
+		map:&dyn RclMap,
 		yCurrent:i16,
 		yTo:RCL_Unit,
 		limit1:i16,
@@ -1006,6 +1123,7 @@ impl RCL_Renderer_Global {
 		let mut dy:RCL_Unit = 0;
 
 		pixelInfo.isWall = false;
+		pixelInfo.isSky = false;
 
 		let limit = RCL_clamp(yTo,limit1 as RCL_Unit,limit2 as RCL_Unit) as i16;
 
@@ -1019,11 +1137,7 @@ impl RCL_Renderer_Global {
 
 		if computeDepth { // branch early
 			doDepth = true;
-			if !computeCoords {
-				doCoords = false;
-			} else {
-				doCoords = true;
-			}
+			doCoords = computeCoords;
 		} else {
 			if !computeCoords {
 				doDepth = false;
@@ -1052,21 +1166,65 @@ impl RCL_Renderer_Global {
 				// ^ int comparison is fast, it is not braching! (= test instr.)
 			}
 			if doCoords { // constant condition - compiler should optimize it out
-				let d = self._RCL_floorPixelDistances.unwrap()[i as usize]; // TODO: remove unwrap
-				let d2 = RCL_nonZero(pixelInfo.hit.distance);
-				pixelInfo.texCoords.x = self._RCL_camera.position.x + ((d * dx) / d2);
-				pixelInfo.texCoords.y = self._RCL_camera.position.y + ((d * dy) / d2);
+				pixelInfo.texCoords = self._RCL_floorTexCoords(i, verticalOffset, dx, dy, pixelInfo.hit.distance);
+
+				let squareX = RCL_divRoundDown(pixelInfo.texCoords.x, RCL_UNITS_PER_SQUARE) as i16;
+				let squareY = RCL_divRoundDown(pixelInfo.texCoords.y, RCL_UNITS_PER_SQUARE) as i16;
+
+				pixelInfo.light = map.ambientLight(squareX, squareY);
+
+				if pixelInfo.isFloor {
+					pixelInfo.texture = map.floorTexture(squareX, squareY);
+				} else if !map.hasCeiling() || map.isSky(squareX, squareY) {
+					pixelInfo.isSky = true;
+					pixelInfo.texCoords = self._RCL_skyTexCoords(pixelInfo.position);
+				} else {
+					pixelInfo.texture = map.ceilingTexture(squareX, squareY);
+				}
 			}
 			pixelFunc(general, pixelInfo);
 			/* TODO: is efficient? */ i += increment;
 		}
 
-		return limit;
+		limit
+	}
+
+	/// World position shown by a floor or ceiling pixel on the given row, for a
+	/// plane verticalOffset above the camera and the ray going dx, dy to a hit
+	/// at the given distance.
+	#[inline]
+	fn _RCL_floorTexCoords(&self, row:i16, verticalOffset:RCL_Unit, dx:RCL_Unit, dy:RCL_Unit, distance:RCL_Unit) -> RCL_Vector2D {
+		let d = self._RCL_floorPixelDistances.as_ref().unwrap()[row as usize] as i64; // TODO: remove unwrap
+		let d = (d * RCL_absVal(verticalOffset) as i64 / RCL_UNITS_PER_SQUARE as i64)
+			.min((RCL_UNITS_PER_SQUARE * RCL_UNITS_PER_SQUARE) as i64);
+			// ^ no further than the horizon, which also keeps the multiplications below in range
+		let d2 = RCL_nonZero(distance) as i64;
+
+		RCL_Vector2D {
+			x: self._RCL_camera.position.x + ((d * dx as i64) / d2) as RCL_Unit,
+			y: self._RCL_camera.position.y + ((d * dy as i64) / d2) as RCL_Unit,
+		}
+	}
+
+	/// Sky texture coordinates of a screen pixel, see RCL_PixelInfo::isSky.
+	#[inline]
+	fn _RCL_skyTexCoords(&self, position:RCL_Vector2D) -> RCL_Vector2D {
+		let halfHeight = RCL_nonZero(self.config.resolutionY as RCL_Unit / 2);
+
+		RCL_Vector2D {
+			x: RCL_wrap(
+				self._RCL_camera.direction - self.config.horizontalFov / 2 +
+				(position.x * self.config.horizontalFov) / self.config.resolutionX as RCL_Unit,
+				RCL_UNITS_PER_SQUARE
+			),
+			y: RCL_clamp(RCL_UNITS_PER_SQUARE - ((self._RCL_middleRow as RCL_Unit - position.y) * RCL_UNITS_PER_SQUARE) / halfHeight, 0, RCL_UNITS_PER_SQUARE - 1),
+		}
 	}
 
 	/// Helper for drawing walls. Returns the last drawn pixel position.
 	#[inline]
 	fn _RCL_drawWall(
+		&self,
 		general:&mut RCL_General,
 		yCurrent:i16,
 		yFrom:RCL_Unit,
@@ -1080,6 +1238,7 @@ impl RCL_Renderer_Global {
 		height = RCL_absVal(height);
 
 		pixelInfo.isWall = true;
+		pixelInfo.isSky = false;
 
 		let limit = RCL_clamp(yTo,limit1 as RCL_Unit,limit2 as RCL_Unit) as i16;
 
@@ -1090,21 +1249,22 @@ impl RCL_Renderer_Global {
 		let heightScaled:RCL_Unit = height * RCL_TEXTURE_INTERPOLATION_SCALE;
 
 		let mut coordStepScaled:RCL_Unit = if RCL_COMPUTE_WALL_TEXCOORDS {
-			if RCL_TEXTURE_VERTICAL_STRETCH == 1 {
-				((RCL_UNITS_PER_SQUARE * RCL_TEXTURE_INTERPOLATION_SCALE) / wallLength)
+			if self.config.textureVerticalStretch {
+				(RCL_UNITS_PER_SQUARE * RCL_TEXTURE_INTERPOLATION_SCALE) / wallLength
 			} else {
-				(heightScaled / wallLength)
+				heightScaled / wallLength
 			}
 		} else {
 			0
 		};
 
-		pixelInfo.texCoords.y = if RCL_COMPUTE_WALL_TEXCOORDS { wallPosition * coordStepScaled } else { 0 };
+		// Overflows for zero length walls far off the screen, nothing is drawn of those
+		pixelInfo.texCoords.y = if RCL_COMPUTE_WALL_TEXCOORDS { wallPosition.wrapping_mul(coordStepScaled) } else { 0 };
 
 		if increment.is_negative() {
 			coordStepScaled *= -1;
 			pixelInfo.texCoords.y =
-				if RCL_TEXTURE_VERTICAL_STRETCH == 1 {
+				if self.config.textureVerticalStretch {
 					(RCL_UNITS_PER_SQUARE * RCL_TEXTURE_INTERPOLATION_SCALE) - pixelInfo.texCoords.y
 				} else {
 					heightScaled - pixelInfo.texCoords.y
@@ -1131,12 +1291,40 @@ impl RCL_Renderer_Global {
 			i += increment;
 		}
 
-		return limit;
+		limit
+	}
+
+	/// Helper for drawing a ceiling wall under an open sky, which would reach up
+	/// to the sky: the sky is drawn instead. Returns the last drawn pixel position.
+	#[inline]
+	fn _RCL_drawSky(
+		&self,
+		general:&mut RCL_General,
+		yCurrent:i16,
+		yTo:RCL_Unit,
+		limit1:i16,
+		limit2:i16,
+		pixelInfo:&mut RCL_PixelInfo
+	) -> i16 {
+		pixelInfo.isWall = false;
+		pixelInfo.isSky = true;
+
+		let limit = RCL_clamp(yTo,limit1 as RCL_Unit,limit2 as RCL_Unit) as i16;
+
+		let mut i = yCurrent + 1;
+		while i <= limit {
+			pixelInfo.position.y = i as RCL_Unit;
+			pixelInfo.texCoords = self._RCL_skyTexCoords(pixelInfo.position);
+			pixelFunc(general, pixelInfo);
+			i += 1;
+		}
+
+		limit
 	}
 
-	fn _RCL_columnFunctionComplex(&mut self, general:&mut RCL_General, hits:&[RCL_HitResult], x:u16, mut ray:RCL_Ray) {
+	fn _RCL_columnFunctionComplex(&mut self, general:&mut RCL_General, map:&dyn RclMap, hits:&[RCL_HitResult], x:u16, ray:RCL_Ray) {
 		// last written Y position, can never go backwards
-		let mut fPosY = CAMERA_RESOLUTION_Y as i16;
+		let mut fPosY = self.config.resolutionY as i16;
 		let mut cPosY = -1i16;
 
 		// world coordinates (relative to camera height though)
@@ -1149,6 +1337,14 @@ impl RCL_Renderer_Global {
 		p.wallHeight = 0;
 		p.texCoords = RCL_Vector2D::ZERO;
 
+		let cameraSquareX = RCL_divRoundDown(self._RCL_camera.position.x,RCL_UNITS_PER_SQUARE) as i16;
+		let cameraSquareY = RCL_divRoundDown(self._RCL_camera.position.y,RCL_UNITS_PER_SQUARE) as i16;
+
+		// whether the ceiling over the floor being drawn is open sky
+		let mut skyAbove = self.config.computeCeilingTexCoords && map.hasCeiling() && map.isSky(cameraSquareX, cameraSquareY);
+		// ambient light of the square the floor being drawn is in, it lights the next wall
+		let mut light = map.ambientLight(cameraSquareX, cameraSquareY);
+
 		// we'll be simulatenously drawing the floor and the ceiling now
 		for j in 0..=hits.len() {
 			//              ^ "=" add extra iteration for horizon plane
@@ -1161,26 +1357,32 @@ impl RCL_Renderer_Global {
 			let mut cWallHeight = 0;
 			let mut fZ2World = 0;
 			let mut cZ2World = 0;
-			let mut fZ1Screen = 0;
+			let fZ1Screen;
 			let mut cZ1Screen = 0;
 			let mut fZ2Screen = 0;
 			let mut cZ2Screen = 0;
 
 			if !drawingHorizon {
-				hit = hits[j as usize];
+				hit = hits[j];
+
+				if map.hasDoors() && RCL_COMPUTE_WALL_TEXCOORDS && hit.doorRoll != 0 && hit._RCL_isUnrolled() {
+					// looking through the rolled away part of a door, the floor and ceiling go on to the next hit
+					continue;
+				}
+
 				distance = RCL_nonZero(hit.distance);
-				p.hit = hit.clone();
+				p.hit = hit;
 
-				fWallHeight = floorHeightFunction(hit.square.x as i16, hit.square.y as i16);
+				fWallHeight = map.floorHeight(hit.square.x as i16, hit.square.y as i16);
 				fZ2World = fWallHeight - self._RCL_camera.height;
-				fZ1Screen = self._RCL_middleRow as RCL_Unit - RCL_perspectiveScale((fZ1World * CAMERA_RESOLUTION_Y as RCL_Unit) / RCL_UNITS_PER_SQUARE, distance);
-				fZ2Screen = self._RCL_middleRow as RCL_Unit - RCL_perspectiveScale((fZ2World * CAMERA_RESOLUTION_Y as RCL_Unit) / RCL_UNITS_PER_SQUARE, distance);
+				fZ1Screen = self._RCL_middleRow as RCL_Unit - self.config.perspectiveScale((fZ1World * self.config.resolutionY as RCL_Unit) / RCL_UNITS_PER_SQUARE, distance);
+				fZ2Screen = self._RCL_middleRow as RCL_Unit - self.config.perspectiveScale((fZ2World * self.config.resolutionY as RCL_Unit) / RCL_UNITS_PER_SQUARE, distance);
 
-				if let Some(chf) = ceilingHeightFunc {
-					cWallHeight = chf(hit.square.x as i16, hit.square.y as i16);
+				if map.hasCeiling() {
+					cWallHeight = map.ceilingHeight(hit.square.x as i16, hit.square.y as i16);
 					cZ2World = cWallHeight - self._RCL_camera.height;
-					cZ1Screen = self._RCL_middleRow as RCL_Unit - RCL_perspectiveScale((cZ1World * CAMERA_RESOLUTION_Y as RCL_Unit) / RCL_UNITS_PER_SQUARE, distance);
-					cZ2Screen = self._RCL_middleRow as RCL_Unit - RCL_perspectiveScale((cZ2World * CAMERA_RESOLUTION_Y as RCL_Unit) / RCL_UNITS_PER_SQUARE, distance);
+					cZ1Screen = self._RCL_middleRow as RCL_Unit - self.config.perspectiveScale((cZ1World * self.config.resolutionY as RCL_Unit) / RCL_UNITS_PER_SQUARE, distance);
+					cZ2Screen = self._RCL_middleRow as RCL_Unit - self.config.perspectiveScale((cZ2World * self.config.resolutionY as RCL_Unit) / RCL_UNITS_PER_SQUARE, distance);
 				}
 			} else {
 				fZ1Screen =  self._RCL_middleRow      as RCL_Unit;
@@ -1192,39 +1394,41 @@ impl RCL_Renderer_Global {
 
 			p.isWall = false;
 			p.isHorizon = drawingHorizon;
+			p.light = light;
 
 			// draw floor until wall
 			p.isFloor = true;
 			p.height = fZ1World + self._RCL_camera.height;
 			p.wallHeight = 0;
 
-			if RCL_COMPUTE_FLOOR_DEPTH {
+			if self.config.computeFloorDepth {
 				p.depth = (self._RCL_fHorizontalDepthStart - fPosY) as RCL_Unit * self._RCL_horizontalDepthStep;
 			} else {
 				p.depth = 0;
 			}
 
-			limit = self._RCL_drawHorizontalColumn(general, fPosY,fZ1Screen,cPosY + 1,
-			 CAMERA_RESOLUTION_Y as i16,fZ1World,NonZeroSignum::NEG,RCL_COMPUTE_FLOOR_DEPTH,
+			limit = self._RCL_drawHorizontalColumn(general, map, fPosY,fZ1Screen,cPosY + 1,
+			 self.config.resolutionY as i16,fZ1World,NonZeroSignum::NEG,self.config.computeFloorDepth,
 			 // ^ purposfully allow outside screen bounds
-				 RCL_COMPUTE_FLOOR_TEXCOORDS && p.height == RCL_FLOOR_TEXCOORDS_HEIGHT,
+				 self.config.computeFloorTexCoords,
 				 1, &ray, &mut p);
 
 			if fPosY > limit {
 				fPosY = limit;
 			}
 
-			if ceilingHeightFunc.is_some() || drawingHorizon {
+			if map.hasCeiling() || drawingHorizon {
 				// draw ceiling until wall
 				p.isFloor = false;
 				p.height = cZ1World + self._RCL_camera.height;
+				p.light = light;
 
-				if RCL_COMPUTE_CEILING_DEPTH {
+				if self.config.computeCeilingDepth {
 					p.depth = (cPosY - self._RCL_cHorizontalDepthStart) as RCL_Unit * self._RCL_horizontalDepthStep;
 				}
 
-				limit = self._RCL_drawHorizontalColumn(general, cPosY,cZ1Screen,
-					-1,fPosY as i16 - 1,cZ1World,NonZeroSignum::POS, RCL_COMPUTE_CEILING_DEPTH, false,1, &ray,&mut p);
+				limit = self._RCL_drawHorizontalColumn(general, map, cPosY,cZ1Screen,
+					-1,fPosY - 1,cZ1World,NonZeroSignum::POS, self.config.computeCeilingDepth, self.config.computeCeilingTexCoords,1, &ray,&mut p);
 				// ^ purposfully allow outside screen bounds here
 
 				if cPosY < limit {
@@ -1235,8 +1439,12 @@ impl RCL_Renderer_Global {
 			if !drawingHorizon { // don't draw walls for horizon plane
 				p.isWall = true;
 				p.depth = distance;
+				p.light = light;
 				p.isFloor = true;
 				p.texCoords.x = hit.textureCoord;
+				if RCL_ROLL_TEXTURE_COORDS && RCL_COMPUTE_WALL_TEXCOORDS {
+					p.texCoords.x -= hit.doorRoll;
+				}
 				p.height = fZ1World + self._RCL_camera.height;
 				p.wallHeight = fWallHeight;
 
@@ -1245,10 +1453,10 @@ impl RCL_Renderer_Global {
 				if fPosY > 0 { // still pixels left?
 					p.isFloor = true;
 
-					limit = Self::_RCL_drawWall(general, fPosY,fZ1Screen,fZ2Screen,cPosY + 1,
-										CAMERA_RESOLUTION_Y as i16,
+					limit = self._RCL_drawWall(general, fPosY,fZ1Screen,fZ2Screen,cPosY + 1,
+										self.config.resolutionY as i16,
 										// ^ purposfully allow outside screen bounds here
-										if RCL_TEXTURE_VERTICAL_STRETCH == 1 {
+										if self.config.textureVerticalStretch {
 											RCL_UNITS_PER_SQUARE
 										} else {
 											fZ2World - fZ1World
@@ -1267,20 +1475,24 @@ impl RCL_Renderer_Global {
 
 				// draw ceiling wall
 
-				if ceilingHeightFunc.is_some() && cPosY < _RCL_camResYLimit as i16 { // pixels left?
+				if map.hasCeiling() && cPosY < (self.config.resolutionY - 1) as i16 { // pixels left?
 					p.isFloor = false;
 					p.height = cZ1World + self._RCL_camera.height;
 					p.wallHeight = cWallHeight;
 
-					limit = Self::_RCL_drawWall(general, cPosY,cZ1Screen,cZ2Screen,
+					limit = if skyAbove {
+						self._RCL_drawSky(general, cPosY,cZ2Screen,-1,fPosY - 1,&mut p)
+					} else {
+						self._RCL_drawWall(general, cPosY,cZ1Screen,cZ2Screen,
 										-1,fPosY - 1,
 									// ^ puposfully allow outside screen bounds here
-							if RCL_TEXTURE_VERTICAL_STRETCH == 1 {
+							if self.config.textureVerticalStretch {
 											RCL_UNITS_PER_SQUARE
 										} else {
 											cZ1World - cZ2World
 										},
-										NonZeroSignum::POS,&mut p);
+										NonZeroSignum::POS,&mut p)
+					};
 
 					if cPosY < limit {
 						cPosY = limit;
@@ -1290,15 +1502,16 @@ impl RCL_Renderer_Global {
 					// puposfully allow outside screen bounds here
 					cZ1World = cZ2World;
 				}
+
+				skyAbove = self.config.computeCeilingTexCoords && map.hasCeiling() && map.isSky(hit.square.x as i16, hit.square.y as i16);
+				light = map.ambientLight(hit.square.x as i16, hit.square.y as i16);
 			}
 		}
 	}
 
-	fn _RCL_columnFunctionSimple(&mut self, general:&mut RCL_General, hits:&[RCL_HitResult], x:u16, ray:RCL_Ray) {
-		let mut y = 0;
+	fn _RCL_columnFunctionSimple(&mut self, general:&mut RCL_General, map:&dyn RclMap, hits:&[RCL_HitResult], x:u16, ray:RCL_Ray) {
 		let mut wallHeightScreen:RCL_Unit = 0;
 		let mut wallStart:RCL_Unit = self._RCL_middleRow as RCL_Unit;
-		let mut heightOffset:RCL_Unit = 0;
 
 		let mut dist:RCL_Unit = 1;
 
@@ -1306,12 +1519,12 @@ impl RCL_Renderer_Global {
 		p.position.x = x as RCL_Unit;
 		p.wallHeight = RCL_UNITS_PER_SQUARE;
 
-		if hits.len() > 0 {
+		if !hits.is_empty() {
 			let mut hit = &hits[0];
 
 			let mut goOn = true;
 
-			if self._RCL_rollFunction.is_some() && RCL_COMPUTE_WALL_TEXCOORDS {
+			if map.hasDoors() && RCL_COMPUTE_WALL_TEXCOORDS {
 				if hit.arrayValue == 0 {
 					// standing inside door square, looking out => move to the next hit
 
@@ -1323,15 +1536,7 @@ impl RCL_Renderer_Global {
 				} else {
 					// normal hit, check the door roll
 
-					let texCoordMod:RCL_Unit = hit.textureCoord % RCL_UNITS_PER_SQUARE;
-
-					let unrolled = if hit.doorRoll >= 0 {
-						hit.doorRoll > texCoordMod
-					} else {
-						texCoordMod > RCL_UNITS_PER_SQUARE + hit.doorRoll
-					};
-
-					if unrolled {
+					if hit._RCL_isUnrolled() {
 						goOn = false;
 
 						if hits.len() > 1 { // should probably always be true (hit on square exit)
@@ -1349,22 +1554,22 @@ impl RCL_Renderer_Global {
 				}
 			}
 
-			p.hit = hit.clone();
+			p.hit = *hit;
 
 			if goOn {
 				dist = hit.distance;
 
-				let wallHeightWorld = floorHeightFunction(hit.square.x as i16, hit.square.y as i16); // Was :i16
+				let wallHeightWorld = map.floorHeight(hit.square.x as i16, hit.square.y as i16); // Was :i16
 
-				wallHeightScreen = RCL_perspectiveScale((wallHeightWorld * CAMERA_RESOLUTION_Y as RCL_Unit) / RCL_UNITS_PER_SQUARE,dist);
+				wallHeightScreen = self.config.perspectiveScale((wallHeightWorld * self.config.resolutionY as RCL_Unit) / RCL_UNITS_PER_SQUARE,dist);
 
 				let RCL_normalizedWallHeight = if wallHeightWorld != 0 {
-					((RCL_UNITS_PER_SQUARE * wallHeightScreen) / wallHeightWorld)
+					(RCL_UNITS_PER_SQUARE * wallHeightScreen) / wallHeightWorld
 				} else {
 					0
 				};
 
-				heightOffset = RCL_perspectiveScale(self._RCL_cameraHeightScreen as RCL_Unit, dist);
+				let heightOffset = self.config.perspectiveScale(self._RCL_cameraHeightScreen as RCL_Unit, dist);
 
 				wallStart = self._RCL_middleRow as RCL_Unit - wallHeightScreen + heightOffset + RCL_normalizedWallHeight;
 			}
@@ -1380,7 +1585,7 @@ impl RCL_Renderer_Global {
 		p.depth = 1;
 		p.height = RCL_UNITS_PER_SQUARE;
 
-		y = self._RCL_drawHorizontalColumn(general, -1,wallStart,-1,self._RCL_middleRow,self._RCL_camera.height,NonZeroSignum::POS, RCL_COMPUTE_CEILING_DEPTH, false, 1, &ray, &mut p);
+		let mut y = self._RCL_drawHorizontalColumn(general, map, -1,wallStart,-1,self._RCL_middleRow,self._RCL_camera.height,NonZeroSignum::POS, self.config.computeCeilingDepth, self.config.computeCeilingTexCoords, 1, &ray, &mut p);
 
 		// draw wall
 
@@ -1396,7 +1601,7 @@ impl RCL_Renderer_Global {
 		p.texCoords.x = p.hit.textureCoord;
 		p.texCoords.y = 0;
 
-		let limit = Self::_RCL_drawWall(general, y,wallStart,wallStart + wallHeightScreen - 1, -1,_RCL_camResYLimit as i16,p.hit.arrayValue,NonZeroSignum::POS,&mut p);
+		let limit = self._RCL_drawWall(general, y,wallStart,wallStart + wallHeightScreen - 1, -1,(self.config.resolutionY - 1) as i16,p.hit.arrayValue,NonZeroSignum::POS,&mut p);
 
 		y = y.max(limit); // take max, in case no wall was drawn
 		y = (y as i32).max(wallStart) as i16;
@@ -1405,25 +1610,25 @@ impl RCL_Renderer_Global {
 
 		p.isWall = false;
 
-		if RCL_COMPUTE_FLOOR_DEPTH {
-			p.depth = (CAMERA_RESOLUTION_Y as RCL_Unit - y as RCL_Unit) * self._RCL_horizontalDepthStep + 1;
+		if self.config.computeFloorDepth {
+			p.depth = (self.config.resolutionY as RCL_Unit - y as RCL_Unit) * self._RCL_horizontalDepthStep + 1;
 		}
 
-		self._RCL_drawHorizontalColumn(general, y,_RCL_camResYLimit as RCL_Unit,-1,_RCL_camResYLimit as i16, self._RCL_camera.height,NonZeroSignum::POS,RCL_COMPUTE_FLOOR_DEPTH,RCL_COMPUTE_FLOOR_TEXCOORDS, -1,&ray,&mut p);
+		self._RCL_drawHorizontalColumn(general, map, y,(self.config.resolutionY - 1) as RCL_Unit,-1,(self.config.resolutionY - 1) as i16, self._RCL_camera.height,NonZeroSignum::POS,self.config.computeFloorDepth,self.config.computeFloorTexCoords, -1,&ray,&mut p);
 	}
 
-	// Precomputes a distance from camera to the floor at each screen row into an array (must be preallocated with sufficient (CAMERA_RESOLUTION_Y) length).
+	// Precomputes a distance from camera to a floor one square below it at each screen row, _RCL_floorTexCoords scales it to the other heights.
 	#[inline]
-	fn _RCL_precomputeFloorDistances(&self, camera:&RCL_Camera, startIndex:u16) -> [RCL_Unit; CAMERA_RESOLUTION_Y as usize] {
-		let mut floorPixelDistances = [0 as RCL_Unit;CAMERA_RESOLUTION_Y as usize];
+	fn _RCL_precomputeFloorDistances(&self) -> [RCL_Unit; RCL_MAX_RESOLUTION_Y as usize] {
+		let mut floorPixelDistances = [0 as RCL_Unit;RCL_MAX_RESOLUTION_Y as usize];
 
-		let camHeightScreenSize = (camera.height * CAMERA_RESOLUTION_Y as RCL_Unit) / RCL_UNITS_PER_SQUARE;
+		let camHeightScreenSize = self.config.resolutionY as RCL_Unit;
 
-		for i in startIndex..CAMERA_RESOLUTION_Y {
-			floorPixelDistances[i as usize] = RCL_perspectiveScaleInverse(camHeightScreenSize, RCL_absVal(i as RCL_Unit - self._RCL_middleRow as RCL_Unit));
+		for i in 0..self.config.resolutionY {
+			floorPixelDistances[i as usize] = self.config.perspectiveScaleInverse(camHeightScreenSize, RCL_absVal(i as RCL_Unit - self._RCL_middleRow as RCL_Unit));
 		}
 
-		return floorPixelDistances;
+		floorPixelDistances
 	}
 
 	/**
@@ -1437,39 +1642,33 @@ impl RCL_Renderer_Global {
 		- accuracy:               higher
 		- wall textures:          yes
 		- different wall heights: yes
-		- floor/ceiling textures: no
+		- floor/ceiling textures: yes (optional, with a sky)
 		- floor geometry:         yes, multilevel
 		- ceiling geometry:       yes (optional), multilevel
-		- rolling door:           no
+		- rolling door:           yes (without the inner sides of half rolled doors)
 		- camera shearing:        yes
 		- rendering order:        left-to-right, not specifically ordered vertically
 
 		@param cam camera whose view to render
-		@param floorHeightFunc function that returns floor height (in RCL_Units)
-		@param ceilingHeightFunc same as floorHeightFunc but for ceiling, can also be
-														 0 (no ceiling will be rendered)
-		@param typeFunction function that says a type of square (e.g. its texture
-											 index), can be 0 (no type in hit result)
-		@param pixelFunc callback function to draw a single pixel on screen
-		@param constraints constraints for each cast ray
+		@param map map to render, its ceiling is only rendered if it has one
 	*/
-	pub fn RCL_renderComplex(&mut self, general:&mut RCL_General, cam:RCL_Camera, typeFunction:Option<RCL_ArrayFunction>) {
+	pub fn RCL_renderComplex(&mut self, general:&mut RCL_General, cam:RCL_Camera, map:&dyn RclMap) {
 		self._RCL_camera = cam.clone();
 
-		self._RCL_middleRow = halfResY as i16 + cam.shear;
+		self._RCL_middleRow = (self.config.resolutionY / 2) as i16 + cam.shear;
 
-		self._RCL_fHorizontalDepthStart = self._RCL_middleRow + halfResY as i16;
-		self._RCL_cHorizontalDepthStart = self._RCL_middleRow - halfResY as i16;
+		self._RCL_fHorizontalDepthStart = self._RCL_middleRow + (self.config.resolutionY / 2) as i16;
+		self._RCL_cHorizontalDepthStart = self._RCL_middleRow - (self.config.resolutionY / 2) as i16;
 
 		self._RCL_startFloorHeight =
-			floorHeightFunction(
+			map.floorHeight(
 				RCL_divRoundDown(cam.position.x,RCL_UNITS_PER_SQUARE) as i16,
 				RCL_divRoundDown(cam.position.y,RCL_UNITS_PER_SQUARE) as i16
 			) - cam.height;
 
 		self._RCL_startCeil_Height =
-			if let Some(chf) = ceilingHeightFunc {
-				chf(
+			if map.hasCeiling() {
+				map.ceilingHeight(
 					RCL_divRoundDown(cam.position.x,RCL_UNITS_PER_SQUARE) as i16,
 					RCL_divRoundDown(cam.position.y,RCL_UNITS_PER_SQUARE) as i16
 				) - cam.height
@@ -1477,14 +1676,16 @@ impl RCL_Renderer_Global {
 				RCL_INFINITY
 			};
 
-		self._RCL_horizontalDepthStep = RCL_HORIZON_DEPTH / CAMERA_RESOLUTION_Y as RCL_Unit;
+		self._RCL_horizontalDepthStep = RCL_HORIZON_DEPTH / self.config.resolutionY as RCL_Unit;
 
-		if RCL_COMPUTE_FLOOR_TEXCOORDS {
-			let floorPixelDistances = self._RCL_precomputeFloorDistances(&cam, 0);
-			self._RCL_floorPixelDistances = Some(floorPixelDistances); // pass to column function
-		}
+		self._RCL_floorPixelDistances =
+			if self.config.computeFloorTexCoords || self.config.computeCeilingTexCoords {
+				Some(self._RCL_precomputeFloorDistances()) // pass to column function
+			} else {
+				None
+			};
 
-		self.RCL_castRaysMultiHit(general, &cam, Self::_RCL_floorCeilFunction, typeFunction, Self::_RCL_columnFunctionComplex);
+		self.RCL_castRaysMultiHit(general, &cam, map, Self::_RCL_floorCeilFunction, Self::_RCL_columnFunctionComplex);
 	}
 
 
@@ -1498,7 +1699,7 @@ impl RCL_Renderer_Global {
 		- accuracy:               lower
 		- wall textures:          yes
 		- different wall heights: yes
-		- floor/ceiling textures: yes (only floor, you can mirror it for ceiling)
+		- floor/ceiling textures: yes (the ceiling is sky, or mirrors the floor without a sky)
 		- floor geometry:         no (just flat floor, with depth information)
 		- ceiling geometry:       no (just flat ceiling, with depth information)
 		- rolling door:           yes
@@ -1509,80 +1710,85 @@ impl RCL_Renderer_Global {
 
 		This function should render each screen pixel exactly once.
 
-		@param rollFunc function that for given square says its door roll in
-					 RCL_Units (0 = no roll, RCL_UNITS_PER_SQUARE = full roll right,
-					 -RCL_UNITS_PER_SQUARE = full roll left), can be zero (no rolling door,
-					 rendering should also be faster as fewer intersections will be tested)
+		@param map map to render, if it has no doors rendering is faster as fewer
+					 intersections will be tested
 	*/
-	fn RCL_renderSimple(&mut self, general:&mut RCL_General, cam:RCL_Camera, typeFunc:Option<RCL_ArrayFunction>) {
+	fn RCL_renderSimple(&mut self, general:&mut RCL_General, cam:RCL_Camera, map:&dyn RclMap) {
 		self._RCL_camera = cam.clone();
-		self._RCL_middleRow = halfResY as i16;
+		self._RCL_middleRow = (self.config.resolutionY / 2) as i16;
 
 		self._RCL_cameraHeightScreen = (
-			(CAMERA_RESOLUTION_Y as RCL_Unit * (self._RCL_camera.height - RCL_UNITS_PER_SQUARE))
+			(self.config.resolutionY as RCL_Unit * (self._RCL_camera.height - RCL_UNITS_PER_SQUARE))
 			/
 			RCL_UNITS_PER_SQUARE
 		) as i16;
 
-		self._RCL_horizontalDepthStep = RCL_HORIZON_DEPTH / CAMERA_RESOLUTION_Y as RCL_Unit;
+		self._RCL_horizontalDepthStep = RCL_HORIZON_DEPTH / self.config.resolutionY as RCL_Unit;
 
 		general.defaultConstraints.maxHits =
-			if self._RCL_rollFunction.is_some() {
+			if map.hasDoors() {
 				3 // for correctly rendering rolling doors we'll need 3 hits (NOT 2)
 			} else {
 				1 // no door => 1 hit is enough
 			};
 
-		if RCL_COMPUTE_FLOOR_TEXCOORDS {
+		if self.config.computeFloorTexCoords || self.config.computeCeilingTexCoords {
 			// pass to column function
-			self._RCL_floorPixelDistances = Some(self._RCL_precomputeFloorDistances(&cam, self._RCL_middleRow as u16));
+			self._RCL_floorPixelDistances = Some(self._RCL_precomputeFloorDistances());
 		}
 
-		self.RCL_castRaysMultiHit(general, &cam, Self::_floorHeightNotZeroFunction,typeFunc, Self::_RCL_columnFunctionSimple);
+		self.RCL_castRaysMultiHit(general, &cam, map, Self::_floorHeightNotZeroFunction, Self::_RCL_columnFunctionSimple);
 
-		if RCL_COMPUTE_FLOOR_TEXCOORDS {
-			self._RCL_floorPixelDistances = None;
-		}
+		self._RCL_floorPixelDistances = None;
 	}
 
 	// checks a single square for collision against the camera
 	// #define
-	fn collCheck(&mut self, dirCollides:&mut bool, s1:i16, s2:i16, computeHeight:bool, bottomLimit:RCL_Unit, topLimit:RCL_Unit) {
+	fn collCheck(&mut self, map:&dyn RclMap, dirCollides:&mut bool, s1:i16, s2:i16, computeHeight:bool, bottomLimit:RCL_Unit, topLimit:RCL_Unit) {
+		if map.hasDoors() {
+			let roll = RCL_absVal(map.doorRoll(s1, s2));
+			if roll != 0 && roll < RCL_UNITS_PER_SQUARE {
+				// a door on the move is in the way whatever the heights say
+				*dirCollides = true;
+				return;
+			}
+		}
+
 		if computeHeight {
-			let height = floorHeightFunction(s1,s2);
+			let height = map.floorHeight(s1,s2);
 			if height > bottomLimit {
 				*dirCollides = true;
-			} else if let Some(chf) = ceilingHeightFunc {
-				let height = chf(s1, s2);
+			} else if map.hasCeiling() {
+				let height = map.ceilingHeight(s1, s2);
 				if height < topLimit {
 					*dirCollides = true;
 				}
 			}
 		} else {
-			*dirCollides = floorHeightFunction(s1, s2) > RCL_CAMERA_COLL_STEP_HEIGHT;
+			*dirCollides = map.floorHeight(s1, s2) > RCL_CAMERA_COLL_STEP_HEIGHT;
 		}
 	}
 
 	// check a collision against non-diagonal square
 	// #define
 	#[inline]
-	fn collCheckOrtho(&mut self,
+	fn collCheckOrtho(&mut self, map:&dyn RclMap,
 		dirCollides:&mut bool, dirSquare:i16, dirSquareNew:i16, dir2Dir:i16, dir2Square:&mut i16, dir2Square2:&mut i16,
 		s1:i16, s2:i16,
 		x:bool,
 		computeHeight:bool, bottomLimit:RCL_Unit, topLimit:RCL_Unit, corner_dir2:RCL_Unit
 	) {
 		if dirSquareNew != dirSquare {
-			self.collCheck(dirCollides, s1, s2, computeHeight, bottomLimit, topLimit);
+			self.collCheck(map, dirCollides, s1, s2, computeHeight, bottomLimit, topLimit);
 		}
 		if !*dirCollides { // now also check for coll on the neighbouring square
 			*dir2Square2 = RCL_divRoundDown(corner_dir2 - dir2Dir as RCL_Unit * RCL_CAMERA_COLL_RADIUS * 2,RCL_UNITS_PER_SQUARE) as i16;
 		}
 		if dir2Square2 != dir2Square {
 			if x {
-				self.collCheck(dirCollides, dirSquareNew, *dir2Square2, computeHeight, bottomLimit, topLimit);
+				self.collCheck(map, dirCollides, dirSquareNew, *dir2Square2, computeHeight, bottomLimit, topLimit);
 			} else {
-				self.collCheck(dirCollides, *dir2Square2, dirSquareNew, computeHeight, bottomLimit, topLimit);
+				self.collCheck(map, dirCollides, *dir2Square2, dirSquareNew, computeHeight, bottomLimit, topLimit);
 			}
 		}
 	}
@@ -1592,20 +1798,19 @@ impl RCL_Renderer_Global {
 		potentially also floor and ceilings. It's meant to help implement player
 		movement.
 
+		@param map map to collide with, the camera only collides with the ceiling
+							 if the map has one
 		@param camera camera to move
 		@param planeOffset offset to move the camera in
 		@param heightOffset height offset to move the camera in
-		@param floorHeightFunc function used to retrieve the floor height
-		@param ceilingHeightFunc function for retrieving ceiling height, can be 0
-														 (camera won't collide with ceiling)
 		@param computeHeight whether to compute height - if false (0), floor and
-												 ceiling functions won't be used and the camera will
+												 ceiling heights won't be used and the camera will
 												 only collide horizontally with walls (good for simpler
 												 game, also faster)
 		@param force if true, forces to recompute collision even if position doesn't
 								 change
 	*/
-	pub fn RCL_moveCameraWithCollision(&mut self,
+	pub fn RCL_moveCameraWithCollision(&mut self, map:&dyn RclMap,
 																 camera:&mut RCL_Camera,
 																 planeOffset:RCL_Vector2D, heightOffset:RCL_Unit,
 																 computeHeight:bool, force:bool
@@ -1619,7 +1824,7 @@ impl RCL_Renderer_Global {
 			let yDir:i16 = if planeOffset.y > 0 { 1 } else { -1 };
 
 			// BBox corner in the movement direction
-			let mut corner:RCL_Vector2D = RCL_Vector2D {
+			let corner:RCL_Vector2D = RCL_Vector2D {
 				x: camera.position.x + xDir as RCL_Unit * RCL_CAMERA_COLL_RADIUS,
 				y: camera.position.y + yDir as RCL_Unit * RCL_CAMERA_COLL_RADIUS,
 			};
@@ -1639,11 +1844,11 @@ impl RCL_Renderer_Global {
 			let topLimit;
 
 			if computeHeight {
-				bottomLimit = camera.height - RCL_CAMERA_COLL_HEIGHT_BELOW + RCL_CAMERA_COLL_STEP_HEIGHT;
+				bottomLimit = camera.height - self.config.cameraCollHeightBelow + RCL_CAMERA_COLL_STEP_HEIGHT;
 				topLimit = camera.height + RCL_CAMERA_COLL_HEIGHT_ABOVE;
 			} else {
 				// TODO: personal, check linter. will it suggest to replace "-1 *" with "-"?
-				bottomLimit = -1 * RCL_INFINITY;
+				bottomLimit = -RCL_INFINITY;
 				topLimit = RCL_INFINITY;
 			}
 
@@ -1651,13 +1856,13 @@ impl RCL_Renderer_Global {
 			let mut ySquare2:i16 = 0;
 			// xy
 			let ys = ySquare;
-			self.collCheckOrtho(&mut xCollides,xSquare,xSquareNew, yDir, &mut ySquare, &mut ySquare2,xSquareNew,ys,true, computeHeight, bottomLimit, topLimit, corner.y);
+			self.collCheckOrtho(map, &mut xCollides,xSquare,xSquareNew, yDir, &mut ySquare, &mut ySquare2,xSquareNew,ys,true, computeHeight, bottomLimit, topLimit, corner.y);
 
 			let mut yCollides = false;
 			let mut xSquare2:i16 = 0;
 			// yx
 			let xs = xSquare;
-			self.collCheckOrtho(&mut yCollides,ySquare, ySquareNew, xDir, &mut xSquare, &mut xSquare2,xs,ySquareNew,false, computeHeight, bottomLimit, topLimit, corner.x);
+			self.collCheckOrtho(map, &mut yCollides,ySquare, ySquareNew, xDir, &mut xSquare, &mut xSquare2,xs,ySquareNew,false, computeHeight, bottomLimit, topLimit, corner.x);
 
 			// #define
 			fn collHandle(dirCollides:bool, dirSquare:i16, dirDir:i16, cornerNewDir:&mut RCL_Unit) {
@@ -1673,7 +1878,7 @@ impl RCL_Renderer_Global {
 			if !xCollides && !yCollides { // if non-diagonal collision happend, corner collision can't happen
 				if xSquare != xSquareNew && ySquare != ySquareNew { // corner?
 					let mut xyCollides = false;
-					self.collCheck(&mut xyCollides, xSquareNew, ySquareNew, computeHeight, bottomLimit, topLimit);
+					self.collCheck(map, &mut xyCollides, xSquareNew, ySquareNew, computeHeight, bottomLimit, topLimit);
 
 					if xyCollides {
 						// normally should slide, but let's KISS
@@ -1700,10 +1905,10 @@ impl RCL_Renderer_Global {
 			let ySquare1 = RCL_divRoundDown(camera.position.y - RCL_CAMERA_COLL_RADIUS, RCL_UNITS_PER_SQUARE) as i16;
 			let ySquare2 = RCL_divRoundDown(camera.position.y + RCL_CAMERA_COLL_RADIUS, RCL_UNITS_PER_SQUARE) as i16;
 
-			let mut bottomLimit = floorHeightFunction(xSquare1, ySquare1);
+			let mut bottomLimit = map.floorHeight(xSquare1, ySquare1);
 			let mut topLimit =
-				if let Some(chf) = ceilingHeightFunc {
-					chf(xSquare1, ySquare1)
+				if map.hasCeiling() {
+					map.ceilingHeight(xSquare1, ySquare1)
 				} else {
 					RCL_INFINITY
 				}
@@ -1713,12 +1918,12 @@ impl RCL_Renderer_Global {
 
 			// #define
 			#[inline]
-			fn checkSquares(xSquare:i16, ySquare:i16, height:&mut RCL_Unit, bottomLimit:&mut RCL_Unit, topLimit:&mut RCL_Unit) {
-				*height = floorHeightFunction(xSquare, ySquare);
+			fn checkSquares(map:&dyn RclMap, xSquare:i16, ySquare:i16, height:&mut RCL_Unit, bottomLimit:&mut RCL_Unit, topLimit:&mut RCL_Unit) {
+				*height = map.floorHeight(xSquare, ySquare);
 				*bottomLimit = *bottomLimit.max(height);
 				*height =
-					if let Some(chf) = ceilingHeightFunc {
-						chf(xSquare, ySquare)
+					if map.hasCeiling() {
+						map.ceilingHeight(xSquare, ySquare)
 					} else {
 						RCL_INFINITY
 					}
@@ -1727,20 +1932,132 @@ impl RCL_Renderer_Global {
 			}
 
 			if xSquare2 != xSquare1 {
-				checkSquares(xSquare2, ySquare1, &mut height, &mut bottomLimit, &mut topLimit);
+				checkSquares(map, xSquare2, ySquare1, &mut height, &mut bottomLimit, &mut topLimit);
 			}
 
 			if ySquare2 != ySquare1 {
-				checkSquares(xSquare1, ySquare2, &mut height, &mut bottomLimit, &mut topLimit);
+				checkSquares(map, xSquare1, ySquare2, &mut height, &mut bottomLimit, &mut topLimit);
 			}
 
 			if xSquare2 != xSquare1 && ySquare2 != ySquare1 {
-				checkSquares(xSquare2, ySquare2, &mut height, &mut bottomLimit, &mut topLimit);
+				checkSquares(map, xSquare2, ySquare2, &mut height, &mut bottomLimit, &mut topLimit);
 			}
 
-			camera.height = RCL_clamp(camera.height, bottomLimit + RCL_CAMERA_COLL_HEIGHT_BELOW, topLimit - RCL_CAMERA_COLL_HEIGHT_ABOVE);
+			camera.height = RCL_clamp(camera.height, bottomLimit + self.config.cameraCollHeightBelow, topLimit - RCL_CAMERA_COLL_HEIGHT_ABOVE);
 
 			// #undef checkSquares
 		}
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Open floor with a wall from x = 4 on
+	struct WallFromX4;
+
+	impl RclMap for WallFromX4 {
+		fn floorHeight(&self, x:i16, _y:i16) -> RCL_Unit {
+			if x >= 4 { RCL_UNITS_PER_SQUARE } else { 0 }
+		}
+	}
+
+	#[test]
+	fn config_is_clamped_to_the_buffers() {
+		let config = RCL_Config { resolutionX: 10000, resolutionY: 0, ..RCL_Config::new() };
+		let renderer = RCL_Renderer_Global::new(config);
+		assert_eq!(renderer.config().resolutionX, RCL_MAX_RESOLUTION_X);
+		assert_eq!(renderer.config().resolutionY, 2);
+
+		let config = RCL_Config::new();
+		assert!(config.resolutionX <= RCL_MAX_RESOLUTION_X && config.resolutionY <= RCL_MAX_RESOLUTION_Y);
+		assert_eq!(config.clamped().resolutionX, config.resolutionX);
+	}
+
+	#[test]
+	fn distances_follow_the_perspective_and_approximation() {
+		// Hits the wall 3.5 squares to the right and 1.75 squares up
+		let ray = RCL_Ray {
+			start: RCL_Vector2D { x: RCL_UNITS_PER_SQUARE / 2, y: RCL_UNITS_PER_SQUARE / 2 },
+			direction: RCL_Vector2D { x: RCL_UNITS_PER_SQUARE, y: RCL_UNITS_PER_SQUARE / 2 },
+		};
+		let euclidean = (3.5f32.hypot(1.75) * RCL_UNITS_PER_SQUARE as f32) as RCL_Unit;
+		let distance = |rectilinear, distApprox| {
+			let mut renderer = RCL_Renderer_Global::new(RCL_Config { rectilinear, distApprox, ..RCL_Config::new() });
+			renderer.RCL_castRay(ray, &WallFromX4, |map, x, y| map.floorHeight(x, y)).distance
+		};
+
+		// Perpendicular to the projection plane, the ray direction is one square long along x
+		let rectilinear = distance(true, RCL_DistApprox::None);
+		assert!((rectilinear - 7 * RCL_UNITS_PER_SQUARE / 2).abs() <= 2, "{}", rectilinear);
+
+		let exact = distance(false, RCL_DistApprox::None);
+		assert!((exact - euclidean).abs() <= 2, "{} != {}", exact, euclidean);
+		let accurate = distance(false, RCL_DistApprox::Accurate);
+		assert!((accurate - euclidean).abs() < euclidean / 16, "{} != {}", accurate, euclidean);
+		let octagonal = distance(false, RCL_DistApprox::Octagonal);
+		assert!(octagonal > euclidean && octagonal < euclidean * 9 / 8, "{} != {}", octagonal, euclidean);
+	}
+
+	#[test]
+	fn resolution_sets_the_screen() {
+		let config = RCL_Config { resolutionX: 40, resolutionY: 30, ..rcl_general::GENERAL_CONFIG };
+		let mut renderer = RCL_Renderer_Global::new(config);
+		let mut general = Box::new(RCL_General::new());
+		general.initGeneral(renderer.config());
+		assert_eq!((general.screen.width(), general.screen.height()), (40, 30));
+
+		// Where demo1 starts
+		let camera = RCL_Camera {
+			position: RCL_Vector2D { x: 12 * RCL_UNITS_PER_SQUARE + RCL_UNITS_PER_SQUARE / 2, y: 6 * RCL_UNITS_PER_SQUARE + RCL_UNITS_PER_SQUARE / 2 },
+			direction: 0,
+			shear: 0,
+			height: RCL_UNITS_PER_SQUARE,
+		};
+		renderer.RCL_renderComplex(&mut general, camera, &demo1::Demo1Level::load());
+
+		let mut image = vec![crate::RGBA::zeroed(); crate::SCREEN_SPACE as usize];
+		general.screen.project(&mut image);
+
+		// Scaled 16 times to 640x480 and centered
+		let width = crate::SCREEN_WIDTH as usize;
+		let left = (width - 640) / 2;
+		let row = &image[240 * width..241 * width];
+		assert!(row[..left].iter().all(|c| c.to_rgb32() == 0));
+		assert!(row[left + 640..].iter().all(|c| c.to_rgb32() == 0));
+		assert!(row[left..left + 640].iter().any(|c| c.to_rgb32() != 0));
+		assert!(row[left..left + 640].chunks(16).all(|block| block.iter().all(|c| c.to_rgb32() == block[0].to_rgb32())));
+	}
+	#[test]
+	fn floor_and_ceiling_texture_coordinates_follow_the_ray() {
+		let config = RCL_Config { computeFloorTexCoords: true, ..rcl_general::GENERAL_CONFIG };
+		let mut renderer = RCL_Renderer_Global::new(config);
+		renderer._RCL_camera = RCL_Camera {
+			position: RCL_Vector2D { x: RCL_UNITS_PER_SQUARE / 2, y: RCL_UNITS_PER_SQUARE / 2 },
+			direction: 0,
+			shear: 0,
+			height: RCL_UNITS_PER_SQUARE / 2,
+		};
+		renderer._RCL_middleRow = (config.resolutionY / 2) as i16;
+		renderer._RCL_floorPixelDistances = Some(renderer._RCL_precomputeFloorDistances());
+
+		// Looking along x, at the row showing the plane 2 squares ahead
+		let coords = |verticalOffset:RCL_Unit| {
+			let row = renderer._RCL_middleRow as RCL_Unit +
+				config.perspectiveScale(-verticalOffset * config.resolutionY as RCL_Unit / RCL_UNITS_PER_SQUARE, 2 * RCL_UNITS_PER_SQUARE);
+			renderer._RCL_floorTexCoords(row as i16, verticalOffset, RCL_UNITS_PER_SQUARE, 0, RCL_UNITS_PER_SQUARE)
+		};
+		let expected = 5 * RCL_UNITS_PER_SQUARE / 2;
+		for &verticalOffset in &[-RCL_UNITS_PER_SQUARE / 2, -3 * RCL_UNITS_PER_SQUARE / 2, RCL_UNITS_PER_SQUARE] {
+			let texCoords = coords(verticalOffset);
+			assert!((texCoords.x - expected).abs() < RCL_UNITS_PER_SQUARE / 8, "{}: {}", verticalOffset, texCoords);
+			assert_eq!(texCoords.y, RCL_UNITS_PER_SQUARE / 2);
+		}
+
+		// The sky goes around with the view and down to the horizon
+		let sky = renderer._RCL_skyTexCoords(RCL_Vector2D { x: config.resolutionX as RCL_Unit / 2, y: 0 });
+		assert_eq!((sky.x, sky.y), (0, 0));
+		let sky = renderer._RCL_skyTexCoords(RCL_Vector2D { x: 0, y: renderer._RCL_middleRow as RCL_Unit });
+		assert_eq!((sky.x, sky.y), (RCL_wrap(-config.horizontalFov / 2, RCL_UNITS_PER_SQUARE), RCL_UNITS_PER_SQUARE - 1));
+	}
+}
@@ -0,0 +1,371 @@
+// Doors of the raycaster levels: squares that open and close over time, when the player uses them or by themselves
+// like moving walls. A door is as high as the floor of its square in the level and rolls away to the side, into the
+// ceiling or into the floor, down to the lowest floor next to it. `RclDoorMap` puts the doors into a map for the
+// renderer and the camera collision.
+
+use crate::rcl::*;
+use crate::rcl::rcl_level::*;
+
+/// Ticks a door takes to open or to close.
+pub const DEFAULT_DOOR_MOVE_TICKS:u32 = 50;
+/// Ticks a door stays open before it closes, and a timed door closed before it opens.
+pub const DEFAULT_DOOR_WAIT_TICKS:u32 = 300;
+/// How far in front of the camera a door can be used.
+pub const DOOR_REACH:RCL_Unit = 3 * RCL_UNITS_PER_SQUARE / 4;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RclDoorState {
+	Closed,
+	Opening,
+	Open,
+	Closing,
+}
+
+#[derive(Copy, Clone)]
+struct Door {
+	door:RclDoor,
+	state:RclDoorState,
+	/// 0 closed to RCL_UNITS_PER_SQUARE fully open.
+	openness:RCL_Unit,
+	/// Ticks left in the Open or Closed state.
+	wait:u32,
+	/// Floor height of the closed door and of the open doorway.
+	top:RCL_Unit,
+	base:RCL_Unit,
+}
+impl Door {
+	/// Height the door has rolled away by, up or down.
+	fn rolled(&self) -> RCL_Unit {
+		(self.top - self.base) * self.openness / RCL_UNITS_PER_SQUARE
+	}
+
+	fn floorHeight(&self) -> RCL_Unit {
+		match self.door.roll {
+			RclDoorRoll::Left | RclDoorRoll::Right if self.openness < RCL_UNITS_PER_SQUARE => self.top,
+			RclDoorRoll::Down => self.top - self.rolled(),
+			_ => self.base,
+		}
+	}
+}
+
+/// Doors of a level and where they are in opening and closing.
+pub struct RclDoors {
+	doors:Vec<Door>,
+	/// Whether some doors roll sideways or into the ceiling, the map needs door rolls or ceilings for them.
+	rollsSideways:bool,
+	rollsUp:bool,
+	pub moveTicks:u32,
+	pub waitTicks:u32,
+}
+impl RclDoors {
+	pub const fn new() -> RclDoors {
+		RclDoors {
+			doors: Vec::new(),
+			rollsSideways: false,
+			rollsUp: false,
+			moveTicks: DEFAULT_DOOR_MOVE_TICKS,
+			waitTicks: DEFAULT_DOOR_WAIT_TICKS,
+		}
+	}
+
+	/// Replaces the doors with `doors` of `map`, all closed.
+	pub fn place(&mut self, map:&dyn RclMap, doors:&[RclDoor]) {
+		self.doors.clear();
+		for &door in doors {
+			let top = map.floorHeight(door.x, door.y);
+			let base = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter()
+				.map(|&(dx, dy)| (door.x + dx, door.y + dy))
+				.filter(|&(x, y)| !doors.iter().any(|d| d.x == x && d.y == y))
+				.map(|(x, y)| map.floorHeight(x, y))
+				.min()
+				.map_or(top, |floor| floor.min(top));
+			self.doors.push(Door { door, state: RclDoorState::Closed, openness: 0, wait: self.waitTicks, top, base });
+		}
+		self.rollsSideways = self.doors.iter().any(|d| d.door.roll == RclDoorRoll::Left || d.door.roll == RclDoorRoll::Right);
+		self.rollsUp = self.doors.iter().any(|d| d.door.roll == RclDoorRoll::Up);
+	}
+
+	#[inline]
+	fn find(&self, x:i16, y:i16) -> Option<&Door> {
+		self.doors.iter().find(|d| d.door.x == x && d.door.y == y)
+	}
+
+	pub fn state(&self, x:i16, y:i16) -> Option<RclDoorState> {
+		self.find(x, y).map(|d| d.state)
+	}
+
+	/// How far the door in the square is open, 0 closed to RCL_UNITS_PER_SQUARE fully open.
+	pub fn openness(&self, x:i16, y:i16) -> Option<RCL_Unit> {
+		self.find(x, y).map(|d| d.openness)
+	}
+
+	/// Opens the door in front of the camera, or keeps it open for longer. Timed doors don't listen, returns whether
+	/// there was a door to use.
+	pub fn activate(&mut self, camera:&RCL_Camera) -> bool {
+		let direction = RCL_angleToDirection(camera.direction);
+		let x = RCL_divRoundDown(camera.position.x + direction.x * DOOR_REACH / RCL_UNITS_PER_SQUARE, RCL_UNITS_PER_SQUARE) as i16;
+		let y = RCL_divRoundDown(camera.position.y + direction.y * DOOR_REACH / RCL_UNITS_PER_SQUARE, RCL_UNITS_PER_SQUARE) as i16;
+		let waitTicks = self.waitTicks;
+
+		match self.doors.iter_mut().find(|d| d.door.x == x && d.door.y == y && d.door.trigger == RclDoorTrigger::Use) {
+			Some(door) => {
+				match door.state {
+					RclDoorState::Closed | RclDoorState::Closing => door.state = RclDoorState::Opening,
+					RclDoorState::Open => door.wait = waitTicks,
+					RclDoorState::Opening => {},
+				}
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Moves the doors on by `ticks`. Doors don't close on the camera, they open again.
+	pub fn update(&mut self, ticks:u32, camera:&RCL_Camera) {
+		// Rounded up, doors get there in moveTicks whatever the ticks per update
+		let moveTicks = core::cmp::max(self.moveTicks, 1) as RCL_Unit;
+		let step = (RCL_UNITS_PER_SQUARE * ticks as RCL_Unit + moveTicks - 1) / moveTicks;
+		// Squares the camera's collision box is in
+		let square = |offset:RCL_Unit, position:RCL_Unit| RCL_divRoundDown(position + offset, RCL_UNITS_PER_SQUARE) as i16;
+		let (x1, x2) = (square(-RCL_CAMERA_COLL_RADIUS, camera.position.x), square(RCL_CAMERA_COLL_RADIUS, camera.position.x));
+		let (y1, y2) = (square(-RCL_CAMERA_COLL_RADIUS, camera.position.y), square(RCL_CAMERA_COLL_RADIUS, camera.position.y));
+
+		for door in &mut self.doors {
+			let blocked = door.door.x >= x1 && door.door.x <= x2 && door.door.y >= y1 && door.door.y <= y2;
+
+			match door.state {
+				RclDoorState::Opening => {
+					door.openness = core::cmp::min(door.openness + step, RCL_UNITS_PER_SQUARE);
+					if door.openness == RCL_UNITS_PER_SQUARE {
+						door.state = RclDoorState::Open;
+						door.wait = self.waitTicks;
+					}
+				},
+				RclDoorState::Open => {
+					door.wait = door.wait.saturating_sub(ticks);
+					if door.wait == 0 && !blocked {
+						door.state = RclDoorState::Closing;
+					}
+				},
+				RclDoorState::Closing if blocked => door.state = RclDoorState::Opening,
+				RclDoorState::Closing => {
+					door.openness = core::cmp::max(door.openness - step, 0);
+					if door.openness == 0 {
+						door.state = RclDoorState::Closed;
+						door.wait = self.waitTicks;
+					}
+				},
+				RclDoorState::Closed => {
+					if door.door.trigger == RclDoorTrigger::Timed {
+						door.wait = door.wait.saturating_sub(ticks);
+						if door.wait == 0 {
+							door.state = RclDoorState::Opening;
+						}
+					}
+				},
+			}
+		}
+	}
+}
+
+/// `map` with `doors` in it, to render and collide with.
+pub struct RclDoorMap<'a> {
+	map:&'a dyn RclMap,
+	doors:&'a RclDoors,
+}
+impl<'a> RclDoorMap<'a> {
+	pub fn new(map:&'a dyn RclMap, doors:&'a RclDoors) -> RclDoorMap<'a> {
+		RclDoorMap { map, doors }
+	}
+}
+
+impl<'a> RclMap for RclDoorMap<'a> {
+	fn floorHeight(&self, x:i16, y:i16) -> RCL_Unit {
+		self.doors.find(x, y).map_or_else(|| self.map.floorHeight(x, y), Door::floorHeight)
+	}
+
+	fn hasCeiling(&self) -> bool { self.map.hasCeiling() || self.doors.rollsUp }
+
+	fn ceilingHeight(&self, x:i16, y:i16) -> RCL_Unit {
+		match self.doors.find(x, y) {
+			Some(door) if door.door.roll == RclDoorRoll::Up => door.base + door.rolled(),
+			_ => self.map.ceilingHeight(x, y),
+		}
+	}
+
+	fn texture(&self, x:i16, y:i16) -> RCL_Unit { self.map.texture(x, y) }
+
+	fn floorTexture(&self, x:i16, y:i16) -> RCL_Unit { self.map.floorTexture(x, y) }
+
+	fn ceilingTexture(&self, x:i16, y:i16) -> RCL_Unit { self.map.ceilingTexture(x, y) }
+
+	fn isSky(&self, x:i16, y:i16) -> bool {
+		match self.doors.find(x, y) {
+			Some(door) if door.door.roll == RclDoorRoll::Up => false,
+			_ => !self.map.hasCeiling() || self.map.isSky(x, y),
+			// ^ doors rolling up give a map without a ceiling one, the rest of it stays sky
+		}
+	}
+
+	fn ambientLight(&self, x:i16, y:i16) -> RCL_Unit { self.map.ambientLight(x, y) }
+
+	fn hasDoors(&self) -> bool { self.map.hasDoors() || self.doors.rollsSideways }
+
+	fn doorRoll(&self, x:i16, y:i16) -> RCL_Unit {
+		match self.doors.find(x, y) {
+			Some(door) if door.door.roll == RclDoorRoll::Left => -door.openness,
+			Some(door) if door.door.roll == RclDoorRoll::Right => door.openness,
+			Some(_) => 0,
+			None => self.map.doorRoll(x, y),
+		}
+	}
+
+	fn metadata(&self, x:i16, y:i16) -> u32 { self.map.metadata(x, y) }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::rcl::rcl_general::*;
+
+	/// A corridor along x with a door in the middle and a wall at the end.
+	const CORRIDOR:&str = "
+size = 4 3
+start = 0.5 1.5 0
+
+[floor]
+16 16 16 16
+0 16 0 16
+16 16 16 16
+[ceiling]
+24 24 24 24
+24 24 24 24
+24 24 24 24
+[texture]
+0 0 0 0
+0 0 0 0
+0 0 0 0
+
+[doors]
+1 1 right
+";
+
+	fn corridor(roll:&str) -> (RclLevel, RclDoors) {
+		let level = RclLevel::parse(&CORRIDOR.replace("right", roll), &[], 1).unwrap_or_else(|e| panic!("{}", e));
+		let mut doors = RclDoors::new();
+		doors.place(&level, &level.doors);
+		(level, doors)
+	}
+
+	fn camera(x:RCL_Unit) -> RCL_Camera {
+		RCL_Camera {
+			position: RCL_Vector2D { x, y: 3 * RCL_UNITS_PER_SQUARE / 2 },
+			direction: 0,
+			shear: 0,
+			height: RCL_UNITS_PER_SQUARE,
+		}
+	}
+
+	#[test]
+	fn doors_open_wait_and_close() {
+		let (level, mut doors) = corridor("right");
+		let front = camera(RCL_UNITS_PER_SQUARE / 2);
+		let inside = camera(3 * RCL_UNITS_PER_SQUARE / 2);
+		assert_eq!(doors.state(1, 1), Some(RclDoorState::Closed));
+		assert_eq!(doors.state(0, 1), None);
+		assert_eq!(RclDoorMap::new(&level, &doors).floorHeight(1, 1), 2 * RCL_UNITS_PER_SQUARE);
+
+		// Nothing in reach when turned around
+		assert!(!doors.activate(&RCL_Camera { direction: RCL_UNITS_PER_SQUARE / 2, ..front.clone() }));
+		assert!(doors.activate(&front));
+		doors.update(DEFAULT_DOOR_MOVE_TICKS / 2, &front);
+		assert_eq!(doors.state(1, 1), Some(RclDoorState::Opening));
+		let map = RclDoorMap::new(&level, &doors);
+		assert_eq!((map.doorRoll(1, 1), map.floorHeight(1, 1)), (RCL_UNITS_PER_SQUARE / 2, 2 * RCL_UNITS_PER_SQUARE));
+
+		doors.update(DEFAULT_DOOR_MOVE_TICKS, &front);
+		assert_eq!(doors.state(1, 1), Some(RclDoorState::Open));
+		let map = RclDoorMap::new(&level, &doors);
+		assert_eq!((map.doorRoll(1, 1), map.floorHeight(1, 1)), (RCL_UNITS_PER_SQUARE, 0));
+
+		// Stays open while the camera is in the doorway
+		doors.update(DEFAULT_DOOR_WAIT_TICKS, &inside);
+		doors.update(1, &inside);
+		assert_eq!(doors.state(1, 1), Some(RclDoorState::Open));
+		doors.update(1, &front);
+		assert_eq!(doors.state(1, 1), Some(RclDoorState::Closing));
+		doors.update(DEFAULT_DOOR_MOVE_TICKS / 2, &inside);
+		assert_eq!(doors.state(1, 1), Some(RclDoorState::Opening));
+
+		doors.update(DEFAULT_DOOR_MOVE_TICKS, &front);
+		doors.update(DEFAULT_DOOR_WAIT_TICKS, &front);
+		doors.update(DEFAULT_DOOR_MOVE_TICKS, &front);
+		assert_eq!((doors.state(1, 1), doors.openness(1, 1)), (Some(RclDoorState::Closed), Some(0)));
+	}
+
+	#[test]
+	fn vertical_and_timed_doors_move_the_heights() {
+		let (level, mut doors) = corridor("up timed");
+		let front = camera(RCL_UNITS_PER_SQUARE / 2);
+		let map = RclDoorMap::new(&level, &doors);
+		assert_eq!((map.floorHeight(1, 1), map.ceilingHeight(1, 1), map.doorRoll(1, 1)), (0, 0, 0));
+		// Moving walls don't listen
+		assert!(!doors.activate(&front));
+
+		doors.update(DEFAULT_DOOR_WAIT_TICKS, &front);
+		doors.update(DEFAULT_DOOR_MOVE_TICKS / 2, &front);
+		assert_eq!(RclDoorMap::new(&level, &doors).ceilingHeight(1, 1), RCL_UNITS_PER_SQUARE);
+		doors.update(DEFAULT_DOOR_MOVE_TICKS, &front);
+		doors.update(DEFAULT_DOOR_WAIT_TICKS, &front);
+		assert_eq!(doors.state(1, 1), Some(RclDoorState::Closing));
+
+		let (level, mut doors) = corridor("down");
+		doors.activate(&front);
+		doors.update(DEFAULT_DOOR_MOVE_TICKS / 2, &front);
+		let map = RclDoorMap::new(&level, &doors);
+		assert_eq!((map.floorHeight(1, 1), map.ceilingHeight(1, 1)), (RCL_UNITS_PER_SQUARE, 3 * RCL_UNITS_PER_SQUARE));
+	}
+
+	#[test]
+	fn rolled_doors_are_seen_and_walked_through() {
+		let (mut level, mut doors) = corridor("left");
+		let config = RCL_Config { resolutionX: 40, resolutionY: 30, ..GENERAL_CONFIG };
+		let mut renderer = RCL_Renderer_Global::new(config);
+		let mut general = Box::new(RCL_General::new());
+		general.initGeneral(&config);
+		let front = camera(RCL_UNITS_PER_SQUARE / 2);
+
+		// Depths along the middle row, the door is half a square away and the wall behind it two and a half
+		let mut depths = |map:&dyn RclMap| {
+			renderer.RCL_renderComplex(&mut general, front.clone(), map);
+			general.zBuffer[..40].to_vec()
+		};
+		let closed = depths(&RclDoorMap::new(&level, &doors));
+		assert!(closed.iter().all(|&depth| depth < RCL_UNITS_PER_SQUARE), "{:?}", closed);
+		doors.activate(&front);
+		doors.update(DEFAULT_DOOR_MOVE_TICKS / 2, &front);
+		let half = depths(&RclDoorMap::new(&level, &doors));
+		assert!(half.iter().any(|&depth| depth > 2 * RCL_UNITS_PER_SQUARE), "{:?}", half);
+		assert!(half.iter().filter(|&&depth| depth < RCL_UNITS_PER_SQUARE).count() > 10, "{:?}", half);
+		doors.update(DEFAULT_DOOR_MOVE_TICKS, &front);
+		let open = depths(&RclDoorMap::new(&level, &doors));
+		let door = level.index(1, 1).unwrap();
+		level.floor[door] = 0;
+		level.doors.clear();
+		assert_eq!(open, depths(&level));
+
+		// Walking into the doorway, only once the door is fully open
+		let (level, mut doors) = corridor("left");
+		let mut walk = |doors:&RclDoors| {
+			let mut camera = front.clone();
+			renderer.RCL_moveCameraWithCollision(&RclDoorMap::new(&level, doors), &mut camera, RCL_Vector2D { x: RCL_UNITS_PER_SQUARE, y: 0 }, 0, true, false);
+			camera.position.x
+		};
+		assert_eq!(walk(&doors), RCL_UNITS_PER_SQUARE - RCL_CAMERA_COLL_RADIUS - 1);
+		doors.activate(&front);
+		doors.update(DEFAULT_DOOR_MOVE_TICKS - 1, &front);
+		assert_eq!(walk(&doors), RCL_UNITS_PER_SQUARE - RCL_CAMERA_COLL_RADIUS - 1);
+		doors.update(1, &front);
+		assert_eq!(walk(&doors), 3 * RCL_UNITS_PER_SQUARE / 2);
+	}
+}
@@ -1,9 +1,10 @@
-/**
+/*
   General definitions common for Pokitto raycasting demos.
 
   The demos use mode 13: 1 byte per pixel = 256 colors. Bitmaps (textures,
   sprites, ...) are also in this format (use the provided python script to
-  convert png images).
+  convert png images). This port renders in true color and also takes the
+  RGBA images of the asset tools, see RclImage.
 
   author: Miloslav "drummyfish" Ciz
   license: CC0 1.0
@@ -11,20 +12,27 @@
 
 //#include "stdio.h" // for debugging raycastlibg
 
-// Redefine camera vertical FOV: RCL_UNITS_PER_SQUARE would normally mean 360 degrees, but it's not an actual angle, just linear approximation, so this is okay.
-const RCL_VERTICAL_FOV:RCL_Unit = RCL_UNITS_PER_SQUARE;
+/// Renderer settings the demos start from.
+pub const GENERAL_CONFIG:RCL_Config = RCL_Config {
+	// Redefine camera vertical FOV: RCL_UNITS_PER_SQUARE would normally mean 360 degrees, but it's not an actual angle, just linear approximation, so this is okay.
+	verticalFov: RCL_UNITS_PER_SQUARE,
+	..RCL_Config::new()
+};
 
 // This has to be defined to the name of the function that will render pixels.
 
 // #include "raycastlib.h"
 use crate::rcl::*;
 use crate::{RGBA, RGB};
+use crate::canvas::Rect;
+use crate::rcl::rcl_sprites::RclSpriteImages;
+use crate::rcl::rcl_lights::{RclLights, RclShade};
 // #include "Pokitto.h"
 // Pokitto::Core pokitto;
 
-const PLAYER_SPEED:RCL_Unit = (4 * RCL_UNITS_PER_SQUARE); // #ifndef PLAYER_SPEED
+const PLAYER_SPEED:RCL_Unit = 4 * RCL_UNITS_PER_SQUARE; // #ifndef PLAYER_SPEED
 
-const PLAYER_ROTATION_SPEED:RCL_Unit = (RCL_UNITS_PER_SQUARE / 2); // #ifndef PLAYER_ROTATION_SPEED
+const PLAYER_ROTATION_SPEED:RCL_Unit = RCL_UNITS_PER_SQUARE / 2; // #ifndef PLAYER_ROTATION_SPEED
 
 const PLAYER_JUMP_SPEED:RCL_Unit = 500; // #ifndef PLAYER_JUMP_SPEED
 
@@ -32,44 +40,38 @@ const HEAD_BOB_HEIGHT:RCL_Unit = 100; // #ifndef HEAD_BOB_HEIGHT
 
 const HEAD_BOB_STEP:u8 = 10; // #ifndef HEAD_BOB_STEP
 
-const GRAVITY_ACCELERATION:RCL_Unit = ((3 * RCL_UNITS_PER_SQUARE) / 2); // #ifndef GRAVITY_ACCELERATION
+const GRAVITY_ACCELERATION:RCL_Unit = (3 * RCL_UNITS_PER_SQUARE) / 2; // #ifndef GRAVITY_ACCELERATION
+
+/// Movement of the player, the `#ifndef`s above which a demo can redefine.
+#[derive(Copy, Clone, Debug)]
+pub struct PlayerConfig {
+	/// Units per second, doubled while running.
+	pub speed:RCL_Unit,
+	/// Angle units per second.
+	pub rotationSpeed:RCL_Unit,
+	/// Vertical units per second at the start of a jump.
+	pub jumpSpeed:RCL_Unit,
+	/// Vertical units per second lost every second.
+	pub gravity:RCL_Unit,
+}
 
-const SCREEN_WIDTH:u8 = 110;
-const SCREEN_HEIGHT:u8 = 88;
-pub const MIDDLE_ROW:u8 = SCREEN_HEIGHT / 2;
-const MIDDLE_COLUMN:u8 = SCREEN_WIDTH / 2;
+/// Player settings the demos start from.
+pub const GENERAL_PLAYER_CONFIG:PlayerConfig = PlayerConfig {
+	speed: PLAYER_SPEED,
+	rotationSpeed: PLAYER_ROTATION_SPEED,
+	jumpSpeed: PLAYER_JUMP_SPEED,
+	gravity: GRAVITY_ACCELERATION,
+};
 
 // was 2
 // const SUBSAMPLE:u8 = 2; // #ifndef SUBSAMPLE
 
-// was const SUBSAMPLED_WIDTH:u8 = (SCREEN_WIDTH / SUBSAMPLE);
-const SUBSAMPLED_WIDTH:u8 = SCREEN_WIDTH;
-
-const TEXTURE_W:u8 = 32;
-const TEXTURE_H:u8 = 32;
-
 /// Transparent color for sprites and GUI.
 const TRANSPARENT_COLOR:u8 = 0x8f;
 
 /// Gives a middle color of given hue (0 to 15).
 pub const fn HUE(c:u8) -> u8 { c * 16 + 8 }
 
-#[inline]
-pub fn putSubsampledPixel(screenbuffer: &mut [u8], x:u8, y:u8, color:u8) {
-	let mut offset =
-		y as usize * SCREEN_WIDTH as usize +
-		x as usize
-		// was x as usize * SUBSAMPLE    as usize
-	;
-	// screenbuffer[offset] = color;
-	if let Some(p) = screenbuffer.get_mut(offset) {
-		*p = color;
-	}
-	// was for i in 0..SUBSAMPLE {
-	// was	screenbuffer[offset + i as usize] = color;
-	// was }
-}
-
 fn encodeHSV(hue:u8, saturation:u8, value:u8) -> u8 {
 	if value > 15 {
 		if saturation > 84 {
@@ -95,10 +97,10 @@ const fn decodeHSV(hsv:u8) -> (u8,u8,u8) {
     let saturation = (1 + ((hsv & 0b10000000) >> 7)) * 127;
     let hue = ((hsv & 0b01110000) >> 4) * 32;
 
-		return (hue, saturation, value);
+		(hue, saturation, value)
   } else {
     // gray/white/black
-		return ( 0, 0, bottomHalf * 17 );
+		( 0, 0, bottomHalf * 17 )
   }
 }
 
@@ -116,23 +118,23 @@ const fn convertHSVtoRGB(hue:u8, saturation:u8, value:u8) -> RGB {
 	// (0..224 % (2*16)) - 16 => 0..31 - 16 => -16..15
 	// abs(-16..15) => 0..16
 	// 0..16
-  let a = ((h % (2 * M)) as i8 - M as i8).abs() as u8;
+  let a = ((h % (2 * M)) as i8 - M as i8).unsigned_abs();
 
 	// (254 * (16 - 0..16)) / 16 ==> (254 * 0..16) / 16 ==> 0..4064 / 16 => 0..254
 	// 0..254
   let x = ((chroma as u16 * (M - a) as u16) / M as u16) as u8;
 
-	let mut r = 0;
-	let mut g = 0;
-	let mut b = 0;
+	let mut r;
+	let mut g;
+	let mut b;
 
-       if h <= 1 * M { r = chroma as u8;   g = x as u8;      b = 0;            }
-  else if h <= 2 * M { r = x as u8;        g = chroma as u8; b = 0;            }
-  else if h <= 3 * M { r = 0;              g = chroma as u8; b = x as u8;      }
-  else if h <= 4 * M { r = 0;              g = x as u8;      b = chroma as u8; }
-  else if h <= 5 * M { r = x as u8;        g = 0;            b = chroma as u8; }
-  else if h <= 6 * M { r = chroma as u8;   g = 0;            b = x as u8;      }
-  else               { r = 0;              g = 0;            b = 0;            }
+       if h <=     M { r = chroma; g = x;      b = 0;      }
+  else if h <= 2 * M { r = x;      g = chroma; b = 0;      }
+  else if h <= 3 * M { r = 0;      g = chroma; b = x;      }
+  else if h <= 4 * M { r = 0;      g = x;      b = chroma; }
+  else if h <= 5 * M { r = x;      g = 0;      b = chroma; }
+  else if h <= 6 * M { r = chroma; g = 0;      b = x;      }
+  else               { r = 0;      g = 0;      b = 0;      }
 
   let m:i32 = value as i32 - chroma as i32;
 
@@ -140,7 +142,7 @@ const fn convertHSVtoRGB(hue:u8, saturation:u8, value:u8) -> RGB {
 	g = (g as i16 + m as i16) as u8;
 	b = (b as i16 + m as i16) as u8;
 
-	return RGB { r, g, b };
+	RGB { r, g, b }
 }
 
 /// Inits and loads a general 256 color palette.
@@ -151,17 +153,23 @@ const fn initPalette() -> [RGB;256] {
 	// TODO: replace with "for i in 0..256 {" when `for` in `const_fn` becomes stable
 	let mut i:u16 = 0;
 	while i < 256 {
-		let mut r = 0;
-		let mut g = 0;
-		let mut b = 0;
-
     let (h, s, v) = decodeHSV(i as u8);
     palette[i as usize] = convertHSVtoRGB(h,s,v);
 
 		i += 1;
   }
 
-	return palette;
+	palette
+}
+
+/// RGB of the colors of the palette, palette images are converted with it as they are drawn.
+static PALETTE:[RGB;256] = initPalette();
+
+/// True color of a color of the palette.
+pub const fn paletteColor(color:u8) -> RGBA {
+	let (h, s, v) = decodeHSV(color);
+	let c = convertHSVtoRGB(h, s, v);
+	RGBA::rgba(c.r, c.g, c.b, 0xFF)
 }
 
 /// Adds given intensity to a color.
@@ -169,7 +177,7 @@ const fn initPalette() -> [RGB;256] {
 pub fn addIntensity(color:u8, add:i8) -> u8 {
   let newValue:u8 = (color as i16 + add as i16) as u8;
 
-	return if (newValue >> 4) == (color >> 4) {
+	if (newValue >> 4) == (color >> 4) {
 		newValue
 	} else {
 		if add > 0 {
@@ -183,87 +191,187 @@ pub fn addIntensity(color:u8, add:i8) -> u8 {
 #[inline]
 fn plusIntensity(color:u8, plus:u8) -> u8 {
   let newValue = color + plus;
-  return
-		if (newValue >> 4) == (color >> 4) {
+  if (newValue >> 4) == (color >> 4) {
 			newValue
 		} else {
 			color | 0x0f
-		};
+		}
 }
 
 #[inline]
 fn minusIntensity(color:u8, minus:u8) -> u8 {
   let newValue = color - minus;
-  return
-		if (newValue >> 4) == (color >> 4) {
+  if (newValue >> 4) == (color >> 4) {
 			newValue
 		} else {
 			0
-		};
+		}
 }
 
-/// Samples an image by normalized coordinates - each coordinate is in range 0 to RCL_UNITS_PER_SQUARE (from raycastlib).
+/// Adds given intensity to a true color, in the steps of addIntensity: one step is 1/16 of the full value. Hue and
+/// saturation stay, like in the palette, so fog and shadows darken towards black. Alpha is kept.
 #[inline]
-pub fn sampleImage(image:&[u8], mut x:RCL_Unit, mut y:RCL_Unit) -> u8 {
-  x = RCL_wrap(x,RCL_UNITS_PER_SQUARE);
-  y = RCL_wrap(y,RCL_UNITS_PER_SQUARE);
+pub fn addIntensityRGB(color:RGBA, add:i8) -> RGBA {
+  let value = core::cmp::max(color.r, core::cmp::max(color.g, color.b)) as i32;
+  let newValue = (value + add as i32 * 16).clamp(0, 0xFF);
+
+  if value == 0 {
+    // black only has a gray to brighten to
+    return RGBA::rgba(newValue as u8, newValue as u8, newValue as u8, color.a);
+  }
+
+  let scale = |c:u8| (c as i32 * newValue / value) as u8;
+  RGBA::rgba(scale(color.r), scale(color.g), scale(color.b), color.a)
+}
+
+/// Texture or sprite image, in either of the two formats the demos take:
+///
+/// - `Palette`: width and height bytes followed by the colors of the palette column by column, `TRANSPARENT_COLOR`
+///   is transparent in sprites. The images of the original demos.
+/// - `Rgba`: `.rust2d` image of the asset tools (`tools/helper`, converted from png): big endian 16 bit width and
+///   height followed by RGBA rows, alpha below half is transparent in sprites.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RclImage {
+  Palette(&'static [u8]),
+  Rgba(&'static [u8]),
+}
+impl RclImage {
+  #[inline]
+  pub fn width(self) -> u16 {
+    match self {
+      RclImage::Palette(data) => data[0] as u16,
+      RclImage::Rgba(data) => u16::from_be_bytes([data[0], data[1]]),
+    }
+  }
+
+  #[inline]
+  pub fn height(self) -> u16 {
+    match self {
+      RclImage::Palette(data) => data[1] as u16,
+      RclImage::Rgba(data) => u16::from_be_bytes([data[2], data[3]]),
+    }
+  }
+
+  /// Color of the pixel `x`, `y` from the top left corner, transparent pixels have zero alpha.
+  #[inline]
+  pub fn pixel(self, x:u16, y:u16) -> RGBA {
+    match self {
+      RclImage::Palette(data) => {
+        let color = data[2 + x as usize * data[1] as usize + y as usize];
+        let c = PALETTE[color as usize];
+        RGBA::rgba(c.r, c.g, c.b, if color == TRANSPARENT_COLOR { 0 } else { 0xFF })
+      },
+      RclImage::Rgba(data) => {
+        let i = 4 + (y as usize * self.width() as usize + x as usize) * 4;
+        RGBA::rgba(data[i], data[i + 1], data[i + 2], if data[i + 3] < 0x80 { 0 } else { 0xFF })
+      },
+    }
+  }
+
+  /// Samples the image by normalized coordinates - each coordinate is in range 0 to RCL_UNITS_PER_SQUARE (from
+  /// raycastlib), outside of it the image repeats.
+  #[inline]
+  pub fn sample(self, x:RCL_Unit, y:RCL_Unit) -> RGBA {
+    let x = RCL_wrap(x, RCL_UNITS_PER_SQUARE) * self.width() as RCL_Unit / RCL_UNITS_PER_SQUARE;
+    let y = RCL_wrap(y, RCL_UNITS_PER_SQUARE) * self.height() as RCL_Unit / RCL_UNITS_PER_SQUARE;
 
-  let index =
-   (x / (RCL_UNITS_PER_SQUARE / TEXTURE_W as i32)) * TEXTURE_H as i32 +
-   (y / (RCL_UNITS_PER_SQUARE / TEXTURE_W as i32));
+    self.pixel(x as u16, y as u16)
+  }
 
-  return image[2 + index as usize];
+  /// Average of the opaque pixels, black if there are none.
+  pub fn averageColor(self) -> RGBA {
+    let (mut r, mut g, mut b, mut count) = (0u32, 0u32, 0u32, 0u32);
+
+    for x in 0..self.width() {
+      for y in 0..self.height() {
+        let c = self.pixel(x, y);
+        if c.a != 0 {
+          r += c.r as u32;
+          g += c.g as u32;
+          b += c.b as u32;
+          count += 1;
+        }
+      }
+    }
+
+    let count = core::cmp::max(count, 1);
+    RGBA::rgba((r / count) as u8, (g / count) as u8, (b / count) as u8, 0xFF)
+  }
 }
 
+/// The demos draw at the resolution of the renderer config, `initGeneral` sets it.
 pub struct Screen {
-	pallete:[RGB;256],
-	data:[[u8; SCREEN_WIDTH as usize]; SCREEN_HEIGHT as usize],
+	width:u16,
+	height:u16,
+	/// Rows of true colors, allocated by `resize`.
+	data:Vec<RGBA>,
 }
 impl Screen {
 	pub const fn new() -> Screen {
 		Screen {
-			pallete: initPalette(),
-			data: [[0; SCREEN_WIDTH as usize]; SCREEN_HEIGHT as usize]
+			width: 0,
+			height: 0,
+			data: Vec::new(),
 		}
 	}
 
+	fn resize(&mut self, width:u16, height:u16) {
+		self.width = width;
+		self.height = height;
+		self.data.clear();
+		self.data.resize(width as usize * height as usize, RGBA::zeroed());
+	}
+
+	#[inline(always)] pub fn width(&self) -> u16 { self.width }
+	#[inline(always)] pub fn height(&self) -> u16 { self.height }
+
 	#[inline]
-	pub fn drawPixel(&mut self, x:i16, y:i16, color:u8) {
+	pub fn drawPixel(&mut self, x:i16, y:i16, color:RGBA) {
 		// TODO: personal: is check nescessary?
 		if
-			x >= 0 && x < SCREEN_WIDTH  as i16 &&
-			y >= 0 && y < SCREEN_HEIGHT as i16
+			x >= 0 && x < self.width  as i16 &&
+			y >= 0 && y < self.height as i16
 		{
-			self.data[y as usize][x as usize] = color;
+			self.data[y as usize * self.width as usize + x as usize] = color;
 		}
 	}
 
+	/// Scales the screen up by the largest whole factor that fits the game screen and centers it, the rest is black.
 	pub fn project(&self, image:&mut [RGBA]) {
-		for y in 0..SCREEN_HEIGHT {
-			let yoff = y as u16 * SCREEN_WIDTH as u16;
-			for x in 0..SCREEN_WIDTH {
-				let c = self.pallete[self.data[y as usize][x as usize] as usize];
-				let image = &mut image[(yoff + x as u16) as usize];
-				image.r = c.r;
-				image.g = c.g;
-				image.b = c.b;
-				image.a = 0xFF;
+		self.projectInto(image, Rect::new(0, 0, crate::SCREEN_WIDTH as i32, crate::SCREEN_HEIGHT as i32));
+	}
+
+	/// Same as `project`, but only into `area` of the game screen.
+	pub fn projectInto(&self, image:&mut [RGBA], area:Rect) {
+		let area = area.intersect(&Rect::new(0, 0, crate::SCREEN_WIDTH as i32, crate::SCREEN_HEIGHT as i32));
+		let (width, height) = (core::cmp::max(self.width, 1) as usize, core::cmp::max(self.height, 1) as usize);
+		let scale = core::cmp::max(1, core::cmp::min(area.width as usize / width, area.height as usize / height));
+		let left = (area.width as usize).saturating_sub(width * scale) / 2;
+		let top = (area.height as usize).saturating_sub(height * scale) / 2;
+
+		let rows = image.chunks_exact_mut(crate::SCREEN_WIDTH as usize).skip(area.y as usize).take(area.height as usize);
+		for (y, row) in rows.enumerate() {
+			let source = if y < top { None } else { self.data.get((y - top) / scale * width..((y - top) / scale + 1) * width) };
+			for (x, pixel) in row[area.x as usize..area.right() as usize].iter_mut().enumerate() {
+				*pixel = match source.and_then(|source| if x < left { None } else { source.get((x - left) / scale) }) {
+					Some(&c) => RGBA::rgba(c.r, c.g, c.b, 0xFF),
+					None => RGBA::rgba(0, 0, 0, 0xFF),
+				};
 			}
 		}
 	}
 }
 
 /// Faster than drawSprite.
-fn drawImage(screen:&mut Screen, image:&[u8], x:u8, y:u8) {
-  for i in 0..image[0] {
-    let xPos = x + i;
-    let column = 2 + i * image[1];
+fn drawImage(screen:&mut Screen, image:RclImage, x:u8, y:u8) {
+  for i in 0..image.width() {
+    let xPos = x as u16 + i;
 
-    for j in 0..image[1] {
-      let c = image[(column + j) as usize];
+    for j in 0..image.height() {
+      let c = image.pixel(i, j);
 
-      if c != TRANSPARENT_COLOR {
-        screen.drawPixel(xPos as i16, (y + j) as i16, image[(column + j) as usize]);
+      if c.a != 0 {
+        screen.drawPixel(xPos as i16, (y as u16 + j) as i16, c);
 			}
     }
   }
@@ -280,18 +388,18 @@ pub struct Player {
   pub mRunning:bool,
 	pub mHeadBob:RCL_Unit,
   pub mHeadBobUp:bool,
+	config:PlayerConfig,
 }
 impl Player {
   pub const fn new() -> Player {
+		Player::withConfig(GENERAL_PLAYER_CONFIG)
+  }
+
+  pub const fn withConfig(config:PlayerConfig) -> Player {
 		Player {
 			mCamera: RCL_Camera {
 				position: RCL_Vector2D::ZERO,
 				direction: 0,
-				resolution: RCL_Vector2D {
-					// was x: SCREEN_WIDTH as RCL_Unit / SUBSAMPLE as RCL_Unit,
-					x: SCREEN_WIDTH  as RCL_Unit,
-					y: SCREEN_HEIGHT as RCL_Unit,
-				},
 				shear: 0,
 				height: RCL_UNITS_PER_SQUARE * 3,
 			},
@@ -300,6 +408,7 @@ impl Player {
 			mRunning: false,
 			mHeadBob: 0,
 			mHeadBobUp: true,
+			config,
 		}
   }
 
@@ -323,15 +432,14 @@ impl Player {
   }
 
   pub fn update(
-		&mut self, renderer:&mut RCL_Renderer_Global,
+		&mut self, renderer:&mut RCL_Renderer_Global, map:&dyn RclMap,
 		moveDirection:i16, strafe:bool, turnDirection:i16, jump:bool,
-		shearDirection:i16, floorHeightFunction:RCL_ArrayFunction,
-		ceilingHeightFunction:RCL_ArrayFunction, computeHeight:bool, dt:u32
+		shearDirection:i16, computeHeight:bool, dt:u32
 	) {
     let mut moveOffset = RCL_Vector2D::ZERO;
 
     if moveDirection != 0 {
-      let horizontalStep:RCL_Unit = (dt as RCL_Unit * PLAYER_SPEED * (if self.mRunning { 2 } else { 1 })) / 1000 * (if moveDirection > 0 { 1 } else { -1 });
+      let horizontalStep:RCL_Unit = (dt as RCL_Unit * self.config.speed * (if self.mRunning { 2 } else { 1 })) / 1000 * (if moveDirection > 0 { 1 } else { -1 });
 
       moveOffset = RCL_angleToDirection(self.mCamera.direction + (if strafe { RCL_UNITS_PER_SQUARE / 4 } else { 0 }));
 
@@ -350,13 +458,13 @@ impl Player {
 		}
 
     if turnDirection != 0 {
-      let rotationStep = (dt as RCL_Unit * PLAYER_ROTATION_SPEED) / 1000;
+      let rotationStep = (dt as RCL_Unit * self.config.rotationSpeed) / 1000;
       self.mCamera.direction = RCL_wrap(self.mCamera.direction + turnDirection as RCL_Unit * rotationStep as RCL_Unit, RCL_UNITS_PER_SQUARE);
     }
 
     let prevHeight = self.mCamera.height;
 
-    renderer.RCL_moveCameraWithCollision(&mut self.mCamera,moveOffset,self.mVericalSpeed, computeHeight, false);
+    renderer.RCL_moveCameraWithCollision(map, &mut self.mCamera,moveOffset,self.mVericalSpeed, computeHeight, false);
 
     let heightDiff = self.mCamera.height - prevHeight;
 
@@ -365,11 +473,12 @@ impl Player {
 		}
 
     if jump && self.mVericalSpeed == 0 && self.mVericalSpeedPrev == 0 {
-			self.mVericalSpeed = PLAYER_JUMP_SPEED; // jump
+			self.mVericalSpeed = self.config.jumpSpeed; // jump
 		}
 
     if shearDirection != 0 {
-			self.mCamera.shear = RCL_clamp((self.mCamera.shear + shearDirection) as RCL_Unit * 10, -self.mCamera.resolution.y, self.mCamera.resolution.y) as i16;
+			let resolutionY = renderer.config().resolutionY as RCL_Unit;
+			self.mCamera.shear = RCL_clamp((self.mCamera.shear + shearDirection) as RCL_Unit * 10, -resolutionY, resolutionY) as i16;
     } else {
 			self.mCamera.shear /= 2;
 		}
@@ -377,22 +486,26 @@ impl Player {
 		self.mVericalSpeedPrev = self.mVericalSpeed;
 
     if computeHeight {
-			self.mVericalSpeed -= (dt as RCL_Unit * GRAVITY_ACCELERATION) / 1000; // gravity
+			self.mVericalSpeed -= (dt as RCL_Unit * self.config.gravity) / 1000; // gravity
 		}
   }
 }
 
 /// Sprite class, again just bare minimum to fit the needs. Prefer writing your own.
+#[derive(Copy, Clone)]
 pub struct Sprite {
-  pub mImage:&'static[u8],
+  pub mImages:RclSpriteImages,
 	pub mPosition:RCL_Vector2D,
 	pub mHeight:RCL_Unit,
 	pub mPixelSize:RCL_Unit,
+	/// Where the front of a directional sprite faces, an angle like the camera direction.
+	pub mDirection:RCL_Unit,
 }
 impl Sprite {
-  pub const fn new(image:&'static[u8], squareX:i16, squareY:i16, z:RCL_Unit, pixelSize:RCL_Unit) -> Sprite {
+  pub const fn new(images:RclSpriteImages, squareX:i16, squareY:i16, z:RCL_Unit, pixelSize:RCL_Unit) -> Sprite {
 		Sprite {
-			mImage: image,
+			mImages: images,
+			mDirection: 0,
 			mPixelSize: pixelSize,
 			mPosition:RCL_Vector2D {
 				x: squareX as RCL_Unit * RCL_UNITS_PER_SQUARE + RCL_UNITS_PER_SQUARE / 2,
@@ -404,23 +517,26 @@ impl Sprite {
 }
 
 pub struct RCL_General {
-	/// 1D z-buffer for visibility determination.
 	// was pub screenBuffer:[u8; (SCREEN_WIDTH as u16 * SCREEN_HEIGHT as u16 * SUBSAMPLE as u16) as usize],
-	pub screenBuffer:[u8; (SCREEN_WIDTH as u16 * SCREEN_HEIGHT as u16) as usize],
-	pub zBuffer:[RCL_Unit; SUBSAMPLED_WIDTH as usize],
+	pub screen:Screen,
+	/// 1D z-buffer for visibility determination.
+	pub zBuffer:[RCL_Unit; RCL_MAX_RESOLUTION_X as usize],
 	pub defaultConstraints:RCL_RayConstraints,
+	/// Lights and fog for the pixel function and the sprites.
+	pub lights:RclLights,
 }
 impl RCL_General {
 	pub const fn new() -> RCL_General {
 		RCL_General {
 			// was screenBuffer: [0; (SCREEN_WIDTH as u16 * SCREEN_HEIGHT as u16 * SUBSAMPLE as u16) as usize],
-			screenBuffer: [0; (SCREEN_WIDTH as u16 * SCREEN_HEIGHT as u16) as usize],
-			zBuffer: [0; SUBSAMPLED_WIDTH as usize],
+			screen: Screen::new(),
+			zBuffer: [0; RCL_MAX_RESOLUTION_X as usize],
 			defaultConstraints: RCL_RayConstraints { maxHits: 0, maxSteps: 0 },
+			lights: RclLights::new(),
 		}
 	}
 
-	pub fn initGeneral(&mut self) {
+	pub fn initGeneral(&mut self, config:&RCL_Config) {
 		// pokitto.begin();
 		// pokitto.setFrameRate(FPS);
 		// pokitto.display.setFont(fontTiny);
@@ -429,37 +545,38 @@ impl RCL_General {
 
 		self.defaultConstraints.init();
 
-		for i in 0..SUBSAMPLED_WIDTH {
+		self.screen.resize(config.resolutionX, config.resolutionY);
+
+		for i in 0..RCL_MAX_RESOLUTION_X {
 			self.zBuffer[i as usize] = 0;
 		}
 	}
 
 	/// Draws a scaled sprite on screen in an optimized way. The sprite has to be square in resolution for that.
 	#[inline]
-	pub fn drawSpriteSquare(&mut self, screen:&mut Screen, sprite:&[u8], mut x:i16, mut y:i16, depth:RCL_Unit, size:u16, intensity:i8) {
+	pub fn drawSpriteSquare(&mut self, sprite:RclImage, mut x:i16, mut y:i16, depth:RCL_Unit, size:u16, shade:RclShade) {
 		if
-			size < 0 || size > 200 || // let's not mess up with the incoming array
-			sprite[0] != sprite[1]    // only draw square sprites
+			size > 200 ||           // let's not mess up with the incoming array
+			sprite.width() != sprite.height()   // only draw square sprites
 		{
 			return;
 		}
 
-		// TODO: personal: is SCREEN_HEIGHT enough?
-		let mut samplingIndices:[u16;SCREEN_HEIGHT as usize] = [0u16; SCREEN_HEIGHT as usize];
+		let mut samplingIndices = [0u16; 256];
 
 		// optimization: precompute the indices
 
 		for i in 0..size {
-			samplingIndices[i as usize] = (i * sprite[0] as u16) / size;
+			samplingIndices[i as usize] = ((i as u32 * sprite.width() as u32) / size as u32) as u16;
 		}
 
 		x -= (size / 2) as i16;
 		y -= (size / 2) as i16;
 
-		let mut c:u8;
+		let mut c:RGBA;
 
-		let jTo:i16 = size as i16 - core::cmp::max(0,y + size as i16 - 88 );
-		let iTo:i16 = size as i16 - core::cmp::max(0,x + size as i16 - 110);
+		let jTo:i16 = size as i16 - core::cmp::max(0,y + size as i16 - self.screen.height as i16);
+		let iTo:i16 = size as i16 - core::cmp::max(0,x + size as i16 - self.screen.width as i16);
 
 		let mut i = core::cmp::max(-x,0) as u16;
 		while (i as i16) < iTo {
@@ -467,17 +584,18 @@ impl RCL_General {
 
 			// was if self.zBuffer[(xPos / SUBSAMPLE as i16) as usize] <= depth {
 			if self.zBuffer[xPos as usize] <= depth {
+				i += 1;
 				continue;
 			}
 
-			let columnLocation = 2 + samplingIndices[i as usize] * sprite[0] as u16;
+			let column = samplingIndices[i as usize];
 
 			let mut j = core::cmp::max(-y,0);
 			while j < jTo {
-				c = sprite[columnLocation as usize + samplingIndices[j as usize] as usize];
+				c = sprite.pixel(column, samplingIndices[j as usize]);
 
-				if c != TRANSPARENT_COLOR {
-					screen.drawPixel(xPos, y + j, addIntensity(c, intensity));
+				if c.a != 0 {
+					self.screen.drawPixel(xPos, y + j, shade.apply(c));
 				}
 				j += 1;
 			}
@@ -510,5 +628,73 @@ fn computeAverageColor(texture:&'static[u8], excludeColor:Option<i16>) -> u8 {
     count += 1;
   }
 
-  return encodeHSV((sumH / count) as u8, (sumS / count) as u8, (sumV / count) as u8);
-}
\ No newline at end of file
+  encodeHSV((sumH / count) as u8, (sumS / count) as u8, (sumV / count) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// 2x2 `.rust2d`: red, green / transparent blue, white
+	static SMALL_RGBA:[u8; 4 + 4 * 4] = [
+		0, 2, 0, 2,
+		0xFF, 0, 0, 0xFF,  0, 0xFF, 0, 0xFF,
+		0, 0, 0xFF, 0x10,  0xFF, 0xFF, 0xFF, 0xFF,
+	];
+
+	fn channels(c:RGBA) -> (u8, u8, u8, u8) { (c.r, c.g, c.b, c.a) }
+
+	#[test]
+	fn images_are_sampled_in_both_formats() {
+		let image = RclImage::Rgba(&SMALL_RGBA);
+		assert_eq!((image.width(), image.height()), (2, 2));
+		assert_eq!(channels(image.pixel(1, 0)), (0, 0xFF, 0, 0xFF));
+		assert_eq!(channels(image.pixel(0, 1)).3, 0);
+		assert_eq!(channels(image.sample(RCL_UNITS_PER_SQUARE / 2, 0)), (0, 0xFF, 0, 0xFF));
+		// Repeats
+		assert_eq!(channels(image.sample(-1, RCL_UNITS_PER_SQUARE + RCL_UNITS_PER_SQUARE / 2)), (0xFF, 0xFF, 0xFF, 0xFF));
+		assert_eq!(channels(image.averageColor()), (0xAA, 0xAA, 0x55, 0xFF));
+
+		// Column by column
+		static PALETTE_IMAGE:[u8; 2 + 2] = [1, 2, HUE(3), TRANSPARENT_COLOR];
+		let image = RclImage::Palette(&PALETTE_IMAGE);
+		assert_eq!(channels(image.pixel(0, 0)), channels(paletteColor(HUE(3))));
+		assert_eq!(channels(image.pixel(0, 1)).3, 0);
+		assert_eq!(channels(image.sample(0, RCL_UNITS_PER_SQUARE - 1)).3, 0);
+
+		let texture = RclImage::Rgba(include_bytes!("../../res/texture.rust2d"));
+		assert_eq!((texture.width(), texture.height()), (512, 512));
+	}
+
+	#[test]
+	fn intensity_keeps_the_hue() {
+		let orange = RGBA::rgba(200, 100, 0, 0xFF);
+		assert_eq!(channels(addIntensityRGB(orange, 0)), (200, 100, 0, 0xFF));
+		assert_eq!(channels(addIntensityRGB(orange, -4)), (136, 68, 0, 0xFF));
+		assert_eq!(channels(addIntensityRGB(orange, 8)), (255, 127, 0, 0xFF));
+		assert_eq!(channels(addIntensityRGB(orange, -20)), (0, 0, 0, 0xFF));
+		assert_eq!(channels(addIntensityRGB(RGBA::rgba(0, 0, 0, 0), 2)), (32, 32, 32, 0));
+	}
+
+	#[test]
+	fn sprites_skip_transparent_pixels() {
+		let config = RCL_Config { resolutionX: 8, resolutionY: 8, ..GENERAL_CONFIG };
+		let mut general = Box::new(RCL_General::new());
+		general.initGeneral(&config);
+		// Everything is behind the sprite
+		for depth in general.zBuffer.iter_mut() {
+			*depth = RCL_INFINITY;
+		}
+
+		// Each pixel of the image becomes 2x2, darkened a step
+		general.drawSpriteSquare(RclImage::Rgba(&SMALL_RGBA), 4, 4, RCL_UNITS_PER_SQUARE, 4, RclShade { intensity: -1, ..RclShade::NONE });
+		let mut image = vec![RGBA::zeroed(); crate::SCREEN_SPACE as usize];
+		general.screen.projectInto(&mut image, crate::canvas::Rect::new(0, 0, 8, 8));
+		let at = |x:usize, y:usize| channels(image[y * crate::SCREEN_WIDTH as usize + x]);
+		assert_eq!(at(2, 2), (0xEF, 0, 0, 0xFF));
+		assert_eq!(at(5, 3), (0, 0xEF, 0, 0xFF));
+		assert_eq!(at(3, 5), (0, 0, 0, 0xFF));
+		assert_eq!(at(5, 5), (0xEF, 0xEF, 0xEF, 0xFF));
+		assert_eq!(at(1, 1), (0, 0, 0, 0xFF));
+	}
+}
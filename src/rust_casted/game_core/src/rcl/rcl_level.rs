@@ -0,0 +1,601 @@
+// Text format for raycaster levels, `#` starts a comment and spaces around `=` are optional:
+//
+//   size = 29 21           width and height in squares
+//   start = 12.5 6.5 -90   player position in squares and direction in degrees
+//
+//   [floor]                `height` lines of `width` floor heights, from the far end
+//   ...                    (y = height - 1) to y = 0, like a top-down map
+//   [ceiling]              same as floor, "-" is open sky
+//   [texture]              same as floor, wall texture indices
+//   [floor texture]        optional, same as texture for the floors (default 0)
+//   [ceiling texture]      optional, same as texture for the ceilings (default 0)
+//   [light]                optional, same as floor, ambient light in intensity steps
+//                          (default 0)
+//   [doors]                x y left|right|up|down [use|timed], a door in the square
+//                          that rolls that way, opened by the player (default) or a timer
+//   [sprites]              image x y z pixelSize, position and height in squares
+//
+// Heights are in eighths of a square, like the level arrays of the C demos.
+
+use std::fmt::{self, Display, Formatter, Write};
+use crate::rcl::*;
+
+/// Height units per square in the floor and ceiling grids.
+pub const HEIGHT_STEPS:RCL_Unit = 8;
+/// Ceiling of open sky squares, "-" in the file.
+pub const SKY:i8 = i8::MAX;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RclDoorRoll {
+	Left,
+	Right,
+	/// Into the ceiling.
+	Up,
+	/// Into the floor.
+	Down,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RclDoorTrigger {
+	/// Opens when the player uses it.
+	Use,
+	/// Opens and closes by itself, a moving wall.
+	Timed,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RclDoor {
+	pub x:i16,
+	pub y:i16,
+	pub roll:RclDoorRoll,
+	pub trigger:RclDoorTrigger,
+}
+
+const DOOR_ROLLS:[(&str, RclDoorRoll); 4] = [
+	("left", RclDoorRoll::Left), ("right", RclDoorRoll::Right), ("up", RclDoorRoll::Up), ("down", RclDoorRoll::Down),
+];
+const DOOR_TRIGGERS:[(&str, RclDoorTrigger); 2] = [("use", RclDoorTrigger::Use), ("timed", RclDoorTrigger::Timed)];
+
+#[derive(Copy, Clone)]
+pub struct RclSpritePlacement {
+	/// Index into the image names the level was parsed with.
+	pub image:usize,
+	pub position:RCL_Vector2D,
+	pub height:RCL_Unit,
+	pub pixelSize:RCL_Unit,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RclLevelError {
+	/// 1-based, like text editors count.
+	pub line:usize,
+	pub column:usize,
+	pub message:String,
+}
+
+impl Display for RclLevelError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}: {}", self.line, self.column, self.message)
+	}
+}
+
+pub struct RclLevel {
+	pub width:u16,
+	pub height:u16,
+	/// Grids are stored in file order, the first row is the far end.
+	pub floor:Vec<i8>,
+	pub ceiling:Vec<i8>,
+	pub texture:Vec<u8>,
+	pub floorTexture:Vec<u8>,
+	pub ceilingTexture:Vec<u8>,
+	pub light:Vec<i8>,
+	pub doors:Vec<RclDoor>,
+	pub sprites:Vec<RclSpritePlacement>,
+	pub startPosition:RCL_Vector2D,
+	pub startDirection:RCL_Unit,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Section {
+	Header,
+	Floor,
+	Ceiling,
+	Texture,
+	FloorTexture,
+	CeilingTexture,
+	Light,
+	Doors,
+	Sprites,
+}
+
+impl Section {
+	const fn name(self) -> &'static str {
+		match self {
+			Section::Header => "header",
+			Section::Floor => "floor",
+			Section::Ceiling => "ceiling",
+			Section::Texture => "texture",
+			Section::FloorTexture => "floor texture",
+			Section::CeilingTexture => "ceiling texture",
+			Section::Light => "light",
+			Section::Doors => "doors",
+			Section::Sprites => "sprites",
+		}
+	}
+}
+
+/// Whitespace separated words of `text` with their 1-based columns.
+fn words(text:&str) -> Vec<(usize, &str)> {
+	let mut words = Vec::new();
+	let mut start = None;
+	for (column, (i, c)) in text.char_indices().enumerate() {
+		match (start, c.is_whitespace()) {
+			(None, false) => start = Some((column + 1, i)),
+			(Some((column, s)), true) => {
+				words.push((column, &text[s..i]));
+				start = None;
+			},
+			_ => {},
+		}
+	}
+	if let Some((column, s)) = start {
+		words.push((column, &text[s..]));
+	}
+	words
+}
+
+/// Squares as written by `RclLevel::toText`, exact for all RCL_Units parseSquares reads.
+fn squares(units:RCL_Unit) -> f32 {
+	units as f32 / RCL_UNITS_PER_SQUARE as f32
+}
+
+/// Converts squares with an optional fraction to RCL_Units.
+fn parseSquares(word:&str) -> Option<RCL_Unit> {
+	let squares:f32 = word.parse().ok()?;
+	if squares.is_finite() && squares.abs() < 1e5 {
+		Some((squares * RCL_UNITS_PER_SQUARE as f32).round() as RCL_Unit)
+	} else {
+		None
+	}
+}
+
+impl RclLevel {
+	pub const fn new() -> RclLevel {
+		RclLevel {
+			width: 0,
+			height: 0,
+			floor: Vec::new(),
+			ceiling: Vec::new(),
+			texture: Vec::new(),
+			floorTexture: Vec::new(),
+			ceilingTexture: Vec::new(),
+			light: Vec::new(),
+			doors: Vec::new(),
+			sprites: Vec::new(),
+			startPosition: RCL_Vector2D::ZERO,
+			startDirection: 0,
+		}
+	}
+
+	/// Parses a level, sprite images are referred to by `imageNames` and wall,
+	/// floor and ceiling textures have to be below `textureCount`.
+	pub fn parse(text:&str, imageNames:&[&str], textureCount:u8) -> Result<RclLevel, RclLevelError> {
+		let mut level = RclLevel::new();
+		let mut section = Section::Header;
+		let mut seen:Vec<(Section, usize)> = Vec::new();
+		let mut hasSize = false;
+		let mut hasStart = false;
+		let mut lineNumber = 0;
+
+		for (i, line) in text.lines().enumerate() {
+			lineNumber = i + 1;
+			let error = |column:usize, message:String| RclLevelError { line: lineNumber, column, message };
+			let content = line.split('#').next().unwrap();
+			let words = words(content);
+			if words.is_empty() {
+				continue;
+			}
+			let (column, first) = words[0];
+
+			if first.starts_with('[') {
+				let name = content.trim();
+				section = match name {
+					"[floor]" => Section::Floor,
+					"[ceiling]" => Section::Ceiling,
+					"[texture]" => Section::Texture,
+					"[floor texture]" => Section::FloorTexture,
+					"[ceiling texture]" => Section::CeilingTexture,
+					"[light]" => Section::Light,
+					"[doors]" => Section::Doors,
+					"[sprites]" => Section::Sprites,
+					_ => return Err(error(column, format!("unknown section {}", name))),
+				};
+				if seen.iter().any(|&(s, _)| s == section) {
+					return Err(error(column, format!("duplicate section {}", name)));
+				}
+				if !hasSize {
+					return Err(error(column, "size has to come before the sections".to_string()));
+				}
+				seen.push((section, lineNumber));
+				continue;
+			}
+
+			match section {
+				Section::Header => {
+					// The key ends at "=", which need not be surrounded by spaces
+					let key = first.split('=').next().unwrap();
+					let equals = match content.find('=') {
+						Some(equals) if !key.is_empty() && content[..equals].trim() == key => equals,
+						_ => return Err(error(column, format!("expected {} = ...", key))),
+					};
+					let offset = content[..=equals].chars().count();
+					let values:Vec<(usize, &str)> = self::words(&content[equals + 1..]).into_iter().map(|(c, word)| (c + offset, word)).collect();
+					let values = values.as_slice();
+					match key {
+						"size" => {
+							let [width, height] = match values {
+								&[width, height] => [width, height],
+								_ => return Err(error(column, "size needs a width and a height".to_string())),
+							};
+							for &(column, word) in &[width, height] {
+								match word.parse::<u16>() {
+									Ok(size) if size > 0 && size <= i16::MAX as u16 => {},
+									_ => return Err(error(column, format!("invalid size {}", word))),
+								}
+							}
+							level.width = width.1.parse().unwrap();
+							level.height = height.1.parse().unwrap();
+							hasSize = true;
+						},
+						"start" => {
+							let [x, y, direction] = match values {
+								&[x, y, direction] => [x, y, direction],
+								_ => return Err(error(column, "start needs x, y and a direction".to_string())),
+							};
+							level.startPosition.x = parseSquares(x.1).ok_or_else(|| error(x.0, format!("invalid position {}", x.1)))?;
+							level.startPosition.y = parseSquares(y.1).ok_or_else(|| error(y.0, format!("invalid position {}", y.1)))?;
+							let degrees:i16 = direction.1.parse().map_err(|_| error(direction.0, format!("invalid direction {}", direction.1)))?;
+							level.startDirection = RCL_degreesToUnitsAngle(degrees);
+							hasStart = true;
+						},
+						_ => return Err(error(column, format!("unknown key {}", key))),
+					}
+				},
+				Section::Floor | Section::Ceiling | Section::Texture | Section::FloorTexture | Section::CeilingTexture | Section::Light => {
+					let rows = match section {
+						Section::Floor => level.floor.len(),
+						Section::Ceiling => level.ceiling.len(),
+						Section::FloorTexture => level.floorTexture.len(),
+						Section::CeilingTexture => level.ceilingTexture.len(),
+						Section::Light => level.light.len(),
+						_ => level.texture.len(),
+					} / level.width as usize;
+					if rows == level.height as usize {
+						return Err(error(column, format!("{} has more than {} rows", section.name(), level.height)));
+					}
+					if words.len() != level.width as usize {
+						let column = words.get(level.width as usize).map_or(content.trim_end().chars().count() + 1, |w| w.0);
+						return Err(error(column, format!("expected {} values, found {}", level.width, words.len())));
+					}
+					for &(column, word) in &words {
+						let invalid = || error(column, format!("invalid {} {}", section.name(), word));
+						match section {
+							Section::Floor => level.floor.push(word.parse().map_err(|_| invalid())?),
+							Section::Ceiling if word == "-" => level.ceiling.push(SKY),
+							Section::Ceiling => match word.parse::<i8>() {
+								Ok(SKY) => return Err(error(column, format!("ceiling {} is reserved for the sky, use -", SKY))),
+								Ok(ceiling) => level.ceiling.push(ceiling),
+								Err(_) => return Err(invalid()),
+							},
+							Section::Light => level.light.push(word.parse().map_err(|_| invalid())?),
+							_ => match word.parse::<u8>() {
+								Ok(texture) if texture < textureCount => match section {
+									Section::FloorTexture => level.floorTexture.push(texture),
+									Section::CeilingTexture => level.ceilingTexture.push(texture),
+									_ => level.texture.push(texture),
+								},
+								_ => return Err(invalid()),
+							},
+						}
+					}
+				},
+				Section::Doors => {
+					let (x, y, roll, trigger) = match *words.as_slice() {
+						[x, y, roll] => (x, y, roll, None),
+						[x, y, roll, trigger] => (x, y, roll, Some(trigger)),
+						_ => return Err(error(column, "a door needs x, y, a roll and optionally a trigger".to_string())),
+					};
+					let square = |(column, word):(usize, &str)| word.parse::<i16>().map_err(|_| error(column, format!("invalid square {}", word)));
+					let (xColumn, x, y) = (x.0, square(x)?, square(y)?);
+					if level.index(x, y).is_none() {
+						return Err(error(xColumn, format!("door {} {} is outside the level", x, y)));
+					}
+					let roll = DOOR_ROLLS.iter().find(|&&(name, _)| name == roll.1).map(|&(_, roll)| roll)
+						.ok_or_else(|| error(roll.0, format!("invalid roll {}, expected left, right, up or down", roll.1)))?;
+					let trigger = match trigger {
+						None => RclDoorTrigger::Use,
+						Some((column, word)) => DOOR_TRIGGERS.iter().find(|&&(name, _)| name == word).map(|&(_, trigger)| trigger)
+							.ok_or_else(|| error(column, format!("invalid trigger {}, expected use or timed", word)))?,
+					};
+					level.doors.push(RclDoor { x, y, roll, trigger });
+				},
+				Section::Sprites => {
+					let [image, x, y, z, size] = match words.as_slice() {
+						&[image, x, y, z, size] => [image, x, y, z, size],
+						_ => return Err(error(column, "a sprite needs an image, x, y, z and a pixel size".to_string())),
+					};
+					let position = |(column, word):(usize, &str)| parseSquares(word).ok_or_else(|| error(column, format!("invalid position {}", word)));
+					level.sprites.push(RclSpritePlacement {
+						image: imageNames.iter().position(|&name| name == image.1)
+							.ok_or_else(|| error(image.0, format!("unknown image {}", image.1)))?,
+						position: RCL_Vector2D { x: position(x)?, y: position(y)? },
+						height: position(z)?,
+						pixelSize: size.1.parse().map_err(|_| error(size.0, format!("invalid pixel size {}", size.1)))?,
+					});
+				},
+			}
+		}
+
+		let end = |message:String| RclLevelError { line: lineNumber + 1, column: 1, message };
+		if !hasSize {
+			return Err(end("missing size".to_string()));
+		}
+		if !hasStart {
+			return Err(end("missing start".to_string()));
+		}
+		let squares = level.width as usize * level.height as usize;
+		for grid in [&mut level.floorTexture, &mut level.ceilingTexture].iter_mut() {
+			if grid.is_empty() {
+				grid.resize(squares, 0);
+			}
+		}
+		if level.light.is_empty() {
+			level.light.resize(squares, 0);
+		}
+		for &(section, length) in &[
+			(Section::Floor, level.floor.len()), (Section::Ceiling, level.ceiling.len()), (Section::Texture, level.texture.len()),
+			(Section::FloorTexture, level.floorTexture.len()), (Section::CeilingTexture, level.ceilingTexture.len()),
+			(Section::Light, level.light.len()),
+		] {
+			match seen.iter().find(|&&(s, _)| s == section) {
+				None if length == squares => {}, // optional
+				None => return Err(end(format!("missing section [{}]", section.name()))),
+				Some(&(_, line)) if length != squares => return Err(RclLevelError {
+					line,
+					column: 1,
+					message: format!("{} has {} rows, expected {}", section.name(), length / level.width as usize, level.height),
+				}),
+				_ => {},
+			}
+		}
+
+		Ok(level)
+	}
+
+	/// The level in the format `parse` reads, sprite images are written by `imageNames`.
+	pub fn toText(&self, imageNames:&[&str]) -> String {
+		let mut text = String::new();
+		self.write(&mut text, imageNames).unwrap(); // writing to a String does not fail
+		text
+	}
+
+	fn write(&self, text:&mut String, imageNames:&[&str]) -> fmt::Result {
+		let degrees = (self.startDirection * 360 + RCL_UNITS_PER_SQUARE / 2).div_euclid(RCL_UNITS_PER_SQUARE);
+		writeln!(text, "size = {} {}", self.width, self.height)?;
+		writeln!(text, "start = {} {} {}", squares(self.startPosition.x), squares(self.startPosition.y), degrees)?;
+
+		// Section name, text of a square and whether the section is written
+		type Grid<'a> = (&'a str, &'a dyn Fn(usize) -> String, bool);
+		let grids:[Grid; 6] = [
+			("floor", &|i| self.floor[i].to_string(), true),
+			("ceiling", &|i| if self.ceiling[i] == SKY { "-".to_string() } else { self.ceiling[i].to_string() }, true),
+			("texture", &|i| self.texture[i].to_string(), true),
+			("floor texture", &|i| self.floorTexture[i].to_string(), self.floorTexture.iter().any(|&t| t != 0)),
+			("ceiling texture", &|i| self.ceilingTexture[i].to_string(), self.ceilingTexture.iter().any(|&t| t != 0)),
+			("light", &|i| self.light[i].to_string(), self.light.iter().any(|&l| l != 0)),
+		];
+		for (name, value, _) in grids.iter().filter(|grid| grid.2) {
+			writeln!(text, "\n[{}]", name)?;
+			for row in 0..self.height as usize {
+				for x in 0..self.width as usize {
+					write!(text, "{}{:>3}", if x == 0 { "" } else { " " }, value(row * self.width as usize + x))?;
+				}
+				writeln!(text)?;
+			}
+		}
+
+		if !self.doors.is_empty() {
+			writeln!(text, "\n[doors]")?;
+			for door in &self.doors {
+				let roll = DOOR_ROLLS.iter().find(|&&(_, roll)| roll == door.roll).unwrap().0;
+				match door.trigger {
+					RclDoorTrigger::Use => writeln!(text, "{} {} {}", door.x, door.y, roll)?,
+					RclDoorTrigger::Timed => writeln!(text, "{} {} {} timed", door.x, door.y, roll)?,
+				}
+			}
+		}
+
+		if !self.sprites.is_empty() {
+			writeln!(text, "\n[sprites]")?;
+			for sprite in &self.sprites {
+				writeln!(text, "{} {} {} {} {}",
+					imageNames[sprite.image], squares(sprite.position.x), squares(sprite.position.y), squares(sprite.height), sprite.pixelSize)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Index of the square in the grids.
+	#[inline(always)]
+	pub fn index(&self, x:i16, y:i16) -> Option<usize> {
+		if x >= 0 && x < self.width as i16 && y >= 0 && y < self.height as i16 {
+			Some((self.height - 1 - y as u16) as usize * self.width as usize + x as usize)
+		} else {
+			None
+		}
+	}
+}
+
+impl RclMap for RclLevel {
+	fn floorHeight(&self, x:i16, y:i16) -> RCL_Unit {
+		self.index(x, y).map_or(0, |i| self.floor[i] as RCL_Unit * (RCL_UNITS_PER_SQUARE / HEIGHT_STEPS))
+	}
+
+	fn hasCeiling(&self) -> bool { true }
+
+	fn ceilingHeight(&self, x:i16, y:i16) -> RCL_Unit {
+		self.index(x, y).map_or(SKY, |i| self.ceiling[i]) as RCL_Unit * (RCL_UNITS_PER_SQUARE / HEIGHT_STEPS)
+	}
+
+	fn texture(&self, x:i16, y:i16) -> RCL_Unit {
+		self.index(x, y).map_or(0, |i| self.texture[i] as RCL_Unit)
+	}
+
+	fn floorTexture(&self, x:i16, y:i16) -> RCL_Unit {
+		self.index(x, y).map_or(0, |i| self.floorTexture[i] as RCL_Unit)
+	}
+
+	fn ceilingTexture(&self, x:i16, y:i16) -> RCL_Unit {
+		self.index(x, y).map_or(0, |i| self.ceilingTexture[i] as RCL_Unit)
+	}
+
+	/// SKY ceilings and everything around the level.
+	fn isSky(&self, x:i16, y:i16) -> bool {
+		self.index(x, y).is_none_or(|i| self.ceiling[i] == SKY)
+	}
+
+	fn ambientLight(&self, x:i16, y:i16) -> RCL_Unit {
+		self.index(x, y).map_or(0, |i| self.light[i] as RCL_Unit)
+	}
+
+	fn hasDoors(&self) -> bool { !self.doors.is_empty() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SMALL:&str = "
+size = 3 2
+start = 1.5 0.5 90 # facing +y
+
+[floor]
+8 0 8
+0 0 16
+[ceiling]
+- 24 24
+24 24 24
+[texture]
+1 0 1
+0 0 2
+[floor texture]
+0 2 0
+1 1 1
+
+[doors]
+1 1 left
+2 0 up timed
+
+[sprites]
+barrel 0.5 0.25 0.75 120
+
+[light]
+-2 0 0
+0 0 3
+";
+
+	fn error(text:&str) -> (usize, usize, String) {
+		let error = RclLevel::parse(text, &["barrel"], 3).err().expect("should not parse");
+		(error.line, error.column, error.message)
+	}
+
+	#[test]
+	fn parses_a_level() {
+		let level = RclLevel::parse(SMALL, &["tree", "barrel"], 3).unwrap_or_else(|e| panic!("{}", e));
+		assert_eq!((level.width, level.height), (3, 2));
+		assert_eq!((level.startPosition.x, level.startPosition.y), (3 * RCL_UNITS_PER_SQUARE / 2, RCL_UNITS_PER_SQUARE / 2));
+		assert_eq!(level.startDirection, RCL_UNITS_PER_SQUARE / 4);
+
+		// The first row is the far end
+		assert_eq!(level.floorHeight(2, 0), 2 * RCL_UNITS_PER_SQUARE);
+		assert_eq!(level.floorHeight(0, 1), RCL_UNITS_PER_SQUARE);
+		assert_eq!(level.floorHeight(2, 1), RCL_UNITS_PER_SQUARE);
+		assert_eq!(level.texture(2, 0), 2);
+		assert_eq!(level.ceilingHeight(0, 1), SKY as RCL_Unit * RCL_UNITS_PER_SQUARE / HEIGHT_STEPS);
+		assert_eq!(level.ceilingHeight(1, 1), 3 * RCL_UNITS_PER_SQUARE);
+		// Outside is open floor under the sky
+		assert_eq!((level.floorHeight(-1, 0), level.texture(3, 0)), (0, 0));
+		assert_eq!(level.ceilingHeight(0, 2), level.ceilingHeight(0, 1));
+		assert!(level.isSky(0, 1) && !level.isSky(1, 1) && level.isSky(0, 2));
+
+		assert_eq!((level.floorTexture(1, 1), level.floorTexture(0, 0), level.floorTexture(-1, 0)), (2, 1, 0));
+		// The ceiling textures are left out
+		assert!(level.ceilingTexture.len() == 6 && level.ceilingTexture.iter().all(|&t| t == 0));
+		assert_eq!((level.ambientLight(0, 1), level.ambientLight(2, 0), level.ambientLight(1, 1), level.ambientLight(5, 5)), (-2, 3, 0, 0));
+
+		assert_eq!(level.doors, vec![
+			RclDoor { x: 1, y: 1, roll: RclDoorRoll::Left, trigger: RclDoorTrigger::Use },
+			RclDoor { x: 2, y: 0, roll: RclDoorRoll::Up, trigger: RclDoorTrigger::Timed },
+		]);
+		assert!(level.hasDoors());
+
+		assert_eq!(level.sprites.len(), 1);
+		let sprite = level.sprites[0];
+		assert_eq!((sprite.image, sprite.position.x, sprite.position.y), (1, RCL_UNITS_PER_SQUARE / 2, RCL_UNITS_PER_SQUARE / 4));
+		assert_eq!((sprite.height, sprite.pixelSize), (3 * RCL_UNITS_PER_SQUARE / 4, 120));
+	}
+
+	#[test]
+	fn errors_point_at_the_mistake() {
+		assert_eq!(error(&SMALL.replace("0 0 16", "0 0 16 4")), (7, 8, "expected 3 values, found 4".to_string()));
+		assert_eq!(error(&SMALL.replace("0 0 16", "0 0")), (7, 4, "expected 3 values, found 2".to_string()));
+		assert_eq!(error(&SMALL.replace("24 24 24", "24 x 24")), (10, 4, "invalid ceiling x".to_string()));
+		assert_eq!(error(&SMALL.replace("0 0 2", "0 0 3")), (13, 5, "invalid texture 3".to_string()));
+		assert_eq!(error(&SMALL.replace("0 2 0", "0 3 0")), (15, 3, "invalid floor texture 3".to_string()));
+		assert_eq!(error(&SMALL.replace("1 1 left", "1 2 left")), (19, 1, "door 1 2 is outside the level".to_string()));
+		assert_eq!(error(&SMALL.replace("up timed", "up later")), (20, 8, "invalid trigger later, expected use or timed".to_string()));
+		assert_eq!(error(&SMALL.replace("barrel 0.5", "crate 0.5")), (23, 1, "unknown image crate".to_string()));
+		assert_eq!(error(&SMALL.replace("start = 1.5", "start = one")), (3, 9, "invalid position one".to_string()));
+		assert_eq!(error(&SMALL.replace("[doors]", "[door]")), (18, 1, "unknown section [door]".to_string()));
+		assert_eq!(error(&SMALL.replace("0 0 2\n", "")), (11, 1, "texture has 1 rows, expected 2".to_string()));
+		assert_eq!(error(&SMALL.replace("start", "# start")), (28, 1, "missing start".to_string()));
+		assert_eq!(error(&SMALL.replace("- 24 24", "127 24 24")), (9, 1, "ceiling 127 is reserved for the sky, use -".to_string()));
+		assert_eq!(error(&SMALL.replace("size = 3 2", "size 3 2")), (2, 1, "expected size = ...".to_string()));
+		assert_eq!(error(&SMALL.replace("start = 1.5", "start=one")), (3, 7, "invalid position one".to_string()));
+	}
+
+	#[test]
+	fn equals_needs_no_spaces() {
+		let level = RclLevel::parse(&SMALL.replace("size = 3 2", "size=3 2").replace("start = ", "start= "), &["barrel"], 3).unwrap_or_else(|e| panic!("{}", e));
+		assert_eq!((level.width, level.height), (3, 2));
+		assert_eq!(level.startDirection, RCL_UNITS_PER_SQUARE / 4);
+	}
+
+	#[test]
+	fn levels_are_written_back_as_parsed() {
+		for (text, names) in [(SMALL, &["tree", "barrel"][..]), (demo1::DEMO1_LEVEL, &demo1::SPRITE_NAMES[..])].iter() {
+			let level = RclLevel::parse(text, names, 5).unwrap();
+			let written = level.toText(names);
+			let again = RclLevel::parse(&written, names, 5).unwrap_or_else(|e| panic!("{}\n{}", e, written));
+
+			assert_eq!((again.width, again.height), (level.width, level.height));
+			assert_eq!((again.startPosition.x, again.startPosition.y, again.startDirection), (level.startPosition.x, level.startPosition.y, level.startDirection));
+			assert!(again.floor == level.floor && again.ceiling == level.ceiling && again.texture == level.texture);
+			assert!(again.floorTexture == level.floorTexture && again.ceilingTexture == level.ceilingTexture && again.light == level.light);
+			assert_eq!(again.doors, level.doors);
+			assert_eq!(again.sprites.len(), level.sprites.len());
+			for (a, b) in again.sprites.iter().zip(&level.sprites) {
+				assert_eq!((a.image, a.position.x, a.position.y, a.height, a.pixelSize), (b.image, b.position.x, b.position.y, b.height, b.pixelSize));
+			}
+			assert_eq!(again.toText(names), written);
+		}
+	}
+
+	#[test]
+	fn demo1_level_parses() {
+		let level = demo1::Demo1Level::load().level;
+		assert_eq!((level.width, level.height), (29, 21));
+		assert_eq!(level.sprites.len(), 17);
+		assert_eq!(level.startDirection, -RCL_UNITS_PER_SQUARE / 4);
+	}
+}
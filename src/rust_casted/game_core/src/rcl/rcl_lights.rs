@@ -0,0 +1,219 @@
+// Lighting of the raycaster demos: the ambient light of the map squares, point lights like torches that brighten what
+// is around them and fog that colors everything in the distance. Light goes through walls, there are no shadows. A
+// pixel or a sprite gets an `RclShade` for where it is and applies it to its colors.
+
+use crate::RGBA;
+use crate::rcl::*;
+use crate::rcl::rcl_general::addIntensityRGB;
+
+/// Ticks a flickering light keeps its brightness for.
+pub const FLICKER_TICKS:u32 = 3;
+
+/// Colors blend into the fog color from `start` to `end` depth.
+#[derive(Copy, Clone)]
+pub struct RclFog {
+	pub color:RGBA,
+	pub start:RCL_Unit,
+	pub end:RCL_Unit,
+}
+impl RclFog {
+	/// No fog at all.
+	pub const NONE:RclFog = RclFog { color: RGBA::rgba(0, 0, 0, 0xFF), start: RCL_INFINITY, end: RCL_INFINITY };
+
+	pub const fn new(color:RGBA, start:RCL_Unit, end:RCL_Unit) -> RclFog {
+		RclFog { color, start, end }
+	}
+
+	/// 0 before the fog starts to 255 from its end on.
+	pub fn amount(&self, depth:RCL_Unit) -> u8 {
+		if depth <= self.start {
+			0
+		} else if depth >= self.end {
+			0xFF
+		} else {
+			((depth - self.start) as i64 * 0xFF / (self.end - self.start) as i64) as u8
+		}
+	}
+}
+
+/// Lighting of a pixel or a sprite, `apply` it to the colors.
+#[derive(Copy, Clone)]
+pub struct RclShade {
+	/// Steps of `addIntensityRGB`.
+	pub intensity:i8,
+	pub fogColor:RGBA,
+	/// 0 none to 255 all fog.
+	pub fogAmount:u8,
+}
+impl RclShade {
+	/// Leaves the colors as they are.
+	pub const NONE:RclShade = RclShade { intensity: 0, fogColor: RGBA::rgba(0, 0, 0, 0xFF), fogAmount: 0 };
+
+	pub fn apply(self, color:RGBA) -> RGBA {
+		let color = addIntensityRGB(color, self.intensity);
+		if self.fogAmount == 0 {
+			return color;
+		}
+
+		let blend = |c:u8, fog:u8| ((c as u32 * (0xFF - self.fogAmount as u32) + fog as u32 * self.fogAmount as u32) / 0xFF) as u8;
+		RGBA::rgba(blend(color.r, self.fogColor.r), blend(color.g, self.fogColor.g), blend(color.b, self.fogColor.b), color.a)
+	}
+}
+
+/// A point light, brightest at its position and fading out to its radius.
+#[derive(Copy, Clone)]
+pub struct RclLight {
+	pub position:RCL_Vector2D,
+	pub radius:RCL_Unit,
+	/// Intensity steps added at the light.
+	pub intensity:i8,
+	/// Steps the light randomly dims by, 0 for a steady light.
+	pub flicker:i8,
+}
+impl RclLight {
+	pub const fn steady(position:RCL_Vector2D, radius:RCL_Unit, intensity:i8) -> RclLight {
+		RclLight { position, radius, intensity, flicker: 0 }
+	}
+
+	/// Like a torch or a fire.
+	pub const fn flickering(position:RCL_Vector2D, radius:RCL_Unit, intensity:i8, flicker:i8) -> RclLight {
+		RclLight { position, radius, intensity, flicker }
+	}
+}
+
+/// The lights of a scene and its fog.
+pub struct RclLights {
+	/// With the current intensity of each.
+	lights:Vec<(RclLight, i8)>,
+	pub fog:RclFog,
+}
+impl RclLights {
+	pub const fn new() -> RclLights {
+		RclLights { lights: Vec::new(), fog: RclFog::NONE }
+	}
+
+	pub fn clear(&mut self) {
+		self.lights.clear();
+	}
+
+	pub fn add(&mut self, light:RclLight) {
+		self.lights.push((light, light.intensity));
+	}
+
+	pub fn len(&self) -> usize {
+		self.lights.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.lights.is_empty()
+	}
+
+	/// Flickers the lights, the same tick number always gives the same intensities.
+	pub fn update(&mut self, tickNumber:u32) {
+		let step = tickNumber / FLICKER_TICKS;
+		for (i, (light, intensity)) in self.lights.iter_mut().enumerate() {
+			let dim = if light.flicker > 0 {
+				let hash = step.wrapping_mul(0x9E3779B1) ^ (i as u32).wrapping_mul(0x85EBCA6B);
+				((hash ^ (hash >> 15)).wrapping_mul(0x2C1B3C6D) >> 24) as i32 % (light.flicker as i32 + 1)
+			} else {
+				0
+			};
+			// In i32, the extremes of i8 don't fit the sums
+			*intensity = (light.intensity as i32 - dim).clamp(i8::MIN as i32, i8::MAX as i32) as i8;
+		}
+	}
+
+	/// Intensity steps the lights add at the position.
+	pub fn intensityAt(&self, position:RCL_Vector2D) -> RCL_Unit {
+		let mut sum = 0;
+		for &(light, intensity) in &self.lights {
+			let distance = RCL_Vector2D::dist(light.position, position, RCL_DistApprox::Octagonal);
+			if distance < light.radius {
+				sum += intensity as RCL_Unit * (light.radius - distance) / light.radius;
+			}
+		}
+		sum
+	}
+
+	/// Lighting of something at the position and depth in a square with the ambient light.
+	pub fn shade(&self, ambient:RCL_Unit, position:RCL_Vector2D, depth:RCL_Unit) -> RclShade {
+		RclShade {
+			intensity: RCL_clamp(ambient + self.intensityAt(position), -16, 16) as i8,
+			fogColor: self.fog.color,
+			fogAmount: self.fog.amount(depth),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn channels(c:RGBA) -> (u8, u8, u8, u8) { (c.r, c.g, c.b, c.a) }
+
+	fn at(x:RCL_Unit, y:RCL_Unit) -> RCL_Vector2D {
+		RCL_Vector2D { x, y }
+	}
+
+	#[test]
+	fn fog_colors_the_distance() {
+		let fog = RclFog::new(RGBA::rgba(0, 0, 0xFF, 0xFF), RCL_UNITS_PER_SQUARE, 3 * RCL_UNITS_PER_SQUARE);
+		assert_eq!((fog.amount(0), fog.amount(2 * RCL_UNITS_PER_SQUARE), fog.amount(10 * RCL_UNITS_PER_SQUARE)), (0, 127, 0xFF));
+
+		let mut lights = RclLights::new();
+		lights.fog = fog;
+		let red = RGBA::rgba(200, 0, 0, 0xFF);
+		assert_eq!(channels(lights.shade(0, at(0, 0), 0).apply(red)), (200, 0, 0, 0xFF));
+		assert_eq!(channels(lights.shade(0, at(0, 0), 2 * RCL_UNITS_PER_SQUARE).apply(red)), (100, 0, 127, 0xFF));
+		assert_eq!(channels(lights.shade(-2, at(0, 0), 4 * RCL_UNITS_PER_SQUARE).apply(red)), (0, 0, 0xFF, 0xFF));
+		// Ambient light without fog
+		assert_eq!(channels(RclLights::new().shade(-2, at(0, 0), 100 * RCL_UNITS_PER_SQUARE).apply(red)), (168, 0, 0, 0xFF));
+	}
+
+	#[test]
+	fn lights_fade_to_their_radius() {
+		let mut lights = RclLights::new();
+		lights.add(RclLight::steady(at(0, 0), 2 * RCL_UNITS_PER_SQUARE, 8));
+		lights.add(RclLight::steady(at(4 * RCL_UNITS_PER_SQUARE, 0), RCL_UNITS_PER_SQUARE, 4));
+		lights.update(0);
+
+		assert_eq!(lights.intensityAt(at(0, 0)), 8);
+		assert_eq!(lights.intensityAt(at(RCL_UNITS_PER_SQUARE, 0)), 4);
+		assert_eq!(lights.intensityAt(at(2 * RCL_UNITS_PER_SQUARE, 0)), 0);
+		assert_eq!(lights.intensityAt(at(7 * RCL_UNITS_PER_SQUARE / 2, 0)), 2);
+		assert_eq!(lights.shade(-1, at(0, 0), 0).intensity, 7);
+	}
+
+	#[test]
+	fn flickering_lights_dim_and_repeat() {
+		let mut lights = RclLights::new();
+		lights.add(RclLight::flickering(at(0, 0), RCL_UNITS_PER_SQUARE, 6, 3));
+		lights.add(RclLight::steady(at(100 * RCL_UNITS_PER_SQUARE, 0), RCL_UNITS_PER_SQUARE, 6));
+
+		let mut seen = Vec::new();
+		for tick in 0..100 {
+			lights.update(tick);
+			let intensity = lights.intensityAt(at(0, 0));
+			assert!((3..=6).contains(&intensity), "{}", intensity);
+			assert_eq!(lights.intensityAt(at(100 * RCL_UNITS_PER_SQUARE, 0)), 6);
+			seen.push(intensity);
+		}
+		// It changes every few ticks
+		assert!(seen.windows(FLICKER_TICKS as usize + 1).any(|w| w[0] != w[FLICKER_TICKS as usize]));
+
+		lights.update(42);
+		let again = lights.intensityAt(at(0, 0));
+		assert_eq!(again, seen[42]);
+	}
+
+	#[test]
+	fn extreme_flicker_stays_in_range() {
+		let mut lights = RclLights::new();
+		lights.add(RclLight::flickering(at(0, 0), RCL_UNITS_PER_SQUARE, i8::MIN, i8::MAX));
+		lights.add(RclLight::flickering(at(0, 0), RCL_UNITS_PER_SQUARE, i8::MAX, i8::MAX));
+		for tick in 0..100 {
+			lights.update(tick);
+			assert!(lights.lights.iter().all(|&(light, intensity)| intensity <= light.intensity));
+		}
+	}
+}
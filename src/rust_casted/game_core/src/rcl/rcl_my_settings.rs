@@ -1,8 +0,0 @@
-use crate::rcl::RCL_Unit;
-
-pub(crate) const SPEED_MULTIPLIER:RCL_Unit = 1;
-const PROJ_SHOW_FPS_COUNTER:bool = false;
-const PROJ_SCREENMODE:u8 = 13;
-const PROJ_MODE13:bool = true;
-const PROJ_ENABLE_SOUND:bool = false;
-const FPS:u8 = 255;
@@ -0,0 +1,258 @@
+// Sprites of the raycaster demos: images standing in the level, facing the camera. They are drawn over the rendered
+// view, walls hide them by the depth buffer the pixel function fills and among themselves they are drawn back to
+// front. Sprites can be animated and directional, showing one of eight images depending on where they are seen from.
+
+use crate::rcl::*;
+use crate::rcl::rcl_general::*;
+use crate::rcl::rcl_lights::RclShade;
+
+/// Sprites further than this are not drawn unless configured otherwise, they would be a few pixels in the fog.
+pub const DEFAULT_SPRITE_MAX_DISTANCE:RCL_Unit = 5 * RCL_UNITS_PER_SQUARE;
+
+/// Images of a sprite: animation frames, each seen from one or eight directions.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RclSpriteImages {
+	/// Frame after frame, the directions of a frame start at the front of the sprite and go around it with growing
+	/// angles.
+	pub images:&'static [RclImage],
+	pub angles:u8,
+	/// Ticks each frame is shown for.
+	pub frameTicks:u16,
+}
+impl RclSpriteImages {
+	/// Looks the same all the time and from everywhere.
+	pub const fn still(images:&'static [RclImage]) -> RclSpriteImages {
+		RclSpriteImages { images, angles: 1, frameTicks: 1 }
+	}
+
+	/// Shows the images one after another, each for `frameTicks`.
+	pub const fn animated(images:&'static [RclImage], frameTicks:u16) -> RclSpriteImages {
+		RclSpriteImages { images, angles: 1, frameTicks }
+	}
+
+	/// Eight images per frame, seen from the front, front right, right, ...
+	pub const fn directional(images:&'static [RclImage], frameTicks:u16) -> RclSpriteImages {
+		RclSpriteImages { images, angles: 8, frameTicks }
+	}
+
+	pub fn frames(&self) -> usize {
+		self.images.len() / core::cmp::max(self.angles, 1) as usize
+	}
+
+	/// Image at `tickNumber`, seen at `angle` from the front of the sprite.
+	pub fn image(&self, tickNumber:u32, angle:RCL_Unit) -> RclImage {
+		let angles = core::cmp::max(self.angles, 1) as RCL_Unit;
+		let frame = (tickNumber / core::cmp::max(self.frameTicks, 1) as u32) as usize % core::cmp::max(self.frames(), 1);
+		// Each image covers the angles around its own
+		let view = (RCL_wrap(angle, RCL_UNITS_PER_SQUARE) * angles + RCL_UNITS_PER_SQUARE / 2) / RCL_UNITS_PER_SQUARE % angles;
+
+		self.images[frame * angles as usize + view as usize]
+	}
+}
+
+/// Angle of a direction, the inverse of `RCL_angleToDirection`.
+fn directionToAngle(direction:RCL_Vector2D) -> RCL_Unit {
+	let turns = (-direction.y as f32).atan2(direction.x as f32) / (2f32 * core::f32::consts::PI);
+	RCL_wrap((turns * RCL_UNITS_PER_SQUARE as f32) as RCL_Unit, RCL_UNITS_PER_SQUARE)
+}
+
+/// Identifies a sprite of `RclSprites` for as long as it is there.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RclSpriteId(u32);
+
+/// Sprites of a level, they can be added, moved and removed while it runs.
+pub struct RclSprites {
+	sprites:Vec<(RclSpriteId, Sprite)>,
+	nextId:u32,
+	/// Sprites further away from the camera along either axis are not drawn.
+	pub maxDistance:RCL_Unit,
+	/// Depth, index and screen position of the sprites in front of the camera, kept between frames for the memory.
+	visible:Vec<(RCL_Unit, usize, i16, i16)>,
+}
+impl RclSprites {
+	pub const fn new() -> RclSprites {
+		RclSprites {
+			sprites: Vec::new(),
+			nextId: 0,
+			maxDistance: DEFAULT_SPRITE_MAX_DISTANCE,
+			visible: Vec::new(),
+		}
+	}
+
+	pub fn add(&mut self, sprite:Sprite) -> RclSpriteId {
+		let id = RclSpriteId(self.nextId);
+		self.nextId += 1;
+		self.sprites.push((id, sprite));
+		id
+	}
+
+	pub fn remove(&mut self, id:RclSpriteId) -> Option<Sprite> {
+		let index = self.sprites.iter().position(|&(spriteId, _)| spriteId == id)?;
+		Some(self.sprites.remove(index).1)
+	}
+
+	pub fn get(&self, id:RclSpriteId) -> Option<&Sprite> {
+		self.sprites.iter().find(|&&(spriteId, _)| spriteId == id).map(|(_, sprite)| sprite)
+	}
+
+	/// To move or change a sprite.
+	pub fn getMut(&mut self, id:RclSpriteId) -> Option<&mut Sprite> {
+		self.sprites.iter_mut().find(|(spriteId, _)| *spriteId == id).map(|(_, sprite)| sprite)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (RclSpriteId, &Sprite)> {
+		self.sprites.iter().map(|(id, sprite)| (*id, sprite))
+	}
+
+	pub fn len(&self) -> usize { self.sprites.len() }
+
+	pub fn clear(&mut self) {
+		self.sprites.clear();
+	}
+
+	/// Draws the sprites over the rendered view of `camera`, the farthest first. `shade` gives the lighting of a
+	/// sprite by its position and depth, like the pixel function does for walls.
+	pub fn draw(
+		&mut self, general:&mut RCL_General, config:&RCL_Config, camera:&RCL_Camera, tickNumber:u32,
+		shade:impl Fn(&RCL_General, RCL_Vector2D, RCL_Unit) -> RclShade,
+	) {
+		self.visible.clear();
+
+		for (i, (_, sprite)) in self.sprites.iter().enumerate() {
+			// use Chebyshew distance instead Euclidean, it's faster
+			if
+				RCL_absVal(sprite.mPosition.x - camera.position.x) > self.maxDistance ||
+				RCL_absVal(sprite.mPosition.y - camera.position.y) > self.maxDistance
+			{
+				continue;
+			}
+
+			let pos = config.mapToScreen(sprite.mPosition, sprite.mHeight, camera);
+			if pos.depth > 0 { // is in front of camera?
+				self.visible.push((pos.depth, i, pos.position.x as i16, pos.position.y as i16));
+			}
+		}
+
+		// The nearer sprites cover the farther ones
+		self.visible.sort_unstable_by_key(|&(depth, ..)| core::cmp::Reverse(depth));
+
+		for &(depth, i, x, y) in &self.visible {
+			let sprite = &self.sprites[i].1;
+
+			let angle = if sprite.mImages.angles > 1 {
+				let toCamera = RCL_Vector2D { x: camera.position.x - sprite.mPosition.x, y: camera.position.y - sprite.mPosition.y };
+				directionToAngle(toCamera) - sprite.mDirection
+			} else {
+				0
+			};
+
+			let shade = shade(general, sprite.mPosition, depth);
+			general.drawSpriteSquare(
+				sprite.mImages.image(tickNumber, angle), x, y,
+				depth,
+				config.perspectiveScale(sprite.mPixelSize, depth) as u16, shade
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// 1x1 `.rust2d` images of a color
+	static RED:[u8; 8] = [0, 1, 0, 1, 0xFF, 0, 0, 0xFF];
+	static GREEN:[u8; 8] = [0, 1, 0, 1, 0, 0xFF, 0, 0xFF];
+	static BLUE:[u8; 8] = [0, 1, 0, 1, 0, 0, 0xFF, 0xFF];
+
+	static RED_ONLY:[RclImage; 1] = [RclImage::Rgba(&RED)];
+	static GREEN_ONLY:[RclImage; 1] = [RclImage::Rgba(&GREEN)];
+	static BLINKING:[RclImage; 2] = [RclImage::Rgba(&RED), RclImage::Rgba(&GREEN)];
+	static ALL_SIDES:[RclImage; 8] = [
+		RclImage::Rgba(&RED), RclImage::Rgba(&GREEN), RclImage::Rgba(&BLUE), RclImage::Rgba(&GREEN),
+		RclImage::Rgba(&BLUE), RclImage::Rgba(&GREEN), RclImage::Rgba(&BLUE), RclImage::Rgba(&GREEN),
+	];
+
+	fn sprite(images:RclSpriteImages, x:RCL_Unit, y:RCL_Unit) -> Sprite {
+		Sprite {
+			mImages: images,
+			mPosition: RCL_Vector2D { x, y },
+			mHeight: RCL_UNITS_PER_SQUARE / 2,
+			// A few pixels large, like the demo ones
+			mPixelSize: 300,
+			mDirection: 0,
+		}
+	}
+
+	#[test]
+	fn frames_and_angles_pick_the_image() {
+		let blinking = RclSpriteImages::animated(&BLINKING, 4);
+		assert_eq!(blinking.frames(), 2);
+		assert_eq!(blinking.image(3, 0), RclImage::Rgba(&RED));
+		assert_eq!(blinking.image(4, 123), RclImage::Rgba(&GREEN));
+		assert_eq!(blinking.image(8, 0), RclImage::Rgba(&RED));
+
+		let turning = RclSpriteImages::directional(&ALL_SIDES, 1);
+		assert_eq!(turning.frames(), 1);
+		assert_eq!(turning.image(0, RCL_UNITS_PER_SQUARE / 20), RclImage::Rgba(&RED));
+		assert_eq!(turning.image(0, -RCL_UNITS_PER_SQUARE / 20), RclImage::Rgba(&RED));
+		assert_eq!(turning.image(0, RCL_UNITS_PER_SQUARE / 4), RclImage::Rgba(&BLUE));
+
+		for &angle in &[0, RCL_UNITS_PER_SQUARE / 8, RCL_UNITS_PER_SQUARE / 2, 3 * RCL_UNITS_PER_SQUARE / 4] {
+			let angle2 = directionToAngle(RCL_angleToDirection(angle));
+			assert!((angle2 - angle).abs() <= 1, "{} != {}", angle2, angle);
+		}
+	}
+
+	#[test]
+	fn sprites_are_drawn_back_to_front() {
+		let config = RCL_Config { resolutionX: 40, resolutionY: 30, ..GENERAL_CONFIG };
+		let mut general = Box::new(RCL_General::new());
+		// A black screen and nothing in front of the sprites
+		let clear = |general:&mut RCL_General| {
+			general.initGeneral(&config);
+			for depth in general.zBuffer.iter_mut() {
+				*depth = RCL_INFINITY;
+			}
+		};
+		clear(&mut general);
+		let center = |general:&RCL_General| {
+			let mut image = vec![crate::RGBA::zeroed(); crate::SCREEN_SPACE as usize];
+			general.screen.projectInto(&mut image, crate::canvas::Rect::new(0, 0, 40, 30));
+			let c = image[15 * crate::SCREEN_WIDTH as usize + 20];
+			(c.r, c.g, c.b)
+		};
+
+		// Looking along x, the nearer red sprite is added last
+		let camera = RCL_Camera { position: RCL_Vector2D::ZERO, direction: 0, shear: 0, height: RCL_UNITS_PER_SQUARE / 2 };
+		let mut sprites = RclSprites::new();
+		let red = sprites.add(sprite(RclSpriteImages::still(&RED_ONLY), 2 * RCL_UNITS_PER_SQUARE, 0));
+		let green = sprites.add(sprite(RclSpriteImages::still(&GREEN_ONLY), 3 * RCL_UNITS_PER_SQUARE, 0));
+		sprites.draw(&mut general, &config, &camera, 0, |_, _, _| RclShade::NONE);
+		assert_eq!(center(&general), (0xFF, 0, 0));
+
+		// Moved behind the green one
+		sprites.getMut(red).unwrap().mPosition.x = 4 * RCL_UNITS_PER_SQUARE;
+		sprites.draw(&mut general, &config, &camera, 0, |_, _, _| RclShade::NONE);
+		assert_eq!(center(&general), (0, 0xFF, 0));
+
+		// Too far once the green one is gone
+		assert_eq!(sprites.remove(green).unwrap().mPosition.x, 3 * RCL_UNITS_PER_SQUARE);
+		assert!(sprites.remove(green).is_none());
+		assert_eq!(sprites.len(), 1);
+		sprites.maxDistance = 3 * RCL_UNITS_PER_SQUARE;
+		clear(&mut general);
+		sprites.draw(&mut general, &config, &camera, 0, |_, _, _| RclShade::NONE);
+		assert_eq!(center(&general), (0, 0, 0));
+
+		// Seen from its right, a quarter turn from its front
+		sprites.clear();
+		let turning = sprites.add(sprite(RclSpriteImages::directional(&ALL_SIDES, 1), 2 * RCL_UNITS_PER_SQUARE, 0));
+		sprites.getMut(turning).unwrap().mDirection = RCL_UNITS_PER_SQUARE / 4;
+		sprites.draw(&mut general, &config, &camera, 0, |_, _, _| RclShade::NONE);
+		assert_eq!(center(&general), (0, 0, 0xFF));
+		sprites.getMut(turning).unwrap().mDirection = RCL_UNITS_PER_SQUARE / 2;
+		sprites.draw(&mut general, &config, &camera, 0, |_, _, _| RclShade::NONE);
+		assert_eq!(center(&general), (0xFF, 0, 0));
+	}
+}
@@ -1,13 +1,157 @@
 use crate::{SCREEN_WIDTH, SCREEN_HEIGHT, RGBA, SCREEN_SPACE};
-use tiny_lib::{matrices::*, vector::*, util::*};
+use tiny_lib::{matrices::*, vector::*};
 use crate::controls::*;
-use core::f32::consts::PI;
+use crate::timing::TARGET_FPS;
+use crate::canvas::{Canvas, Image, Rect};
+use core::fmt::Write;
+
+mod span;
+mod post;
+mod animation;
+mod camera;
+mod shadow;
+use span::{Lanes, I32Lanes, F32Lanes, MAX_LANES};
+use animation::{Skeleton, Skin, AnimationError};
+pub use camera::{Camera, CameraMode};
+use shadow::ShadowMap;
 
 pub struct SoftwareRenderer {
 	s_mesh:MeshBasic,
 	s_texture:Texture,
 	s_made_with:Texture,
-	image_depth:[f32; SCREEN_SPACE as usize],
+	image_depth:Vec<f32>,
+	lights:[Light; MAX_LIGHTS],
+	lights_amount:u8,
+	shade_mode:ShadeMode,
+	cull_mode:CullMode,
+	s_material:Material,
+	transparent_triangles:Vec<Triangle>,
+	supersampling:Supersampling,
+	fxaa:bool,
+	accumulation:Vec<[u16; 4]>,
+	post_buffer:Vec<RGBA>,
+	animation_clip:Option<u8>,
+	animation_speed:f32,
+	camera:Camera,
+	shadow_map:ShadowMap,
+	/// Directional light the shadow map of the current frame was rendered from.
+	shadow_light:Option<u8>,
+	debug_view:DebugView,
+	/// View key of the previous frame, the view changes once per press.
+	last_view:bool,
+}
+
+/// How the per-vertex light of a triangle is computed.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ShadeMode {
+	/// Texels are drawn as is.
+	Unlit,
+	/// One light value per triangle, computed from the face normal at the centroid.
+	Flat,
+	/// Light is computed at every vertex from its normal and interpolated across the triangle.
+	Gouraud,
+}
+
+/// Which triangles are skipped depending on the side facing the camera.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum CullMode {
+	None,
+	Back,
+	Front,
+}
+impl CullMode {
+	/// `normal` follows the winding of the triangle, `ray_towards_triangle` goes from the camera to any of its vertices.
+	fn is_culled(self, ray_towards_triangle:&Vec3, normal:&Vec3) -> bool {
+		let facing_camera = ray_towards_triangle.dot(normal) < 0f32;
+		match self {
+			CullMode::None => false,
+			CullMode::Back => !facing_camera,
+			CullMode::Front => facing_camera,
+		}
+	}
+}
+
+/// What the renderer shows, everything but `Shaded` is meant for debugging.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum DebugView {
+	Shaded,
+	/// Edges of every visible triangle over the shaded mesh.
+	Wireframe,
+	/// Distance from the camera, white is nearest.
+	Depth,
+	/// Lit checkerboard instead of the texture, shows how the uvs are laid out and stretched.
+	UvCheckerboard,
+	/// World space normals as colours, x, y, z map to red, green, blue.
+	Normals,
+}
+impl DebugView {
+	pub const fn next(self) -> DebugView {
+		match self {
+			DebugView::Shaded => DebugView::Wireframe,
+			DebugView::Wireframe => DebugView::Depth,
+			DebugView::Depth => DebugView::UvCheckerboard,
+			DebugView::UvCheckerboard => DebugView::Normals,
+			DebugView::Normals => DebugView::Shaded,
+		}
+	}
+
+	pub const fn name(self) -> &'static str {
+		match self {
+			DebugView::Shaded => "shaded",
+			DebugView::Wireframe => "wireframe",
+			DebugView::Depth => "depth",
+			DebugView::UvCheckerboard => "uv checkerboard",
+			DebugView::Normals => "normals",
+		}
+	}
+}
+
+/// Ordered grid supersampling, every sample is a full pass over the mesh with the geometry shifted by a sub-pixel offset.
+/// Off by default, FXAA smooths the edges for a fraction of the cost.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Supersampling {
+	Off,
+	/// Two samples side by side.
+	X2,
+	/// A 2x2 grid.
+	X4,
+}
+impl Supersampling {
+	/// Sample positions relative to the pixel center.
+	const fn offsets(self) -> &'static [(f32, f32)] {
+		match self {
+			Supersampling::Off => &[(0f32, 0f32)],
+			Supersampling::X2  => &[(-0.25f32, 0f32), (0.25f32, 0f32)],
+			Supersampling::X4  => &[(-0.25f32, -0.25f32), (0.25f32, -0.25f32), (-0.25f32, 0.25f32), (0.25f32, 0.25f32)],
+		}
+	}
+}
+
+const MAX_LIGHTS:usize = 8;
+
+#[derive(Copy, Clone)]
+pub enum Light {
+	Ambient { color:Vec3 },
+	/// `direction` points from the surface towards the light and is normalized.
+	/// With `shadows` the light is blocked by the mesh, only the first such light gets a shadow map.
+	Directional { direction:Vec3, color:Vec3, shadows:bool },
+	/// Light fades out quadratically and reaches zero at `range`.
+	Point { position:Vec3, color:Vec3, range:f32 },
+}
+impl Light {
+	pub const fn ambient(color:Vec3) -> Light {
+		Light::Ambient { color }
+	}
+	pub fn directional(direction:Vec3, color:Vec3) -> Light {
+		Light::Directional { direction: direction.normalize(), color, shadows: false }
+	}
+	pub const fn point(position:Vec3, color:Vec3, range:f32) -> Light {
+		Light::Point { position, color, range }
+	}
+}
+
+impl Default for SoftwareRenderer {
+	fn default() -> SoftwareRenderer { SoftwareRenderer::new() }
 }
 
 impl SoftwareRenderer {
@@ -17,233 +161,456 @@ impl SoftwareRenderer {
 			s_mesh: MeshBasic::new(),
 			s_texture: Texture::zero_resolution_texture(),
 			s_made_with: Texture::zero_resolution_texture(),
-			image_depth: [0f32; SCREEN_SPACE as usize]
+			image_depth: Vec::new(),
+			lights: [Light::ambient(Vec3::ZERO); MAX_LIGHTS],
+			lights_amount: 0,
+			shade_mode: ShadeMode::Gouraud,
+			cull_mode: CullMode::Back,
+			s_material: Material::new(BlendMode::AlphaTest(1), 0xFF),
+			transparent_triangles: Vec::new(),
+			supersampling: Supersampling::Off,
+			fxaa: true,
+			accumulation: Vec::new(),
+			post_buffer: Vec::new(),
+			animation_clip: None,
+			animation_speed: 1f32,
+			camera: Camera::new(),
+			shadow_map: ShadowMap::new(),
+			shadow_light: None,
+			debug_view: DebugView::Shaded,
+			last_view: false,
 		}
 	}
 	#[inline(always)]
 	pub fn init(&mut self) {
-		load_mesh(&mut self.s_mesh, MODEL_BIN);
+		self.image_depth = vec![0f32; SCREEN_SPACE as usize];
+		self.transparent_triangles = Vec::with_capacity(MAX_TRANSPARENT_TRIANGLES);
+		self.accumulation = vec![[0u16; 4]; SCREEN_SPACE as usize];
+		self.post_buffer = vec![RGBA::zeroed(); SCREEN_SPACE as usize];
+		load_mesh(&mut self.s_mesh, MODEL_BIN).expect("model.rust3d");
 		load_texture(&mut self.s_texture, TEXTURE_BIN);
 		load_texture(&mut self.s_made_with, MADE_WITH_BIN);
+
+		self.add_light(Light::ambient(Vec3::new(0.3, 0.3, 0.35)));
+		self.add_light(Light::directional(Vec3::new(1f32, 0.5f32, 0.25f32), Vec3::new(0.8, 0.75, 0.7)));
+		self.set_light_shadows(1, true);
+		self.add_light(Light::point(Vec3::new(-3f32, 4f32, 6f32), Vec3::new(0.6, 0.4, 0.2), 12f32));
+		// Idle clip written by tools/helper
+		self.play_animation(0, 1f32);
+	}
+
+	/// Returns `false` if there is no room left for another light.
+	pub fn add_light(&mut self, light:Light) -> bool {
+		if self.lights_amount as usize == MAX_LIGHTS {
+			return false;
+		}
+		self.lights[self.lights_amount as usize] = light;
+		self.lights_amount += 1;
+		true
+	}
+	pub fn clear_lights(&mut self) {
+		self.lights_amount = 0;
+	}
+	/// Turns shadows of light `index` on or off. Returns `false` if it is not a directional light.
+	pub fn set_light_shadows(&mut self, index:u8, shadows:bool) -> bool {
+		if index >= self.lights_amount {
+			return false;
+		}
+		match &mut self.lights[index as usize] {
+			Light::Directional { shadows: light_shadows, .. } => {
+				*light_shadows = shadows;
+				true
+			},
+			_ => false,
+		}
+	}
+	pub fn set_shade_mode(&mut self, shade_mode:ShadeMode) {
+		self.shade_mode = shade_mode;
+	}
+	pub fn set_cull_mode(&mut self, cull_mode:CullMode) {
+		self.cull_mode = cull_mode;
+	}
+	/// Loops clip `clip` of the mesh, `speed` scales its playback rate. Returns `false` if the mesh has no such clip.
+	pub fn play_animation(&mut self, clip:u8, speed:f32) -> bool {
+		if clip >= self.s_mesh.skeleton.clips_amount() {
+			return false;
+		}
+		self.animation_clip = Some(clip);
+		self.animation_speed = speed;
+		true
+	}
+	/// Puts the mesh back into bind pose.
+	pub fn stop_animation(&mut self) {
+		self.animation_clip = None;
+		self.s_mesh.skeleton.clear_pose();
+	}
+	/// Switches between orbiting the model and flying freely, the right mouse button toggles it as well.
+	pub fn set_camera_mode(&mut self, mode:CameraMode) {
+		self.camera.set_mode(mode);
+	}
+	/// The view key cycles through them as well.
+	pub fn set_debug_view(&mut self, debug_view:DebugView) {
+		self.debug_view = debug_view;
+	}
+	pub fn set_supersampling(&mut self, supersampling:Supersampling) {
+		self.supersampling = supersampling;
+	}
+	pub fn set_fxaa(&mut self, fxaa:bool) {
+		self.fxaa = fxaa;
+	}
+	pub fn set_material(&mut self, material:Material) {
+		self.s_material = material;
+	}
+	pub fn set_texture_sampler(&mut self, sampler:Sampler) {
+		self.s_texture.sampler = sampler;
+	}
+
+	/// Sums the contribution of every light at a point in world space. The light casting the shadow map
+	/// is summed up separately, as the second value, so the rasterizer can scale it by the visibility.
+	fn illuminate(&self, position:&Vec3, normal:&Vec3) -> (Vec3, Vec3) {
+		let mut light = Vec3::ZERO;
+		let mut shadowed = Vec3::ZERO;
+		for (i, l) in self.lights[0..self.lights_amount as usize].iter().enumerate() {
+			match *l {
+				Light::Ambient { color } => {
+					light += color;
+				},
+				Light::Directional { direction, color, .. } => {
+					if self.shadow_light == Some(i as u8) {
+						shadowed += color * normal.dot(&direction).max(0f32);
+					} else {
+						light += color * normal.dot(&direction).max(0f32);
+					}
+				},
+				Light::Point { position: light_position, color, range } => {
+					let to_light = light_position - *position;
+					let distance = to_light.len();
+					if distance < range && distance != 0f32 {
+						let falloff = 1f32 - distance / range;
+						light += color * (normal.dot(&to_light) / distance).max(0f32) * (falloff * falloff);
+					}
+				},
+			}
+		}
+		(light, shadowed)
+	}
+
+	/// Renders the depth of the mesh from the first directional light with shadows, fitted around the whole mesh.
+	fn render_shadow_map(&mut self, mat_world:&Matrix4x4) {
+		self.shadow_light = None;
+		let mut caster = None;
+		for (i, l) in self.lights[0..self.lights_amount as usize].iter().enumerate() {
+			if let Light::Directional { direction, shadows: true, .. } = *l {
+				caster = Some((i as u8, direction));
+				break;
+			}
+		}
+		let (index, direction) = match caster {
+			Some(caster) => caster,
+			None => return,
+		};
+
+		let triangles = &self.s_mesh.triangles[0..self.s_mesh.amount_of_triangles as usize];
+		let skeleton = &self.s_mesh.skeleton;
+		let skins = &self.s_mesh.skins;
+		let world_vertices = |i:usize, triangle:&Triangle| {
+			let mut vertices = [Vec4::ZERO; 3];
+			for j in 0..3 {
+				let vertex = if skeleton.is_posed() {
+					skeleton.skin_position(&skins[i][j], &triangle.vertices[j])
+				} else {
+					triangle.vertices[j]
+				};
+				vertices[j] = multiply_vector_matrix(&vertex, mat_world);
+			}
+			vertices
+		};
+
+		let mut min = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
+		let mut max = Vec3::new(f32::MIN, f32::MIN, f32::MIN);
+		for (i, triangle) in triangles.iter().enumerate() {
+			for v in &world_vertices(i, triangle) {
+				min = Vec3::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z));
+				max = Vec3::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z));
+			}
+		}
+		if triangles.is_empty() {
+			return;
+		}
+
+		self.shadow_map.fit(&direction, &min, &max);
+		for (i, triangle) in triangles.iter().enumerate() {
+			self.shadow_map.draw_triangle(&world_vertices(i, triangle));
+		}
+		self.shadow_light = Some(index);
 	}
 	#[inline(always)]
 	pub fn render(&mut self, image:&mut [RGBA], tick_number:u32, controls:&Controls) {
-		for i in 0..(SCREEN_SPACE as usize) {
-			image[i] = RGBA::zeroed();
-			self.image_depth[i] = 0f32;
-		}
-
-		// Projection Matrix
-		let fov = 5f32;
-		let plane_near = 0.5f32;
-		let plane_far = 1000f32;
-		let clip_plane_near = 0.01;
-		// let aspect = SCREEN_HEIGHT as f32 / SCREEN_WIDTH as f32; // TODO: swap?
-		// TODO: почему?
-		let aspect = 1.5f32;
-
-		let projection_matrix = projection_matrix(fov, aspect, plane_near, plane_far);
-
-		/*	// TODO: projection matrix можно заменить просто на несколько hard coded комманд, без всяких операций с матрицей
-			// TODO play around struct Matrix4x4 projection_matrix = { .matrix = {0} };
-			let mut projection_matrix = Matrix4x4::zeros();
-			// fill_matrix_4x4(&mut projection_matrix, 0f32);
-			// TODO: fill matrix with 0s
-			projection_matrix.matrix[0][0] = aspect * fFovRad;
-			projection_matrix.matrix[1][1] =          fFovRad;
-			projection_matrix.matrix[2][2] =   plane_far              / (plane_far - plane_near);
-			projection_matrix.matrix[3][2] = (-plane_far * plane_near) / (plane_far - plane_near);
-			projection_matrix.matrix[2][3] = 1.0f32;
-			projection_matrix.matrix[3][3] = 0.0f32;*/
-
-		// Illumination
-		// let light_dir = normalize(&Vec3d::xyz1(1f32, 0.5f32, 0.25f32));
-
-		// Create "Point At" Matrix for camera
-		let camera = Vec4::xyz1(0f32, 0f32, 0f32);
-		// unsafe { (m_prec_x - (SCREEN_WIDTH/2) as f32) / 256f32 },
-		// unsafe { (m_prec_y - (SCREEN_WIDTH/2) as f32) / 256f32 },
-		let look_dir = Vec4::xyz1(0f32 /* (theta*2f32).sin(), */, 0f32, 1f32);
-		let up = Vec4::xyz1(0f32, 1f32, 0f32);
-		let target = camera + look_dir;
-		let camera_matrix = point_at_matrix(&camera, &target, &up);
-
-		for i in 0..1 {
-			let theta = (tick_number as f32) * 0.01 + i as f32;
-
-			let rotx =  tick_number as f32 * 0.006f32;
-			let roty = (tick_number as f32 * 0.013f32).cos() * PI * 0.1f32 - PI * 0.2f32;
-
-			let mut rot_x_m = rotation_matrix_x(roty);
-			let mut rot_y_m = rotation_matrix_y(rotx);
-
-			let mat_rot = &rot_y_m * &rot_x_m;
-
-			// TODO: заменить все повороты, развороты на единую world matrix
-			let mut mat_world = Matrix4x4::ones_cascade(); // Form World Matrix
-			mat_world = &mat_world * &transition_matrix(&Vec3::new(0f32, -1.25f32, 0f32));
-			mat_world = &mat_world * &mat_rot;
-			// TODO: без трансформации моделька рендерится на 7 юнитов ниже чем нужно
-			mat_world = &mat_world * &transition_matrix(&Vec3::new(0f32, 7.3f32, 10f32));
-
-			// Make view matrix from camera
-			let view_matrix = inverse_transformation_matrix(&camera_matrix);
-
-			// Draw Triangles
-			for triangle in &self.s_mesh.triangles[0usize..self.s_mesh.amount_of_triangles as usize] {
-				// World Matrix Transform
-				let mut translated_triangle = Triangle {
-					vertices: [
-						multiply_vector_matrix(&triangle.vertices[0], &mat_world),
-						multiply_vector_matrix(&triangle.vertices[1], &mat_world),
-						multiply_vector_matrix(&triangle.vertices[2], &mat_world),
-					],
-					uvs: triangle.uvs.clone(),
-				};
+		if let Some(clip) = self.animation_clip {
+			let time = tick_number as f32 * (1f32 / TARGET_FPS as f32) * self.animation_speed;
+			self.s_mesh.skeleton.set_pose(clip, time);
+		}
 
-				// Get lines either side of triangle
-				// let line1 = sub_vec3d(&translated_triangle.vertices[1], &translated_triangle.vertices[0]);
-				// let line2 = sub_vec3d(&translated_triangle.vertices[2], &translated_triangle.vertices[0]);
-
-				// Calculate triangle Normal
-				// Take cross product of lines to get normal to triangle surface
-				// You normally need to normalise a normal!
-				// let normal = normalize(&cross_product(&line1, &line2));
-
-				// Get Ray from triangle to camera
-				// let ray_towards_triangle = normalize(&sub_vec3d(&translated_triangle.vertices[0], &camera));
-
-				// If ray is aligned with normal, then triangle is visible
-				// if Vec3d::dot_product(&ray_towards_triangle, &normal) < 0f32 {
-				if true {
-					// if true {
-					// How "aligned" are light direction and triangle surface normal?
-					// let light = Vec3d::dot_product(&light_dir, &normal); // Todo math max
-					// let color = ((light * 255f32).clamp(0f32, 255f32) + 0.5f32) as u8;
-
-					// Convert World Space --> View Space
-					let mut viewed_triangle = Triangle {
-						vertices: [
-							multiply_vector_matrix(&translated_triangle.vertices[0], &view_matrix),
-							multiply_vector_matrix(&translated_triangle.vertices[1], &view_matrix),
-							multiply_vector_matrix(&translated_triangle.vertices[2], &view_matrix),
-						],
-						uvs: translated_triangle.uvs.clone(),
-					};
+		self.camera.update(controls, tick_number);
+		let view = controls.view.is_pressed();
+		if view && !self.last_view {
+			self.debug_view = self.debug_view.next();
+		}
+		self.last_view = view;
+		let view_matrix = self.camera.view_matrix();
+		let projection_matrix = self.camera.projection_matrix();
+		let position = self.camera.position();
+		let camera = Vec4::xyz1(position.x, position.y, position.z);
+
+		// The model stands on y = 0, center it on the orbit pivot
+		let mat_world = transition_matrix(&Vec3::new(0f32, -1.25f32, 0f32));
+
+		// Light does not depend on the sample position, so the shadow map is shared by every pass
+		self.render_shadow_map(&mat_world);
+
+		let offsets = self.supersampling.offsets();
+		for (sample, offset) in offsets.iter().enumerate() {
+			image[..SCREEN_SPACE as usize].fill(RGBA::zeroed());
+			self.image_depth.fill(0f32);
+
+			self.draw_mesh(image, &mat_world, &view_matrix, &projection_matrix, &camera, *offset);
+			if self.debug_view == DebugView::Depth {
+				post::visualize_depth(image, &self.image_depth);
+			}
+			// Needs the coverage of a single sample, resolved alpha is partial along every edge
+			Self::render_outline(image);
 
-					// Clip Viewed Triangle against near plane, this could form two additional additional triangles.
-					let (clipped_n, clipped_array) = triangle_clip_against_plane(
-						&Vec4::xyz1(0f32,0f32,clip_plane_near), // Clip Position w = 0 or 1?
-						&Vec4::xyz1(0f32,0f32,1.0f32), // Clip Normal w = 0 or 1?
-						&viewed_triangle
-					);
-
-					// We may end up with multiple triangles form the clip, so project as required
-					for clipped_triangle in &clipped_array[0..clipped_n] {
-						// Project triangles from 3D --> 2D
-						let mut projected_triangle = Triangle {
-							vertices: [
-								multiply_vector_matrix(&clipped_triangle.vertices[0], &projection_matrix),
-								multiply_vector_matrix(&clipped_triangle.vertices[1], &projection_matrix),
-								multiply_vector_matrix(&clipped_triangle.vertices[2], &projection_matrix),
-							],
-							uvs: clipped_triangle.uvs.clone(),
-						};
-
-						*projected_triangle.uvs[0].u() /= projected_triangle.vertices[0].w;
-						*projected_triangle.uvs[1].u() /= projected_triangle.vertices[1].w;
-						*projected_triangle.uvs[2].u() /= projected_triangle.vertices[2].w;
-
-						*projected_triangle.uvs[0].v() /= projected_triangle.vertices[0].w;
-						*projected_triangle.uvs[1].v() /= projected_triangle.vertices[1].w;
-						*projected_triangle.uvs[2].v() /= projected_triangle.vertices[2].w;
-
-						*projected_triangle.uvs[0].w() = 1f32 / projected_triangle.vertices[0].w;
-						*projected_triangle.uvs[1].w() = 1f32 / projected_triangle.vertices[1].w;
-						*projected_triangle.uvs[2].w() = 1f32 / projected_triangle.vertices[2].w;
-
-						// Scale into view, we moved the normalising into cartesian space out of the matrix.vector function from the previous videos, so do this manually
-						projected_triangle.vertices[0] = div_vec3d(&projected_triangle.vertices[0], projected_triangle.vertices[0].w);
-						projected_triangle.vertices[1] = div_vec3d(&projected_triangle.vertices[1], projected_triangle.vertices[1].w);
-						projected_triangle.vertices[2] = div_vec3d(&projected_triangle.vertices[2], projected_triangle.vertices[2].w);
-
-						// X/Y are inverted so put them back
-						projected_triangle.vertices[0].x *= -1f32; // TODO: vec invert func
-						projected_triangle.vertices[1].x *= -1f32;
-						projected_triangle.vertices[2].x *= -1f32;
-						projected_triangle.vertices[0].y *= -1f32;
-						projected_triangle.vertices[1].y *= -1f32;
-						projected_triangle.vertices[2].y *= -1f32;
-
-						// Move to center of screen
-						projected_triangle.vertices[0].x += 1f32;
-						projected_triangle.vertices[0].y += 1f32;
-						projected_triangle.vertices[1].x += 1f32;
-						projected_triangle.vertices[1].y += 1f32;
-						projected_triangle.vertices[2].x += 1f32;
-						projected_triangle.vertices[2].y += 1f32;
-
-						// scale to screen size
-						projected_triangle.vertices[0].x *= 0.5 * SCREEN_WIDTH as f32;
-						projected_triangle.vertices[0].y *= 0.5 * SCREEN_HEIGHT as f32;
-						projected_triangle.vertices[1].x *= 0.5 * SCREEN_WIDTH as f32;
-						projected_triangle.vertices[1].y *= 0.5 * SCREEN_HEIGHT as f32;
-						projected_triangle.vertices[2].x *= 0.5 * SCREEN_WIDTH as f32;
-						projected_triangle.vertices[2].y *= 0.5 * SCREEN_HEIGHT as f32;
-
-						// Clip triangles against all four screen edges, this could yield
-						// a bunch of triangles, so create a queue that we traverse to
-						//  ensure we only test new triangles generated against planes
-						let (clipped_t, clipped_t_array) = triangle_clip_against_plane(
-							&Vec4::xyz1(0f32, 0f32, 0f32), // Clip Position
-							&Vec4::xyz1(0f32, 1f32, 0f32), // Clip Normal
-							&projected_triangle
+			if offsets.len() > 1 {
+				post::accumulate(&mut self.accumulation, image, sample == 0);
+			}
+		}
+		if offsets.len() > 1 {
+			post::resolve(&self.accumulation, image, offsets.len() as u16);
+		}
+
+		if self.fxaa {
+			post::fxaa(image, &mut self.post_buffer);
+		}
+
+		self.render_overlay(image);
+	}
+
+	/// Transforms, lights, clips and rasterizes the mesh, then the transparent triangles back to front.
+	/// `offset` shifts the sample position inside of every pixel for supersampling.
+	fn draw_mesh(
+		&mut self,
+		image:&mut [RGBA],
+		mat_world:&Matrix4x4,
+		view_matrix:&Matrix4x4,
+		projection_matrix:&Matrix4x4,
+		camera:&Vec4,
+		offset:(f32, f32),
+	) {
+		self.transparent_triangles.clear();
+
+		let mut clip_queue = ClipQueue::new();
+		let mut clipped_array = [Triangle::zero_spaced_verticies(); MAX_CLIPPED_TRIANGLES];
+
+		let posed = self.s_mesh.skeleton.is_posed();
+
+		// Draw Triangles
+		for (i, triangle) in self.s_mesh.triangles[0usize..self.s_mesh.amount_of_triangles as usize].iter().enumerate() {
+			// Skinning moves the vertices in model space, before the world transform
+			let mut skinned_triangle;
+			let triangle = if posed {
+				skinned_triangle = *triangle;
+				for j in 0..3 {
+					let skin = &self.s_mesh.skins[i][j];
+					skinned_triangle.vertices[j] = self.s_mesh.skeleton.skin_position(skin, &triangle.vertices[j]);
+					skinned_triangle.normals[j] = self.s_mesh.skeleton.skin_direction(skin, &triangle.normals[j]);
+				}
+				&skinned_triangle
+			} else {
+				triangle
+			};
+
+			// World Matrix Transform
+			let mut translated_triangle = Triangle {
+				vertices: [
+					multiply_vector_matrix(&triangle.vertices[0], mat_world),
+					multiply_vector_matrix(&triangle.vertices[1], mat_world),
+					multiply_vector_matrix(&triangle.vertices[2], mat_world),
+				],
+				uvs: triangle.uvs,
+				normals: [
+					multiply_direction_matrix(&triangle.normals[0], mat_world).normalize(),
+					multiply_direction_matrix(&triangle.normals[1], mat_world).normalize(),
+					multiply_direction_matrix(&triangle.normals[2], mat_world).normalize(),
+				],
+				lights: [Vec3::ONE; 3],
+				shadow_lights: [Vec3::ZERO; 3],
+				shadow_coords: [Vec3::ZERO; 3],
+			};
+
+			// Get lines either side of triangle
+			let line1 = translated_triangle.vertices[1] - translated_triangle.vertices[0];
+			let line2 = translated_triangle.vertices[2] - translated_triangle.vertices[0];
+
+			// Calculate triangle Normal
+			// Take cross product of lines to get normal to triangle surface
+			// You normally need to normalise a normal!
+			let normal = Vec3::cross_product(&line1.xyz(), &line2.xyz()).normalize();
+
+			// Illumination is computed in world space, so it does not depend on the camera
+			match self.shade_mode {
+				_ if self.debug_view == DebugView::Normals => {
+					for i in 0..3 {
+						translated_triangle.lights[i] = translated_triangle.normals[i] * 0.5f32 + Vec3::new(0.5f32, 0.5f32, 0.5f32);
+					}
+				},
+				ShadeMode::Unlit => {},
+				ShadeMode::Flat => {
+					let centroid = (
+						translated_triangle.vertices[0] +
+						translated_triangle.vertices[1] +
+						translated_triangle.vertices[2]
+					).xyz() * (1f32 / 3f32);
+					let (light, shadowed) = self.illuminate(&centroid, &normal);
+					translated_triangle.lights = [light; 3];
+					translated_triangle.shadow_lights = [shadowed; 3];
+				},
+				ShadeMode::Gouraud => {
+					for i in 0..3 {
+						let (light, shadowed) = self.illuminate(
+							&translated_triangle.vertices[i].xyz(),
+							&translated_triangle.normals[i]
 						);
-						for t in &clipped_t_array[0..clipped_t] {
-							let (clipped_t, clipped_t_array) = triangle_clip_against_plane(
-								&Vec4::xyz1(0f32, (SCREEN_HEIGHT - 1) as f32, 0f32), // Clip Position
-								&Vec4::xyz1(0f32, -1f32, 0f32), // Clip Normal
-								&t
-							);
-							for t in &clipped_t_array[0..clipped_t] {
-								let (clipped_t, clipped_t_array) = triangle_clip_against_plane(
-									&Vec4::xyz1(0f32, 0f32, 0f32), // Clip Position
-									&Vec4::xyz1(1f32, 0f32, 0f32), // Clip Normal
-									&t
-								);
-								for t in &clipped_t_array[0..clipped_t] {
-									let (clipped_t, clipped_t_array) = triangle_clip_against_plane(
-										&Vec4::xyz1(SCREEN_WIDTH as f32, 0f32, 0f32), // Clip Position
-										&Vec4::xyz1(-1f32, 0f32, 0f32), // Clip Normal
-										&t
-									);
-									for t in &clipped_t_array[0..clipped_t] {
-										unsafe {
-											// // fill_triangle_safe(&mut image, &t, &RGB::rgb(126,127,126));
-											texture_triangle_safe(image, &mut self.image_depth, &t, &self.s_texture);
-											// draw_triangle_safe(&mut image, &t);
-										}
-									}
-								}
-							}
-						}
+						translated_triangle.lights[i] = light;
+						translated_triangle.shadow_lights[i] = shadowed;
 					}
+				},
+			}
+			if self.shadow_light.is_some() {
+				for i in 0..3 {
+					translated_triangle.shadow_coords[i] = self.shadow_map.project(&translated_triangle.vertices[i]);
 				}
 			}
 
-			Self::render_outline(image);
+			// Get Ray from triangle to camera
+			let ray_towards_triangle = (translated_triangle.vertices[0] - *camera).xyz();
+
+			// If ray is aligned with normal, then triangle is facing away from the camera
+			if self.cull_mode.is_culled(&ray_towards_triangle, &normal) {
+				continue;
+			}
+
+			// Convert World Space --> View Space
+			let viewed_triangle = Triangle {
+				vertices: [
+					multiply_vector_matrix(&translated_triangle.vertices[0], view_matrix),
+					multiply_vector_matrix(&translated_triangle.vertices[1], view_matrix),
+					multiply_vector_matrix(&translated_triangle.vertices[2], view_matrix),
+				],
+				..translated_triangle
+			};
+
+			// Project triangles from 3D --> homogeneous clip space, the divide by w happens after clipping
+			let projected_triangle = Triangle {
+				vertices: [
+					project_vector_matrix(&viewed_triangle.vertices[0], projection_matrix),
+					project_vector_matrix(&viewed_triangle.vertices[1], projection_matrix),
+					project_vector_matrix(&viewed_triangle.vertices[2], projection_matrix),
+				],
+				..viewed_triangle
+			};
+
+			// Clip against all six planes of the view frustum, this could yield a bunch of triangles
+			let clipped_n = clip_triangle(&projected_triangle, &mut clip_queue, &mut clipped_array);
+
+			for t in &mut clipped_array[0..clipped_n] {
+				*t.uvs[0].u() /= t.vertices[0].w;
+				*t.uvs[1].u() /= t.vertices[1].w;
+				*t.uvs[2].u() /= t.vertices[2].w;
+
+				*t.uvs[0].v() /= t.vertices[0].w;
+				*t.uvs[1].v() /= t.vertices[1].w;
+				*t.uvs[2].v() /= t.vertices[2].w;
+
+				*t.uvs[0].w() = 1f32 / t.vertices[0].w;
+				*t.uvs[1].w() = 1f32 / t.vertices[1].w;
+				*t.uvs[2].w() = 1f32 / t.vertices[2].w;
+
+				// Light is interpolated in the same perspective correct way as u/v
+				t.lights[0] *= t.uvs[0].ws();
+				t.lights[1] *= t.uvs[1].ws();
+				t.lights[2] *= t.uvs[2].ws();
+				t.shadow_lights[0] *= t.uvs[0].ws();
+				t.shadow_lights[1] *= t.uvs[1].ws();
+				t.shadow_lights[2] *= t.uvs[2].ws();
+				t.shadow_coords[0] *= t.uvs[0].ws();
+				t.shadow_coords[1] *= t.uvs[1].ws();
+				t.shadow_coords[2] *= t.uvs[2].ws();
+
+				// Perspective divide into normalized device coordinates
+				t.vertices[0] = div_vec3d(&t.vertices[0], t.vertices[0].w);
+				t.vertices[1] = div_vec3d(&t.vertices[1], t.vertices[1].w);
+				t.vertices[2] = div_vec3d(&t.vertices[2], t.vertices[2].w);
+
+				// Screen y goes down, view space y goes up
+				t.vertices[0].y *= -1f32;
+				t.vertices[1].y *= -1f32;
+				t.vertices[2].y *= -1f32;
+
+				// Move to center of screen
+				t.vertices[0].x += 1f32;
+				t.vertices[0].y += 1f32;
+				t.vertices[1].x += 1f32;
+				t.vertices[1].y += 1f32;
+				t.vertices[2].x += 1f32;
+				t.vertices[2].y += 1f32;
+
+				// scale to screen size
+				t.vertices[0].x *= 0.5 * SCREEN_WIDTH as f32;
+				t.vertices[0].y *= 0.5 * SCREEN_HEIGHT as f32;
+				t.vertices[1].x *= 0.5 * SCREEN_WIDTH as f32;
+				t.vertices[1].y *= 0.5 * SCREEN_HEIGHT as f32;
+				t.vertices[2].x *= 0.5 * SCREEN_WIDTH as f32;
+				t.vertices[2].y *= 0.5 * SCREEN_HEIGHT as f32;
+
+				// Shift the geometry against the sample position, the rasterizer always samples pixel centers
+				t.vertices[0].x -= offset.0;
+				t.vertices[0].y -= offset.1;
+				t.vertices[1].x -= offset.0;
+				t.vertices[1].y -= offset.1;
+				t.vertices[2].x -= offset.0;
+				t.vertices[2].y -= offset.1;
+
+				// Transparent triangles are drawn after every opaque one, see below
+				if self.s_material.blend_mode.is_transparent() {
+					if self.transparent_triangles.len() < MAX_TRANSPARENT_TRIANGLES {
+						self.transparent_triangles.push(*t);
+					}
+					continue;
+				}
 
-			self.render_overlay(image);
+				// fill_triangle_safe(&mut image, &t, &RGB::rgb(126,127,126));
+				let shadow = if self.shadow_light.is_some() { Some(&self.shadow_map) } else { None };
+				texture_triangle_safe(image, &mut self.image_depth, t, &self.s_texture, &self.s_material, shadow, self.debug_view);
+				if self.debug_view == DebugView::Wireframe {
+					draw_triangle_safe(image, &self.image_depth, t, WIREFRAME_COLOR);
+				}
+			}
 		}
 
-		// Sort triangles from back to front
-		/*sort(vecTrianglesToRaster.begin(), vecTrianglesToRaster.end(), [](triangle &t1, triangle &t2)
-		{
-			float z1 = (t1.p[0].z + t1.p[1].z + t1.p[2].z) / 3.0f;
-			float z2 = (t2.p[0].z + t2.p[1].z + t2.p[2].z) / 3.0f;
-			return z1 > z2;
-		});*/
-		// Loop through all transformed, viewed, projected, and sorted triangles
+		// Sort transparent triangles from back to front, uvs hold 1/w so the farthest triangle has the smallest sum
+		let transparent = &mut self.transparent_triangles;
+		transparent.sort_unstable_by(|t1, t2| {
+			let z1 = t1.uvs[0].ws() + t1.uvs[1].ws() + t1.uvs[2].ws();
+			let z2 = t2.uvs[0].ws() + t2.uvs[1].ws() + t2.uvs[2].ws();
+			z1.partial_cmp(&z2).unwrap_or(core::cmp::Ordering::Equal)
+		});
+		let shadow = if self.shadow_light.is_some() { Some(&self.shadow_map) } else { None };
+		for t in transparent.iter() {
+			texture_triangle_safe(image, &mut self.image_depth, t, &self.s_texture, &self.s_material, shadow, self.debug_view);
+			if self.debug_view == DebugView::Wireframe {
+				draw_triangle_safe(image, &self.image_depth, t, WIREFRAME_COLOR);
+			}
+		}
 	}
 
 	fn render_outline(image: &mut [RGBA]) {
@@ -277,64 +644,44 @@ impl SoftwareRenderer {
 		}
 	}
 
-	fn render_overlay(&mut self, image: &mut [RGBA]) -> () {
-		for i in 0..SCREEN_SPACE {
-			unsafe {
-				let texture_pixel = self.s_made_with.pixels.get_unchecked(i as usize);
+	/// Title, what is shown and the "made with" logos in the bottom right corner.
+	fn render_overlay(&mut self, image: &mut [RGBA]) {
+		let mut canvas = Canvas::screen(image);
+		canvas.draw_text(8, 8, "SOFTWARE RENDERER", HUD_COLOR, 2);
+		let camera = match self.camera.mode() {
+			CameraMode::Orbit => "orbit",
+			CameraMode::FreeFly => "free-fly",
+		};
+		let _ = write!(canvas.text(8, 28, HUD_COLOR, 1), "view: {} (v)\ncamera: {} (rmb)", self.debug_view.name(), camera);
 
-				if texture_pixel.a != 0 {
-					*image.get_unchecked_mut(i as usize) = *texture_pixel;
-				}
-			}
-		}
+		let made_with = &self.s_made_with;
+		let logos = Image::new(made_with.resolution_u as i32, made_with.resolution_v as i32, &made_with.pixels);
+		canvas.blit_region(
+			SCREEN_WIDTH as i32 - MADE_WITH_LOGOS.width, SCREEN_HEIGHT as i32 - MADE_WITH_LOGOS.height,
+			&logos, MADE_WITH_LOGOS,
+		);
 	}
 }
 
-fn mul_vec3d(vec:&Vec4, multiplier:f32) -> Vec4 {
-	return Vec4::xyz1(vec.x * multiplier, vec.y * multiplier, vec.z * multiplier);
-}
-
 fn div_vec3d(vec:&Vec4, divider:f32) -> Vec4 {
-	return Vec4::xyz1(vec.x / divider, vec.y / divider, vec.z / divider);
-}
-
-fn cross_product(vec1:&Vec4, vec2:&Vec4) -> Vec4 {
-	return Vec4 {
-		x: vec1.y * vec2.z - vec1.z * vec2.y,
-		y: vec1.z * vec2.x - vec1.x * vec2.z,
-		z: vec1.x * vec2.y - vec1.y * vec2.x,
-		w: vec1.w * vec2.w - vec1.w * vec2.w, // TODO: 0 / 1 / this code?
-	};
-}
-
-fn normalize2(vec:&Vec4) -> Vec4 {
-	let len = vec.len();
-	return Vec4 {
-		x: vec.x / len,
-		y: vec.y / len,
-		z: vec.z / len,
-		w: vec.w, // TODO: делить на len?
-	};
-}
-
-fn normalize(vec:&Vec4) -> Vec4 {
-	let rlen = vec.inv_len();
-	return Vec4 {
-		x: vec.x * rlen,
-		y: vec.y * rlen,
-		z: vec.z * rlen,
-		w: vec.w, // TODO: делить на len?
-	};
+	Vec4::xyz1(vec.x / divider, vec.y / divider, vec.z / divider)
 }
 
 #[derive(Copy, Clone)]
 struct Triangle {
 	vertices:[Vec4; 3],
 	uvs:[Vec3; 3],
+	normals:[Vec3; 3],
+	/// Per-vertex light intensity, multiplied with the texel colour.
+	lights:[Vec3; 3],
+	/// Light of the shadow casting light, added to `lights` as far as the shadow map lets it through.
+	shadow_lights:[Vec3; 3],
+	/// Shadow map x, y and depth of every vertex.
+	shadow_coords:[Vec3; 3],
 }
 impl Triangle {
 	const fn zero_spaced_verticies() -> Triangle {
-		return Triangle {
+		Triangle {
 			vertices: [
 				Vec4::ZERO,
 				Vec4::ZERO,
@@ -344,61 +691,347 @@ impl Triangle {
 				Vec3::ZERO,
 				Vec3::ZERO,
 				Vec3::ZERO,
-			]
-		};
+			],
+			normals: [
+				Vec3::ZERO,
+				Vec3::ZERO,
+				Vec3::ZERO,
+			],
+			lights: [
+				Vec3::ONE,
+				Vec3::ONE,
+				Vec3::ONE,
+			],
+			shadow_lights: [Vec3::ZERO; 3],
+			shadow_coords: [Vec3::ZERO; 3],
+		}
+	}
+
+	/// Copies every attribute of vertex `from` of `src` into vertex `to`.
+	fn copy_vertex(&mut self, to:usize, src:&Triangle, from:usize) {
+		self.vertices[to] = src.vertices[from];
+		self.uvs     [to] = src.uvs     [from];
+		self.normals [to] = src.normals [from];
+		self.lights  [to] = src.lights  [from];
+		self.shadow_lights[to] = src.shadow_lights[from];
+		self.shadow_coords[to] = src.shadow_coords[from];
+	}
+
+	fn swap_vertices(&mut self, a:usize, b:usize) {
+		self.vertices.swap(a, b);
+		self.uvs     .swap(a, b);
+		self.normals .swap(a, b);
+		self.lights  .swap(a, b);
+		self.shadow_lights.swap(a, b);
+		self.shadow_coords.swap(a, b);
+	}
+
+	/// Sets vertex `to` to the point `t` of the way from vertex `a` to vertex `b` of `src`.
+	fn lerp_vertex(&mut self, to:usize, src:&Triangle, a:usize, b:usize, t:f32) {
+		self.vertices[to] = src.vertices[a] + (src.vertices[b] - src.vertices[a]) * t;
+		self.uvs     [to] = src.uvs     [a] + (src.uvs    [b] - src.uvs    [a]) * t;
+		self.normals [to] = src.normals [a] + (src.normals[b] - src.normals[a]) * t;
+		self.lights  [to] = src.lights  [a] + (src.lights [b] - src.lights [a]) * t;
+		self.shadow_lights[to] = src.shadow_lights[a] + (src.shadow_lights[b] - src.shadow_lights[a]) * t;
+		self.shadow_coords[to] = src.shadow_coords[a] + (src.shadow_coords[b] - src.shadow_coords[a]) * t;
 	}
 }
 
+const MAX_TRANSPARENT_TRIANGLES:usize = MAX_BASIC_MESH_TRIANGLES;
+
 const MAX_TEXTURE_SIZE:usize = 1024;
+const MAX_MIP_LEVELS:usize = 11; // 1024x1024 down to 1x1
+/// Level 0 followed by the whole mip chain, which takes at most a third of level 0 (plus rounding).
+const MAX_TEXTURE_PIXELS:usize = MAX_TEXTURE_SIZE*MAX_TEXTURE_SIZE / 3 * 4 + 2*MAX_TEXTURE_SIZE;
+
+/// What happens to texture coordinates outside of `[0, 1)`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Wrap {
+	Repeat,
+	Clamp,
+	Mirror,
+}
+impl Wrap {
+	/// Maps a texel coordinate into `[0, size)`.
+	#[inline(always)]
+	fn apply(self, coord:i32, size:i32) -> i32 {
+		match self {
+			Wrap::Repeat => coord.rem_euclid(size),
+			Wrap::Clamp  => coord.clamp(0, size - 1),
+			Wrap::Mirror => {
+				let coord = coord.rem_euclid(2 * size);
+				if coord < size { coord } else { 2 * size - 1 - coord }
+			},
+		}
+	}
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Filter {
+	Nearest,
+	Bilinear,
+}
+
+#[derive(Copy, Clone)]
+pub struct Sampler {
+	pub wrap_u:Wrap,
+	pub wrap_v:Wrap,
+	pub filter:Filter,
+	/// Picks the mip level from screen space derivatives instead of always sampling level 0.
+	pub mipmaps:bool,
+}
+impl Sampler {
+	pub const fn new(wrap:Wrap, filter:Filter, mipmaps:bool) -> Sampler {
+		Sampler { wrap_u: wrap, wrap_v: wrap, filter, mipmaps }
+	}
+}
+
+/// How a fragment is combined with the pixel already in the frame buffer.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum BlendMode {
+	/// Texel replaces the pixel, its alpha is ignored.
+	Opaque,
+	/// Texels with alpha below the cutoff are discarded, the rest are drawn opaque.
+	AlphaTest(u8),
+	/// `src * a + dst * (1 - a)`. Depth is tested but not written.
+	AlphaBlend,
+	/// `dst + src * a`, saturating. Depth is tested but not written.
+	Additive,
+}
+impl BlendMode {
+	/// Transparent fragments do not write depth, so their triangles have to be drawn back to front after the opaque ones.
+	#[inline(always)]
+	const fn is_transparent(self) -> bool {
+		matches!(self, BlendMode::AlphaBlend | BlendMode::Additive)
+	}
+}
+
+#[derive(Copy, Clone)]
+pub struct Material {
+	pub blend_mode:BlendMode,
+	/// Multiplied with the alpha of every texel.
+	pub opacity:u8,
+}
+impl Material {
+	pub const fn new(blend_mode:BlendMode, opacity:u8) -> Material {
+		Material { blend_mode, opacity }
+	}
+}
+
 struct Texture {
-	pixels:[RGBA; MAX_TEXTURE_SIZE*MAX_TEXTURE_SIZE],
+	/// Level 0 followed by the mip chain, `MAX_TEXTURE_PIXELS` long once allocated.
+	pixels:Vec<RGBA>,
 	resolution_u:u16,
 	resolution_v:u16,
+	mip_levels:u8,
+	mip_offsets:[u32; MAX_MIP_LEVELS],
+	sampler:Sampler,
 }
 impl Texture {
 	const fn zero_resolution_texture() -> Texture {
 		Texture {
-			pixels: [RGBA::zeroed(); MAX_TEXTURE_SIZE*MAX_TEXTURE_SIZE],
+			pixels: Vec::new(),
 			resolution_u: 0,
 			resolution_v: 0,
+			mip_levels: 0,
+			mip_offsets: [0; MAX_MIP_LEVELS],
+			sampler: Sampler::new(Wrap::Repeat, Filter::Bilinear, true),
+		}
+	}
+
+	/// Clears the texture to transparent black at the given resolution, the mip chain has to be generated again.
+	fn allocate(&mut self, resolution_u:u16, resolution_v:u16) {
+		debug_assert!(resolution_u as usize <= MAX_TEXTURE_SIZE && resolution_v as usize <= MAX_TEXTURE_SIZE);
+		self.resolution_u = resolution_u;
+		self.resolution_v = resolution_v;
+		self.pixels = vec![RGBA::zeroed(); MAX_TEXTURE_PIXELS];
+	}
+
+	#[inline(always)]
+	fn level_resolution(&self, level:u8) -> (u16, u16) {
+		(
+			core::cmp::max(self.resolution_u >> level, 1),
+			core::cmp::max(self.resolution_v >> level, 1),
+		)
+	}
+
+	/// Builds every mip level from level 0 with a 2x2 box filter.
+	fn generate_mipmaps(&mut self) {
+		self.mip_levels = 1;
+		self.mip_offsets[0] = 0;
+		if self.resolution_u == 0 || self.resolution_v == 0 {
+			return;
+		}
+		while (self.mip_levels as usize) < MAX_MIP_LEVELS {
+			let src_level = self.mip_levels - 1;
+			let (src_u, src_v) = self.level_resolution(src_level);
+			if src_u == 1 && src_v == 1 {
+				break;
+			}
+			let (dst_u, dst_v) = self.level_resolution(self.mip_levels);
+			let src_offset = self.mip_offsets[src_level as usize] as usize;
+			let dst_offset = src_offset + src_u as usize * src_v as usize;
+
+			for y in 0..dst_v as usize {
+				for x in 0..dst_u as usize {
+					// Odd sizes drop the last row / column, a single texel is repeated instead of read past the edge
+					let x0 = core::cmp::min(x * 2, src_u as usize - 1);
+					let y0 = core::cmp::min(y * 2, src_v as usize - 1);
+					let x1 = core::cmp::min(x0 + 1, src_u as usize - 1);
+					let y1 = core::cmp::min(y0 + 1, src_v as usize - 1);
+					let p = [
+						self.pixels[src_offset + y0 * src_u as usize + x0],
+						self.pixels[src_offset + y0 * src_u as usize + x1],
+						self.pixels[src_offset + y1 * src_u as usize + x0],
+						self.pixels[src_offset + y1 * src_u as usize + x1],
+					];
+					self.pixels[dst_offset + y * dst_u as usize + x] = RGBA {
+						r: ((p[0].r as u16 + p[1].r as u16 + p[2].r as u16 + p[3].r as u16 + 2) / 4) as u8,
+						g: ((p[0].g as u16 + p[1].g as u16 + p[2].g as u16 + p[3].g as u16 + 2) / 4) as u8,
+						b: ((p[0].b as u16 + p[1].b as u16 + p[2].b as u16 + p[3].b as u16 + 2) / 4) as u8,
+						a: ((p[0].a as u16 + p[1].a as u16 + p[2].a as u16 + p[3].a as u16 + 2) / 4) as u8,
+					};
+				}
+			}
+
+			self.mip_offsets[self.mip_levels as usize] = dst_offset as u32;
+			self.mip_levels += 1;
+		}
+	}
+
+	#[inline(always)]
+	fn texel(&self, level:u8, x:i32, y:i32) -> RGBA {
+		let (res_u, res_v) = self.level_resolution(level);
+		let x = self.sampler.wrap_u.apply(x, res_u as i32) as usize;
+		let y = self.sampler.wrap_v.apply(y, res_v as i32) as usize;
+		let idx = self.mip_offsets[level as usize] as usize + y * res_u as usize + x;
+		debug_assert!(self.pixels.get(idx).is_some());
+		unsafe { *self.pixels.get_unchecked(idx) }
+	}
+
+	/// `lod` is the base 2 logarithm of how many texels of level 0 fall on one pixel.
+	fn sample(&self, u:f32, v:f32, lod:f32) -> RGBA {
+		if self.mip_levels == 0 {
+			return RGBA::zeroed();
+		}
+
+		let level = if self.sampler.mipmaps && lod > 0f32 {
+			core::cmp::min((lod + 0.5f32) as u8, self.mip_levels - 1)
+		} else {
+			0
+		};
+		let (res_u, res_v) = self.level_resolution(level);
+		let u = u * res_u as f32;
+		let v = v * res_v as f32;
+
+		match self.sampler.filter {
+			Filter::Nearest => self.texel(level, u.floor() as i32, v.floor() as i32),
+			Filter::Bilinear => {
+				// Texel centers are at .5
+				let u = u - 0.5f32;
+				let v = v - 0.5f32;
+				let x = u.floor();
+				let y = v.floor();
+				let fx = u - x;
+				let fy = v - y;
+				let x = x as i32;
+				let y = y as i32;
+
+				let p00 = self.texel(level, x    , y    );
+				let p10 = self.texel(level, x + 1, y    );
+				let p01 = self.texel(level, x    , y + 1);
+				let p11 = self.texel(level, x + 1, y + 1);
+
+				#[inline(always)]
+				fn blend(c00:u8, c10:u8, c01:u8, c11:u8, fx:f32, fy:f32) -> u8 {
+					let top    = c00 as f32 + (c10 as f32 - c00 as f32) * fx;
+					let bottom = c01 as f32 + (c11 as f32 - c01 as f32) * fx;
+					(top + (bottom - top) * fy + 0.5f32) as u8
+				}
+
+				RGBA {
+					r: blend(p00.r, p10.r, p01.r, p11.r, fx, fy),
+					g: blend(p00.g, p10.g, p01.g, p11.g, fx, fy),
+					b: blend(p00.b, p10.b, p01.b, p11.b, fx, fy),
+					a: blend(p00.a, p10.a, p01.a, p11.a, fx, fy),
+				}
+			},
 		}
 	}
 }
 
-fn point_at_matrix(pos:&Vec4, target:&Vec4, up:&Vec4) -> Matrix4x4 {
-	// TODO: нужны ли все эти new_up, new_right, или их можно просчитать заранее?
-	// TODO: need normalization?
-	let new_forward = normalize(&(*target - *pos));
+/// Screen space gradients of `u/w`, `v/w` and `1/w` across a triangle, used to pick mip levels.
+#[derive(Copy, Clone)]
+struct TexGradients {
+	du_dx:f32, du_dy:f32,
+	dv_dx:f32, dv_dy:f32,
+	dw_dx:f32, dw_dy:f32,
+}
+impl TexGradients {
+	/// Expects screen space vertices and uvs which are already divided by w.
+	fn new(triangle:&Triangle) -> TexGradients {
+		let e1x = triangle.vertices[1].x - triangle.vertices[0].x;
+		let e1y = triangle.vertices[1].y - triangle.vertices[0].y;
+		let e2x = triangle.vertices[2].x - triangle.vertices[0].x;
+		let e2y = triangle.vertices[2].y - triangle.vertices[0].y;
+		let det = e1x * e2y - e2x * e1y;
+		if det == 0f32 {
+			return TexGradients { du_dx: 0f32, du_dy: 0f32, dv_dx: 0f32, dv_dy: 0f32, dw_dx: 0f32, dw_dy: 0f32 };
+		}
+		let rdet = 1f32 / det;
 
-	let a = mul_vec3d(&new_forward, Vec4::dot(up, &new_forward));
-	let new_up = normalize(&(*up - a));
+		// Attributes are planar in screen space, solve for the plane slopes
+		let gradient = |a0:f32, a1:f32, a2:f32| -> (f32, f32) {
+			let d1 = a1 - a0;
+			let d2 = a2 - a0;
+			((d1 * e2y - d2 * e1y) * rdet, (d2 * e1x - d1 * e2x) * rdet)
+		};
+		let (du_dx, du_dy) = gradient(triangle.uvs[0].us(), triangle.uvs[1].us(), triangle.uvs[2].us());
+		let (dv_dx, dv_dy) = gradient(triangle.uvs[0].vs(), triangle.uvs[1].vs(), triangle.uvs[2].vs());
+		let (dw_dx, dw_dy) = gradient(triangle.uvs[0].ws(), triangle.uvs[1].ws(), triangle.uvs[2].ws());
 
-	let new_right = cross_product(&new_up, &new_forward);
+		TexGradients { du_dx, du_dy, dv_dx, dv_dy, dw_dx, dw_dy }
+	}
 
-	return Matrix4x4 {
-		m: [
-			[  new_right.x,   new_right.y,   new_right.z, 0f32],
-			[     new_up.x,      new_up.y,      new_up.z, 0f32],
-			[new_forward.x, new_forward.y, new_forward.z, 0f32],
-			[        pos.x,         pos.y,         pos.z, 1f32]
-		]
-	};
+	/// Level of detail at a pixel with the interpolated `u/w`, `v/w` and `1/w`.
+	#[inline(always)]
+	fn lod(&self, tex_u:f32, tex_v:f32, tex_w:f32, texture:&Texture) -> f32 {
+		if !texture.sampler.mipmaps || texture.mip_levels <= 1 {
+			return 0f32;
+		}
+		// d(u/w / 1/w) = (d(u/w) - u * d(1/w)) / (1/w)
+		let rw = 1f32 / tex_w;
+		let u = tex_u * rw;
+		let v = tex_v * rw;
+		let res_u = texture.resolution_u as f32;
+		let res_v = texture.resolution_v as f32;
+		let dudx = (self.du_dx - u * self.dw_dx) * rw * res_u;
+		let dudy = (self.du_dy - u * self.dw_dy) * rw * res_u;
+		let dvdx = (self.dv_dx - v * self.dw_dx) * rw * res_v;
+		let dvdy = (self.dv_dy - v * self.dw_dy) * rw * res_v;
+		let rho_sq = (dudx * dudx + dvdx * dvdx).max(dudy * dudy + dvdy * dvdy);
+		// log2(sqrt(x)) = log2(x) / 2
+		0.5f32 * rho_sq.log2()
+	}
 }
 
-fn inverse_transformation_matrix(matrix:&Matrix4x4) -> Matrix4x4 {
-	return Matrix4x4 {
-		m: [
-			[matrix.m[0][0], matrix.m[1][0], matrix.m[2][0], 0f32],
-			[matrix.m[0][1], matrix.m[1][1], matrix.m[2][1], 0f32],
-			[matrix.m[0][2], matrix.m[1][2], matrix.m[2][2], 0f32],
-			[
-				-(matrix.m[3][0] * matrix.m[0][0] + matrix.m[3][1] * matrix.m[0][1] + matrix.m[3][2] * matrix.m[0][2]),
-				-(matrix.m[3][0] * matrix.m[1][0] + matrix.m[3][1] * matrix.m[1][1] + matrix.m[3][2] * matrix.m[1][2]),
-				-(matrix.m[3][0] * matrix.m[2][0] + matrix.m[3][1] * matrix.m[2][1] + matrix.m[3][2] * matrix.m[2][2]),
-				1f32
-			]
-		]
-	};
+/// Transforms a direction, ignoring the translation part of the matrix.
+fn multiply_direction_matrix(inp:&Vec3, matrix:&Matrix4x4) -> Vec3 {
+	Vec3 {
+		x: inp.x * matrix.m[0][0] + inp.y * matrix.m[1][0] + inp.z * matrix.m[2][0],
+		y: inp.x * matrix.m[0][1] + inp.y * matrix.m[1][1] + inp.z * matrix.m[2][1],
+		z: inp.x * matrix.m[0][2] + inp.y * matrix.m[1][2] + inp.z * matrix.m[2][2],
+	}
+}
+
+/// Same as `multiply_vector_matrix`, but keeps `w` and does not divide by it.
+fn project_vector_matrix(inp:&Vec4, matrix:&Matrix4x4) -> Vec4 {
+	Vec4 {
+		x: inp.x * matrix.m[0][0] + inp.y * matrix.m[1][0] + inp.z * matrix.m[2][0] + matrix.m[3][0],
+		y: inp.x * matrix.m[0][1] + inp.y * matrix.m[1][1] + inp.z * matrix.m[2][1] + matrix.m[3][1],
+		z: inp.x * matrix.m[0][2] + inp.y * matrix.m[1][2] + inp.z * matrix.m[2][2] + matrix.m[3][2],
+		w: inp.x * matrix.m[0][3] + inp.y * matrix.m[1][3] + inp.z * matrix.m[2][3] + matrix.m[3][3],
+	}
 }
 
 fn multiply_vector_matrix(inp:&Vec4, matrix:&Matrix4x4) -> Vec4 {
@@ -417,90 +1050,134 @@ fn multiply_vector_matrix(inp:&Vec4, matrix:&Matrix4x4) -> Vec4 {
 		out.z /= w;
 	}
 
-	return out;
+	out
 }
 
 const MAX_BASIC_MESH_TRIANGLES:usize = 2048;
 struct MeshBasic {
-	triangles:[Triangle; MAX_BASIC_MESH_TRIANGLES],
+	triangles:Vec<Triangle>,
 	amount_of_triangles:u16,
+	/// Bones of every vertex of every triangle, only used when the mesh has an animation section.
+	skins:Vec<[Skin; 3]>,
+	skeleton:Skeleton,
 }
 impl MeshBasic {
 	const fn new() -> MeshBasic {
-		return MeshBasic {
+		MeshBasic {
 			amount_of_triangles: 0,
-			triangles: [Triangle::zero_spaced_verticies(); MAX_BASIC_MESH_TRIANGLES]
+			triangles: Vec::new(),
+			skins: Vec::new(),
+			skeleton: Skeleton::new(),
 		}
 	}
 }
 
 fn u8_to_u16(u1:u8, u2:u8) -> u16 {
-	((u1 as u16) << 8) |
-		((u2 as u16) << 0)
+	u16::from_be_bytes([u1, u2])
 }
 
 fn u8_to_u32(u1:u8, u2:u8, u3:u8, u4:u8) -> u32 {
-	((u1 as u32) << 24) |
-		((u2 as u32) << 16) |
-		((u3 as u32) <<  8) |
-		((u4 as u32) <<  0)
+	u32::from_be_bytes([u1, u2, u3, u4])
 }
 
 fn u8_to_f32(u1:u8, u2:u8, u3:u8, u4:u8) -> f32 {
 	f32::from_bits(u8_to_u32(u1, u2, u3, u4))
 }
 
-fn load_mesh(mesh:&mut MeshBasic, binary:&[u8]) {
+/// Big-endian, as written by `tools/helper`:
+/// ```text
+/// u16 vertices, u16 texture vertices, u16 triangles
+/// vertices × f32 x, y, z
+/// texture vertices × f32 u, v
+/// triangles × u16 vertex indices[3], u16 texture vertex indices[3]
+/// ```
+/// followed by an optional animation section:
+/// ```text
+/// u8 bones
+/// bones × u8 parent (0xFF for roots, parents come first), f32 translation[3], f32 rotation quaternion xyzw[4], f32 scale[3]
+/// vertices × u8 bone indices[4], u8 weights[4] (adding up to 255)
+/// u8 clips
+/// clips × f32 duration in seconds, u16 tracks
+///     tracks × u8 bone, u8 kind (0 translation, 1 rotation, 2 scale), u16 keyframes
+///         keyframes × f32 time, f32 value[3] (or [4] for rotations)
+/// ```
+fn load_mesh(mesh:&mut MeshBasic, binary:&[u8]) -> Result<(), AnimationError> {
 	let         verticies_amount = u8_to_u16(binary[0], binary[1]);
 	let texture_verticies_amount = u8_to_u16(binary[2], binary[3]);
 	mesh.amount_of_triangles = u8_to_u16(binary[4], binary[5]);
+	debug_assert!(mesh.amount_of_triangles as usize <= MAX_BASIC_MESH_TRIANGLES);
+	mesh.triangles = vec![Triangle::zero_spaced_verticies(); mesh.amount_of_triangles as usize];
 
-	let mut         verticies = [Vec4::ZERO; 4*1024]; // TODO: dynamic buffer?
-	let mut texture_verticies = [Vec3::ZERO; 4*1024]; // TODO: dynamic buffer?
+	let mut         verticies = vec![Vec4::ZERO; verticies_amount as usize];
+	let mut texture_verticies = vec![Vec3::ZERO; texture_verticies_amount as usize];
 
 	let mut offset = 6;
-	for i in 0usize..verticies_amount as usize {
-		verticies[i].x = u8_to_f32(binary[offset + 0], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
+	for vertex in verticies.iter_mut() {
+		vertex.x = u8_to_f32(binary[offset], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
 		offset += 4;
-		verticies[i].y = u8_to_f32(binary[offset + 0], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
+		vertex.y = u8_to_f32(binary[offset], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
 		offset += 4;
-		verticies[i].z = u8_to_f32(binary[offset + 0], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
+		vertex.z = u8_to_f32(binary[offset], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
 		offset += 4;
 	}
-	for i in 0usize..texture_verticies_amount as usize {
-		*texture_verticies[i].u() = u8_to_f32(binary[offset + 0], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
+	for texture_vertex in texture_verticies.iter_mut() {
+		*texture_vertex.u() = u8_to_f32(binary[offset], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
 		offset += 4;
-		*texture_verticies[i].v() = u8_to_f32(binary[offset + 0], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
+		*texture_vertex.v() = u8_to_f32(binary[offset], binary[offset + 1], binary[offset + 2], binary[offset + 3]);
 		offset += 4;
 	}
+	// The format has no normals, so smooth ones are built by summing
+	// the normals of every face that shares a vertex
+	let mut vertex_indices = vec![[0u16; 3]; mesh.amount_of_triangles as usize];
+	let mut         normals = vec![Vec3::ZERO; verticies_amount as usize];
+
 	for i in 0usize..mesh.amount_of_triangles as usize {
-		mesh.triangles[i].vertices[0] =         verticies[u8_to_u16(binary[offset + 0], binary[offset + 1]) as usize];
-		offset += 2;
-		mesh.triangles[i].vertices[1] =         verticies[u8_to_u16(binary[offset + 0], binary[offset + 1]) as usize];
-		offset += 2;
-		mesh.triangles[i].vertices[2] =         verticies[u8_to_u16(binary[offset + 0], binary[offset + 1]) as usize];
-		offset += 2;
-		mesh.triangles[i].   uvs[0] = texture_verticies[u8_to_u16(binary[offset + 0], binary[offset + 1]) as usize];
-		offset += 2;
-		mesh.triangles[i].   uvs[1] = texture_verticies[u8_to_u16(binary[offset + 0], binary[offset + 1]) as usize];
-		offset += 2;
-		mesh.triangles[i].   uvs[2] = texture_verticies[u8_to_u16(binary[offset + 0], binary[offset + 1]) as usize];
-		offset += 2;
+		for j in 0..3 {
+			vertex_indices[i][j] = u8_to_u16(binary[offset], binary[offset + 1]);
+			mesh.triangles[i].vertices[j] = verticies[vertex_indices[i][j] as usize];
+			offset += 2;
+		}
+		for j in 0..3 {
+			mesh.triangles[i].uvs[j] = texture_verticies[u8_to_u16(binary[offset], binary[offset + 1]) as usize];
+			offset += 2;
+		}
+
+		let line1 = mesh.triangles[i].vertices[1] - mesh.triangles[i].vertices[0];
+		let line2 = mesh.triangles[i].vertices[2] - mesh.triangles[i].vertices[0];
+		let face_normal = Vec3::cross_product(&line1.xyz(), &line2.xyz());
+		for j in 0..3 {
+			normals[vertex_indices[i][j] as usize] += face_normal;
+		}
 	}
+	for i in 0usize..mesh.amount_of_triangles as usize {
+		for j in 0..3 {
+			mesh.triangles[i].normals[j] = normals[vertex_indices[i][j] as usize].normalize();
+		}
+	}
+
+	if offset < binary.len() {
+		let mut skins = vec![Skin::rigid(0); verticies_amount as usize];
+		mesh.skeleton.load(binary, offset, &mut skins)?;
+		mesh.skins = vertex_indices.iter()
+			.map(|indices| [skins[indices[0] as usize], skins[indices[1] as usize], skins[indices[2] as usize]])
+			.collect();
+	}
+	Ok(())
 }
 
 fn load_texture(texture:&mut Texture, binary:&[u8]) {
-	texture.resolution_u = u8_to_u16(binary[0], binary[1]);
-	texture.resolution_v = u8_to_u16(binary[2], binary[3]);
+	texture.allocate(u8_to_u16(binary[0], binary[1]), u8_to_u16(binary[2], binary[3]));
 
 	for i in 0..(texture.resolution_u as u32 * texture.resolution_v as u32 ) {
 		let pixel = &mut texture.pixels[i as usize];
 		let off = i * 4;
-		pixel.r = binary[(off+0) as usize];
+		pixel.r = binary[off as usize];
 		pixel.g = binary[(off+1) as usize];
 		pixel.b = binary[(off+2) as usize];
 		pixel.a = binary[(off+3) as usize];
 	}
+
+	texture.generate_mipmaps();
 }
 
 //void draw_line_unsafe(u8 *screen, s16 x0, s16 y0, s16 x1, s16 y1) {
@@ -521,7 +1198,11 @@ fn load_texture(texture:&mut Texture, binary:&[u8]) {
 //	}
 //}
 //
-fn draw_line_safe(screen:&mut [u8], mut x0:i32, mut y0:i32, x1:i32, y1:i32) {
+/// Bresenham line from pixel to pixel, the ends are `(x, y, w)` with the 1/w depth as `w`.
+/// Pixels behind the depth buffer are skipped, the depth buffer itself is left as is.
+fn draw_line_safe(screen:&mut [RGBA], depth_buffer:&[f32], from:(i32, i32, f32), to:(i32, i32, f32), color:RGBA) {
+	let (mut x0, mut y0, w0) = from;
+	let (x1, y1, w1) = to;
 	let dx = (x1-x0).abs();
 	let dy = (y1-y0).abs();
 	let sx = if x0 < x1 { 1 } else { -1 };
@@ -529,16 +1210,21 @@ fn draw_line_safe(screen:&mut [u8], mut x0:i32, mut y0:i32, x1:i32, y1:i32) {
 	let mut err = (if dx>dy { dx } else { -dy })/2;
 	let mut e2;
 
+	// 1/w is affine in screen space, so it can be stepped along the line
+	let steps = core::cmp::max(dx, dy);
+	let w_step = if steps == 0 { 0f32 } else { (w1 - w0) / steps as f32 };
+	let mut w = w0;
+
 	loop {
 		if
 			x0 >= 0 && x0 < SCREEN_WIDTH  as i32 &&
 			y0 >= 0 && y0 < SCREEN_HEIGHT as i32
 		{
-			let offset = (y0 as usize * SCREEN_WIDTH as usize + x0 as usize)*4;
-			screen[offset+0] = 0xFFu8;
-			screen[offset+1] = 0xFFu8;
-			screen[offset+2] = 0xFFu8;
-			screen[offset+3] = 0xFFu8;
+			let offset = y0 as usize * SCREEN_WIDTH as usize + x0 as usize;
+			// An edge pixel may hold the depth of the neighbouring triangle, so a slightly farther line still passes
+			if w >= depth_buffer[offset] * WIREFRAME_DEPTH_TOLERANCE {
+				screen[offset] = color;
+			}
 		}
 
 		if x0 == x1 && y0 == y1 {
@@ -548,879 +1234,1142 @@ fn draw_line_safe(screen:&mut [u8], mut x0:i32, mut y0:i32, x1:i32, y1:i32) {
 		e2 = err;
 		if e2 > -dx { err -= dy; x0 += sx; }
 		if e2 <  dy { err += dx; y0 += sy; }
+		w += w_step;
 	}
 }
 
 const MODEL_BIN:&[u8] = include_bytes!("../res/model.rust3d");
 const TEXTURE_BIN:&[u8] = include_bytes!("../res/texture.rust2d");
 const MADE_WITH_BIN:&[u8] = include_bytes!("../res/made_with.rust2d");
-
-fn draw_triangle_safe(screen:&mut [u8], triangle:&Triangle) {
-	draw_line_safe(screen, triangle.vertices[0].x as i32, triangle.vertices[0].y as i32, triangle.vertices[1].x as i32, triangle.vertices[1].y as i32);
-	draw_line_safe(screen, triangle.vertices[0].x as i32, triangle.vertices[0].y as i32, triangle.vertices[2].x as i32, triangle.vertices[2].y as i32);
-	draw_line_safe(screen, triangle.vertices[1].x as i32, triangle.vertices[1].y as i32, triangle.vertices[2].x as i32, triangle.vertices[2].y as i32);
+/// Part of the "made with" image below its title text.
+const MADE_WITH_LOGOS:Rect = Rect::new(136, 392, 240, 112);
+
+const WIREFRAME_COLOR:RGBA = RGBA::rgba(0x40, 0xFF, 0x40, 0xFF);
+const HUD_COLOR:RGBA = RGBA::rgba(0xFF, 0xFF, 0xFF, 0xE0);
+const WIREFRAME_DEPTH_TOLERANCE:f32 = 0.99f32;
+
+/// Draws the edges of a screen space triangle, expects 1/w in `uvs[i].w` like `texture_triangle_safe`.
+fn draw_triangle_safe(screen:&mut [RGBA], depth_buffer:&[f32], triangle:&Triangle, color:RGBA) {
+	let end = |i:usize| (triangle.vertices[i].x as i32, triangle.vertices[i].y as i32, triangle.uvs[i].ws());
+	draw_line_safe(screen, depth_buffer, end(0), end(1), color);
+	draw_line_safe(screen, depth_buffer, end(0), end(2), color);
+	draw_line_safe(screen, depth_buffer, end(1), end(2), color);
 }
 
-fn fill_triangle_safe(screen:&mut [RGBA], triangle:&Triangle, color:&RGB) {
-	// TODO: другие алгоритмы
-	unsafe {
-		fill_triangle_1(
-			screen,
-			(triangle.vertices[0].x as i16).clamp(0, SCREEN_WIDTH as i16 - 1), (triangle.vertices[0].y as i16).clamp(0, SCREEN_HEIGHT as i16 - 1),
-			(triangle.vertices[1].x as i16).clamp(0, SCREEN_WIDTH as i16 - 1), (triangle.vertices[1].y as i16).clamp(0, SCREEN_HEIGHT as i16 - 1),
-			(triangle.vertices[2].x as i16).clamp(0, SCREEN_WIDTH as i16 - 1), (triangle.vertices[2].y as i16).clamp(0, SCREEN_HEIGHT as i16 - 1),
-			color
-		);
+/// Expects screen space vertices and uvs/lights already divided by w, with `uvs[i].w` holding 1/w.
+/// With a `shadow` map the shadow light is added per pixel, scaled by its visibility.
+/// The checkerboard and normals debug views replace the texture.
+/// Goes through the widest lanes the CPU has.
+fn texture_triangle_safe(
+	screen:&mut [RGBA],
+	depth_buffer:&mut [f32],
+	triangle:&Triangle,
+	texture:&Texture,
+	material:&Material,
+	shadow:Option<&ShadowMap>,
+	view:DebugView,
+) {
+	#[cfg(target_arch = "x86_64")]
+	{
+		if span::has_avx2() {
+			return unsafe { texture_triangle_avx2(screen, depth_buffer, triangle, texture, material, shadow, view) };
+		}
 	}
+	texture_triangle_spans::<span::Native>(screen, depth_buffer, triangle, texture, material, shadow, view);
 }
 
-fn texture_triangle_safe(screen:&mut [RGBA], depth_buffer:&mut [f32], triangle:&Triangle, texture:&Texture) {
-	// TODO: другие алгоритмы
-	// texture_triangle_1(
-	texture_triangle_unsafe(
-		screen,
-		depth_buffer,
-		(triangle.vertices[0].x.round() as i16).clamp(0, SCREEN_WIDTH as i16), (triangle.vertices[0].y.round() as i16).clamp(0, SCREEN_HEIGHT as i16), triangle.uvs[0].us(), triangle.uvs[0].vs(), triangle.uvs[0].ws(), // TODO: rounding / not rounding macro
-		(triangle.vertices[1].x.round() as i16).clamp(0, SCREEN_WIDTH as i16), (triangle.vertices[1].y.round() as i16).clamp(0, SCREEN_HEIGHT as i16), triangle.uvs[1].us(), triangle.uvs[1].vs(), triangle.uvs[1].ws(),
-		(triangle.vertices[2].x.round() as i16).clamp(0, SCREEN_WIDTH as i16), (triangle.vertices[2].y.round() as i16).clamp(0, SCREEN_HEIGHT as i16), triangle.uvs[2].us(), triangle.uvs[2].vs(), triangle.uvs[2].ws(),
-		texture,
-	);
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn texture_triangle_avx2(
+	screen:&mut [RGBA],
+	depth_buffer:&mut [f32],
+	triangle:&Triangle,
+	texture:&Texture,
+	material:&Material,
+	shadow:Option<&ShadowMap>,
+	view:DebugView,
+) {
+	texture_triangle_spans::<span::Avx2>(screen, depth_buffer, triangle, texture, material, shadow, view);
 }
 
-#[derive(Copy, Clone)]
-struct RGB {
-	r:u8,g:u8,b:u8
+/// `texture_triangle_safe` with lanes `L`.
+#[inline(always)]
+fn texture_triangle_spans<L:Lanes>(
+	screen:&mut [RGBA],
+	depth_buffer:&mut [f32],
+	triangle:&Triangle,
+	texture:&Texture,
+	material:&Material,
+	shadow:Option<&ShadowMap>,
+	view:DebugView,
+) {
+	let gradients = TexGradients::new(triangle);
+
+	// Attributes are affine in screen space once divided by w, so plain barycentric weights interpolate them
+	let tex_0 = triangle.uvs[0];
+	let tex_d1 = triangle.uvs[1] - tex_0;
+	let tex_d2 = triangle.uvs[2] - tex_0;
+	let light_0 = triangle.lights[0];
+	let light_d1 = triangle.lights[1] - light_0;
+	let light_d2 = triangle.lights[2] - light_0;
+
+	// Every attribute as (start, delta to the second vertex, delta to the third vertex)
+	let splat = |a:f32, b:f32, c:f32| (L::F32s::splat(a), L::F32s::splat(b), L::F32s::splat(c));
+	let tex_u = splat(tex_0.us(), tex_d1.us(), tex_d2.us());
+	let tex_v = splat(tex_0.vs(), tex_d1.vs(), tex_d2.vs());
+	let tex_w = splat(tex_0.ws(), tex_d1.ws(), tex_d2.ws());
+	let light_r = splat(light_0.x, light_d1.x, light_d2.x);
+	let light_g = splat(light_0.y, light_d1.y, light_d2.y);
+	let light_b = splat(light_0.z, light_d1.z, light_d2.z);
+	let shadow_0 = triangle.shadow_lights[0];
+	let shadow_d1 = triangle.shadow_lights[1] - shadow_0;
+	let shadow_d2 = triangle.shadow_lights[2] - shadow_0;
+	let coords_0 = triangle.shadow_coords[0];
+	let coords_d1 = triangle.shadow_coords[1] - coords_0;
+	let coords_d2 = triangle.shadow_coords[2] - coords_0;
+	let shadow_r = splat(shadow_0.x, shadow_d1.x, shadow_d2.x);
+	let shadow_g = splat(shadow_0.y, shadow_d1.y, shadow_d2.y);
+	let shadow_b = splat(shadow_0.z, shadow_d1.z, shadow_d2.z);
+	let shadow_x = splat(coords_0.x, coords_d1.x, coords_d2.x);
+	let shadow_y = splat(coords_0.y, coords_d1.y, coords_d2.y);
+	let shadow_z = splat(coords_0.z, coords_d1.z, coords_d2.z);
+	let one = L::F32s::splat(1f32);
+
+	// Inlined by force, so with AVX2 the lanes are compiled for it as well
+	rasterize_triangle_spans::<L, _>(triangle, #[inline(always)] |pix_idx, mask, b1, b2| {
+		let lerp = |a:(L::F32s, L::F32s, L::F32s)| a.0 + a.1 * b1 + a.2 * b2;
+
+		let span_w = lerp(tex_w);
+		let mask = mask & span_w.gt_mask(unsafe { L::F32s::load(depth_buffer, pix_idx) });
+		if mask == 0 {
+			return;
+		}
+
+		let span_u = lerp(tex_u);
+		let span_v = lerp(tex_v);
+		let inv_w = one / span_w;
+		let cu = (span_u / span_w).to_array();
+		let cv = (span_v / span_w).to_array();
+		let mut r = (lerp(light_r) * inv_w).to_array();
+		let mut g = (lerp(light_g) * inv_w).to_array();
+		let mut b = (lerp(light_b) * inv_w).to_array();
+		if let Some(shadow) = shadow {
+			let sr = (lerp(shadow_r) * inv_w).to_array();
+			let sg = (lerp(shadow_g) * inv_w).to_array();
+			let sb = (lerp(shadow_b) * inv_w).to_array();
+			let sx = (lerp(shadow_x) * inv_w).to_array();
+			let sy = (lerp(shadow_y) * inv_w).to_array();
+			let sz = (lerp(shadow_z) * inv_w).to_array();
+			for i in 0..L::LANES {
+				if mask & (1 << i) != 0 {
+					let visibility = shadow.visibility(sx[i], sy[i], sz[i]);
+					r[i] += sr[i] * visibility;
+					g[i] += sg[i] * visibility;
+					b[i] += sb[i] * visibility;
+				}
+			}
+		}
+		let span_u = span_u.to_array();
+		let span_v = span_v.to_array();
+		let span_w = span_w.to_array();
+
+		for i in 0..L::LANES {
+			if mask & (1 << i) != 0 {
+				let texel = match view {
+					DebugView::UvCheckerboard => checkerboard(cu[i], cv[i]),
+					DebugView::Normals => RGBA::rgba(0xFF, 0xFF, 0xFF, 0xFF),
+					_ => texture.sample(cu[i], cv[i], gradients.lod(span_u[i], span_v[i], span_w[i], texture)),
+				};
+				unsafe {
+					draw_unsafe(screen, pix_idx + i, depth_buffer, span_w[i], Vec3::new(r[i], g[i], b[i]), texel, material);
+				}
+			}
+		}
+	});
 }
-impl RGB {
-	const fn black() -> RGB {
-		return RGB { r: 0, g:0, b:0 };
-	}
-	const fn rgb(r:u8, g:u8, b:u8) -> RGB {
-		return RGB { r, g, b };
+
+/// Checks per unit of texture space.
+const CHECKERBOARD_CELLS:f32 = 8f32;
+
+#[inline(always)]
+fn checkerboard(u:f32, v:f32) -> RGBA {
+	let cell = (u * CHECKERBOARD_CELLS).floor() as i32 + (v * CHECKERBOARD_CELLS).floor() as i32;
+	if cell & 1 == 0 {
+		RGBA::rgba(0xE0, 0xE0, 0xE0, 0xFF)
+	} else {
+		RGBA::rgba(0x40, 0x40, 0x40, 0xFF)
 	}
 }
 
-// Triangle Renderer 1
-unsafe fn draw_line(screen:&mut [RGBA], y:u16, x0:u16, x1:u16, color:&RGB) {
-	let offset = y as u32 * SCREEN_WIDTH as u32;
-	for i in x0..x1 {
-		let screen = screen.get_unchecked_mut((offset + i as u32) as usize);
-		screen.r = color.r;
-		screen.g = color.g;
-		screen.b = color.b;
-		screen.a = 0xFF;
-	}
+// Triangle Renderer
+
+/// Extent of the guard band in normalized device coordinates. Triangles are clipped against the x/y planes only
+/// once they leave it, the part inside of it but outside of the screen is cut off by the rasterizer bounding box.
+const GUARD_BAND:f32 = 2f32;
+
+/// Bits of sub-pixel precision of the fixed-point vertex positions.
+/// Inside of the guard band edge functions of a 856x480 screen stay below 2^30, so i32 is enough.
+const SUB_PIXEL_BITS:i32 = 4;
+const SUB_PIXEL_ONE:i32 = 1 << SUB_PIXEL_BITS;
+
+/// Positive when `p` is on the clockwise side (screen space, y down) of the edge `a -> b`.
+#[inline(always)]
+const fn orient_2d(ax:i32, ay:i32, bx:i32, by:i32, px:i32, py:i32) -> i32 {
+	(bx - ax) * (py - ay) - (by - ay) * (px - ax)
 }
-unsafe fn fill_bottom_flat_triangle(screen:&mut [RGBA], ty:i16, by:i16, lx:i16, rx:i16, tx:i16, color:&RGB) {
-	debug_assert!(ty <= by);
-	debug_assert!(lx <= tx);
-	debug_assert!(tx <= rx);
-	let invslope1 = (lx - tx) as f32 / (by - ty) as f32;
-	let invslope2 = (rx - tx) as f32 / (by - ty) as f32;
-
-	let mut curx1 = tx as f32;
-	let mut curx2 = tx as f32;
-
-	for scan_line_y in ty..=by {
-		let c1;
-		let c2;
-		if curx1 > curx2 {
-			c1 = curx2;
-			c2 = curx1;
-		} else {
-			c1 = curx1;
-			c2 = curx2;
-		}
-		let x1 = (c1 as i16    ).clamp(0, SCREEN_WIDTH as i16) as u16;
-		let x2 = (c2 as i16 + 1).clamp(0, SCREEN_WIDTH as i16) as u16;
-		draw_line(screen, scan_line_y as u16, x1, x2, color);
-		curx1 += invslope1;
-		curx2 += invslope2;
-	}
+
+/// Top-left fill rule: a pixel center exactly on an edge belongs to the triangle only if it is a top or a left edge,
+/// so two triangles sharing an edge never both draw, or both skip, the same pixel.
+#[inline(always)]
+const fn is_top_left(ax:i32, ay:i32, bx:i32, by:i32) -> bool {
+	(ay == by && bx > ax) || by < ay
 }
-unsafe fn fill_top_flat_triangle(screen:&mut [RGBA], mut ty:i16, mut by:i16, mut lx:i16, mut rx:i16, mut bx:i16, color:&RGB) {
-	debug_assert!(ty <= by);
-	debug_assert!(lx <= bx);
-	debug_assert!(bx <= rx);
-	let invslope1 = (bx - lx) as f32 / (by - ty) as f32;
-	let invslope2 = (bx - rx) as f32 / (by - ty) as f32;
-
-	let mut curx1= bx as f32;
-	let mut curx2= bx as f32;
-
-	let mut scan_line_y = by;
-	while scan_line_y >= ty { // scan_line_y > ty тоже пойдёт
-		// if scan_line_y >= 0 && scan_line_y < SCREEN_HEIGHT as i16 {
-		let c1;
-		let c2;
-		if curx1 > curx2 {
-			c1 = curx2;
-			c2 = curx1;
-		} else {
-			c1 = curx1;
-			c2 = curx2;
+
+/// Fixed-point edge functions of a screen space triangle, walked over its bounding box on the screen.
+struct EdgeSetup {
+	min_px:i32,
+	min_py:i32,
+	max_px:i32,
+	max_py:i32,
+	/// Edge functions at the center of the first pixel, each one is the weight of the vertex opposite to its edge.
+	e_row:[i32; 3],
+	/// Pixels exactly on an edge have a zero edge function, they are only accepted on top-left edges.
+	bias:[i32; 3],
+	step_x:[i32; 3],
+	step_y:[i32; 3],
+	inv_area:f32,
+	/// Counter-clockwise triangles are walked as clockwise ones, which swaps the weights of the second and the third vertex.
+	swapped:bool,
+}
+impl EdgeSetup {
+	/// `None` if the triangle is degenerate or does not touch the screen.
+	#[inline(always)]
+	fn new(triangle:&Triangle) -> Option<EdgeSetup> {
+		const MIN_X:f32 = (1f32 - GUARD_BAND) * 0.5 * SCREEN_WIDTH as f32;
+		const MAX_X:f32 = (1f32 + GUARD_BAND) * 0.5 * SCREEN_WIDTH as f32;
+		const MIN_Y:f32 = (1f32 - GUARD_BAND) * 0.5 * SCREEN_HEIGHT as f32;
+		const MAX_Y:f32 = (1f32 + GUARD_BAND) * 0.5 * SCREEN_HEIGHT as f32;
+
+		// Snap to the sub-pixel grid, clipping is done in floats so vertices may overshoot the guard band a tiny bit
+		let mut xs = [0i32; 3];
+		let mut ys = [0i32; 3];
+		for i in 0..3 {
+			xs[i] = (triangle.vertices[i].x.clamp(MIN_X, MAX_X) * SUB_PIXEL_ONE as f32).round() as i32;
+			ys[i] = (triangle.vertices[i].y.clamp(MIN_Y, MAX_Y) * SUB_PIXEL_ONE as f32).round() as i32;
+		}
+
+		let area = orient_2d(xs[0], ys[0], xs[1], ys[1], xs[2], ys[2]);
+		if area == 0 {
+			return None;
+		}
+
+		let swapped = area < 0;
+		let (i1, i2) = if swapped { (2, 1) } else { (1, 2) };
+		let (ax, ay) = (xs[0], ys[0]);
+		let (bx, by) = (xs[i1], ys[i1]);
+		let (cx, cy) = (xs[i2], ys[i2]);
+
+		// Bounding box of pixels, clamped to the screen
+		let min_px = (core::cmp::min(ax, core::cmp::min(bx, cx)) >> SUB_PIXEL_BITS).max(0);
+		let min_py = (core::cmp::min(ay, core::cmp::min(by, cy)) >> SUB_PIXEL_BITS).max(0);
+		let max_px = (core::cmp::max(ax, core::cmp::max(bx, cx)) >> SUB_PIXEL_BITS).min(SCREEN_WIDTH as i32 - 1);
+		let max_py = (core::cmp::max(ay, core::cmp::max(by, cy)) >> SUB_PIXEL_BITS).min(SCREEN_HEIGHT as i32 - 1);
+		if min_px > max_px || min_py > max_py {
+			return None;
 		}
-		let x1 = (c1 as i16    ).clamp(0, SCREEN_WIDTH as i16) as u16;
-		let x2 = (c2 as i16 + 1).clamp(0, SCREEN_WIDTH as i16) as u16;
-		draw_line(screen, scan_line_y as u16, x1, x2, color);
-		// }
-		curx1 -= invslope1;
-		curx2 -= invslope2;
-		scan_line_y -= 1;
+
+		let px = (min_px << SUB_PIXEL_BITS) + SUB_PIXEL_ONE / 2;
+		let py = (min_py << SUB_PIXEL_BITS) + SUB_PIXEL_ONE / 2;
+
+		Some(EdgeSetup {
+			min_px, min_py, max_px, max_py,
+			e_row: [
+				orient_2d(bx, by, cx, cy, px, py),
+				orient_2d(cx, cy, ax, ay, px, py),
+				orient_2d(ax, ay, bx, by, px, py),
+			],
+			bias: [
+				if is_top_left(bx, by, cx, cy) { 0 } else { -1 },
+				if is_top_left(cx, cy, ax, ay) { 0 } else { -1 },
+				if is_top_left(ax, ay, bx, by) { 0 } else { -1 },
+			],
+			step_x: [
+				(by - cy) << SUB_PIXEL_BITS,
+				(cy - ay) << SUB_PIXEL_BITS,
+				(ay - by) << SUB_PIXEL_BITS,
+			],
+			step_y: [
+				(cx - bx) << SUB_PIXEL_BITS,
+				(ax - cx) << SUB_PIXEL_BITS,
+				(bx - ax) << SUB_PIXEL_BITS,
+			],
+			inv_area: 1f32 / area.abs() as f32,
+			swapped,
+		})
 	}
 }
 
-unsafe fn fill_triangle_1(screen:&mut [RGBA], mut x1:i16, mut y1:i16, mut x2:i16, mut y2:i16, mut x3:i16, mut y3:i16, color:&RGB) {
-	// at first sort the three vertices by y-coordinate ascending so v1 is the topmost vertice
+// Spans are aligned to their lanes, so a span never runs over the end of a row
+const _SPANS_FIT_INTO_ROWS:[(); 0] = [(); SCREEN_WIDTH as usize % MAX_LANES];
 
-	if y2 < y1 {
-		core::mem::swap(&mut x1, &mut x2);
-		core::mem::swap(&mut y1, &mut y2);
-	}
+/// Edge function rasterizer, walks every row in spans of `L::LANES` pixels. Calls `fragments(pix_idx, mask, b1, b2)`
+/// for every span with at least one covered pixel center, bit `i` of `mask` is set when the pixel `pix_idx + i` is covered.
+/// `b1` and `b2` are the barycentric weights of the second and the third vertex. Both windings are drawn, culling happens earlier.
+#[inline(always)]
+fn rasterize_triangle_spans<L:Lanes, F:FnMut(usize, u32, L::F32s, L::F32s)>(triangle:&Triangle, mut fragments:F) {
+	debug_assert_eq!(MAX_LANES % L::LANES, 0);
+	let setup = match EdgeSetup::new(triangle) {
+		Some(setup) => setup,
+		None => return,
+	};
 
-	if y3 < y1 {
-		core::mem::swap(&mut x1, &mut x3);
-		core::mem::swap(&mut y1, &mut y3);
-	}
+	// Lanes left of the bounding box are outside of the triangle anyway, so the span can start a few pixels earlier
+	let start_px = setup.min_px & !(L::LANES as i32 - 1);
+	let skipped = setup.min_px - start_px;
 
-	if y3 < y2 {
-		core::mem::swap(&mut x2, &mut x3);
-		core::mem::swap(&mut y2, &mut y3);
+	let mut e_row = [L::I32s::splat(0); 3];
+	let mut step_x = [L::I32s::splat(0); 3];
+	let mut step_y = [L::I32s::splat(0); 3];
+	for i in 0..3 {
+		e_row[i] = L::I32s::splat(setup.e_row[i] + setup.bias[i] - skipped * setup.step_x[i]) + L::I32s::ramp(setup.step_x[i]);
+		step_x[i] = L::I32s::splat(setup.step_x[i] * L::LANES as i32);
+		step_y[i] = L::I32s::splat(setup.step_y[i]);
 	}
+	// Edge functions are kept biased for the coverage test, weights are computed without the bias
+	let bias_b = L::I32s::splat(setup.bias[1]);
+	let bias_c = L::I32s::splat(setup.bias[2]);
+	let inv_area = L::F32s::splat(setup.inv_area);
+
+	for y in setup.min_py..=setup.max_py {
+		let mut e = e_row;
+		let row = y as usize * SCREEN_WIDTH as usize;
+
+		let mut x = start_px;
+		while x <= setup.max_px {
+			let mask = (e[0] | e[1] | e[2]).non_negative_mask();
+			if mask != 0 {
+				let w_b = (e[1] - bias_b).to_f32() * inv_area;
+				let w_c = (e[2] - bias_c).to_f32() * inv_area;
+				if setup.swapped {
+					fragments(row + x as usize, mask, w_c, w_b);
+				} else {
+					fragments(row + x as usize, mask, w_b, w_c);
+				}
+			}
+			e[0] = e[0] + step_x[0];
+			e[1] = e[1] + step_x[1];
+			e[2] = e[2] + step_x[2];
+			x += L::LANES as i32;
+		}
 
-	// here we know that v1.y <= v2.y <= v3.y
-	if y2 == y3 { // trivial case of bottom-flat triangle
-		fill_bottom_flat_triangle(screen, y1, y2, x2, x3, x1, color);
-	} else if y1 == y2 { // trivial case of top-flat triangle
-		fill_top_flat_triangle(screen, y1, y3, x1, x2, x3, color);
-	} else { // general case - split the triangle in a topflat and bottom-flat one
-		let portion = (y2 - y1) as f32 / (y3 - y1) as f32;
+		e_row[0] = e_row[0] + step_y[0];
+		e_row[1] = e_row[1] + step_y[1];
+		e_row[2] = e_row[2] + step_y[2];
+	}
+}
 
-		let xdiff = x3-x1;
-		let x4 = x1 + (portion * xdiff as f32) as i16;
-		fill_bottom_flat_triangle(screen, y1, y2, x2, x4, x1, color);
-		fill_top_flat_triangle(screen, y2, y3, x2, x4, x3, color);
+/// Modulates a texel by the interpolated light, saturating at white.
+#[inline(always)]
+fn apply_light(texel:RGBA, light:&Vec3) -> RGBA {
+	RGBA {
+		r: (texel.r as f32 * light.x).min(255f32) as u8,
+		g: (texel.g as f32 * light.y).min(255f32) as u8,
+		b: (texel.b as f32 * light.z).min(255f32) as u8,
+		a: texel.a,
 	}
 }
 
-/*fn texture_triangle_1(
-	screen:&mut [u8],
-	mut x1:i16, mut y1:i16, mut u1: f32, mut v1:f32,
-	mut x2:i16, mut y2:i16, mut u2: f32, mut v2:f32,
-	mut x3:i16, mut y3:i16, mut u3: f32, mut v3:f32,
-	texture:&Texture
+#[inline(always)]
+unsafe fn draw_unsafe(
+	screen:&mut [RGBA],
+	pix_idx:usize,
+	depth_buffer:&mut [f32],
+	tex_w:f32,
+	light:Vec3,
+	texel:RGBA,
+	material:&Material,
 ) {
-	// at first sort the three vertices by y-coordinate ascending so v1 is the topmost vertice
-	// TODO: optimize
-	/*
-	// naive
-	if y1 <= y2 && y2 <= y3 { // 123
-		// Nothing
-	} else if y1 <= y3 && y3 <= y2 { // 132
-		core::mem::swap(&mut x2, &mut x3);
-		core::mem::swap(&mut y2, &mut y3);
-	} else if y2 <= y1 && y1 <= y3 { // 213
-		core::mem::swap(&mut x1, &mut x2);
-		core::mem::swap(&mut y1, &mut y2);
-	} else if y2 <= y3 && y3 <= y1 { // 231
-		let lx = x2;
-		let ly = y2;
-
-		x2 = x3;
-		y2 = y3;
-
-		x3 = x1;
-		y3 = y1;
-
-		x1 = lx;
-		y1 = ly;
-	} else if y3 <= y1 && y1 <= y2 { // 312
-		let lx = x3;
-		let ly = y3;
-
-		x3 = x2;
-		y3 = y2;
-
-		x2 = x1;
-		y2 = y1;
-
-		x1 = lx;
-		y1 = ly;
-	} else if y3 <= y2 && y2 <= y1 { // 321
-		core::mem::swap(&mut x1, &mut x3);
-		core::mem::swap(&mut y1, &mut y3);
-	}
-	*/
-
-	if y2 < y1 {
-		core::mem::swap(&mut x1, &mut x2);
-		core::mem::swap(&mut y1, &mut y2);
-		core::mem::swap(&mut u1, &mut u2);
-		core::mem::swap(&mut v1, &mut v2);
-	}
-
-	if y3 < y1 {
-		core::mem::swap(&mut x1, &mut x3);
-		core::mem::swap(&mut y1, &mut y3);
-		core::mem::swap(&mut u1, &mut u3);
-		core::mem::swap(&mut v1, &mut v3);
-	}
-
-	if y3 < y2 {
-		core::mem::swap(&mut x2, &mut x3);
-		core::mem::swap(&mut y2, &mut y3);
-		core::mem::swap(&mut u2, &mut u3);
-		core::mem::swap(&mut v2, &mut v3);
-	}
-
-	let dx1 = x2-x1;
-	let dy1 = y2-y1;
-	let du1 = u2-u1;
-	let dv1 = v2-v1;
-
-	let dx2 = x3-x1;
-	let dy2 = y3-y1;
-	let du2 = u3-u1;
-	let dv2 = v3-v1;
-
-	let mut dax_step = 0f32;
-	let mut dbx_step = 0f32;
-	let mut du1_step = 0f32; let mut dv1_step = 0f32;
-	let mut du2_step = 0f32; let mut dv2_step = 0f32;
-
-	if dy1 != 0 { dax_step = dx1 as f32 / dy1.abs() as f32; }
-	if dy2 != 0 { dbx_step = dx2 as f32 / dy2.abs() as f32; }
-
-	if dy1 != 0 { du1_step = du1 as f32 / dy1.abs() as f32; }
-	if dy1 != 0 { dv1_step = dv1 as f32 / dy1.abs() as f32; }
-
-	if dy2 != 0 { du2_step = du2 as f32 / dy2.abs() as f32; }
-	if dy2 != 0 { dv2_step = dv2 as f32 / dy2.abs() as f32; }
-
-	if dy1 != 0 {
-		for i in y1 as isize ..=y2 as isize  {
-			let mut ax = x1 as isize + ((i - y1 as isize) as f32 * dax_step) as isize;
-			let mut bx = x1 as isize + ((i - y1 as isize) as f32 * dbx_step) as isize;
-
-			let mut tex_su = u1 + (i - y1 as isize) as f32 * du1_step;
-			let mut tex_sv = v1 + (i - y1 as isize) as f32 * dv1_step;
-
-			let mut tex_eu = u1 + (i - y1 as isize) as f32 * du2_step;
-			let mut tex_ev = v1 + (i - y1 as isize) as f32 * dv2_step;
-
-			if ax > bx {
-				core::mem::swap(&mut ax, &mut bx);
-				core::mem::swap(&mut tex_su, &mut tex_eu);
-				core::mem::swap(&mut tex_sv, &mut tex_ev);
-			}
-
-			let mut tex_u = tex_su;
-			let mut tex_v = tex_sv;
-
-			let t_step = 1f32 / (bx - ax) as f32;
-			let mut t = 0f32;
+	let alpha = (texel.a as u16 * material.opacity as u16 / 0xFF) as u8;
+	let texture = apply_light(RGBA { a: alpha, ..texel }, &light);
+	let screen = screen.get_unchecked_mut(pix_idx);
+	match material.blend_mode {
+		BlendMode::Opaque => {},
+		BlendMode::AlphaTest(cutoff) => if alpha < cutoff { return; },
+		blend_mode => {
+			blend_pixel(screen, texture, blend_mode);
+			return;
+		},
+	}
+	screen.r = texture.r;
+	screen.g = texture.g;
+	screen.b = texture.b;
+	screen.a = 0xFF;
+	*depth_buffer.get_unchecked_mut(pix_idx) = tex_w;
+}
 
-			for j in ax..bx {
-				let tex_u = (((1f32 - t) * tex_su + t * tex_eu) as i16).clamp(0, texture.resolution_u as i16 - 1) as usize;
-				let tex_v = (((1f32 - t) * tex_sv + t * tex_ev) as i16).clamp(0, texture.resolution_v as i16 - 1) as usize;
+/// Combines `src` into `dst` by its alpha. The alpha of `dst` accumulates coverage, so `render_outline` only sees fully covered pixels as solid.
+#[inline(always)]
+fn blend_pixel(dst:&mut RGBA, src:RGBA, blend_mode:BlendMode) {
+	let a = src.a as u16;
+	let ia = 0xFF - a;
+	match blend_mode {
+		BlendMode::Opaque => *dst = RGBA { a: 0xFF, ..src },
+		BlendMode::AlphaTest(cutoff) => if src.a >= cutoff { *dst = RGBA { a: 0xFF, ..src } },
+		BlendMode::AlphaBlend => {
+			dst.r = ((src.r as u16 * a + dst.r as u16 * ia) / 0xFF) as u8;
+			dst.g = ((src.g as u16 * a + dst.g as u16 * ia) / 0xFF) as u8;
+			dst.b = ((src.b as u16 * a + dst.b as u16 * ia) / 0xFF) as u8;
+			dst.a = (a + dst.a as u16 * ia / 0xFF) as u8;
+		},
+		BlendMode::Additive => {
+			dst.r = (dst.r as u16 + src.r as u16 * a / 0xFF).min(0xFF) as u8;
+			dst.g = (dst.g as u16 + src.g as u16 * a / 0xFF).min(0xFF) as u8;
+			dst.b = (dst.b as u16 + src.b as u16 * a / 0xFF).min(0xFF) as u8;
+			dst.a = (dst.a as u16 + a).min(0xFF) as u8;
+		},
+	}
+}
 
-				screen[i as usize * SCREEN_WIDTH as usize + j as usize].r = texture.pixels[tex_v * texture.resolution_u as usize + tex_u].r;
-				screen[i as usize * SCREEN_WIDTH as usize + j as usize].g = texture.pixels[tex_v * texture.resolution_u as usize + tex_u].g;
-				screen[i as usize * SCREEN_WIDTH as usize + j as usize].b = texture.pixels[tex_v * texture.resolution_u as usize + tex_u].b;
-				screen[i as usize * SCREEN_WIDTH as usize + j as usize].a = texture.pixels[tex_v * texture.resolution_u as usize + tex_u].a;
-				// x = j
-				// y = i
+/// Frustum planes in homogeneous clip space. A vertex `v` is inside a plane `p` when `dot4(p, v) >= 0`.
+/// The side planes enclose the guard band instead of the screen.
+const FRUSTUM_PLANES:[Vec4; 6] = [
+	Vec4::new( 0f32,  0f32,  1f32, 0f32), // Near:   z >= 0
+	Vec4::new( 0f32,  0f32, -1f32, 1f32), // Far:    z <= w
+	Vec4::new( 1f32,  0f32,  0f32, GUARD_BAND), // Left:   x >= -w * GUARD_BAND
+	Vec4::new(-1f32,  0f32,  0f32, GUARD_BAND), // Right:  x <=  w * GUARD_BAND
+	Vec4::new( 0f32,  1f32,  0f32, GUARD_BAND), // Bottom: y >= -w * GUARD_BAND
+	Vec4::new( 0f32, -1f32,  0f32, GUARD_BAND), // Top:    y <=  w * GUARD_BAND
+];
+
+/// Every plane can at most double the amount of triangles, but the near plane
+/// is applied first and a triangle can only be split by 5 more planes.
+const MAX_CLIPPED_TRIANGLES:usize = 64;
+
+/// Ring buffer of triangles waiting to be clipped against the next plane.
+struct ClipQueue {
+	triangles:[Triangle; MAX_CLIPPED_TRIANGLES],
+	head:usize,
+	len:usize,
+}
+impl ClipQueue {
+	const fn new() -> ClipQueue {
+		ClipQueue {
+			triangles: [Triangle::zero_spaced_verticies(); MAX_CLIPPED_TRIANGLES],
+			head: 0,
+			len: 0,
+		}
+	}
+	fn clear(&mut self) {
+		self.head = 0;
+		self.len = 0;
+	}
+	fn push(&mut self, triangle:&Triangle) {
+		debug_assert!(self.len < MAX_CLIPPED_TRIANGLES);
+		self.triangles[(self.head + self.len) % MAX_CLIPPED_TRIANGLES] = *triangle;
+		self.len += 1;
+	}
+	fn pop(&mut self) -> Triangle {
+		debug_assert!(self.len != 0);
+		let triangle = self.triangles[self.head];
+		self.head = (self.head + 1) % MAX_CLIPPED_TRIANGLES;
+		self.len -= 1;
+		triangle
+	}
+}
 
-				t += t_step;
+/// Clips a triangle in homogeneous clip space against the whole view frustum.
+/// Returns how many triangles were written to `out`.
+fn clip_triangle(triangle:&Triangle, queue:&mut ClipQueue, out:&mut [Triangle; MAX_CLIPPED_TRIANGLES]) -> usize {
+	queue.clear();
+	queue.push(triangle);
+
+	// Each plane only has to test the triangles which were produced by the previous one
+	for plane in &FRUSTUM_PLANES {
+		let mut to_test = queue.len;
+		while to_test != 0 {
+			let t = queue.pop();
+			to_test -= 1;
+
+			let (clipped_n, clipped_array) = triangle_clip_against_plane(plane, &t);
+			for clipped in &clipped_array[0..clipped_n] {
+				queue.push(clipped);
 			}
 		}
+		if queue.len == 0 {
+			return 0;
+		}
+	}
 
-		let dx1 = x3 - x2;
-		let dy1 = y3 - y2;
-		let du1 = u3 - u2;
-		let dv1 = v3 - v2;
+	let clipped_n = queue.len;
+	for triangle in out.iter_mut().take(clipped_n) {
+		*triangle = queue.pop();
+	}
+	clipped_n
+}
 
-		if dy1 != 0 { dax_step = dx1 as f32 / dy1.abs() as f32; }
-		if dy2 != 0 { dbx_step = dx2 as f32 / dy2.abs() as f32; }
+fn triangle_clip_against_plane(plane:&Vec4, in_tri:&Triangle) -> (usize, [Triangle;2]) {
+	// Create two temporary storage arrays to classify points either side of plane
+	let mut  inside_points = [0usize;3];
+	let mut outside_points = [0usize;3];
+	let mut distances = [0f32;3];
+	let mut  inside_counter = 0;
+	let mut outside_counter = 0;
 
-		du1_step = 0f32;
-		dv1_step = 0f32;
+	for (i, distance) in distances.iter_mut().enumerate() {
+		// Get signed distance of each point in triangle to plane
+		*distance = Vec4::dot4(plane, &in_tri.vertices[i]);
 
-		if dy1 != 0 { du1_step = du1 as f32 / dy1.abs() as f32; }
-		if dy1 != 0 { dv1_step = dv1 as f32 / dy1.abs() as f32; }
+		// If distance sign is positive, point lies on "inside" of plane
+		if *distance >= 0f32 {
+			inside_points[inside_counter] = i;
+			inside_counter += 1;
+		} else {
+			outside_points[outside_counter] = i;
+			outside_counter += 1;
+		}
+	}
 
-		for i in y2 as isize ..=y3 as isize {
-			let mut ax = x2 as isize + ((i - y2 as isize) as f32 * dax_step) as isize;
-			let mut bx = x1 as isize + ((i - y1 as isize) as f32 * dbx_step) as isize;
+	// Distances are linear along an edge in clip space, so is the position of the intersection
+	let intersect = |inside:usize, outside:usize| -> f32 {
+		distances[inside] / (distances[inside] - distances[outside])
+	};
 
-			let mut tex_su = u2 + (i - y2 as isize) as f32 * du1_step;
-			let mut tex_sv = v2 + (i - y2 as isize) as f32 * dv1_step;
+	// Now classify triangle points, and break the input triangle into smaller output triangles if required. There are four possible outcomes...
+	match inside_counter {
+		0 => {
+			// All points lie on the outside of plane, so clip whole triangle It ceases to exist
+			(0, [Triangle::zero_spaced_verticies(); 2]) // No returned triangles are valid
+		},
+		3 => {
+			// All points lie on the inside of plane, so do nothing and allow the triangle to simply pass through
+			(1, [*in_tri, Triangle::zero_spaced_verticies()]) // Just the one returned original triangle is valid
+		},
+		1 => {
+			// Triangle should be clipped. As two points lie outside the plane, the triangle simply becomes a smaller triangle
+			let mut ot = *in_tri;
+			// The inside point is valid, so keep that...
+			ot.copy_vertex(0, in_tri, inside_points[0]);
 
-			let mut tex_eu = u1 + (i - y1 as isize) as f32 * du2_step;
-			let mut tex_ev = v1 + (i - y1 as isize) as f32 * dv2_step;
+			// but the two new points are at the locations where the original sides of the triangle (lines) intersect with the plane
+			ot.lerp_vertex(1, in_tri, inside_points[0], outside_points[0], intersect(inside_points[0], outside_points[0]));
+			ot.lerp_vertex(2, in_tri, inside_points[0], outside_points[1], intersect(inside_points[0], outside_points[1]));
 
-			if ax > bx {
-				core::mem::swap(&mut ax, &mut bx);
-				core::mem::swap(&mut tex_su, &mut tex_eu);
-				core::mem::swap(&mut tex_sv, &mut tex_ev);
+			// Keep the winding of the input triangle
+			if inside_points[0] == 1 {
+				ot.swap_vertices(1, 2);
 			}
 
-			let mut tex_u = tex_su;
-			let mut tex_v = tex_sv;
-
-			let t_step = 1f32 / (bx - ax) as f32;
-			let mut t = 0f32;
+			(1, [ot, Triangle::zero_spaced_verticies()]) // Return the newly formed single triangle
+		},
+		2 => {
+			// Triangle should be clipped. As two points lie inside the plane,
+			// the clipped triangle becomes a "quad". Fortunately, we can
+			// represent a quad with two new triangles
 
-			for j in ax..bx {
-				let tex_u = (((1f32 - t) * tex_su + t * tex_eu) as i16).clamp(0, texture.resolution_u as i16 - 1) as usize;
-				let tex_v = (((1f32 - t) * tex_sv + t * tex_ev) as i16).clamp(0, texture.resolution_v as i16 - 1) as usize;
+			// Copy appearance info to new triangles
+			let mut ot1 = *in_tri;
+			let mut ot2 = *in_tri;
 
-				screen[4 * (i as usize * SCREEN_WIDTH as usize + j as usize) + 0] = texture.pixels[4 * (tex_v * texture.resolution_u as usize + tex_u) + 0];
-				screen[4 * (i as usize * SCREEN_WIDTH as usize + j as usize) + 1] = texture.pixels[4 * (tex_v * texture.resolution_u as usize + tex_u) + 1];
-				screen[4 * (i as usize * SCREEN_WIDTH as usize + j as usize) + 2] = texture.pixels[4 * (tex_v * texture.resolution_u as usize + tex_u) + 2];
-				screen[4 * (i as usize * SCREEN_WIDTH as usize + j as usize) + 3] = texture.pixels[4 * (tex_v * texture.resolution_u as usize + tex_u) + 3];
-				// x = j
-				// y = i
+			// The first triangle consists of the two inside points and a new
+			// point determined by the location where one side of the triangle
+			// intersects with the plane
+			ot1.copy_vertex(0, in_tri, inside_points[0]);
+			ot1.copy_vertex(1, in_tri, inside_points[1]);
+			ot1.lerp_vertex(2, in_tri, inside_points[0], outside_points[0], intersect(inside_points[0], outside_points[0]));
 
-				t += t_step;
+			// The second triangle is composed of one of he inside points, a new point determined by the intersection of the other side of the triangle and the plane, and the newly created point above
+			ot2.copy_vertex(0, in_tri, inside_points[1]);
+			ot2.lerp_vertex(1, in_tri, inside_points[1], outside_points[0], intersect(inside_points[1], outside_points[0]));
+			ot2.copy_vertex(2, &ot1, 2);
+
+			// Keep the winding of the input triangle
+			if outside_points[0] == 1 {
+				ot1.swap_vertices(1, 2);
+				ot2.swap_vertices(1, 2);
 			}
-		}
+
+			(2, [ot1, ot2]) // Return two newly formed triangles which form a quad
+		},
+		_ => unreachable!()
 	}
+}
 
-//	// here we know that v1.y <= v2.y <= v3.y
-//	if y2 == y3 { // trivial case of bottom-flat triangle
-//		fill_bottom_flat_triangle(screen, y1, y2, x2, x3, x1, color);
-//	} else if y1 == y2 { // trivial case of top-flat triangle
-//		fill_top_flat_triangle(screen, y1, y3, x1, x2, x3, color);
-//	} else { // general case - split the triangle in a topflat and bottom-flat one
-//		let portion = (y2 - y1) as f32 / (y3 - y1) as f32;
-//
-//		let xdiff = x3-x1;
-//		let x4 = x1 + (portion * xdiff as f32) as i16;
-//		fill_bottom_flat_triangle(screen, y1, y2, x2, x4, x1, color);
-//		fill_top_flat_triangle(screen, y2, y3, x2, x4, x3, color);
-//	}
-}*/
+#[cfg(test)]
+mod tests {
+	use super::*;
 
-#[inline(always)]
-fn draw(
-	screen:&mut [RGBA],
-	texture:&Texture,
-	x:usize, y:usize, u:f32, v:f32,
-) {
-	let screen = &mut screen[y * SCREEN_WIDTH as usize + x];
+	const EPS:f32 = 1e-4;
 
-	let texture = texture.pixels[
-		(v * texture.resolution_v as f32) as usize * texture.resolution_u as usize +
-		(u * texture.resolution_u as f32) as usize
-	];
+	/// u and v follow clip space x and y, so interpolation can be checked after clipping.
+	fn triangle(a:Vec4, b:Vec4, c:Vec4) -> Triangle {
+		let mut t = Triangle::zero_spaced_verticies();
+		t.vertices = [a, b, c];
+		for i in 0..3 {
+			t.uvs[i] = Vec3::new(t.vertices[i].x, t.vertices[i].y, t.vertices[i].w);
+		}
+		t
+	}
 
-	*screen = texture;
-}
+	fn clip(t:&Triangle) -> ([Triangle; MAX_CLIPPED_TRIANGLES], usize) {
+		let mut queue = ClipQueue::new();
+		let mut out = [Triangle::zero_spaced_verticies(); MAX_CLIPPED_TRIANGLES];
+		let n = clip_triangle(t, &mut queue, &mut out);
+		(out, n)
+	}
 
-fn texture_triangle(
-	screen:&mut [RGBA],
-	depth_buffer:&mut [f32],
-	mut x1:i16, mut y1:i16, mut u1:f32, mut v1:f32, mut w1:f32,
-	mut x2:i16, mut y2:i16, mut u2:f32, mut v2:f32, mut w2:f32,
-	mut x3:i16, mut y3:i16, mut u3:f32, mut v3:f32, mut w3:f32,
-	texture: &Texture
-) {
-	if y2 < y1 {
-		core::mem::swap(&mut y1, &mut y2);
-		core::mem::swap(&mut x1, &mut x2);
-		core::mem::swap(&mut u1, &mut u2);
-		core::mem::swap(&mut v1, &mut v2);
-		core::mem::swap(&mut w1, &mut w2);
-	}
-
-	if y3 < y1 {
-		core::mem::swap(&mut y1, &mut y3);
-		core::mem::swap(&mut x1, &mut x3);
-		core::mem::swap(&mut u1, &mut u3);
-		core::mem::swap(&mut v1, &mut v3);
-		core::mem::swap(&mut w1, &mut w3);
-	}
-
-	if y3 < y2 {
-		core::mem::swap(&mut y2, &mut y3);
-		core::mem::swap(&mut x2, &mut x3);
-		core::mem::swap(&mut u2, &mut u3);
-		core::mem::swap(&mut v2, &mut v3);
-		core::mem::swap(&mut w2, &mut w3);
-	}
-
-	let dy1 = y2 - y1;
-	let dx1 = x2 - x1;
-	let dv1 = v2 - v1;
-	let du1 = u2 - u1;
-	let dw1 = w2 - w1;
-
-	let dy2 = y3 - y1;
-	let dx2 = x3 - x1;
-	let dv2 = v3 - v1;
-	let du2 = u3 - u1;
-	let dw2 = w3 - w1;
-
-	let mut tex_u;
-	let mut tex_v;
-	let mut tex_w;
-
-	let mut dax_step = 0f32;
-	let mut dbx_step = 0f32;
-	let mut du1_step = 0f32;
-	let mut dv1_step = 0f32;
-	let mut du2_step = 0f32;
-	let mut dv2_step = 0f32;
-	let mut dw1_step = 0f32;
-	let mut dw2_step = 0f32;
-
-	if dy2 != 0 {
-		dbx_step = dx2 as f32 / dy2.abs() as f32;
-		du2_step = du2 / dy2.abs() as f32;
-		dv2_step = dv2 / dy2.abs() as f32;
-		dw2_step = dw2 / dy2.abs() as f32;
-	}
-
-	if dy1 != 0 {
-		dax_step = dx1 as f32 / dy1.abs() as f32;
-		du1_step = du1 / dy1.abs() as f32;
-		dv1_step = dv1 / dy1.abs() as f32;
-		dw1_step = dw1 / dy1.abs() as f32;
-
-		for y in y1..=y2 {
-			let mut ax = x1 + ((y - y1) as f32 * dax_step) as i16;
-			let mut bx = x1 + ((y - y1) as f32 * dbx_step) as i16;
-
-			let mut tex_su = u1 + (y - y1) as f32 * du1_step;
-			let mut tex_sv = v1 + (y - y1) as f32 * dv1_step;
-			let mut tex_sw = w1 + (y - y1) as f32 * dw1_step;
-
-			let mut tex_eu = u1 + (y - y1) as f32 * du2_step;
-			let mut tex_ev = v1 + (y - y1) as f32 * dv2_step;
-			let mut tex_ew = w1 + (y - y1) as f32 * dw2_step;
-
-			if ax > bx {
-				core::mem::swap(&mut ax, &mut bx);
-				core::mem::swap(&mut tex_su, &mut tex_eu);
-				core::mem::swap(&mut tex_sv, &mut tex_ev);
-				core::mem::swap(&mut tex_sw, &mut tex_ew);
-			}
+	/// Signed area after the perspective divide.
+	fn ndc_area(t:&Triangle) -> f32 {
+		let a = div_vec3d(&t.vertices[0], t.vertices[0].w);
+		let b = div_vec3d(&t.vertices[1], t.vertices[1].w);
+		let c = div_vec3d(&t.vertices[2], t.vertices[2].w);
+		0.5 * ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y))
+	}
 
-			tex_u = tex_su;
-			tex_v = tex_sv;
-			tex_w = tex_sw;
-
-			let tstep = 1f32 / ((bx - ax) as f32);
-			let mut t = 0f32;
-
-			for x in ax..bx {
-				tex_u = (1f32 - t) * tex_su + t * tex_eu;
-				tex_v = (1f32 - t) * tex_sv + t * tex_ev;
-				tex_w = (1f32 - t) * tex_sw + t * tex_ew;
-				if y >= 0 && x >= 0 && y < SCREEN_HEIGHT as i16 && x < SCREEN_WIDTH as i16 {
-					if tex_w > depth_buffer[y as usize * SCREEN_WIDTH as usize + x as usize] {
-						draw(screen, texture, x as usize, y as usize, tex_u / tex_w, tex_v / tex_w);
-						depth_buffer[y as usize * SCREEN_WIDTH as usize + x as usize] = tex_w;
-					}
+	fn assert_clipped(input:&Triangle, out:&[Triangle]) {
+		let input_area = ndc_area(input);
+		for t in out {
+			for v in &t.vertices {
+				for plane in &FRUSTUM_PLANES {
+					assert!(Vec4::dot4(plane, v) >= -EPS, "vertex {:?} is outside of plane {:?}", v, plane);
 				}
-				t += tstep;
 			}
+			for i in 0..3 {
+				assert!((t.uvs[i].x - t.vertices[i].x).abs() < EPS);
+				assert!((t.uvs[i].y - t.vertices[i].y).abs() < EPS);
+				assert!((t.uvs[i].z - t.vertices[i].w).abs() < EPS);
+			}
+			let area = ndc_area(t);
+			assert!(area == 0f32 || area.signum() == input_area.signum(), "winding was flipped");
 		}
 	}
 
-	let dy1 = y3 - y2;
-	let dx1 = x3 - x2;
-	let dv1 = v3 - v2;
-	let du1 = u3 - u2;
-	let dw1 = w3 - w2;
-
-	if dy2 != 0 {
-		dbx_step = dx2 as f32 / dy2.abs() as f32;
+	#[test]
+	fn inside_triangle_passes_through() {
+		let t = triangle(
+			Vec4::new(-0.5, -0.5, 0.5, 1.0),
+			Vec4::new( 0.5, -0.5, 0.5, 1.0),
+			Vec4::new( 0.0,  0.5, 0.5, 1.0),
+		);
+		let (out, n) = clip(&t);
+		assert_eq!(n, 1);
+		assert_eq!(out[0].vertices, t.vertices);
 	}
 
-	du1_step = 0f32;
-	dv1_step = 0f32;
+	#[test]
+	fn outside_triangles_are_removed() {
+		let behind_near = triangle(
+			Vec4::new(-0.5, -0.5, -0.5, 1.0),
+			Vec4::new( 0.5, -0.5, -0.5, 1.0),
+			Vec4::new( 0.0,  0.5, -0.1, 1.0),
+		);
+		let beyond_far = triangle(
+			Vec4::new(-0.5, -0.5, 1.5, 1.0),
+			Vec4::new( 0.5, -0.5, 1.5, 1.0),
+			Vec4::new( 0.0,  0.5, 1.1, 1.0),
+		);
+		let right_of_guard_band = triangle(
+			Vec4::new(GUARD_BAND + 0.5, -0.5, 0.5, 1.0),
+			Vec4::new(GUARD_BAND + 1.5, -0.5, 0.5, 1.0),
+			Vec4::new(GUARD_BAND + 1.0,  0.5, 0.5, 1.0),
+		);
+		assert_eq!(clip(&behind_near).1, 0);
+		assert_eq!(clip(&beyond_far).1, 0);
+		assert_eq!(clip(&right_of_guard_band).1, 0);
+	}
 
-	if dy1 != 0 {
-		dax_step = dx1 as f32 / dy1.abs() as f32;
-		du1_step = du1 / dy1.abs() as f32;
-		dv1_step = dv1 / dy1.abs() as f32;
-		dw1_step = dw1 / dy1.abs() as f32;
+	#[test]
+	fn straddling_left_and_top() {
+		let t = triangle(
+			Vec4::new(-2.0 * GUARD_BAND,  2.0 * GUARD_BAND, 0.5, 1.0),
+			Vec4::new( 0.5,  0.0, 0.5, 1.0),
+			Vec4::new( 0.0, -0.5, 0.5, 1.0),
+		);
+		let (out, n) = clip(&t);
+		assert!(n >= 2);
+		assert_clipped(&t, &out[0..n]);
+	}
 
-		for y in y2..=y3 {
-			let mut ax = x2 + ((y - y2) as f32 * dax_step) as i16;
-			let mut bx = x1 + ((y - y1) as f32 * dbx_step) as i16;
+	#[test]
+	fn straddling_near_and_far() {
+		let t = triangle(
+			Vec4::new(-0.5, -0.5, -0.5, 1.0),
+			Vec4::new( 0.5, -0.5,  1.5, 1.0),
+			Vec4::new( 0.0,  0.5,  0.5, 1.0),
+		);
+		let (out, n) = clip(&t);
+		assert!(n >= 2);
+		assert_clipped(&t, &out[0..n]);
+	}
 
-			let mut tex_su = u2 + (y - y2) as f32 * du1_step;
-			let mut tex_sv = v2 + (y - y2) as f32 * dv1_step;
-			let mut tex_sw = w2 + (y - y2) as f32 * dw1_step;
+	#[test]
+	fn straddling_every_plane() {
+		// Slanted in depth, crosses the near and far planes and covers the whole viewport in between
+		let t = triangle(
+			Vec4::new(-10.0, -10.0, -1.0, 1.0),
+			Vec4::new( 30.0, -10.0,  2.0, 1.0),
+			Vec4::new(-10.0,  30.0,  0.5, 1.0),
+		);
+		let (out, n) = clip(&t);
+		assert!(n >= 2);
+		assert_clipped(&t, &out[0..n]);
+	}
 
-			let mut tex_eu = u1 + (y - y1) as f32 * du2_step;
-			let mut tex_ev = v1 + (y - y1) as f32 * dv2_step;
-			let mut tex_ew = w1 + (y - y1) as f32 * dw2_step;
+	#[test]
+	fn triangle_covering_the_guard_band_is_clipped_to_it() {
+		let t = triangle(
+			Vec4::new(-10.0, -10.0, 0.5, 1.0),
+			Vec4::new( 30.0, -10.0, 0.5, 1.0),
+			Vec4::new(-10.0,  30.0, 0.5, 1.0),
+		);
+		let (out, n) = clip(&t);
+		assert_clipped(&t, &out[0..n]);
+		let area:f32 = out[0..n].iter().map(ndc_area).sum();
+		let guard_band_area = 4.0 * GUARD_BAND * GUARD_BAND;
+		assert!((area - guard_band_area).abs() < 1e-3, "clipped area is {}", area);
+	}
 
-			if ax > bx {
-				core::mem::swap(&mut ax, &mut bx);
-				core::mem::swap(&mut tex_su, &mut tex_eu);
-				core::mem::swap(&mut tex_sv, &mut tex_ev);
-				core::mem::swap(&mut tex_sw, &mut tex_ew);
-			}
+	#[test]
+	fn perspective_triangle_is_clipped_in_clip_space() {
+		// Different w per vertex, as produced by the projection matrix
+		let t = triangle(
+			Vec4::new(-3.0,  0.0, 1.0, 2.0),
+			Vec4::new( 3.0,  0.0, 5.0, 6.0),
+			Vec4::new( 0.0,  9.0, 3.0, 4.0),
+		);
+		let (out, n) = clip(&t);
+		assert!(n >= 1);
+		assert_clipped(&t, &out[0..n]);
+	}
 
-			tex_u = tex_su;
-			tex_v = tex_sv;
-			tex_w = tex_sw;
+	#[test]
+	fn wrap_modes() {
+		assert_eq!(Wrap::Repeat.apply(-1, 4), 3);
+		assert_eq!(Wrap::Repeat.apply( 5, 4), 1);
+		assert_eq!(Wrap::Clamp .apply(-1, 4), 0);
+		assert_eq!(Wrap::Clamp .apply( 5, 4), 3);
+		assert_eq!(Wrap::Mirror.apply(-1, 4), 0);
+		assert_eq!(Wrap::Mirror.apply( 4, 4), 3);
+		assert_eq!(Wrap::Mirror.apply( 9, 4), 1);
+	}
 
-			let tstep = 1f32 / (bx - ax) as f32;
-			let mut t = 0f32;
+	/// 4x4 texture where every texel is grey with the intensity of its index.
+	fn gradient_texture(sampler:Sampler) -> Texture {
+		let mut texture = Texture::zero_resolution_texture();
+		texture.allocate(4, 4);
+		for i in 0..16 {
+			let c = (i * 16) as u8;
+			texture.pixels[i] = RGBA::rgba(c, c, c, 0xFF);
+		}
+		texture.sampler = sampler;
+		texture.generate_mipmaps();
+		texture
+	}
 
-			for x in ax..bx {
-				tex_u = (1f32 - t) * tex_su + t * tex_eu;
-				tex_v = (1f32 - t) * tex_sv + t * tex_ev;
-				tex_w = (1f32 - t) * tex_sw + t * tex_ew;
+	#[test]
+	fn mip_chain() {
+		let texture = gradient_texture(Sampler::new(Wrap::Repeat, Filter::Nearest, true));
+		assert_eq!(texture.mip_levels, 3);
+		assert_eq!(texture.level_resolution(1), (2, 2));
+		assert_eq!(texture.level_resolution(2), (1, 1));
+		// Average of texels 0, 1, 4, 5
+		assert_eq!(texture.texel(1, 0, 0).r, ((16 + 64 + 80 + 2) / 4) as u8);
+		// Average of everything
+		assert_eq!(texture.sample(0.5, 0.5, 10.0).r, 120);
+		// Mip mapping disabled always samples level 0
+		let texture = gradient_texture(Sampler::new(Wrap::Repeat, Filter::Nearest, false));
+		assert_eq!(texture.sample(0.0, 0.0, 10.0).r, 0);
+	}
 
-				if y >= 0 && x >= 0 && y < SCREEN_HEIGHT as i16 && x < SCREEN_WIDTH as i16 {
-					if tex_w > depth_buffer[y as usize * SCREEN_WIDTH as usize + x as usize] {
-						draw(screen, texture, x as usize, y as usize, tex_u / tex_w, tex_v / tex_w);
-						depth_buffer[y as usize * SCREEN_WIDTH as usize + x as usize] = tex_w;
-					}
-				}
-				t += tstep;
-			}
-		}
+	#[test]
+	fn bilinear_filtering() {
+		let texture = gradient_texture(Sampler::new(Wrap::Clamp, Filter::Bilinear, false));
+		// Exactly on a texel center
+		assert_eq!(texture.sample(0.125, 0.125, 0.0).r, 0);
+		// Half way between texels 0 and 1
+		assert_eq!(texture.sample(0.25, 0.125, 0.0).r, 8);
+		// Clamped outside of the texture
+		assert_eq!(texture.sample(-1.0, 0.125, 0.0).r, 0);
+		let texture = gradient_texture(Sampler::new(Wrap::Repeat, Filter::Bilinear, false));
+		// Half way between texel 3 and the wrapped texel 0
+		assert_eq!(texture.sample(1.0, 0.125, 0.0).r, 24);
 	}
-}
 
-#[inline(always)]
-unsafe fn draw_unsafe(
-	screen:&mut [RGBA],
-	texture:&Texture,
-	pix_idx:usize, uv_idx:usize,
-	depth_buffer:&mut [f32],
-	tex_w:f32
-) {
-	let texture = *texture.pixels.get_unchecked(uv_idx);
-	if texture.a != 0 {
-		let screen = screen.get_unchecked_mut(pix_idx);
-		screen.r = texture.r;
-		screen.g = texture.g;
-		screen.b = texture.b;
-		screen.a = 0xFF;
-		*depth_buffer.get_unchecked_mut(pix_idx) = tex_w;
+	#[test]
+	fn cull_modes() {
+		let towards_camera = Vec3::new(0.0, 0.0, -1.0);
+		let away_from_camera = Vec3::new(0.0, 0.0, 1.0);
+		let ray = Vec3::new(0.0, 0.0, 1.0);
+
+		assert!(!CullMode::None.is_culled(&ray, &towards_camera));
+		assert!(!CullMode::None.is_culled(&ray, &away_from_camera));
+		assert!(!CullMode::Back.is_culled(&ray, &towards_camera));
+		assert!( CullMode::Back.is_culled(&ray, &away_from_camera));
+		assert!( CullMode::Front.is_culled(&ray, &towards_camera));
+		assert!(!CullMode::Front.is_culled(&ray, &away_from_camera));
 	}
-}
 
-#[inline(always)]
-fn draw_safe(
-	screen:&mut [RGBA],
-	texture:&Texture,
-	pix_idx:usize, uv_idx:usize,
-) {
-	screen[pix_idx] = texture.pixels[uv_idx];
-}
+	#[test]
+	fn clip_queue_is_first_in_first_out() {
+		let mut queue = ClipQueue::new();
+		for i in 0..MAX_CLIPPED_TRIANGLES * 2 {
+			let mut t = Triangle::zero_spaced_verticies();
+			t.vertices[0].x = i as f32;
+			queue.push(&t);
+			assert_eq!(queue.pop().vertices[0].x, i as f32);
+		}
+		assert_eq!(queue.len, 0);
+	}
 
-fn texture_triangle_unsafe(
-	screen:&mut [RGBA],
-	depth_buffer:&mut [f32],
-	mut x1:i16, mut y1:i16, mut u1:f32, mut v1:f32, mut w1:f32,
-	mut x2:i16, mut y2:i16, mut u2:f32, mut v2:f32, mut w2:f32,
-	mut x3:i16, mut y3:i16, mut u3:f32, mut v3:f32, mut w3:f32,
-	texture: &Texture
-) {
-	if y2 < y1 {
-		core::mem::swap(&mut y1, &mut y2);
-		core::mem::swap(&mut x1, &mut x2);
-		core::mem::swap(&mut u1, &mut u2);
-		core::mem::swap(&mut v1, &mut v2);
-		core::mem::swap(&mut w1, &mut w2);
-	}
-
-	if y3 < y1 {
-		core::mem::swap(&mut y1, &mut y3);
-		core::mem::swap(&mut x1, &mut x3);
-		core::mem::swap(&mut u1, &mut u3);
-		core::mem::swap(&mut v1, &mut v3);
-		core::mem::swap(&mut w1, &mut w3);
-	}
-
-	if y3 < y2 {
-		core::mem::swap(&mut y2, &mut y3);
-		core::mem::swap(&mut x2, &mut x3);
-		core::mem::swap(&mut u2, &mut u3);
-		core::mem::swap(&mut v2, &mut v3);
-		core::mem::swap(&mut w2, &mut w3);
-	}
-
-	let dy1 = y2 - y1;
-	let dx1 = x2 - x1;
-	let dv1 = v2 - v1;
-	let du1 = u2 - u1;
-	let dw1 = w2 - w1;
-
-	let dy2 = y3 - y1;
-	let dx2 = x3 - x1;
-	let dv2 = v3 - v1;
-	let du2 = u3 - u1;
-	let dw2 = w3 - w1;
-
-	let mut tex_u;
-	let mut tex_v;
-	let mut tex_w;
-
-	let mut dax_step = 0f32;
-	let mut dbx_step = 0f32;
-	let mut du1_step = 0f32;
-	let mut dv1_step = 0f32;
-	let mut du2_step = 0f32;
-	let mut dv2_step = 0f32;
-	let mut dw1_step = 0f32;
-	let mut dw2_step = 0f32;
-
-	if dy2 != 0 {
-		dbx_step = dx2 as f32 / dy2.abs() as f32;
-		du2_step = du2 / dy2.abs() as f32;
-		dv2_step = dv2 / dy2.abs() as f32;
-		dw2_step = dw2 / dy2.abs() as f32;
-	}
-
-	if dy1 != 0 {
-		dax_step = dx1 as f32 / dy1.abs() as f32;
-		du1_step = du1 / dy1.abs() as f32;
-		dv1_step = dv1 / dy1.abs() as f32;
-		dw1_step = dw1 / dy1.abs() as f32;
-
-		for y in y1..=y2 {
-			let mut ax = x1 + ((y - y1) as f32 * dax_step) as i16;
-			let mut bx = x1 + ((y - y1) as f32 * dbx_step) as i16;
-
-			let mut tex_su = u1 + (y - y1) as f32 * du1_step;
-			let mut tex_sv = v1 + (y - y1) as f32 * dv1_step;
-			let mut tex_sw = w1 + (y - y1) as f32 * dw1_step;
-
-			let mut tex_eu = u1 + (y - y1) as f32 * du2_step;
-			let mut tex_ev = v1 + (y - y1) as f32 * dv2_step;
-			let mut tex_ew = w1 + (y - y1) as f32 * dw2_step;
-
-			if ax > bx {
-				core::mem::swap(&mut ax, &mut bx);
-				core::mem::swap(&mut tex_su, &mut tex_eu);
-				core::mem::swap(&mut tex_sv, &mut tex_ev);
-				core::mem::swap(&mut tex_sw, &mut tex_ew);
-			}
+	#[test]
+	fn blend_modes() {
+		let dst = RGBA::rgba(100, 200, 0, 0);
+		let src = RGBA::rgba(200, 100, 255, 0x80);
+
+		let mut p = dst;
+		blend_pixel(&mut p, src, BlendMode::Opaque);
+		assert_eq!((p.r, p.g, p.b, p.a), (200, 100, 255, 0xFF));
+
+		let mut p = dst;
+		blend_pixel(&mut p, src, BlendMode::AlphaTest(0x81));
+		assert_eq!((p.r, p.g, p.b, p.a), (100, 200, 0, 0));
+		blend_pixel(&mut p, src, BlendMode::AlphaTest(0x80));
+		assert_eq!((p.r, p.g, p.b, p.a), (200, 100, 255, 0xFF));
+
+		let mut p = dst;
+		blend_pixel(&mut p, src, BlendMode::AlphaBlend);
+		assert_eq!((p.r, p.g, p.b, p.a), (150, 149, 128, 0x80));
+		blend_pixel(&mut p, src, BlendMode::AlphaBlend);
+		assert_eq!(p.a, 0xBF);
+
+		let mut p = dst;
+		blend_pixel(&mut p, src, BlendMode::Additive);
+		assert_eq!((p.r, p.g, p.b, p.a), (200, 250, 128, 0x80));
+		blend_pixel(&mut p, src, BlendMode::Additive);
+		assert_eq!((p.r, p.g, p.b, p.a), (255, 255, 0xFF, 0xFF));
+	}
 
-			let tstep = 1f32 / ((bx - ax) as f32);
-			let mut t = 0f32;
+	fn screen_triangle(a:(f32, f32), b:(f32, f32), c:(f32, f32)) -> Triangle {
+		triangle(Vec4::xyz1(a.0, a.1, 0.0), Vec4::xyz1(b.0, b.1, 0.0), Vec4::xyz1(c.0, c.1, 0.0))
+	}
 
-			for x in ax..bx {
-				tex_u = (1f32 - t) * tex_su + t * tex_eu;
-				tex_v = (1f32 - t) * tex_sv + t * tex_ev;
-				tex_w = (1f32 - t) * tex_sw + t * tex_ew;
-				// if y >= 0 && x >= 0 && y < SCREEN_HEIGHT as i16 && x < SCREEN_WIDTH as i16 {
-				let pix_idx = y as usize * SCREEN_WIDTH as usize + x as usize;
-				unsafe {
-					// if tex_w > depth_buffer[pix_idx] {
-					if tex_w > *depth_buffer.get_unchecked(pix_idx) {
-						let cu = tex_u / tex_w;
-						let cv = tex_v / tex_w;
-						let uv_idx = (cv * texture.resolution_v as f32) as usize * texture.resolution_u as usize + (cu * texture.resolution_u as f32) as usize;
-						draw_unsafe(screen, texture, pix_idx, uv_idx, depth_buffer, tex_w);
-						// draw_safe(screen, texture, pix_idx, uv_idx);
-						// depth_buffer[pix_idx] = tex_w;
+	/// The spans of `rasterize_triangle_spans` one pixel at a time, kept as a reference for them.
+	/// Calls `fragment(pix_idx, b1, b2)` for every covered pixel center.
+	fn rasterize_triangle<F:FnMut(usize, f32, f32)>(triangle:&Triangle, mut fragment:F) {
+		let setup = match EdgeSetup::new(triangle) {
+			Some(setup) => setup,
+			None => return,
+		};
+		let mut e_row = setup.e_row;
+
+		for y in setup.min_py..=setup.max_py {
+			let mut e = e_row;
+			let row = y as usize * SCREEN_WIDTH as usize;
+
+			for x in setup.min_px..=setup.max_px {
+				if ((e[0] + setup.bias[0]) | (e[1] + setup.bias[1]) | (e[2] + setup.bias[2])) >= 0 {
+					let w_b = e[1] as f32 * setup.inv_area;
+					let w_c = e[2] as f32 * setup.inv_area;
+					if setup.swapped {
+						fragment(row + x as usize, w_c, w_b);
+					} else {
+						fragment(row + x as usize, w_b, w_c);
 					}
 				}
-				// }
-				t += tstep;
+				e[0] += setup.step_x[0];
+				e[1] += setup.step_x[1];
+				e[2] += setup.step_x[2];
 			}
+
+			e_row[0] += setup.step_y[0];
+			e_row[1] += setup.step_y[1];
+			e_row[2] += setup.step_y[2];
 		}
 	}
 
-	let dy1 = y3 - y2;
-	let dx1 = x3 - x2;
-	let dv1 = v3 - v2;
-	let du1 = u3 - u2;
-	let dw1 = w3 - w2;
-
-	if dy2 != 0 {
-		dbx_step = dx2 as f32 / dy2.abs() as f32;
+	fn coverage(triangles:&[Triangle]) -> Box<[u8]> {
+		let mut hits = vec![0u8; SCREEN_SPACE as usize].into_boxed_slice();
+		for t in triangles {
+			rasterize_triangle(t, |pix_idx, _, _| hits[pix_idx] += 1);
+		}
+		hits
 	}
 
-	du1_step = 0f32;
-	dv1_step = 0f32;
-
-	if dy1 != 0 {
-		dax_step = dx1 as f32 / dy1.abs() as f32;
-		du1_step = du1 / dy1.abs() as f32;
-		dv1_step = dv1 / dy1.abs() as f32;
-		dw1_step = dw1 / dy1.abs() as f32;
-
-		for y in y2..=y3 {
-			let mut ax = x2 + ((y - y2) as f32 * dax_step) as i16;
-			let mut bx = x1 + ((y - y1) as f32 * dbx_step) as i16;
-
-			let mut tex_su = u2 + (y - y2) as f32 * du1_step;
-			let mut tex_sv = v2 + (y - y2) as f32 * dv1_step;
-			let mut tex_sw = w2 + (y - y2) as f32 * dw1_step;
+	#[test]
+	fn shared_edges_are_drawn_exactly_once() {
+		// A fan of triangles with sub-pixel vertices around a center, in both windings
+		let center = (100.3, 100.7);
+		let ring = [(60.1, 70.4), (140.6, 62.2), (151.3, 120.9), (98.8, 150.5), (50.25, 131.75)];
+		let mut fan = [Triangle::zero_spaced_verticies(); 5];
+		for i in 0..5 {
+			let (a, b) = (ring[i], ring[(i + 1) % 5]);
+			fan[i] = if i % 2 == 0 { screen_triangle(center, a, b) } else { screen_triangle(center, b, a) };
+		}
+		let hits = coverage(&fan);
+		assert!(hits.iter().all(|&h| h <= 1), "overdraw on a shared edge");
 
-			let mut tex_eu = u1 + (y - y1) as f32 * du2_step;
-			let mut tex_ev = v1 + (y - y1) as f32 * dv2_step;
-			let mut tex_ew = w1 + (y - y1) as f32 * dw2_step;
+		// Every pixel center strictly inside the polygon has to be drawn
+		let covered = hits.iter().filter(|&&h| h == 1).count();
+		assert!(covered > 5000);
+		assert_eq!(hits[100 * SCREEN_WIDTH as usize + 100], 1);
+	}
 
-			if ax > bx {
-				core::mem::swap(&mut ax, &mut bx);
-				core::mem::swap(&mut tex_su, &mut tex_eu);
-				core::mem::swap(&mut tex_sv, &mut tex_ev);
-				core::mem::swap(&mut tex_sw, &mut tex_ew);
+	#[test]
+	fn top_left_fill_rule() {
+		// Edges run exactly through pixel centers: the top and left ones are drawn, the bottom and right ones are not
+		let square = [
+			screen_triangle((10.5, 10.5), (14.5, 10.5), (14.5, 14.5)),
+			screen_triangle((10.5, 10.5), (14.5, 14.5), (10.5, 14.5)),
+		];
+		let hits = coverage(&square);
+		for y in 0..20 {
+			for x in 0..20 {
+				let expected = (10..14).contains(&x) && (10..14).contains(&y);
+				assert_eq!(hits[y * SCREEN_WIDTH as usize + x] == 1, expected, "pixel {} {}", x, y);
 			}
+		}
+		assert_eq!(hits.iter().map(|&h| h as usize).sum::<usize>(), 16);
+	}
 
-			if ax < 0 {
-				if bx < 0 {
-					continue;
-				}
+	#[test]
+	fn guard_band_triangles_are_cut_at_the_screen() {
+		let w = SCREEN_WIDTH as f32;
+		let h = SCREEN_HEIGHT as f32;
+		let t = screen_triangle((-0.5 * w, -0.5 * h), (1.5 * w, -0.5 * h), (-0.5 * w, 1.5 * h));
+		let hits = coverage(&[t]);
+		assert_eq!(hits[0], 1);
+		assert_eq!(hits[SCREEN_SPACE as usize - 1], 0);
+	}
 
-				if bx >= SCREEN_WIDTH as i16 {
-					bx = SCREEN_WIDTH as i16  - 1;
-				}
-				ax = 0;
+	#[test]
+	fn barycentric_weights() {
+		let t = screen_triangle((0.5, 0.5), (100.5, 0.5), (0.5, 100.5));
+		let mut at = [(0f32, 0f32); 3];
+		rasterize_triangle(&t, |pix_idx, b1, b2| {
+			match pix_idx {
+				0 => at[0] = (b1, b2),
+				50 => at[1] = (b1, b2),
+				i if i == 50 * SCREEN_WIDTH as usize => at[2] = (b1, b2),
+				_ => {},
 			}
+		});
+		assert_eq!(at[0], (0.0, 0.0));
+		assert!((at[1].0 - 0.5).abs() < EPS && at[1].1.abs() < EPS);
+		assert!(at[2].0.abs() < EPS && (at[2].1 - 0.5).abs() < EPS);
+	}
 
-			let tstep = 1f32 / (bx - ax) as f32;
-			let mut t = 0f32;
-
-			for x in ax..bx {
-				tex_u = (1f32 - t) * tex_su + t * tex_eu;
-				tex_v = (1f32 - t) * tex_sv + t * tex_ev;
-				tex_w = (1f32 - t) * tex_sw + t * tex_ew;
+	/// The per-pixel path `texture_triangle_safe` had before spans, kept as a reference for the span renderer.
+	fn texture_triangle_per_pixel(screen:&mut [RGBA], depth_buffer:&mut [f32], triangle:&Triangle, texture:&Texture, material:&Material, view:DebugView) {
+		let gradients = TexGradients::new(triangle);
+		let tex_0 = triangle.uvs[0];
+		let tex_d1 = triangle.uvs[1] - tex_0;
+		let tex_d2 = triangle.uvs[2] - tex_0;
+		let light_0 = triangle.lights[0];
+		let light_d1 = triangle.lights[1] - light_0;
+		let light_d2 = triangle.lights[2] - light_0;
+
+		rasterize_triangle(triangle, |pix_idx, b1, b2| {
+			let tex = tex_0 + tex_d1 * b1 + tex_d2 * b2;
+			let tex_w = tex.ws();
+			if tex_w > depth_buffer[pix_idx] {
+				let texel = match view {
+					DebugView::Normals => RGBA::rgba(0xFF, 0xFF, 0xFF, 0xFF),
+					_ => texture.sample(tex.us() / tex_w, tex.vs() / tex_w, gradients.lod(tex.us(), tex.vs(), tex_w, texture)),
+				};
+				let light = (light_0 + light_d1 * b1 + light_d2 * b2) * (1f32 / tex_w);
+				unsafe { draw_unsafe(screen, pix_idx, depth_buffer, tex_w, light, texel, material); }
+			}
+		});
+	}
 
-				// if y >= 0 && x >= 0 && y < SCREEN_HEIGHT as i16 && x < SCREEN_WIDTH as i16 {
-				let pix_idx = y as usize * SCREEN_WIDTH as usize + x as usize;
-				unsafe {
-					// if tex_w > depth_buffer[pix_idx] {
-					if tex_w > *depth_buffer.get_unchecked(pix_idx) {
-						let cu = tex_u / tex_w;
-						let cv = tex_v / tex_w;
-						let uv_idx = (cv * texture.resolution_v as f32) as usize * texture.resolution_u as usize + (cu * texture.resolution_u as f32) as usize;
-						draw_unsafe(screen, texture, pix_idx, uv_idx, depth_buffer, tex_w);
-						// draw_safe(screen, texture, pix_idx, uv_idx);
-						// depth_buffer[pix_idx] = tex_w;
-					}
-				}
-				// }
-				t += tstep;
+	/// Overlapping triangles at different depths, with perspective uvs and lights.
+	fn textured_scene() -> [Triangle; 3] {
+		let mut scene = [
+			screen_triangle((13.3, 7.6), (700.2, 55.9), (120.7, 461.1)),
+			screen_triangle((845.1, 470.5), (300.6, 20.2), (80.4, 300.3)),
+			screen_triangle((400.0, 240.0), (401.5, 242.0), (399.0, 243.5)),
+		];
+		for (i, t) in scene.iter_mut().enumerate() {
+			for v in 0..3 {
+				let inv_w = 0.1 + 0.2 * v as f32 + 0.15 * i as f32;
+				t.uvs[v] = Vec3::new(v as f32 * 3.0 * inv_w, (v + i) as f32 * 1.7 * inv_w, inv_w);
+				t.lights[v] = Vec3::new(0.5 + 0.2 * v as f32, 1.0, 1.5 - 0.3 * v as f32) * inv_w;
 			}
 		}
+		scene
 	}
-}
-
-fn intersect_vector_plane(plane_p:&Vec4, plane_n:&Vec4, line_start:&Vec4, line_end:&Vec4) -> (Vec4, f32) {
-	// TODO: let plane_n = normalize(plane_n);
-	let plane_d = -Vec4::dot(&plane_n, &plane_p);
-	let ad = Vec4::dot(line_start, &plane_n);
-	let bd = Vec4::dot(line_end,   &plane_n);
-	let t = (-plane_d -  ad) / (bd - ad);
-	let line_start_to_end = *line_end - *line_start;
-	let line_to_intersect = mul_vec3d(&line_start_to_end, t);
-	return (*line_start + line_to_intersect, t);
-}
 
-fn triangle_clip_against_plane(plane_p:&Vec4, plane_n:&Vec4, in_tri:&Triangle) -> (usize, [Triangle;2]) {
-	// Make sure plane normal is indeed normal
-	// TODO: let plane_n = normalize(plane_n);
-
-	// Return signed shortest distance from point to plane, plane normal must be normalised
-	fn dist(plane_p:&Vec4, plane_n:&Vec4, p:&Vec4) -> f32 {
-		plane_n.x*p.x + plane_n.y*p.y + plane_n.z*p.z - Vec4::dot(plane_n, plane_p)
+	fn frame_buffers() -> (Box<[RGBA]>, Box<[f32]>) {
+		(
+			vec![RGBA::zeroed(); SCREEN_SPACE as usize].into_boxed_slice(),
+			vec![0f32; SCREEN_SPACE as usize].into_boxed_slice(),
+		)
 	}
 
-	// Create two temporary storage arrays to classify points either side of plane
-	let zv3:Vec4 = Vec4::ZERO;
-	let zv2:Vec3 = Vec3::ZERO;
-	let mut  inside_points = [&zv3;3];
-	let mut outside_points = [&zv3;3];
-	let mut  inside_uvs    = [&zv2;3];
-	let mut outside_uvs    = [&zv2;3];
-	let mut  inside_counter = 0;
-	let mut outside_counter = 0;
-
-	for i in 0..3 {
-		let v = &in_tri.vertices[i];
-		let uv = &in_tri.uvs[i];
-		// Get signed distance of each point in triangle to plane
-		let d = dist(plane_p, &plane_n, v);
+	#[test]
+	fn spans_match_the_per_pixel_rasterizer() {
+		let texture = gradient_texture(Sampler::new(Wrap::Repeat, Filter::Bilinear, true));
+		let material = Material::new(BlendMode::Opaque, 0xFF);
+		let (mut screen_pixels, mut depth_pixels) = frame_buffers();
+		let (mut screen_scalar, mut depth_scalar) = frame_buffers();
+		let (mut screen_native, mut depth_native) = frame_buffers();
+		// With the widest lanes of the CPU running the test
+		let (mut screen_spans, mut depth_spans) = frame_buffers();
+
+		for t in &textured_scene() {
+			texture_triangle_per_pixel(&mut screen_pixels, &mut depth_pixels, t, &texture, &material, DebugView::Shaded);
+			texture_triangle_spans::<span::Scalar>(&mut screen_scalar, &mut depth_scalar, t, &texture, &material, None, DebugView::Shaded);
+			texture_triangle_spans::<span::Native>(&mut screen_native, &mut depth_native, t, &texture, &material, None, DebugView::Shaded);
+			texture_triangle_safe(&mut screen_spans, &mut depth_spans, t, &texture, &material, None, DebugView::Shaded);
+		}
 
-		// If distance sign is positive, point lies on "inside" of plane
-		if d >= 0f32 {
-			inside_points[inside_counter] = v;
-			inside_uvs[inside_counter] = uv;
-			inside_counter += 1;
-		} else {
-			outside_points[outside_counter] = v;
-			outside_uvs[outside_counter] = uv;
-			outside_counter += 1;
+		for (screen, depth) in &[(&screen_scalar, &depth_scalar), (&screen_native, &depth_native), (&screen_spans, &depth_spans)] {
+			for i in 0..SCREEN_SPACE as usize {
+				let (a, b) = (screen_pixels[i], screen[i]);
+				assert_eq!((a.r, a.g, a.b, a.a), (b.r, b.g, b.b, b.a), "pixel {}", i);
+				assert_eq!(depth_pixels[i].to_bits(), depth[i].to_bits(), "depth {}", i);
+			}
 		}
+		assert!(screen_spans.iter().filter(|p| p.a == 0xFF).count() > 100_000);
 	}
 
-	// Now classify triangle points, and break the input triangle into smaller output triangles if required. There are four possible outcomes...
-	match inside_counter {
-		0 => {
-			// All points lie on the outside of plane, so clip whole triangle It ceases to exist
-			return (0, [Triangle::zero_spaced_verticies(); 2]); // No returned triangles are valid
-		},
-		3 => {
-			// All points lie on the inside of plane, so do nothing and allow the triangle to simply pass through
-			return (1, [in_tri.clone(), Triangle::zero_spaced_verticies()]); // Just the one returned original triangle is valid
-		},
-		1 => {
-			// Triangle should be clipped. As two points lie outside the plane, the triangle simply becomes a smaller triangle
-			let mut ot = in_tri.clone();
-			// The inside point is valid, so keep that...
-			ot.vertices[0] = inside_points[0].clone();
-			ot.uvs[0] = inside_uvs[0].clone();
+	#[inline(always)]
+	fn span_masks_of<L:Lanes>() {
+		let mut hits = vec![0u8; SCREEN_SPACE as usize].into_boxed_slice();
+		let t = screen_triangle((3.5, 0.5), (3.5 + 2.0 * L::LANES as f32, 0.5), (3.5, 4.5));
+		rasterize_triangle_spans::<L, _>(&t, |pix_idx, mask, _, _| {
+			assert_eq!(pix_idx % L::LANES, 0);
+			for i in 0..L::LANES {
+				hits[pix_idx + i] += (mask >> i) as u8 & 1;
+			}
+		});
+		let expected = coverage(&[t]);
+		assert_eq!(&hits[..], &expected[..]);
+	}
 
-			// but the two new points are at the locations where the original sides of the triangle (lines) intersect with the plane
+	#[cfg(target_arch = "x86_64")]
+	#[target_feature(enable = "avx2")]
+	unsafe fn span_masks_avx2() {
+		span_masks_of::<span::Avx2>();
+	}
 
-			let (v, t) = intersect_vector_plane(plane_p, &plane_n, &inside_points[0], &outside_points[0]);
-			ot.vertices[1] = v;
-			ot.uvs[1] = *inside_uvs[0] + (*outside_uvs[0] - *inside_uvs[0]) * t;
+	#[test]
+	fn span_masks() {
+		span_masks_of::<span::Scalar>();
+		span_masks_of::<span::Native>();
+		#[cfg(target_arch = "x86_64")]
+		{
+			if span::has_avx2() {
+				unsafe { span_masks_avx2(); }
+			}
+		}
+	}
 
-			let (v, t) = intersect_vector_plane(plane_p, &plane_n, &inside_points[0], &outside_points[1]);
-			ot.vertices[2] = v;
-			ot.uvs[2] = *inside_uvs[0] + (*outside_uvs[1] - *inside_uvs[0]) * t;
+	#[test]
+	fn supersampled_edges_are_outlined() {
+		let mut renderer = SoftwareRenderer::new();
+		renderer.init();
+		renderer.set_supersampling(Supersampling::X4);
+		renderer.set_fxaa(false);
+		let mut image = vec![RGBA::zeroed(); SCREEN_SPACE as usize];
+		renderer.render(&mut image, 0, &Controls::new(SCREEN_WIDTH, SCREEN_HEIGHT));
+
+		// Samples with only the outline in them resolve to a grey with the same alpha, anything else has the mesh in it
+		let mesh = |c:RGBA| c.a != 0 && !(c.r == c.g && c.g == c.b && c.b == c.a);
+		const W:usize = SCREEN_WIDTH as usize;
+		let mut edges = 0;
+		// Rows between the overlay text and the logos
+		for y in 48..(SCREEN_HEIGHT as i32 - MADE_WITH_LOGOS.height) as usize {
+			for x in 1..W - 1 {
+				let i = y * W + x;
+				if image[i].a == 0 {
+					assert!(![i - W, i - 1, i + 1, i + W].iter().any(|&n| mesh(image[n])), "gap in the outline at {}, {}", x, y);
+				} else if mesh(image[i]) && image[i].a < 0xFF {
+					edges += 1;
+				}
+			}
+		}
+		assert!(edges > 0, "no partially covered pixels");
+	}
 
-			return (1, [ot, Triangle::zero_spaced_verticies()]); // Return the newly formed single triangle
-		},
-		2 => {
-			// Triangle should be clipped. As two points lie inside the plane,
-			// the clipped triangle becomes a "quad". Fortunately, we can
-			// represent a quad with two new triangles
+	#[test]
+	fn bundled_model_is_animated() {
+		let mut mesh = MeshBasic::new();
+		load_mesh(&mut mesh, MODEL_BIN).unwrap();
+		assert_eq!(mesh.skins.len(), mesh.amount_of_triangles as usize);
+		assert!(mesh.skeleton.clips_amount() > 0);
+
+		// Half a second into the idle clip the head is tilted, while the feet only bob with the body
+		mesh.skeleton.set_pose(0, 0.5);
+		let (mut head, mut feet) = (0f32, 0f32);
+		for (triangle, skins) in mesh.triangles.iter().zip(&mesh.skins) {
+			for (v, skin) in triangle.vertices.iter().zip(skins) {
+				let moved = (mesh.skeleton.skin_position(skin, v).xyz() - v.xyz()).len();
+				if v.y > 1.8 {
+					head = head.max(moved);
+				} else if v.y < 0.1 {
+					feet = feet.max(moved);
+				}
+			}
+		}
+		assert!(head > 0.05, "head moved by {}", head);
+		assert!(feet < 0.03, "feet moved by {}", feet);
+	}
 
-			// Copy appearance info to new triangles
-			let mut ot1 = in_tri.clone();
-			let mut ot2 = in_tri.clone();
+	extern crate test;
+
+	/// Screen filling grid of small triangles, tilted in depth and overlapping the neighbours, in front of the textured scene.
+	fn bench_scene() -> Vec<Triangle> {
+		let mut scene = textured_scene().to_vec();
+		let (columns, rows) = (32, 18);
+		let (width, height) = (SCREEN_WIDTH as f32 / columns as f32, SCREEN_HEIGHT as f32 / rows as f32);
+		for row in 0..rows {
+			for column in 0..columns {
+				let (x, y) = (column as f32 * width, row as f32 * height);
+				let mut t = screen_triangle((x - 3.3, y - 2.1), (x + width + 4.7, y + 0.6), (x + 1.2, y + height + 3.9));
+				for v in 0..3 {
+					let inv_w = 0.2 + 0.01 * ((row + column + v) % 7) as f32;
+					t.uvs[v] = Vec3::new(v as f32 * inv_w, 0.5 * inv_w, inv_w);
+				}
+				scene.push(t);
+			}
+		}
+		scene
+	}
 
-			// The first triangle consists of the two inside points and a new
-			// point determined by the location where one side of the triangle
-			// intersects with the plane
-			ot1.vertices[0] = inside_points[0].clone();
-			ot1.vertices[1] = inside_points[1].clone();
-			ot1.uvs[0] = inside_uvs[0].clone();
-			ot1.uvs[1] = inside_uvs[1].clone();
-			let (v, t) = intersect_vector_plane(plane_p, &plane_n, &inside_points[0], &outside_points[0]);
-			ot1.vertices[2] = v;
-			ot1.uvs[2] = *inside_uvs[0] + (*outside_uvs[0] - *inside_uvs[0]) * t;
+	type DrawTriangle = dyn Fn(&mut [RGBA], &mut [f32], &Triangle, &Texture, &Material);
+
+	/// Draws `bench_scene` in the normals view, which leaves out the per pixel texture sampling, so what is measured is
+	/// what the spans do in lanes: coverage, interpolation and the depth test. Throughput is reported per frame pixel.
+	fn bench_raster(b:&mut test::Bencher, draw:&DrawTriangle) {
+		let texture = gradient_texture(Sampler::new(Wrap::Repeat, Filter::Bilinear, true));
+		let material = Material::new(BlendMode::Opaque, 0xFF);
+		let scene = bench_scene();
+		let (mut screen, mut depth) = frame_buffers();
+		b.bytes = SCREEN_SPACE as u64;
+		b.iter(|| {
+			for d in depth.iter_mut() {
+				*d = 0f32;
+			}
+			for t in &scene {
+				draw(&mut screen, &mut depth, t, &texture, &material);
+			}
+			test::black_box(&screen);
+		});
+	}
 
-			// The second triangle is composed of one of he inside points, a new point determined by the intersection of the other side of the triangle and the plane, and the newly created point above
-			ot2.vertices[0] = inside_points[1].clone();
-			ot2.uvs[0] = inside_uvs[1].clone();
-			ot2.vertices[1] = ot1.vertices[2].clone();
-			ot2.uvs[1] = ot1.uvs[2].clone();
-			// TODO: Bug ot2.uvs[1] = inside_uvs[2].clone();
+	#[bench]
+	fn bench_raster_per_pixel(b:&mut test::Bencher) {
+		bench_raster(b, &|screen, depth, t, texture, material| texture_triangle_per_pixel(screen, depth, t, texture, material, DebugView::Normals));
+	}
 
-			let (v, t) = intersect_vector_plane(plane_p, &plane_n, &inside_points[1], &outside_points[0]);
-			ot2.vertices[2] = v;
-			ot2.uvs[2] = *inside_uvs[1] + (*outside_uvs[0] - *inside_uvs[1]) * t;
+	#[bench]
+	fn bench_raster_spans_native(b:&mut test::Bencher) {
+		bench_raster(b, &|screen, depth, t, texture, material| texture_triangle_spans::<span::Native>(screen, depth, t, texture, material, None, DebugView::Normals));
+	}
 
-			return (2, [ot1, ot2]); // Return two newly formed triangles which form a quad
-		},
-		_ => unreachable!()
+	/// Whatever `texture_triangle_safe` picks, AVX2 on CPUs which have it.
+	#[bench]
+	fn bench_raster_spans_widest(b:&mut test::Bencher) {
+		bench_raster(b, &|screen, depth, t, texture, material| texture_triangle_safe(screen, depth, t, texture, material, None, DebugView::Normals));
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,461 @@
+use tiny_lib::{matrices::*, vector::*, quaternion::Quaternion};
+use super::{u8_to_u16, u8_to_f32, multiply_vector_matrix, multiply_direction_matrix};
+
+pub const MAX_BONES:usize = 32;
+/// Parent of the root bones.
+const NO_PARENT:u8 = 0xFF;
+
+/// Why `Skeleton::load` rejected an animation section.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AnimationError {
+	/// More than `MAX_BONES` bones.
+	TooManyBones(u8),
+	/// Parents have to come before their children, so global transforms can be built in one pass.
+	ParentAfterChild { bone:u8, parent:u8 },
+}
+impl core::fmt::Display for AnimationError {
+	fn fmt(&self, f:&mut core::fmt::Formatter) -> core::fmt::Result {
+		match *self {
+			AnimationError::TooManyBones(amount) => write!(f, "{} bones, at most {} are supported", amount, MAX_BONES),
+			AnimationError::ParentAfterChild { bone, parent } => write!(f, "bone {} has parent {}, which doesn't come before it", bone, parent),
+		}
+	}
+}
+
+/// Scale, then rotation, then translation, as a row-vector matrix like the rest of the renderer uses.
+fn transform_matrix(translation:&Vec3, rotation:&Quaternion, scale:&Vec3) -> Matrix4x4 {
+	let mut matrix = rotation.to_matrix();
+	// Every row is the image of a basis vector
+	for (row, scale) in matrix.m.iter_mut().zip(&[scale.x, scale.y, scale.z]) {
+		for value in row.iter_mut() {
+			*value *= scale;
+		}
+	}
+	matrix.m[3] = [translation.x, translation.y, translation.z, 1f32];
+	matrix
+}
+
+#[derive(Copy, Clone)]
+struct Bone {
+	parent:u8,
+	/// Bind pose relative to the parent.
+	translation:Vec3,
+	rotation:Quaternion,
+	scale:Vec3,
+	/// Brings model space into the space of the bone in bind pose.
+	inverse_bind:Matrix4x4,
+}
+impl Bone {
+	const fn new() -> Bone {
+		Bone {
+			parent: NO_PARENT,
+			translation: Vec3::ZERO,
+			rotation: Quaternion::IDENTITY,
+			scale: Vec3::ONE,
+			inverse_bind: Matrix4x4::ones_cascade(),
+		}
+	}
+}
+
+/// Up to four bones that move a vertex, weights add up to 255.
+#[derive(Copy, Clone)]
+pub struct Skin {
+	pub bones:[u8; 4],
+	pub weights:[u8; 4],
+}
+impl Skin {
+	pub const fn rigid(bone:u8) -> Skin {
+		Skin { bones: [bone, 0, 0, 0], weights: [0xFF, 0, 0, 0] }
+	}
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TrackKind {
+	/// Interpolated linearly.
+	Translation,
+	/// Interpolated with slerp.
+	Rotation,
+	/// Interpolated linearly.
+	Scale,
+}
+
+/// Translations and scales use xyz, rotations are quaternions in xyzw.
+#[derive(Copy, Clone)]
+struct Keyframe {
+	time:f32,
+	value:Vec4,
+}
+
+#[derive(Copy, Clone)]
+struct Track {
+	bone:u8,
+	kind:TrackKind,
+	first_keyframe:usize,
+	keyframes_amount:usize,
+}
+
+#[derive(Copy, Clone)]
+struct Clip {
+	duration:f32,
+	first_track:usize,
+	tracks_amount:usize,
+}
+
+pub struct Skeleton {
+	bones:[Bone; MAX_BONES],
+	bones_amount:u8,
+	clips:Vec<Clip>,
+	tracks:Vec<Track>,
+	keyframes:Vec<Keyframe>,
+	/// Skinning matrices of the current pose, model space in bind pose to model space in the pose.
+	pose:[Matrix4x4; MAX_BONES],
+	posed:bool,
+}
+impl Skeleton {
+	pub const fn new() -> Skeleton {
+		Skeleton {
+			bones: [Bone::new(); MAX_BONES],
+			bones_amount: 0,
+			clips: Vec::new(),
+			tracks: Vec::new(),
+			keyframes: Vec::new(),
+			pose: [Matrix4x4::ones_cascade(); MAX_BONES],
+			posed: false,
+		}
+	}
+
+	#[inline(always)]
+	pub fn clips_amount(&self) -> u8 {
+		self.clips.len() as u8
+	}
+
+	/// `false` while the mesh is in bind pose and skinning can be skipped.
+	#[inline(always)]
+	pub fn is_posed(&self) -> bool {
+		self.posed
+	}
+
+	/// Reads the animation section of a mesh, see `load_mesh`. Fills the skin of every vertex and returns the offset past the section.
+	pub fn load(&mut self, binary:&[u8], mut offset:usize, vertex_skins:&mut [Skin]) -> Result<usize, AnimationError> {
+		fn f32_at(binary:&[u8], offset:&mut usize) -> f32 {
+			let v = u8_to_f32(binary[*offset], binary[*offset + 1], binary[*offset + 2], binary[*offset + 3]);
+			*offset += 4;
+			v
+		}
+		fn u16_at(binary:&[u8], offset:&mut usize) -> u16 {
+			let v = u8_to_u16(binary[*offset], binary[*offset + 1]);
+			*offset += 2;
+			v
+		}
+
+		let bones_amount = binary[offset];
+		offset += 1;
+		if bones_amount as usize > MAX_BONES {
+			return Err(AnimationError::TooManyBones(bones_amount));
+		}
+		self.bones_amount = bones_amount;
+		for i in 0..bones_amount as usize {
+			let mut bone = Bone::new();
+			bone.parent = binary[offset];
+			offset += 1;
+			if bone.parent != NO_PARENT && bone.parent as usize >= i {
+				return Err(AnimationError::ParentAfterChild { bone: i as u8, parent: bone.parent });
+			}
+			bone.translation = Vec3::new(f32_at(binary, &mut offset), f32_at(binary, &mut offset), f32_at(binary, &mut offset));
+			bone.rotation = Quaternion::new(f32_at(binary, &mut offset), f32_at(binary, &mut offset), f32_at(binary, &mut offset), f32_at(binary, &mut offset)).normalize();
+			bone.scale = Vec3::new(f32_at(binary, &mut offset), f32_at(binary, &mut offset), f32_at(binary, &mut offset));
+			self.bones[i] = bone;
+		}
+
+		for skin in vertex_skins.iter_mut() {
+			for k in 0..4 {
+				skin.bones[k] = core::cmp::min(binary[offset + k], self.bones_amount.saturating_sub(1));
+				skin.weights[k] = binary[offset + 4 + k];
+			}
+			offset += 8;
+		}
+
+		self.clips.clear();
+		self.tracks.clear();
+		self.keyframes.clear();
+		let clips_amount = binary[offset];
+		offset += 1;
+		for _ in 0..clips_amount {
+			let duration = f32_at(binary, &mut offset);
+			let tracks_amount = u16_at(binary, &mut offset);
+			let first_track = self.tracks.len();
+			for _ in 0..tracks_amount {
+				let bone = binary[offset];
+				let kind = match binary[offset + 1] {
+					0 => TrackKind::Translation,
+					1 => TrackKind::Rotation,
+					_ => TrackKind::Scale,
+				};
+				offset += 2;
+				let keyframes_amount = u16_at(binary, &mut offset);
+				let first_keyframe = self.keyframes.len();
+				for _ in 0..keyframes_amount {
+					let time = f32_at(binary, &mut offset);
+					let x = f32_at(binary, &mut offset);
+					let y = f32_at(binary, &mut offset);
+					let z = f32_at(binary, &mut offset);
+					let w = if kind == TrackKind::Rotation { f32_at(binary, &mut offset) } else { 0f32 };
+					self.keyframes.push(Keyframe { time, value: Vec4::new(x, y, z, w) });
+				}
+				if bone < self.bones_amount {
+					self.tracks.push(Track {
+						bone,
+						kind,
+						first_keyframe,
+						keyframes_amount: self.keyframes.len() - first_keyframe,
+					});
+				}
+			}
+			self.clips.push(Clip { duration, first_track, tracks_amount: self.tracks.len() - first_track });
+		}
+
+		self.compute_inverse_bind();
+		Ok(offset)
+	}
+
+	fn compute_inverse_bind(&mut self) {
+		let mut global = [Matrix4x4::ones_cascade(); MAX_BONES];
+		for i in 0..self.bones_amount as usize {
+			let bone = &self.bones[i];
+			let local = transform_matrix(&bone.translation, &bone.rotation, &bone.scale);
+			global[i] = if bone.parent == NO_PARENT {
+				local
+			} else {
+				local * global[bone.parent as usize]
+			};
+			self.bones[i].inverse_bind = global[i].inverse().unwrap_or(Matrix4x4::ones_cascade());
+		}
+	}
+
+	/// Value of a track at `time`, held at the first and the last keyframe.
+	fn sample_track(&self, track:&Track, time:f32) -> Vec4 {
+		let keyframes = &self.keyframes[track.first_keyframe..track.first_keyframe + track.keyframes_amount];
+		let last = keyframes.len() - 1;
+		if time <= keyframes[0].time {
+			return keyframes[0].value;
+		}
+		if time >= keyframes[last].time {
+			return keyframes[last].value;
+		}
+
+		let next = keyframes.iter().position(|k| k.time > time).unwrap_or(last);
+		let (a, b) = (&keyframes[next - 1], &keyframes[next]);
+		let t = (time - a.time) / (b.time - a.time);
+		match track.kind {
+			TrackKind::Rotation => {
+				let q = Quaternion::slerp(
+					&Quaternion::new(a.value.x, a.value.y, a.value.z, a.value.w),
+					&Quaternion::new(b.value.x, b.value.y, b.value.z, b.value.w),
+					t,
+				);
+				Vec4::new(q.x, q.y, q.z, q.w)
+			},
+			TrackKind::Translation | TrackKind::Scale => Vec4::lerp(a.value, b.value, t),
+		}
+	}
+
+	/// Poses the skeleton with `clip` at `time` seconds, looping it. Bones without tracks keep their bind pose.
+	pub fn set_pose(&mut self, clip:u8, time:f32) {
+		if clip >= self.clips_amount() {
+			return;
+		}
+		let clip = self.clips[clip as usize];
+
+		let mut translations = [Vec3::ZERO; MAX_BONES];
+		let mut rotations = [Quaternion::IDENTITY; MAX_BONES];
+		let mut scales = [Vec3::ONE; MAX_BONES];
+		for i in 0..self.bones_amount as usize {
+			translations[i] = self.bones[i].translation;
+			rotations[i] = self.bones[i].rotation;
+			scales[i] = self.bones[i].scale;
+		}
+
+		let time = if clip.duration > 0f32 { time.rem_euclid(clip.duration) } else { 0f32 };
+		for track in &self.tracks[clip.first_track..clip.first_track + clip.tracks_amount] {
+			if track.keyframes_amount == 0 {
+				continue;
+			}
+			let value = self.sample_track(track, time);
+			let bone = track.bone as usize;
+			match track.kind {
+				TrackKind::Translation => translations[bone] = value.xyz(),
+				TrackKind::Rotation => rotations[bone] = Quaternion::new(value.x, value.y, value.z, value.w),
+				TrackKind::Scale => scales[bone] = value.xyz(),
+			}
+		}
+
+		let mut global = [Matrix4x4::ones_cascade(); MAX_BONES];
+		for i in 0..self.bones_amount as usize {
+			let local = transform_matrix(&translations[i], &rotations[i], &scales[i]);
+			let parent = self.bones[i].parent;
+			global[i] = if parent == NO_PARENT {
+				local
+			} else {
+				local * global[parent as usize]
+			};
+			self.pose[i] = self.bones[i].inverse_bind * global[i];
+		}
+		self.posed = true;
+	}
+
+	/// Back to bind pose.
+	pub fn clear_pose(&mut self) {
+		self.posed = false;
+	}
+
+	pub fn skin_position(&self, skin:&Skin, position:&Vec4) -> Vec4 {
+		let mut skinned = Vec3::ZERO;
+		for k in 0..4 {
+			if skin.weights[k] != 0 {
+				let weight = skin.weights[k] as f32 * (1f32 / 255f32);
+				skinned += multiply_vector_matrix(position, &self.pose[skin.bones[k] as usize]).xyz() * weight;
+			}
+		}
+		Vec4::xyz1(skinned.x, skinned.y, skinned.z)
+	}
+
+	pub fn skin_direction(&self, skin:&Skin, direction:&Vec3) -> Vec3 {
+		let mut skinned = Vec3::ZERO;
+		for k in 0..4 {
+			if skin.weights[k] != 0 {
+				let weight = skin.weights[k] as f32 * (1f32 / 255f32);
+				skinned += multiply_direction_matrix(direction, &self.pose[skin.bones[k] as usize]) * weight;
+			}
+		}
+		skinned.normalize()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use core::f32::consts::PI;
+
+	const EPS:f32 = 1e-4;
+
+	fn assert_near(a:Vec3, b:Vec3) {
+		assert!((a - b).len() < EPS, "{:?} != {:?}", a, b);
+	}
+
+	/// Big-endian writer for the animation section.
+	#[derive(Default)]
+	struct Section(Vec<u8>);
+	impl Section {
+		fn u8(&mut self, v:u8) -> &mut Self { self.0.push(v); self }
+		fn u16(&mut self, v:u16) -> &mut Self { self.0.extend_from_slice(&v.to_be_bytes()); self }
+		fn f32s(&mut self, v:&[f32]) -> &mut Self { for f in v { self.0.extend_from_slice(&f.to_be_bytes()); } self }
+	}
+
+	/// A root at the origin and a child one unit up the y axis. The only clip turns the child
+	/// a quarter around z and then back over two seconds, and moves the root along x.
+	fn arm() -> (Skeleton, [Skin; 3]) {
+		let quarter = Quaternion::from_axis_angle(&Vec3::new(0.0, 0.0, 1.0), PI * 0.5);
+		let mut s = Section::default();
+		s.u8(2);
+		s.u8(NO_PARENT).f32s(&[0.0, 0.0, 0.0]).f32s(&[0.0, 0.0, 0.0, 1.0]).f32s(&[1.0, 1.0, 1.0]);
+		s.u8(0).f32s(&[0.0, 1.0, 0.0]).f32s(&[0.0, 0.0, 0.0, 1.0]).f32s(&[1.0, 1.0, 1.0]);
+		// Vertices: on the root, on the child, shared half and half
+		s.u8(0).u8(0).u8(0).u8(0).u8(0xFF).u8(0).u8(0).u8(0);
+		s.u8(1).u8(0).u8(0).u8(0).u8(0xFF).u8(0).u8(0).u8(0);
+		s.u8(0).u8(1).u8(0).u8(0).u8(0x80).u8(0x7F).u8(0).u8(0);
+		s.u8(1);
+		s.f32s(&[2.0]).u16(2);
+		s.u8(1).u8(1).u16(3);
+		s.f32s(&[0.0, 0.0, 0.0, 0.0, 1.0]);
+		s.f32s(&[1.0, quarter.x, quarter.y, quarter.z, quarter.w]);
+		s.f32s(&[2.0, 0.0, 0.0, 0.0, 1.0]);
+		s.u8(0).u8(0).u16(2);
+		s.f32s(&[0.0, 0.0, 0.0, 0.0]);
+		s.f32s(&[2.0, 4.0, 0.0, 0.0]);
+
+		let mut skeleton = Skeleton::new();
+		let mut skins = [Skin::rigid(0); 3];
+		let end = skeleton.load(&s.0, 0, &mut skins).unwrap();
+		assert_eq!(end, s.0.len());
+		(skeleton, skins)
+	}
+
+	#[test]
+	fn parents_have_to_come_first() {
+		let mut s = Section::default();
+		s.u8(2);
+		s.u8(1).f32s(&[0.0, 0.0, 0.0]).f32s(&[0.0, 0.0, 0.0, 1.0]).f32s(&[1.0, 1.0, 1.0]);
+		s.u8(NO_PARENT).f32s(&[0.0, 1.0, 0.0]).f32s(&[0.0, 0.0, 0.0, 1.0]).f32s(&[1.0, 1.0, 1.0]);
+		s.u8(0);
+		assert_eq!(Skeleton::new().load(&s.0, 0, &mut []), Err(AnimationError::ParentAfterChild { bone: 0, parent: 1 }));
+
+		// A bone can't be its own parent either
+		let mut s = Section::default();
+		s.u8(1);
+		s.u8(0).f32s(&[0.0, 0.0, 0.0]).f32s(&[0.0, 0.0, 0.0, 1.0]).f32s(&[1.0, 1.0, 1.0]);
+		s.u8(0);
+		assert_eq!(Skeleton::new().load(&s.0, 0, &mut []), Err(AnimationError::ParentAfterChild { bone: 0, parent: 0 }));
+
+		let mut s = Section::default();
+		s.u8(MAX_BONES as u8 + 1);
+		assert_eq!(Skeleton::new().load(&s.0, 0, &mut []), Err(AnimationError::TooManyBones(MAX_BONES as u8 + 1)));
+	}
+
+	#[test]
+	fn slerp_follows_the_arc() {
+		let y = Vec3::new(0.0, 1.0, 0.0);
+		let a = Quaternion::IDENTITY;
+		let b = Quaternion::from_axis_angle(&y, PI * 0.5);
+		let half = Quaternion::slerp(&a, &b, 0.5);
+		let expected = Quaternion::from_axis_angle(&y, PI * 0.25);
+		assert!((half.dot(&expected) - 1.0).abs() < EPS);
+		// Both ends are reached exactly, and the shorter arc is taken for a negated quaternion
+		assert!((Quaternion::slerp(&a, &b, 1.0).dot(&b) - 1.0).abs() < EPS);
+		let negated = Quaternion::new(-b.x, -b.y, -b.z, -b.w);
+		assert!((Quaternion::slerp(&a, &negated, 0.5).dot(&expected).abs() - 1.0).abs() < EPS);
+	}
+
+	#[test]
+	fn inverse_bind_undoes_bind_pose() {
+		let m = transform_matrix(&Vec3::new(1.0, -2.0, 3.0), &Quaternion::from_axis_angle(&Vec3::new(0.0, 1.0, 0.0), 0.7), &Vec3::new(2.0, 2.0, 0.5));
+		let identity = m * m.inverse().unwrap();
+		for r in 0..4 {
+			for c in 0..4 {
+				let expected = if r == c { 1.0 } else { 0.0 };
+				assert!((identity.m[r][c] - expected).abs() < EPS);
+			}
+		}
+	}
+
+	#[test]
+	fn tracks_are_interpolated_and_held() {
+		let (skeleton, _) = arm();
+		let translation = &skeleton.tracks[1];
+		assert_eq!(translation.kind, TrackKind::Translation);
+		assert_near(skeleton.sample_track(translation, -1.0).xyz(), Vec3::new(0.0, 0.0, 0.0));
+		assert_near(skeleton.sample_track(translation, 0.5).xyz(), Vec3::new(1.0, 0.0, 0.0));
+		assert_near(skeleton.sample_track(translation, 3.0).xyz(), Vec3::new(4.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn skinning_follows_the_bones() {
+		let (mut skeleton, skins) = arm();
+		let tip = Vec4::xyz1(0.0, 2.0, 0.0);
+
+		// In bind pose every vertex stays where it is
+		skeleton.set_pose(0, 0.0);
+		assert_near(skeleton.skin_position(&skins[1], &tip).xyz(), tip.xyz());
+
+		// At one second the root moved by 2 along x and the child is turned a quarter around z, its tip points along -x
+		skeleton.set_pose(0, 1.0);
+		assert_near(skeleton.skin_position(&skins[0], &tip).xyz(), Vec3::new(2.0, 2.0, 0.0));
+		assert_near(skeleton.skin_position(&skins[1], &tip).xyz(), Vec3::new(1.0, 1.0, 0.0));
+		assert_near(skeleton.skin_direction(&skins[1], &Vec3::new(0.0, 1.0, 0.0)), Vec3::new(-1.0, 0.0, 0.0));
+
+		// Shared vertices land in between, and the clip loops after two seconds
+		let blended = skeleton.skin_position(&skins[2], &tip).xyz();
+		assert!((blended.x - 1.5).abs() < 0.01 && (blended.y - 1.5).abs() < 0.01);
+		skeleton.set_pose(0, 3.0);
+		assert_near(skeleton.skin_position(&skins[1], &tip).xyz(), Vec3::new(1.0, 1.0, 0.0));
+	}
+}
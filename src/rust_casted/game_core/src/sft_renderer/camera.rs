@@ -0,0 +1,254 @@
+use tiny_lib::{matrices::*, vector::*};
+use crate::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::controls::Controls;
+use crate::timing::TARGET_FPS;
+use core::f32::consts::PI;
+
+const DEFAULT_FOV:f32 = PI / 3f32;
+const MIN_FOV:f32 = PI / 18f32;
+const MAX_FOV:f32 = PI * 5f32 / 9f32;
+const DEFAULT_DISTANCE:f32 = 4f32;
+const MIN_DISTANCE:f32 = 1f32;
+const MAX_DISTANCE:f32 = 50f32;
+/// Looking straight up or down would make the up vector parallel to the view direction.
+const MAX_PITCH:f32 = PI * 0.49f32;
+/// Radians per pixel of pointer movement.
+const POINTER_SPEED:f32 = 0.005f32;
+/// Radians per second while an arrow key is held.
+const KEY_ROTATE_SPEED:f32 = 1.5f32;
+/// Units per second in free-fly mode.
+const FLY_SPEED:f32 = 3f32;
+/// Distance or fov is multiplied by this for every wheel step up.
+const WHEEL_ZOOM:f32 = 0.9f32;
+const PLANE_NEAR:f32 = 0.1f32;
+const PLANE_FAR:f32 = 1000f32;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+	/// Circles around `target`, dragging or arrow keys rotate and the wheel moves closer or further.
+	Orbit,
+	/// Arrow keys fly forward and sideways, dragging looks around and the wheel narrows or widens the fov.
+	FreeFly,
+}
+
+/// Left handed camera: x goes right, y up and z forward. Yaw 0 and pitch 0 look down +z.
+pub struct Camera {
+	mode:CameraMode,
+	/// Orbit pivot, the camera keeps looking at it.
+	target:Vec3,
+	/// Eye of the free-fly camera, derived from `target` and `distance` in orbit mode.
+	position:Vec3,
+	yaw:f32,
+	pitch:f32,
+	distance:f32,
+	/// Vertical field of view in radians.
+	fov:f32,
+	last_pointer:(f32, f32),
+	last_rmb:bool,
+	last_tick:Option<u32>,
+}
+
+impl Default for Camera {
+	fn default() -> Camera { Camera::new() }
+}
+
+impl Camera {
+	pub const fn new() -> Camera {
+		Camera {
+			mode: CameraMode::Orbit,
+			target: Vec3::new(0f32, 0f32, 0f32),
+			position: Vec3::new(0f32, 0f32, DEFAULT_DISTANCE),
+			// In front of the model, it faces -z
+			yaw: PI,
+			pitch: -0.3f32,
+			distance: DEFAULT_DISTANCE,
+			fov: DEFAULT_FOV,
+			last_pointer: (0f32, 0f32),
+			last_rmb: false,
+			last_tick: None,
+		}
+	}
+
+	#[inline(always)]
+	pub const fn mode(&self) -> CameraMode { self.mode }
+
+	/// Switching keeps the current view, free-fly starts at the orbit eye and orbit pivots `distance` ahead of it.
+	pub fn set_mode(&mut self, mode:CameraMode) {
+		if mode == self.mode {
+			return;
+		}
+		match mode {
+			CameraMode::FreeFly => self.position = self.position(),
+			CameraMode::Orbit => self.target = self.position + self.forward() * self.distance,
+		}
+		self.mode = mode;
+	}
+
+	/// Unit vector the camera looks along.
+	pub fn forward(&self) -> Vec3 {
+		let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+		let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+		Vec3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw)
+	}
+
+	/// Unit vector to the right of the view, always horizontal.
+	fn right(&self) -> Vec3 {
+		let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+		Vec3::new(cos_yaw, 0f32, -sin_yaw)
+	}
+
+	/// Eye position in world space.
+	pub fn position(&self) -> Vec3 {
+		match self.mode {
+			CameraMode::Orbit => self.target - self.forward() * self.distance,
+			CameraMode::FreeFly => self.position,
+		}
+	}
+
+	/// Moves the camera by the input gathered since the previous call, right mouse button toggles the mode.
+	pub fn update(&mut self, controls:&Controls, tick_number:u32) {
+		let pointer = (controls.pointer_precise_x(), controls.pointer_precise_y());
+		// A long stall should not throw the free-fly camera across the scene
+		let dt = match self.last_tick {
+			Some(last) => core::cmp::min(tick_number.wrapping_sub(last), TARGET_FPS as u32) as f32 / TARGET_FPS as f32,
+			None => 0f32,
+		};
+		let first = self.last_tick.is_none();
+		self.last_tick = Some(tick_number);
+
+		let rmb = controls.rmb.is_pressed();
+		if rmb && !self.last_rmb {
+			self.set_mode(match self.mode {
+				CameraMode::Orbit => CameraMode::FreeFly,
+				CameraMode::FreeFly => CameraMode::Orbit,
+			});
+		}
+		self.last_rmb = rmb;
+
+		// Pointer lock accumulates deltas into the pointer position, so both ways move it
+		if !first && (controls.is_pointer_locked() || controls.lmb.is_pressed()) {
+			self.yaw += (pointer.0 - self.last_pointer.0) * POINTER_SPEED;
+			self.pitch -= (pointer.1 - self.last_pointer.1) * POINTER_SPEED;
+		}
+		self.last_pointer = pointer;
+
+		let axis = |positive:bool, negative:bool| positive as i8 as f32 - negative as i8 as f32;
+		let vertical = axis(controls.arrow_up.is_pressed(), controls.arrow_down.is_pressed());
+		let horizontal = axis(controls.arrow_right.is_pressed(), controls.arrow_left.is_pressed());
+		let zoom = WHEEL_ZOOM.powf(controls.wheel());
+
+		match self.mode {
+			CameraMode::Orbit => {
+				self.yaw -= horizontal * KEY_ROTATE_SPEED * dt;
+				self.pitch -= vertical * KEY_ROTATE_SPEED * dt;
+				self.distance = (self.distance * zoom).clamp(MIN_DISTANCE, MAX_DISTANCE);
+			},
+			CameraMode::FreeFly => {
+				self.position += (self.forward() * vertical + self.right() * horizontal) * (FLY_SPEED * dt);
+				self.fov = (self.fov * zoom).clamp(MIN_FOV, MAX_FOV);
+			},
+		}
+		self.pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+		self.yaw %= 2f32 * PI;
+	}
+
+	/// World space --> view space.
+	pub fn view_matrix(&self) -> Matrix4x4 {
+		let position = self.position();
+		look_at_matrix(&position, &(position + self.forward()), &Vec3::new(0f32, 1f32, 0f32))
+	}
+
+	/// View space --> homogeneous clip space, z ends up in 0..w between the near and far plane and w is the view depth.
+	pub fn projection_matrix(&self) -> Matrix4x4 {
+		// The framebuffer is always SCREEN_WIDTH x SCREEN_HEIGHT. Hosts never stretch it: the desktop window
+		// can't be resized and the page scales the canvas by whole steps and letterboxes the rest,
+		// so pixels stay square and the aspect ratio is a constant
+		perspective_matrix(self.fov, SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32, PLANE_NEAR, PLANE_FAR)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::{multiply_vector_matrix, project_vector_matrix};
+	use crate::Input;
+
+	fn input(controls:&mut Controls, (x, y):(f32, f32), wheel:f32, lmb:bool, rmb:bool, up:bool, right:bool) {
+		controls.input_loop(&Input { abs_x: x, abs_y: y, wheel, lmb, rmb, up, right, ..Input::new() }, SCREEN_WIDTH, SCREEN_HEIGHT);
+	}
+
+	fn near(a:f32, b:f32) -> bool {
+		(a - b).abs() < 1e-4f32
+	}
+
+	#[test]
+	fn orbit_looks_at_the_target() {
+		let mut camera = Camera::new();
+		let mut controls = Controls::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+		input(&mut controls, (100f32, 100f32), 0f32, false, false, false, false);
+		camera.update(&controls, 0);
+		// Drag to the right and down, then zoom in by two wheel steps
+		input(&mut controls, (160f32, 120f32), 2f32, true, false, false, false);
+		camera.update(&controls, 1);
+
+		assert!(near(camera.yaw, PI + 60f32 * POINTER_SPEED));
+		assert!(near(camera.pitch, -0.3f32 - 20f32 * POINTER_SPEED));
+		assert!(near(camera.distance, DEFAULT_DISTANCE * WHEEL_ZOOM * WHEEL_ZOOM));
+
+		let target = multiply_vector_matrix(&Vec4::xyz1(0f32, 0f32, 0f32), &camera.view_matrix());
+		assert!(near(target.x, 0f32) && near(target.y, 0f32) && near(target.z, camera.distance));
+
+		// Moving the pointer without a button does not rotate
+		input(&mut controls, (300f32, 300f32), 0f32, false, false, false, false);
+		camera.update(&controls, 2);
+		assert!(near(camera.yaw, PI + 60f32 * POINTER_SPEED));
+	}
+
+	#[test]
+	fn projection_keeps_the_aspect_ratio() {
+		let camera = Camera::new();
+		let projection = camera.projection_matrix();
+		// A square in front of the camera spans the same amount of pixels in both directions
+		let corner = project_vector_matrix(&Vec4::xyz1(1f32, 1f32, 5f32), &projection);
+		let width = corner.x / corner.w * 0.5f32 * SCREEN_WIDTH as f32;
+		let height = corner.y / corner.w * 0.5f32 * SCREEN_HEIGHT as f32;
+		assert!(near(width, height));
+
+		let on_near = project_vector_matrix(&Vec4::xyz1(0f32, 0f32, PLANE_NEAR), &projection);
+		let on_far = project_vector_matrix(&Vec4::xyz1(0f32, 0f32, PLANE_FAR), &projection);
+		assert!(near(on_near.z, 0f32));
+		assert!((on_far.z - on_far.w).abs() < 1e-2f32);
+	}
+
+	#[test]
+	fn free_fly_moves_along_the_view() {
+		let mut camera = Camera::new();
+		let mut controls = Controls::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+		camera.update(&controls, 0);
+		let eye = camera.position();
+
+		input(&mut controls, (0f32, 0f32), 0f32, false, true, false, false);
+		camera.update(&controls, 1);
+		assert_eq!(camera.mode(), CameraMode::FreeFly);
+		assert!(near(camera.position().x, eye.x) && near(camera.position().z, eye.z));
+
+		// Holding the button does not toggle again, half a second of flying forward and right
+		let forward = camera.forward();
+		let right = camera.right();
+		input(&mut controls, (0f32, 0f32), 0f32, false, true, true, true);
+		camera.update(&controls, 1 + TARGET_FPS as u32 / 2);
+		assert_eq!(camera.mode(), CameraMode::FreeFly);
+		let expected = eye + (forward + right) * (FLY_SPEED * 0.5f32);
+		let position = camera.position();
+		assert!(near(position.x, expected.x) && near(position.y, expected.y) && near(position.z, expected.z));
+
+		// Going back to orbit keeps the view
+		input(&mut controls, (0f32, 0f32), 0f32, false, false, false, false);
+		camera.update(&controls, 100);
+		input(&mut controls, (0f32, 0f32), 0f32, false, true, false, false);
+		camera.update(&controls, 100);
+		assert_eq!(camera.mode(), CameraMode::Orbit);
+		let position = camera.position();
+		assert!(near(position.x, expected.x) && near(position.y, expected.y) && near(position.z, expected.z));
+	}
+}
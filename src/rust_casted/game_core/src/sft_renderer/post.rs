@@ -0,0 +1,239 @@
+use crate::{SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_SPACE, RGBA};
+
+/// Adds `image` to the running sum of the supersampling passes, the first pass overwrites it.
+pub fn accumulate(accumulation:&mut [[u16; 4]], image:&[RGBA], first:bool) {
+	for i in 0..SCREEN_SPACE as usize {
+		let p = image[i];
+		let sum = &mut accumulation[i];
+		if first {
+			*sum = [p.r as u16, p.g as u16, p.b as u16, p.a as u16];
+		} else {
+			sum[0] += p.r as u16;
+			sum[1] += p.g as u16;
+			sum[2] += p.b as u16;
+			sum[3] += p.a as u16;
+		}
+	}
+}
+
+/// Box filters the accumulated passes back into `image`.
+pub fn resolve(accumulation:&[[u16; 4]], image:&mut [RGBA], samples:u16) {
+	let half = samples / 2;
+	for i in 0..SCREEN_SPACE as usize {
+		let sum = accumulation[i];
+		image[i] = RGBA::rgba(
+			((sum[0] + half) / samples) as u8,
+			((sum[1] + half) / samples) as u8,
+			((sum[2] + half) / samples) as u8,
+			((sum[3] + half) / samples) as u8,
+		);
+	}
+}
+
+/// Replaces the colour of every pixel by its distance, white at the nearest and dark grey at the farthest point of the frame.
+/// `depth` holds 1/w, pixels without depth turn black. Alpha is kept as is.
+pub fn visualize_depth(image:&mut [RGBA], depth:&[f32]) {
+	let mut nearest = f32::MAX;
+	let mut farthest = 0f32;
+	for &d in depth.iter().filter(|&&d| d > 0f32) {
+		let distance = 1f32 / d;
+		nearest = nearest.min(distance);
+		farthest = farthest.max(distance);
+	}
+	let scale = if farthest > nearest { 1f32 / (farthest - nearest) } else { 0f32 };
+
+	for i in 0..SCREEN_SPACE as usize {
+		let p = &mut image[i];
+		let grey = if depth[i] > 0f32 {
+			let t = (1f32 / depth[i] - nearest) * scale;
+			(255f32 - t * 223f32 + 0.5f32) as u8
+		} else {
+			0
+		};
+		p.r = grey;
+		p.g = grey;
+		p.b = grey;
+	}
+}
+
+// FXAA, the simple variant of Timothy Lottes' pass: find edges by luma contrast
+// and blend along the edge direction with bilinear fetches
+const FXAA_EDGE_THRESHOLD:f32 = 1f32 / 8f32;
+const FXAA_EDGE_THRESHOLD_MIN:f32 = 1f32 / 16f32;
+const FXAA_REDUCE_MUL:f32 = 1f32 / 8f32;
+const FXAA_REDUCE_MIN:f32 = 1f32 / 128f32;
+const FXAA_SPAN_MAX:f32 = 8f32;
+
+#[inline(always)]
+fn luma(r:f32, g:f32, b:f32) -> f32 {
+	(r * 0.299f32 + g * 0.587f32 + b * 0.114f32) * (1f32 / 255f32)
+}
+
+#[inline(always)]
+fn pixel_luma(p:RGBA) -> f32 {
+	luma(p.r as f32, p.g as f32, p.b as f32)
+}
+
+/// Bilinear fetch of rgb at pixel coordinates, pixel centers are at .5 and the screen edge is clamped.
+#[inline(always)]
+fn fetch(src:&[RGBA], x:f32, y:f32) -> [f32; 3] {
+	let x = (x - 0.5f32).clamp(0f32, SCREEN_WIDTH as f32 - 1f32);
+	let y = (y - 0.5f32).clamp(0f32, SCREEN_HEIGHT as f32 - 1f32);
+	let x0 = x as usize;
+	let y0 = y as usize;
+	let x1 = core::cmp::min(x0 + 1, SCREEN_WIDTH as usize - 1);
+	let y1 = core::cmp::min(y0 + 1, SCREEN_HEIGHT as usize - 1);
+	let fx = x - x0 as f32;
+	let fy = y - y0 as f32;
+
+	let p00 = src[y0 * SCREEN_WIDTH as usize + x0];
+	let p10 = src[y0 * SCREEN_WIDTH as usize + x1];
+	let p01 = src[y1 * SCREEN_WIDTH as usize + x0];
+	let p11 = src[y1 * SCREEN_WIDTH as usize + x1];
+
+	let blend = |c00:u8, c10:u8, c01:u8, c11:u8| {
+		let top    = c00 as f32 + (c10 as f32 - c00 as f32) * fx;
+		let bottom = c01 as f32 + (c11 as f32 - c01 as f32) * fx;
+		top + (bottom - top) * fy
+	};
+	[
+		blend(p00.r, p10.r, p01.r, p11.r),
+		blend(p00.g, p10.g, p01.g, p11.g),
+		blend(p00.b, p10.b, p01.b, p11.b),
+	]
+}
+
+/// Smooths aliased edges of `image`, `scratch` receives a copy of the unfiltered frame. Alpha is kept as is.
+pub fn fxaa(image:&mut [RGBA], scratch:&mut [RGBA]) {
+	const W:usize = SCREEN_WIDTH as usize;
+	const H:usize = SCREEN_HEIGHT as usize;
+	scratch.copy_from_slice(image);
+	let src = &*scratch;
+
+	for y in 1..H - 1 {
+		for x in 1..W - 1 {
+			let idx = y * W + x;
+			let l_m = pixel_luma(src[idx]);
+			let l_n = pixel_luma(src[idx - W]);
+			let l_s = pixel_luma(src[idx + W]);
+			let l_w = pixel_luma(src[idx - 1]);
+			let l_e = pixel_luma(src[idx + 1]);
+
+			let min = l_m.min(l_n).min(l_s).min(l_w).min(l_e);
+			let max = l_m.max(l_n).max(l_s).max(l_w).max(l_e);
+			if max - min < FXAA_EDGE_THRESHOLD_MIN.max(max * FXAA_EDGE_THRESHOLD) {
+				continue;
+			}
+
+			let l_nw = pixel_luma(src[idx - W - 1]);
+			let l_ne = pixel_luma(src[idx - W + 1]);
+			let l_sw = pixel_luma(src[idx + W - 1]);
+			let l_se = pixel_luma(src[idx + W + 1]);
+			let min = min.min(l_nw).min(l_ne).min(l_sw).min(l_se);
+			let max = max.max(l_nw).max(l_ne).max(l_sw).max(l_se);
+
+			// Edge direction is perpendicular to the luma gradient
+			let dir_x = -((l_nw + l_ne) - (l_sw + l_se));
+			let dir_y =   (l_nw + l_sw) - (l_ne + l_se);
+			let dir_reduce = ((l_nw + l_ne + l_sw + l_se) * 0.25f32 * FXAA_REDUCE_MUL).max(FXAA_REDUCE_MIN);
+			let rcp_dir_min = 1f32 / (dir_x.abs().min(dir_y.abs()) + dir_reduce);
+			let dir_x = (dir_x * rcp_dir_min).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX);
+			let dir_y = (dir_y * rcp_dir_min).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX);
+
+			let cx = x as f32 + 0.5f32;
+			let cy = y as f32 + 0.5f32;
+			let a1 = fetch(src, cx + dir_x * (1f32 / 3f32 - 0.5f32), cy + dir_y * (1f32 / 3f32 - 0.5f32));
+			let a2 = fetch(src, cx + dir_x * (2f32 / 3f32 - 0.5f32), cy + dir_y * (2f32 / 3f32 - 0.5f32));
+			let b1 = fetch(src, cx - dir_x * 0.5f32, cy - dir_y * 0.5f32);
+			let b2 = fetch(src, cx + dir_x * 0.5f32, cy + dir_y * 0.5f32);
+
+			let mut rgb_a = [0f32; 3];
+			let mut rgb_b = [0f32; 3];
+			for c in 0..3 {
+				rgb_a[c] = 0.5f32 * (a1[c] + a2[c]);
+				rgb_b[c] = 0.5f32 * rgb_a[c] + 0.25f32 * (b1[c] + b2[c]);
+			}
+
+			// The wider fetch may have crossed another edge, fall back to the narrow one then
+			let l_b = luma(rgb_b[0], rgb_b[1], rgb_b[2]);
+			let rgb = if l_b < min || l_b > max { rgb_a } else { rgb_b };
+
+			let p = &mut image[idx];
+			p.r = (rgb[0] + 0.5f32) as u8;
+			p.g = (rgb[1] + 0.5f32) as u8;
+			p.b = (rgb[2] + 0.5f32) as u8;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn frame() -> Box<[RGBA]> {
+		vec![RGBA::zeroed(); SCREEN_SPACE as usize].into_boxed_slice()
+	}
+
+	#[test]
+	fn resolve_averages_the_passes() {
+		let mut accumulation = vec![[0u16; 4]; SCREEN_SPACE as usize].into_boxed_slice();
+		let mut image = frame();
+
+		for (pass, value) in [0u8, 255, 255, 0].iter().enumerate() {
+			image[7] = RGBA::rgba(*value, 10, 255 - *value, 0xFF);
+			accumulate(&mut accumulation, &image, pass == 0);
+		}
+		resolve(&accumulation, &mut image, 4);
+		let p = image[7];
+		assert_eq!((p.r, p.g, p.b, p.a), (128, 10, 128, 0xFF));
+		assert!(image[8].is_zero());
+	}
+
+	#[test]
+	fn depth_is_shaded_from_near_to_far() {
+		let mut image = frame();
+		let mut depth = vec![0f32; SCREEN_SPACE as usize].into_boxed_slice();
+		image[0] = RGBA::rgba(10, 20, 30, 0x80);
+		depth[1] = 1f32 / 2f32;
+		depth[2] = 1f32 / 6f32;
+		depth[3] = 1f32 / 10f32;
+		visualize_depth(&mut image, &depth);
+
+		assert_eq!((image[0].r, image[0].g, image[0].b, image[0].a), (0, 0, 0, 0x80));
+		assert_eq!(image[1].r, 255);
+		assert_eq!(image[2].r, 144);
+		assert_eq!(image[3].r, 32);
+	}
+
+	#[test]
+	fn fxaa_smooths_edges_only() {
+		const W:usize = SCREEN_WIDTH as usize;
+		let mut image = frame();
+		let mut scratch = frame();
+
+		// White below a shallow staircase diagonal
+		for y in 0..SCREEN_HEIGHT as usize {
+			for x in 0..W {
+				if y * 4 > x + 40 {
+					image[y * W + x] = RGBA::rgba(0xFF, 0xFF, 0xFF, 0xFF);
+				}
+			}
+		}
+		let original = image.clone();
+		fxaa(&mut image, &mut scratch);
+
+		let changed = (0..SCREEN_SPACE as usize).filter(|&i| image[i].r != original[i].r).count();
+		assert!(changed > 100, "only {} pixels were smoothed", changed);
+
+		// Flat areas stay untouched, and alpha is never filtered
+		assert_eq!(image[5].r, 0);
+		assert_eq!(image[(SCREEN_HEIGHT as usize - 2) * W + 5].r, 0xFF);
+		for i in 0..SCREEN_SPACE as usize {
+			assert_eq!(image[i].a, original[i].a);
+			if image[i].r != original[i].r {
+				let (x, y) = ((i % W) as i32, (i / W) as i32);
+				assert!((y * 4 - (x + 40)).abs() <= 8, "pixel {} {} is far from the edge", x, y);
+			}
+		}
+	}
+}
@@ -0,0 +1,198 @@
+use tiny_lib::{matrices::*, vector::*};
+use crate::{SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_SPACE};
+use super::{Triangle, rasterize_triangle_spans, multiply_vector_matrix};
+use super::span::{self, Lanes, F32Lanes};
+
+// The map shares the size of the screen, so it goes through the same rasterizer as the frame
+const W:usize = SCREEN_WIDTH as usize;
+const H:usize = SCREEN_HEIGHT as usize;
+
+/// Depth offset of every shadow caster, in depth units, about one texel of a surface at 45 degrees.
+const CONSTANT_BIAS:f32 = 1f32 / SCREEN_HEIGHT as f32;
+/// Depth offset per unit of depth slope, PCF samples one texel aside where a sloped surface is that much further.
+const SLOPE_BIAS:f32 = 1.5f32;
+/// Surfaces almost parallel to the light would otherwise be pushed through whatever is behind them.
+const MAX_BIAS:f32 = 16f32 / SCREEN_HEIGHT as f32;
+/// Taps of the percentage closer filter, a 3x3 box around the looked up texel.
+const PCF_RADIUS:i32 = 1;
+
+/// Depth of the scene as seen from a directional light. Depth is stored like the frame depth, greater is nearer
+/// to the light and 0 means nothing was drawn, but it comes from an orthographic projection, so it is linear.
+pub struct ShadowMap {
+	/// `SCREEN_SPACE` long after the first `fit`.
+	depth:Vec<f32>,
+	/// World space --> light view space.
+	view:Matrix4x4,
+	/// Half of the extent the map covers vertically, in world units.
+	radius:f32,
+}
+
+impl ShadowMap {
+	pub const fn new() -> ShadowMap {
+		ShadowMap {
+			depth: Vec::new(),
+			view: Matrix4x4::zeros(),
+			radius: 1f32,
+		}
+	}
+
+	/// Points the map along `direction`, which goes from the scene towards the light, fits it around the box `min`..`max` and clears it.
+	pub fn fit(&mut self, direction:&Vec3, min:&Vec3, max:&Vec3) {
+		let center = (*min + *max) * 0.5f32;
+		self.radius = ((*max - *min).len() * 0.5f32).max(f32::EPSILON);
+
+		let eye = center + *direction * self.radius;
+		// Any up vector works as long as it is not parallel to the light
+		let up = if direction.y.abs() > 0.99f32 { Vec3::new(0f32, 0f32, 1f32) } else { Vec3::new(0f32, 1f32, 0f32) };
+		self.view = look_at_matrix(&eye, &center, &up);
+
+		self.depth.clear();
+		self.depth.resize(SCREEN_SPACE as usize, 0f32);
+	}
+
+	/// World space --> map pixel x, y and depth. The bounding sphere ends up inside of the map, the depth in `0..=1`.
+	pub fn project(&self, position:&Vec4) -> Vec3 {
+		let view = multiply_vector_matrix(position, &self.view);
+		let inv_size = 0.5f32 / self.radius;
+		Vec3::new(
+			W as f32 * 0.5f32 + view.x * inv_size * H as f32,
+			H as f32 * (0.5f32 - view.y * inv_size),
+			1f32 - view.z * inv_size,
+		)
+	}
+
+	/// Draws the depth of a world space triangle, offset away from the light by its slope.
+	pub fn draw_triangle(&mut self, vertices:&[Vec4; 3]) {
+		let p = [self.project(&vertices[0]), self.project(&vertices[1]), self.project(&vertices[2])];
+
+		// Depth is affine over the map, its gradient tells how fast the surface goes away from the light
+		let (dx1, dy1, dz1) = (p[1].x - p[0].x, p[1].y - p[0].y, p[1].z - p[0].z);
+		let (dx2, dy2, dz2) = (p[2].x - p[0].x, p[2].y - p[0].y, p[2].z - p[0].z);
+		let area = dx1 * dy2 - dx2 * dy1;
+		if area == 0f32 {
+			return;
+		}
+		let slope_x = (dz1 * dy2 - dz2 * dy1) / area;
+		let slope_y = (dz2 * dx1 - dz1 * dx2) / area;
+		let bias = (CONSTANT_BIAS + SLOPE_BIAS * slope_x.abs().max(slope_y.abs())).min(MAX_BIAS);
+
+		let mut triangle = Triangle::zero_spaced_verticies();
+		for (i, point) in p.iter().enumerate() {
+			triangle.vertices[i] = Vec4::xyz1(point.x, point.y, 0f32);
+			*triangle.uvs[i].w() = point.z - bias;
+		}
+
+		#[cfg(target_arch = "x86_64")]
+		{
+			if span::has_avx2() {
+				return unsafe { self.draw_spans_avx2(&triangle) };
+			}
+		}
+		self.draw_spans::<span::Native>(&triangle);
+	}
+
+	#[cfg(target_arch = "x86_64")]
+	#[target_feature(enable = "avx2")]
+	unsafe fn draw_spans_avx2(&mut self, triangle:&Triangle) {
+		self.draw_spans::<span::Avx2>(triangle);
+	}
+
+	/// Depth of a triangle already projected onto the map, with lanes `L`.
+	#[inline(always)]
+	fn draw_spans<L:Lanes>(&mut self, triangle:&Triangle) {
+		let depth_0 = L::F32s::splat(triangle.uvs[0].ws());
+		let depth_d1 = L::F32s::splat(triangle.uvs[1].ws() - triangle.uvs[0].ws());
+		let depth_d2 = L::F32s::splat(triangle.uvs[2].ws() - triangle.uvs[0].ws());
+		let depth_buffer = &mut self.depth;
+		rasterize_triangle_spans::<L, _>(triangle, #[inline(always)] |pix_idx, mask, b1, b2| {
+			let depth = depth_0 + depth_d1 * b1 + depth_d2 * b2;
+			let mask = mask & depth.gt_mask(unsafe { L::F32s::load(depth_buffer, pix_idx) });
+			let depth = depth.to_array();
+			for i in 0..L::LANES {
+				if mask & (1 << i) != 0 {
+					depth_buffer[pix_idx + i] = depth[i];
+				}
+			}
+		});
+	}
+
+	/// Fraction of the taps around map position `x`, `y` which do not see anything nearer to the light than `depth`.
+	/// Everything outside of the map is lit.
+	pub fn visibility(&self, x:f32, y:f32, depth:f32) -> f32 {
+		let cx = x.floor() as i32;
+		let cy = y.floor() as i32;
+		let mut lit = 0u8;
+		for ty in cy - PCF_RADIUS..=cy + PCF_RADIUS {
+			for tx in cx - PCF_RADIUS..=cx + PCF_RADIUS {
+				if tx < 0 || ty < 0 || tx >= W as i32 || ty >= H as i32 || depth >= self.depth[ty as usize * W + tx as usize] {
+					lit += 1;
+				}
+			}
+		}
+		const TAPS:u8 = ((2 * PCF_RADIUS + 1) * (2 * PCF_RADIUS + 1)) as u8;
+		lit as f32 * (1f32 / TAPS as f32)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn quad(y:f32, half:f32) -> [[Vec4; 3]; 2] {
+		let a = Vec4::xyz1(-half, y, -half);
+		let b = Vec4::xyz1( half, y, -half);
+		let c = Vec4::xyz1( half, y,  half);
+		let d = Vec4::xyz1(-half, y,  half);
+		[[a, b, c], [a, c, d]]
+	}
+
+	#[test]
+	fn occluders_cast_soft_edged_shadows() {
+		// Light from straight above, a small quad floating over a large floor
+		let mut map = ShadowMap::new();
+		let direction = Vec3::new(0f32, 1f32, 0f32);
+		map.fit(&direction, &Vec3::new(-4f32, 0f32, -4f32), &Vec3::new(4f32, 2f32, 4f32));
+		for t in quad(0f32, 4f32).iter().chain(quad(2f32, 1f32).iter()) {
+			map.draw_triangle(t);
+		}
+
+		let lookup = |x:f32, z:f32| {
+			let p = map.project(&Vec4::xyz1(x, 0f32, z));
+			map.visibility(p.x, p.y, p.z)
+		};
+		assert_eq!(lookup(3f32, 3f32), 1f32, "open floor");
+		assert_eq!(lookup(0f32, 0f32), 0f32, "under the occluder");
+		assert_eq!(lookup(-0.3f32, 0.6f32), 0f32, "under the occluder");
+
+		// The occluder itself is lit, so is everything off the map
+		let top = map.project(&Vec4::xyz1(0f32, 2f32, 0f32));
+		assert_eq!(map.visibility(top.x, top.y, top.z), 1f32);
+		assert_eq!(map.visibility(-5f32, 3f32, 0f32), 1f32);
+
+		// PCF blends across the border of the shadow
+		let edge = map.project(&Vec4::xyz1(1f32, 0f32, 0f32));
+		let partial = map.visibility(edge.x, edge.y, edge.z);
+		assert!(partial > 0f32 && partial < 1f32, "visibility {} at the edge", partial);
+	}
+
+	#[test]
+	fn sloped_receivers_do_not_shadow_themselves() {
+		// A single plane at a steep angle to the light
+		let mut map = ShadowMap::new();
+		let direction = Vec3::new(0.2f32, 1f32, 0.1f32).normalize();
+		let plane = [
+			Vec4::xyz1(-3f32, -2.5f32, -3f32),
+			Vec4::xyz1( 3f32,  2.5f32, -3f32),
+			Vec4::xyz1( 0f32,  0f32,  3f32),
+		];
+		map.fit(&direction, &Vec3::new(-3f32, -2.5f32, -3f32), &Vec3::new(3f32, 2.5f32, 3f32));
+		map.draw_triangle(&plane);
+
+		for i in 1..20 {
+			let (b1, b2) = (i as f32 * 0.02f32, 0.3f32);
+			let p = plane[0] + (plane[1] - plane[0]) * b1 + (plane[2] - plane[0]) * b2;
+			let p = map.project(&p);
+			assert_eq!(map.visibility(p.x, p.y, p.z), 1f32, "sample {}", i);
+		}
+	}
+}
@@ -0,0 +1,283 @@
+// Lanes of the span renderer, the rasterizer walks `Lanes::LANES` pixels of a row at once. Coverage, the interpolation of
+// the attributes and the depth test run on all of them together, texture sampling, shadow lookups and blending stay per pixel.
+// SSE2 (always there on x86_64) and wasm simd128 give 4 lanes. AVX2 gives 8, it is not part of the x86_64 baseline, so
+// it is compiled in next to SSE2 and picked at runtime when the CPU has it. Every other target falls back to plain
+// arrays of 4 that the compiler is free to vectorize, tests build them on every target to check the others against.
+
+use core::ops::{Add, Sub, Mul, Div, BitOr, IndexMut};
+
+/// Lanes of the widest instruction set, rows have to be made of whole spans of it.
+pub const MAX_LANES:usize = 8;
+
+/// One instruction set, the span renderer is generic over it.
+pub trait Lanes {
+	const LANES:usize;
+	type I32s:I32Lanes<F32s = Self::F32s>;
+	type F32s:F32Lanes;
+}
+
+pub trait I32Lanes: Copy + Add<Output = Self> + Sub<Output = Self> + BitOr<Output = Self> {
+	type F32s;
+	fn splat(v:i32) -> Self;
+	/// `[0, step, 2 * step, ...]`
+	fn ramp(step:i32) -> Self;
+	/// Bit `i` is set when lane `i` is `>= 0`.
+	fn non_negative_mask(self) -> u32;
+	fn to_f32(self) -> Self::F32s;
+}
+
+pub trait F32Lanes: Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> {
+	type Array:Copy + IndexMut<usize, Output = f32>;
+	fn splat(v:f32) -> Self;
+	/// Loads the lanes starting at `idx` without bounds checks.
+	unsafe fn load(slice:&[f32], idx:usize) -> Self;
+	/// Bit `i` is set when lane `i` of `self` is greater than the one of `o`.
+	fn gt_mask(self, o:Self) -> u32;
+	fn to_array(self) -> Self::Array;
+}
+
+/// Whether the CPU running the game has AVX2, checked once and cached by std.
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+pub fn has_avx2() -> bool {
+	is_x86_feature_detected!("avx2")
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+	use core::arch::x86_64::*;
+	use core::ops::{Add, Sub, Mul, Div, BitOr};
+	use super::{Lanes, I32Lanes, F32Lanes};
+
+	/// The CPU has to have AVX2, see `has_avx2`. Code using it should be compiled with `#[target_feature(enable = "avx2")]`
+	/// so the intrinsics are inlined.
+	pub struct Avx2;
+	impl Lanes for Avx2 {
+		const LANES:usize = 8;
+		type I32s = I32s;
+		type F32s = F32s;
+	}
+
+	#[derive(Copy, Clone)]
+	pub struct I32s(__m256i);
+	#[derive(Copy, Clone)]
+	pub struct F32s(__m256);
+
+	impl I32Lanes for I32s {
+		type F32s = F32s;
+		#[inline(always)]
+		fn splat(v:i32) -> I32s { unsafe { I32s(_mm256_set1_epi32(v)) } }
+		#[inline(always)]
+		fn ramp(step:i32) -> I32s { unsafe { I32s(_mm256_setr_epi32(0, step, 2 * step, 3 * step, 4 * step, 5 * step, 6 * step, 7 * step)) } }
+		#[inline(always)]
+		fn non_negative_mask(self) -> u32 { unsafe { !(_mm256_movemask_ps(_mm256_castsi256_ps(self.0)) as u32) & 0xFF } }
+		#[inline(always)]
+		fn to_f32(self) -> F32s { unsafe { F32s(_mm256_cvtepi32_ps(self.0)) } }
+	}
+	impl Add for I32s { type Output = I32s; #[inline(always)] fn add(self, o:I32s) -> I32s { unsafe { I32s(_mm256_add_epi32(self.0, o.0)) } } }
+	impl Sub for I32s { type Output = I32s; #[inline(always)] fn sub(self, o:I32s) -> I32s { unsafe { I32s(_mm256_sub_epi32(self.0, o.0)) } } }
+	impl BitOr for I32s { type Output = I32s; #[inline(always)] fn bitor(self, o:I32s) -> I32s { unsafe { I32s(_mm256_or_si256(self.0, o.0)) } } }
+
+	impl F32Lanes for F32s {
+		type Array = [f32; 8];
+		#[inline(always)]
+		fn splat(v:f32) -> F32s { unsafe { F32s(_mm256_set1_ps(v)) } }
+		#[inline(always)]
+		unsafe fn load(slice:&[f32], idx:usize) -> F32s { F32s(_mm256_loadu_ps(slice.as_ptr().add(idx))) }
+		#[inline(always)]
+		fn gt_mask(self, o:F32s) -> u32 { unsafe { _mm256_movemask_ps(_mm256_cmp_ps(self.0, o.0, _CMP_GT_OQ)) as u32 } }
+		#[inline(always)]
+		fn to_array(self) -> [f32; 8] { unsafe { core::mem::transmute(self.0) } }
+	}
+	impl Add for F32s { type Output = F32s; #[inline(always)] fn add(self, o:F32s) -> F32s { unsafe { F32s(_mm256_add_ps(self.0, o.0)) } } }
+	impl Sub for F32s { type Output = F32s; #[inline(always)] fn sub(self, o:F32s) -> F32s { unsafe { F32s(_mm256_sub_ps(self.0, o.0)) } } }
+	impl Mul for F32s { type Output = F32s; #[inline(always)] fn mul(self, o:F32s) -> F32s { unsafe { F32s(_mm256_mul_ps(self.0, o.0)) } } }
+	impl Div for F32s { type Output = F32s; #[inline(always)] fn div(self, o:F32s) -> F32s { unsafe { F32s(_mm256_div_ps(self.0, o.0)) } } }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+	use core::arch::x86_64::*;
+	use core::ops::{Add, Sub, Mul, Div, BitOr};
+	use super::{Lanes, I32Lanes, F32Lanes};
+
+	pub struct Sse2;
+	impl Lanes for Sse2 {
+		const LANES:usize = 4;
+		type I32s = I32s;
+		type F32s = F32s;
+	}
+
+	#[derive(Copy, Clone)]
+	pub struct I32s(__m128i);
+	#[derive(Copy, Clone)]
+	pub struct F32s(__m128);
+
+	impl I32Lanes for I32s {
+		type F32s = F32s;
+		#[inline(always)]
+		fn splat(v:i32) -> I32s { unsafe { I32s(_mm_set1_epi32(v)) } }
+		#[inline(always)]
+		fn ramp(step:i32) -> I32s { unsafe { I32s(_mm_setr_epi32(0, step, 2 * step, 3 * step)) } }
+		#[inline(always)]
+		fn non_negative_mask(self) -> u32 { unsafe { !(_mm_movemask_ps(_mm_castsi128_ps(self.0)) as u32) & 0xF } }
+		#[inline(always)]
+		fn to_f32(self) -> F32s { unsafe { F32s(_mm_cvtepi32_ps(self.0)) } }
+	}
+	impl Add for I32s { type Output = I32s; #[inline(always)] fn add(self, o:I32s) -> I32s { unsafe { I32s(_mm_add_epi32(self.0, o.0)) } } }
+	impl Sub for I32s { type Output = I32s; #[inline(always)] fn sub(self, o:I32s) -> I32s { unsafe { I32s(_mm_sub_epi32(self.0, o.0)) } } }
+	impl BitOr for I32s { type Output = I32s; #[inline(always)] fn bitor(self, o:I32s) -> I32s { unsafe { I32s(_mm_or_si128(self.0, o.0)) } } }
+
+	impl F32Lanes for F32s {
+		type Array = [f32; 4];
+		#[inline(always)]
+		fn splat(v:f32) -> F32s { unsafe { F32s(_mm_set1_ps(v)) } }
+		#[inline(always)]
+		unsafe fn load(slice:&[f32], idx:usize) -> F32s { F32s(_mm_loadu_ps(slice.as_ptr().add(idx))) }
+		#[inline(always)]
+		fn gt_mask(self, o:F32s) -> u32 { unsafe { _mm_movemask_ps(_mm_cmpgt_ps(self.0, o.0)) as u32 } }
+		#[inline(always)]
+		fn to_array(self) -> [f32; 4] { unsafe { core::mem::transmute(self.0) } }
+	}
+	impl Add for F32s { type Output = F32s; #[inline(always)] fn add(self, o:F32s) -> F32s { unsafe { F32s(_mm_add_ps(self.0, o.0)) } } }
+	impl Sub for F32s { type Output = F32s; #[inline(always)] fn sub(self, o:F32s) -> F32s { unsafe { F32s(_mm_sub_ps(self.0, o.0)) } } }
+	impl Mul for F32s { type Output = F32s; #[inline(always)] fn mul(self, o:F32s) -> F32s { unsafe { F32s(_mm_mul_ps(self.0, o.0)) } } }
+	impl Div for F32s { type Output = F32s; #[inline(always)] fn div(self, o:F32s) -> F32s { unsafe { F32s(_mm_div_ps(self.0, o.0)) } } }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod simd128 {
+	use core::arch::wasm32::*;
+	use core::ops::{Add, Sub, Mul, Div, BitOr};
+	use super::{Lanes, I32Lanes, F32Lanes};
+
+	pub struct Simd128;
+	impl Lanes for Simd128 {
+		const LANES:usize = 4;
+		type I32s = I32s;
+		type F32s = F32s;
+	}
+
+	#[derive(Copy, Clone)]
+	pub struct I32s(v128);
+	#[derive(Copy, Clone)]
+	pub struct F32s(v128);
+
+	impl I32Lanes for I32s {
+		type F32s = F32s;
+		#[inline(always)]
+		fn splat(v:i32) -> I32s { I32s(i32x4_splat(v)) }
+		#[inline(always)]
+		fn ramp(step:i32) -> I32s { I32s(i32x4(0, step, 2 * step, 3 * step)) }
+		#[inline(always)]
+		fn non_negative_mask(self) -> u32 { !(i32x4_bitmask(self.0) as u32) & 0xF }
+		#[inline(always)]
+		fn to_f32(self) -> F32s { F32s(f32x4_convert_i32x4(self.0)) }
+	}
+	impl Add for I32s { type Output = I32s; #[inline(always)] fn add(self, o:I32s) -> I32s { I32s(i32x4_add(self.0, o.0)) } }
+	impl Sub for I32s { type Output = I32s; #[inline(always)] fn sub(self, o:I32s) -> I32s { I32s(i32x4_sub(self.0, o.0)) } }
+	impl BitOr for I32s { type Output = I32s; #[inline(always)] fn bitor(self, o:I32s) -> I32s { I32s(v128_or(self.0, o.0)) } }
+
+	impl F32Lanes for F32s {
+		type Array = [f32; 4];
+		#[inline(always)]
+		fn splat(v:f32) -> F32s { F32s(f32x4_splat(v)) }
+		#[inline(always)]
+		unsafe fn load(slice:&[f32], idx:usize) -> F32s { F32s(v128_load(slice.as_ptr().add(idx) as *const v128)) }
+		#[inline(always)]
+		fn gt_mask(self, o:F32s) -> u32 { i32x4_bitmask(f32x4_gt(self.0, o.0)) as u32 }
+		#[inline(always)]
+		fn to_array(self) -> [f32; 4] { unsafe { core::mem::transmute(self.0) } }
+	}
+	impl Add for F32s { type Output = F32s; #[inline(always)] fn add(self, o:F32s) -> F32s { F32s(f32x4_add(self.0, o.0)) } }
+	impl Sub for F32s { type Output = F32s; #[inline(always)] fn sub(self, o:F32s) -> F32s { F32s(f32x4_sub(self.0, o.0)) } }
+	impl Mul for F32s { type Output = F32s; #[inline(always)] fn mul(self, o:F32s) -> F32s { F32s(f32x4_mul(self.0, o.0)) } }
+	impl Div for F32s { type Output = F32s; #[inline(always)] fn div(self, o:F32s) -> F32s { F32s(f32x4_div(self.0, o.0)) } }
+}
+
+#[cfg(any(test, not(any(target_arch = "x86_64", all(target_arch = "wasm32", target_feature = "simd128")))))]
+mod scalar {
+	use core::ops::{Add, Sub, Mul, Div, BitOr};
+	use super::{Lanes, I32Lanes, F32Lanes};
+
+	const LANES:usize = 4;
+
+	pub struct Scalar;
+	impl Lanes for Scalar {
+		const LANES:usize = LANES;
+		type I32s = I32s;
+		type F32s = F32s;
+	}
+
+	#[derive(Copy, Clone)]
+	pub struct I32s([i32; LANES]);
+	#[derive(Copy, Clone)]
+	pub struct F32s([f32; LANES]);
+
+	macro_rules! lanewise {
+		($t:ident, $tr:ident, $f:ident, $op:tt) => {
+			impl $tr for $t {
+				type Output = $t;
+				#[inline(always)]
+				fn $f(self, o:$t) -> $t {
+					let mut r = self.0;
+					for i in 0..LANES { r[i] = self.0[i] $op o.0[i]; }
+					$t(r)
+				}
+			}
+		};
+	}
+
+	impl I32Lanes for I32s {
+		type F32s = F32s;
+		#[inline(always)]
+		fn splat(v:i32) -> I32s { I32s([v; LANES]) }
+		#[inline(always)]
+		fn ramp(step:i32) -> I32s { I32s([0, step, 2 * step, 3 * step]) }
+		#[inline(always)]
+		fn non_negative_mask(self) -> u32 {
+			let mut mask = 0;
+			for i in 0..LANES { mask |= ((self.0[i] >= 0) as u32) << i; }
+			mask
+		}
+		#[inline(always)]
+		fn to_f32(self) -> F32s {
+			F32s(self.0.map(|v| v as f32))
+		}
+	}
+	lanewise!(I32s, Add, add, +);
+	lanewise!(I32s, Sub, sub, -);
+	lanewise!(I32s, BitOr, bitor, |);
+
+	impl F32Lanes for F32s {
+		type Array = [f32; LANES];
+		#[inline(always)]
+		fn splat(v:f32) -> F32s { F32s([v; LANES]) }
+		#[inline(always)]
+		unsafe fn load(slice:&[f32], idx:usize) -> F32s { F32s(*(slice.as_ptr().add(idx) as *const [f32; LANES])) }
+		#[inline(always)]
+		fn gt_mask(self, o:F32s) -> u32 {
+			let mut mask = 0;
+			for i in 0..LANES { mask |= ((self.0[i] > o.0[i]) as u32) << i; }
+			mask
+		}
+		#[inline(always)]
+		fn to_array(self) -> [f32; LANES] { self.0 }
+	}
+	lanewise!(F32s, Add, add, +);
+	lanewise!(F32s, Sub, sub, -);
+	lanewise!(F32s, Mul, mul, *);
+	lanewise!(F32s, Div, div, /);
+}
+
+#[cfg(target_arch = "x86_64")]
+pub use avx2::Avx2;
+/// Lanes every CPU of the target has.
+#[cfg(target_arch = "x86_64")]
+pub use sse2::Sse2 as Native;
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub use simd128::Simd128 as Native;
+#[cfg(not(any(target_arch = "x86_64", all(target_arch = "wasm32", target_feature = "simd128"))))]
+pub use scalar::Scalar as Native;
+#[cfg(test)]
+pub use scalar::Scalar;
@@ -1,6 +1,6 @@
 use core::num::NonZeroU32;
 
-const TARGET_FPS:u8 = 100;
+pub const TARGET_FPS:u8 = 100;
 
 // const MAX_TICKS_PER_FRAME:u8 = 4;
 // const MAX_TICKS_PER_FRAME:u8 = 8;
@@ -28,13 +28,13 @@ impl Timing {
 	pub const fn new() -> Timing {
 		Timing {
 			tick_number: 0,
-			last_tick_time_stamp: core::i32::MIN,
+			last_tick_time_stamp: i32::MIN,
 		}
 	}
 	pub fn timing_loop(&mut self, time_stamp:i32) -> Option<Time> {
 		let mut delta;
 
-		if self.last_tick_time_stamp == core::i32::MIN {
+		if self.last_tick_time_stamp == i32::MIN {
 			self.last_tick_time_stamp = time_stamp;
 			delta = TARGET_SLEEP as i32;
 		} else {
@@ -57,16 +57,13 @@ impl Timing {
 			self.last_tick_time_stamp += ticks as i32 * TARGET_SLEEP as i32;
 		}
 
-		let ticks = match NonZeroU32::new(ticks) {
-			None => return None, // ticks = 0
-			Some(v) => v,
-		};
+		let ticks = NonZeroU32::new(ticks)?;
 
 		self.tick_number += ticks.get();
 
-		return Some(Time {
+		Some(Time {
 			ticks,
 			tick_number: self.tick_number,
-		});
+		})
 	}
 }
\ No newline at end of file
@@ -0,0 +1,73 @@
+use game_core::*;
+
+/// Runs `frames` ticks of the raycaster scene, 10ms apart, holding `input`.
+fn render(frames:i32, input:&Input) -> Vec<RGBA> {
+	let input = *input;
+	// The game is too large for the test thread stack
+	std::thread::Builder::new().stack_size(1 << 28).spawn(move || {
+		let mut state = Box::new(State::new());
+		state.set_scene(Scene::Raycaster);
+		state.init();
+
+		let mut image = vec![RGBA::zeroed(); SCREEN_SPACE as usize];
+		for frame in 0..frames {
+			state.tick(frame * 10, &input, &mut image);
+		}
+		image
+	}).unwrap().join().unwrap()
+}
+
+fn distinct_colors(image:&[RGBA]) -> usize {
+	let mut colors:Vec<u32> = image.iter().map(|c| c.to_rgb32()).collect();
+	colors.sort_unstable();
+	colors.dedup();
+	colors.len()
+}
+
+#[test]
+fn scenes_are_named() {
+	for &scene in Scene::ALL.iter() {
+		assert_eq!(Scene::from_name(scene.name()), Some(scene));
+	}
+	assert_eq!(Scene::from_name("demo"), None);
+	assert_eq!(State::new().scene(), Scene::Minecraft4k);
+}
+
+#[test]
+fn renders_a_frame_of_demo1() {
+	let image = render(1, &Input::new());
+
+	assert!(image.iter().all(|c| c.a == 0xFF));
+	// Walls, floor, sky and sprites with fog make for plenty of shades
+	assert!(distinct_colors(&image) > 16, "{} colors", distinct_colors(&image));
+
+	// The camera image fills the screen, there is no black border
+	let corners = [0, SCREEN_WIDTH as usize - 1, SCREEN_SPACE as usize - SCREEN_WIDTH as usize, SCREEN_SPACE as usize - 1];
+	assert!(corners.iter().any(|&i| image[i].to_rgb32() != 0));
+
+	// Every camera pixel covers a 4x4 block
+	for y in (0..SCREEN_HEIGHT as usize).step_by(4) {
+		for x in (0..SCREEN_WIDTH as usize).step_by(4) {
+			let color = image[y * SCREEN_WIDTH as usize + x].to_rgb32();
+			assert_eq!(image[(y + 3) * SCREEN_WIDTH as usize + x + 3].to_rgb32(), color);
+		}
+	}
+}
+
+#[test]
+fn walking_and_turning_change_the_view() {
+	let still = render(20, &Input::new());
+
+	let mut forward = Input::new();
+	forward.up = true;
+	let walked = render(20, &forward);
+
+	let mut turn = Input::new();
+	turn.right = true;
+	let turned = render(20, &turn);
+
+	let differs = |a:&[RGBA], b:&[RGBA]| a.iter().zip(b).filter(|(a, b)| a.to_rgb32() != b.to_rgb32()).count() > SCREEN_SPACE as usize / 20;
+	assert!(differs(&still, &walked));
+	assert!(differs(&still, &turned));
+	assert!(differs(&walked, &turned));
+}
@@ -0,0 +1,56 @@
+use game_core::*;
+
+/// Runs `frames` ticks of the software renderer scene, 10ms apart, holding `input`.
+fn render(frames:i32, input:&Input) -> Vec<RGBA> {
+	let mut state = State::new();
+	state.set_scene(Scene::SoftwareRenderer);
+	state.init();
+
+	let mut image = vec![RGBA::zeroed(); SCREEN_SPACE as usize];
+	for frame in 0..frames {
+		state.tick(frame * 10, input, &mut image);
+	}
+	image
+}
+
+/// Pixels the mesh covers, the background is cleared to transparent black.
+fn covered(image:&[RGBA]) -> usize {
+	image.iter().filter(|c| c.a == 0xFF).count()
+}
+
+#[test]
+fn renders_the_model() {
+	let image = render(1, &Input::new());
+
+	let covered = covered(&image);
+	assert!(covered > SCREEN_SPACE as usize / 50 && covered < SCREEN_SPACE as usize / 2, "{} pixels", covered);
+	// Lit and textured, not a flat silhouette
+	let mut colors:Vec<u32> = image.iter().filter(|c| c.a == 0xFF).map(|c| c.to_rgb32()).collect();
+	colors.sort_unstable();
+	colors.dedup();
+	assert!(colors.len() > 64, "{} colors", colors.len());
+}
+
+#[test]
+fn wheel_zooms_the_orbit_camera() {
+	let still = render(10, &Input::new());
+	let closer = render(10, &Input { wheel: 1f32, ..Input::new() });
+
+	assert!(covered(&closer) > covered(&still) * 3 / 2, "{} vs {}", covered(&closer), covered(&still));
+}
+
+/// Pixels whose colour differs between two frames.
+fn changed(a:&[RGBA], b:&[RGBA]) -> usize {
+	a.iter().zip(b).filter(|(a, b)| a.to_rgb32() != b.to_rgb32()).count()
+}
+
+#[test]
+fn view_key_cycles_the_debug_view() {
+	let still = render(1, &Input::new());
+
+	// Tab belongs to the raycaster demo, it leaves the view alone
+	assert_eq!(changed(&still, &render(1, &Input { tab: true, ..Input::new() })), 0);
+
+	let changed = changed(&still, &render(1, &Input { view: true, ..Input::new() }));
+	assert!(changed > SCREEN_SPACE as usize / 50, "{} pixels changed", changed);
+}
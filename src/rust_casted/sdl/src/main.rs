@@ -0,0 +1,152 @@
+use sdl2::EventPump;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::mouse::MouseUtil;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+use std::ptr::addr_of_mut;
+use std::time::Instant;
+
+use game_core::*;
+use game_core::platform::window_scale;
+
+static mut STATE:State = State::new();
+static mut IMAGE:[RGBA; SCREEN_SPACE as usize] = [RGBA::zeroed(); SCREEN_SPACE as usize];
+
+struct SdlPlatform<'t> {
+	canvas:WindowCanvas,
+	/// Streaming texture in the memory layout of `RGBA`, the frame is uploaded as is.
+	texture:Texture<'t>,
+	events:EventPump,
+	mouse:MouseUtil,
+	earliest_time:Instant,
+	/// Reported by the next `resized` call.
+	new_size:Option<(u32, u32)>,
+	scale:u32,
+	/// Where the editor saves to.
+	level_path:Option<String>,
+}
+
+impl Platform for SdlPlatform<'_> {
+	fn time(&self) -> i32 {
+		let time_stamp = Instant::now().duration_since(self.earliest_time).as_millis() as u32;
+		(i32::MIN as i64 + time_stamp as i64) as i32
+	}
+
+	fn poll(&mut self, input:&mut Input) -> bool {
+		input.space = false;
+		input.wheel = 0f32;
+		input.delta_x = 0f32;
+		input.delta_y = 0f32;
+		for event in self.events.poll_iter() {
+			match event {
+				Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => return false,
+				Event::KeyDown { keycode: Some(Keycode::Space), repeat: false, .. } => input.space = true,
+				Event::MouseWheel { y, .. } => input.wheel += y as f32,
+				// Relative motion keeps coming while the pointer is locked
+				Event::MouseMotion { xrel, yrel, .. } => {
+					input.delta_x += xrel as f32;
+					input.delta_y += yrel as f32;
+				},
+				Event::Window { win_event: WindowEvent::SizeChanged(width, height), .. } => {
+					self.new_size = Some((width as u32, height as u32));
+				},
+				_ => {},
+			}
+		}
+
+		let keyboard = self.events.keyboard_state();
+		input.up = keyboard.is_scancode_pressed(Scancode::W);
+		input.down = keyboard.is_scancode_pressed(Scancode::S);
+		input.left = keyboard.is_scancode_pressed(Scancode::A);
+		input.right = keyboard.is_scancode_pressed(Scancode::D);
+		input.tab = keyboard.is_scancode_pressed(Scancode::Tab);
+		input.view = keyboard.is_scancode_pressed(Scancode::V);
+
+		let mouse = self.events.mouse_state();
+		input.lmb = mouse.left();
+		input.rmb = mouse.right();
+		input.abs_x = mouse.x() as f32;
+		input.abs_y = mouse.y() as f32;
+		true
+	}
+
+	fn resized(&mut self) -> Option<(u32, u32)> {
+		let size = self.new_size.take()?;
+		self.scale = window_scale(size.0, size.1) as u32;
+		Some(size)
+	}
+
+	fn set_pointer_lock(&mut self, locked:bool) -> bool {
+		self.mouse.set_relative_mouse_mode(locked);
+		self.mouse.relative_mouse_mode()
+	}
+
+	fn save_level(&mut self, text:&str) -> bool {
+		match &self.level_path {
+			Some(path) => std::fs::write(path, text).map_err(|error| eprintln!("Saving {:?}: {}", path, error)).is_ok(),
+			None => false,
+		}
+	}
+
+	fn present(&mut self, image:&mut [RGBA]) {
+		// `RGBA` is four bytes in r, g, b, a order, which is what RGBA32 means on any endianness
+		let bytes = unsafe { core::slice::from_raw_parts(image.as_ptr() as *const u8, image.len() * 4) };
+		self.texture.update(None, bytes, SCREEN_WIDTH as usize * 4).unwrap();
+		let target = Rect::new(0, 0, SCREEN_WIDTH as u32 * self.scale, SCREEN_HEIGHT as u32 * self.scale);
+		self.canvas.clear();
+		self.canvas.copy(&self.texture, None, Some(target)).unwrap();
+		self.canvas.present();
+	}
+}
+
+fn main() {
+	// The scene is picked by name, e.g. `raycaster`
+	if let Some(name) = std::env::args().nth(1) {
+		match Scene::from_name(&name) {
+			Some(scene) => unsafe { (*addr_of_mut!(STATE)).set_scene(scene) },
+			None => eprintln!("Unknown scene {:?}, expected one of {:?}", name, Scene::ALL.iter().map(|scene| scene.name()).collect::<Vec<_>>()),
+		}
+	}
+	let level_path = unsafe { open_level(&mut *addr_of_mut!(STATE)) };
+
+	let sdl = sdl2::init().unwrap();
+	let video = sdl.video().unwrap();
+	let window = video.window("Rust Window", SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+		.position_centered()
+		.resizable()
+		.build()
+		.unwrap();
+	let canvas = window.into_canvas().build().unwrap();
+	let texture_creator = canvas.texture_creator();
+	let texture = texture_creator
+		.create_texture_streaming(PixelFormatEnum::RGBA32, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+		.unwrap();
+
+	let mut platform = SdlPlatform {
+		canvas,
+		texture,
+		events: sdl.event_pump().unwrap(),
+		mouse: sdl.mouse(),
+		earliest_time: Instant::now(),
+		new_size: None,
+		scale: 1,
+		level_path,
+	};
+	unsafe { run(&mut platform, &mut *addr_of_mut!(STATE), &mut *addr_of_mut!(IMAGE)) };
+}
+
+/// In the editor the second argument is the level file, it is opened when it exists and saved to.
+fn open_level(state:&mut State) -> Option<String> {
+	if state.scene() != Scene::Editor {
+		return None;
+	}
+	let path = std::env::args().nth(2).unwrap_or_else(|| "editor.level".to_string());
+	if let Ok(text) = std::fs::read_to_string(&path) {
+		if let Err(error) = state.open_level(&text) {
+			eprintln!("{}:{}", path, error);
+		}
+	}
+	Some(path)
+}
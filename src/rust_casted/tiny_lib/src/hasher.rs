@@ -0,0 +1,13 @@
+pub struct Hasher();
+
+impl Hasher {
+	/// Stateless 64 bit to 32 bit hash (splitmix64 finalizer).
+	#[inline(always)]
+	pub const fn hash_u64(seed:u64, value:u64) -> u32 {
+		let mut z = value.wrapping_add(seed).wrapping_add(0x9E37_79B9_7F4A_7C15);
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z = z ^ (z >> 31);
+		(z >> 32) as u32
+	}
+}
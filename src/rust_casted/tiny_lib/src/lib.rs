@@ -0,0 +1,9 @@
+// Math shared by the renderers: vectors, row-vector matrices, quaternions and a few float helpers.
+
+pub mod trig;
+pub mod util;
+pub mod vector;
+pub mod matrices;
+pub mod quaternion;
+pub mod prng;
+pub mod hasher;
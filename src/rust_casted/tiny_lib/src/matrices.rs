@@ -0,0 +1,319 @@
+use core::ops::Mul;
+use crate::vector::*;
+
+/// Row-major matrix, vectors are treated as rows and multiplied from the left, so translation lives in `m[3]`.
+/// `a * b` applies `a` first and then `b`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Matrix4x4 {
+	pub m:[[f32; 4]; 4],
+}
+
+impl Matrix4x4 {
+	pub const fn zeros() -> Matrix4x4 {
+		Matrix4x4 { m: [[0.0; 4]; 4] }
+	}
+	/// Identity matrix
+	pub const fn ones_cascade() -> Matrix4x4 {
+		Matrix4x4 {
+			m: [
+				[1.0, 0.0, 0.0, 0.0],
+				[0.0, 1.0, 0.0, 0.0],
+				[0.0, 0.0, 1.0, 0.0],
+				[0.0, 0.0, 0.0, 1.0],
+			]
+		}
+	}
+
+	pub fn transpose(&self) -> Matrix4x4 {
+		let mut matrix = Matrix4x4::zeros();
+		for r in 0..4 {
+			for c in 0..4 {
+				matrix.m[r][c] = self.m[c][r];
+			}
+		}
+		matrix
+	}
+
+	pub fn determinant(&self) -> f32 {
+		let (c, s) = self.cofactor_pairs();
+		s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+	}
+
+	/// General inverse, `None` when the matrix is singular.
+	pub fn inverse(&self) -> Option<Matrix4x4> {
+		let (c, s) = self.cofactor_pairs();
+		let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+		if det == 0.0 || !det.is_finite() {
+			return None;
+		}
+		let inv_det = 1.0 / det;
+		let m = &self.m;
+		Some(Matrix4x4 {
+			m: [
+				[
+					( m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3]) * inv_det,
+					(-m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3]) * inv_det,
+					( m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3]) * inv_det,
+					(-m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3]) * inv_det,
+				],
+				[
+					(-m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1]) * inv_det,
+					( m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1]) * inv_det,
+					(-m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1]) * inv_det,
+					( m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1]) * inv_det,
+				],
+				[
+					( m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0]) * inv_det,
+					(-m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0]) * inv_det,
+					( m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0]) * inv_det,
+					(-m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0]) * inv_det,
+				],
+				[
+					(-m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0]) * inv_det,
+					( m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0]) * inv_det,
+					(-m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0]) * inv_det,
+					( m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0]) * inv_det,
+				],
+			]
+		})
+	}
+
+	/// 2x2 determinants of the bottom (`c`) and top (`s`) row pairs, the inverse and determinant are built from them.
+	fn cofactor_pairs(&self) -> ([f32; 6], [f32; 6]) {
+		let m = &self.m;
+		let s = [
+			m[0][0] * m[1][1] - m[1][0] * m[0][1],
+			m[0][0] * m[1][2] - m[1][0] * m[0][2],
+			m[0][0] * m[1][3] - m[1][0] * m[0][3],
+			m[0][1] * m[1][2] - m[1][1] * m[0][2],
+			m[0][1] * m[1][3] - m[1][1] * m[0][3],
+			m[0][2] * m[1][3] - m[1][2] * m[0][3],
+		];
+		let c = [
+			m[2][0] * m[3][1] - m[3][0] * m[2][1],
+			m[2][0] * m[3][2] - m[3][0] * m[2][2],
+			m[2][0] * m[3][3] - m[3][0] * m[2][3],
+			m[2][1] * m[3][2] - m[3][1] * m[2][2],
+			m[2][1] * m[3][3] - m[3][1] * m[2][3],
+			m[2][2] * m[3][3] - m[3][2] * m[2][3],
+		];
+		(c, s)
+	}
+}
+
+impl Mul for &Matrix4x4 {
+	type Output = Matrix4x4;
+	fn mul(self, o:&Matrix4x4) -> Matrix4x4 {
+		let mut matrix = Matrix4x4::zeros();
+		for r in 0..4 {
+			for c in 0..4 {
+				matrix.m[r][c] =
+					self.m[r][0] * o.m[0][c] +
+					self.m[r][1] * o.m[1][c] +
+					self.m[r][2] * o.m[2][c] +
+					self.m[r][3] * o.m[3][c];
+			}
+		}
+		matrix
+	}
+}
+impl Mul for Matrix4x4 {
+	type Output = Matrix4x4;
+	#[inline(always)]
+	fn mul(self, o:Matrix4x4) -> Matrix4x4 { Mul::mul(&self, &o) }
+}
+
+/// Transforms a point, the implicit `w` is 1 and no perspective divide happens.
+impl Mul<&Matrix4x4> for &Vec3 {
+	type Output = Vec3;
+	fn mul(self, m:&Matrix4x4) -> Vec3 {
+		Vec3 {
+			x: self.x * m.m[0][0] + self.y * m.m[1][0] + self.z * m.m[2][0] + m.m[3][0],
+			y: self.x * m.m[0][1] + self.y * m.m[1][1] + self.z * m.m[2][1] + m.m[3][1],
+			z: self.x * m.m[0][2] + self.y * m.m[1][2] + self.z * m.m[2][2] + m.m[3][2],
+		}
+	}
+}
+impl Mul<&Matrix4x4> for &Vec4 {
+	type Output = Vec4;
+	fn mul(self, m:&Matrix4x4) -> Vec4 {
+		Vec4 {
+			x: self.x * m.m[0][0] + self.y * m.m[1][0] + self.z * m.m[2][0] + self.w * m.m[3][0],
+			y: self.x * m.m[0][1] + self.y * m.m[1][1] + self.z * m.m[2][1] + self.w * m.m[3][1],
+			z: self.x * m.m[0][2] + self.y * m.m[1][2] + self.z * m.m[2][2] + self.w * m.m[3][2],
+			w: self.x * m.m[0][3] + self.y * m.m[1][3] + self.z * m.m[2][3] + self.w * m.m[3][3],
+		}
+	}
+}
+
+pub fn rotation_matrix_x(angle:f32) -> Matrix4x4 {
+	let (s, c) = angle.sin_cos();
+	Matrix4x4 {
+		m: [
+			[1.0, 0.0, 0.0, 0.0],
+			[0.0,   c,   s, 0.0],
+			[0.0,  -s,   c, 0.0],
+			[0.0, 0.0, 0.0, 1.0],
+		]
+	}
+}
+pub fn rotation_matrix_y(angle:f32) -> Matrix4x4 {
+	let (s, c) = angle.sin_cos();
+	Matrix4x4 {
+		m: [
+			[  c, 0.0,  -s, 0.0],
+			[0.0, 1.0, 0.0, 0.0],
+			[  s, 0.0,   c, 0.0],
+			[0.0, 0.0, 0.0, 1.0],
+		]
+	}
+}
+pub fn rotation_matrix_z(angle:f32) -> Matrix4x4 {
+	let (s, c) = angle.sin_cos();
+	Matrix4x4 {
+		m: [
+			[  c,   s, 0.0, 0.0],
+			[ -s,   c, 0.0, 0.0],
+			[0.0, 0.0, 1.0, 0.0],
+			[0.0, 0.0, 0.0, 1.0],
+		]
+	}
+}
+
+pub fn transition_matrix(offset:&Vec3) -> Matrix4x4 {
+	let mut matrix = Matrix4x4::ones_cascade();
+	matrix.m[3][0] = offset.x;
+	matrix.m[3][1] = offset.y;
+	matrix.m[3][2] = offset.z;
+	matrix
+}
+
+pub fn scale_matrix(scale:&Vec3) -> Matrix4x4 {
+	let mut matrix = Matrix4x4::ones_cascade();
+	matrix.m[0][0] = scale.x;
+	matrix.m[1][1] = scale.y;
+	matrix.m[2][2] = scale.z;
+	matrix
+}
+
+/// World space --> view space of a left-handed camera at `eye` looking at `target`: x goes right, y up and z forward.
+/// `up` only has to be not parallel to the view direction.
+pub fn look_at_matrix(eye:&Vec3, target:&Vec3, up:&Vec3) -> Matrix4x4 {
+	let forward = (*target - *eye).normalize();
+	let right = Vec3::cross_product(up, &forward).normalize();
+	let up = Vec3::cross_product(&forward, &right);
+	Matrix4x4 {
+		m: [
+			[right.x, up.x, forward.x, 0.0],
+			[right.y, up.y, forward.y, 0.0],
+			[right.z, up.z, forward.z, 0.0],
+			[-right.dot(eye), -up.dot(eye), -forward.dot(eye), 1.0],
+		]
+	}
+}
+
+/// View space --> homogeneous clip space. `fov` is vertical, in radians. `w` of the result is the view depth
+/// and `z` ends up in `0..w` between the near and far plane.
+pub fn perspective_matrix(fov:f32, aspect_ratio:f32, plane_near:f32, plane_far:f32) -> Matrix4x4 {
+	let focal = 1.0 / (fov * 0.5).tan();
+	let mut matrix = Matrix4x4::zeros();
+	matrix.m[0][0] = focal / aspect_ratio;
+	matrix.m[1][1] = focal;
+	matrix.m[2][2] = plane_far / (plane_far - plane_near);
+	matrix.m[3][2] = -plane_far * plane_near / (plane_far - plane_near);
+	matrix.m[2][3] = 1.0;
+	matrix
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::prng::RNG;
+
+	fn near(a:&Matrix4x4, b:&Matrix4x4, epsilon:f32) -> bool {
+		a.m.iter().flatten().zip(b.m.iter().flatten()).all(|(a, b)| (a - b).abs() <= epsilon)
+	}
+
+	fn random_vec3(rng:&mut RNG, min:f32, max:f32) -> Vec3 {
+		Vec3::new(rng.get_f32_range(min, max), rng.get_f32_range(min, max), rng.get_f32_range(min, max))
+	}
+
+	/// Random scale, rotation and translation, never singular.
+	fn random_transform(rng:&mut RNG) -> Matrix4x4 {
+		scale_matrix(&random_vec3(rng, 0.25, 4.0))
+			* rotation_matrix_x(rng.get_f32_range(-3.0, 3.0))
+			* rotation_matrix_y(rng.get_f32_range(-3.0, 3.0))
+			* rotation_matrix_z(rng.get_f32_range(-3.0, 3.0))
+			* transition_matrix(&random_vec3(rng, -10.0, 10.0))
+	}
+
+	#[test]
+	fn products_apply_left_to_right() {
+		let mut rng = RNG::new(1);
+		for _ in 0..100 {
+			let (a, b) = (random_transform(&mut rng), random_transform(&mut rng));
+			let v = random_vec3(&mut rng, -10.0, 10.0);
+			let one_by_one = &(&v * &a) * &b;
+			let combined = &v * &(a * b);
+			assert!((one_by_one - combined).len() < 1e-3, "{:?} != {:?}", one_by_one, combined);
+		}
+
+		// Rotate a quarter turn around z, then move along x
+		let m = rotation_matrix_z(core::f32::consts::FRAC_PI_2) * transition_matrix(&Vec3::new(5.0, 0.0, 0.0));
+		let p = &Vec3::new(1.0, 0.0, 0.0) * &m;
+		assert!((p - Vec3::new(5.0, 1.0, 0.0)).len() < 1e-6);
+	}
+
+	#[test]
+	fn inverse_undoes_the_transform() {
+		let identity = Matrix4x4::ones_cascade();
+		let mut rng = RNG::new(2);
+		for _ in 0..100 {
+			let m = random_transform(&mut rng);
+			let inverse = m.inverse().unwrap();
+			assert!(near(&(m * inverse), &identity, 1e-4), "{:?}", m);
+			assert!(near(&(inverse * m), &identity, 1e-4), "{:?}", m);
+			assert!((m.determinant() * inverse.determinant() - 1.0).abs() < 1e-3);
+			assert_eq!(m.transpose().transpose(), m);
+		}
+
+		// Projections are invertible too, only the depth range is lost in the divide
+		let projection = perspective_matrix(1.0, 1.5, 0.1, 100.0);
+		assert!(near(&(projection * projection.inverse().unwrap()), &identity, 1e-4));
+
+		assert_eq!(scale_matrix(&Vec3::new(1.0, 0.0, 1.0)).inverse(), None);
+		assert_eq!(Matrix4x4::zeros().determinant(), 0.0);
+	}
+
+	#[test]
+	fn look_at_puts_the_target_straight_ahead() {
+		let mut rng = RNG::new(3);
+		let up = Vec3::new(0.0, 1.0, 0.0);
+		for _ in 0..100 {
+			let eye = random_vec3(&mut rng, -10.0, 10.0);
+			let target = random_vec3(&mut rng, -10.0, 10.0);
+			let view = look_at_matrix(&eye, &target, &up);
+
+			assert!((&eye * &view).len() < 1e-4);
+			let ahead = &target * &view;
+			assert!(ahead.x.abs() < 1e-3 && ahead.y.abs() < 1e-3);
+			assert!((ahead.z - (target - eye).len()).abs() < 1e-3);
+			// No roll, right stays horizontal
+			assert!(view.m[1][0].abs() < 1e-5);
+		}
+	}
+
+	#[test]
+	fn perspective_maps_the_planes_to_the_depth_range() {
+		let projection = perspective_matrix(core::f32::consts::FRAC_PI_2, 2.0, 0.5, 50.0);
+		let on_near = &Vec4::xyz1(0.0, 0.0, 0.5) * &projection;
+		let on_far = &Vec4::xyz1(0.0, 0.0, 50.0) * &projection;
+		assert!(on_near.z.abs() < 1e-6 && (on_near.w - 0.5).abs() < 1e-6);
+		assert!((on_far.z / on_far.w - 1.0).abs() < 1e-6);
+
+		// The edges of a 90 degree frustum end up on the border of clip space
+		let corner = &Vec4::xyz1(4.0, 2.0, 2.0) * &projection;
+		assert!((corner.x / corner.w - 1.0).abs() < 1e-6);
+		assert!((corner.y / corner.w - 1.0).abs() < 1e-6);
+	}
+}
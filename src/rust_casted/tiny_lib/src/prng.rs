@@ -0,0 +1,52 @@
+/// xorshift32
+pub struct RNG {
+	state:u32,
+}
+
+pub static mut GLOBAL_PRNG:RNG = RNG::new(18295169);
+
+impl RNG {
+	pub const fn new(seed:u32) -> RNG {
+		RNG { state: if seed == 0 { 0x9E37_79B9 } else { seed } }
+	}
+	#[inline(always)]
+	pub fn get_u32(&mut self) -> u32 {
+		let mut x = self.state;
+		x ^= x << 13;
+		x ^= x >> 17;
+		x ^= x << 5;
+		self.state = x;
+		x
+	}
+	/// Returns `[0, 1)`
+	#[inline(always)]
+	pub fn get_f32(&mut self) -> f32 {
+		(self.get_u32() >> 8) as f32 / (1u32 << 24) as f32
+	}
+	/// Returns `[min, max)`
+	#[inline(always)]
+	pub fn get_f32_range(&mut self, min:f32, max:f32) -> f32 {
+		min + (max - min) * self.get_f32()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn floats_stay_in_range() {
+		let mut rng = RNG::new(0);
+		let (mut low, mut high) = (false, false);
+		for _ in 0..10_000 {
+			let f = rng.get_f32();
+			assert!((0.0..1.0).contains(&f));
+			low |= f < 0.01;
+			high |= f > 0.99;
+			let r = rng.get_f32_range(-2.0, 3.0);
+			assert!((-2.0..3.0).contains(&r));
+		}
+		assert!(low && high);
+		assert_eq!(RNG::new(7).get_u32(), RNG::new(7).get_u32());
+	}
+}
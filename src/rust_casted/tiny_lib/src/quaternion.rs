@@ -0,0 +1,186 @@
+use core::ops::Mul;
+use crate::vector::*;
+use crate::matrices::Matrix4x4;
+
+/// Rotation as a unit quaternion, `w` is the scalar part.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Quaternion {
+	pub x:f32,
+	pub y:f32,
+	pub z:f32,
+	pub w:f32,
+}
+
+impl Quaternion {
+	pub const IDENTITY:Quaternion = Quaternion::new(0.0, 0.0, 0.0, 1.0);
+
+	#[inline(always)]
+	pub const fn new(x:f32, y:f32, z:f32, w:f32) -> Quaternion {
+		Quaternion { x, y, z, w }
+	}
+
+	/// Rotation by `angle` radians around the unit vector `axis`.
+	pub fn from_axis_angle(axis:&Vec3, angle:f32) -> Quaternion {
+		let (sin, cos) = (angle * 0.5).sin_cos();
+		Quaternion::new(axis.x * sin, axis.y * sin, axis.z * sin, cos)
+	}
+
+	#[inline(always)]
+	pub fn dot(&self, o:&Quaternion) -> f32 {
+		self.x * o.x + self.y * o.y + self.z * o.z + self.w * o.w
+	}
+
+	#[inline(always)]
+	pub fn len(&self) -> f32 {
+		self.dot(self).sqrt()
+	}
+
+	/// Unit length, a zero quaternion becomes the identity.
+	pub fn normalize(self) -> Quaternion {
+		let len = self.len();
+		if len == 0.0 {
+			return Quaternion::IDENTITY;
+		}
+		let inv = 1.0 / len;
+		Quaternion::new(self.x * inv, self.y * inv, self.z * inv, self.w * inv)
+	}
+
+	/// Inverse rotation of a unit quaternion.
+	#[inline(always)]
+	pub const fn conjugate(&self) -> Quaternion {
+		Quaternion::new(-self.x, -self.y, -self.z, self.w)
+	}
+
+	/// Rotates `v` by a unit quaternion.
+	pub fn rotate(&self, v:&Vec3) -> Vec3 {
+		// v + 2w(q x v) + 2q x (q x v)
+		let q = Vec3::new(self.x, self.y, self.z);
+		let t = Vec3::cross_product(&q, v) * 2.0;
+		*v + t * self.w + Vec3::cross_product(&q, &t)
+	}
+
+	/// Spherical interpolation along the shorter arc, nearly equal rotations fall back to a normalized lerp.
+	pub fn slerp(a:&Quaternion, b:&Quaternion, t:f32) -> Quaternion {
+		let mut cos = a.dot(b);
+		let mut b = *b;
+		if cos < 0.0 {
+			cos = -cos;
+			b = Quaternion::new(-b.x, -b.y, -b.z, -b.w);
+		}
+
+		let (wa, wb) = if cos > 0.9995 {
+			(1.0 - t, t)
+		} else {
+			let angle = cos.acos();
+			let inv_sin = 1.0 / angle.sin();
+			(((1.0 - t) * angle).sin() * inv_sin, (t * angle).sin() * inv_sin)
+		};
+		Quaternion::new(
+			a.x * wa + b.x * wb,
+			a.y * wa + b.y * wb,
+			a.z * wa + b.z * wb,
+			a.w * wa + b.w * wb,
+		).normalize()
+	}
+
+	/// Rotation matrix of a unit quaternion for row vectors, every row is the image of a basis vector.
+	pub fn to_matrix(&self) -> Matrix4x4 {
+		let Quaternion { x, y, z, w } = *self;
+		Matrix4x4 {
+			m: [
+				[1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + w * z), 2.0 * (x * z - w * y), 0.0],
+				[2.0 * (x * y - w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + w * x), 0.0],
+				[2.0 * (x * z + w * y), 2.0 * (y * z - w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+				[0.0, 0.0, 0.0, 1.0],
+			]
+		}
+	}
+}
+
+/// Hamilton product, `a * b` rotates by `b` first and then by `a`. Note that matrices compose the other way around.
+impl Mul for Quaternion {
+	type Output = Quaternion;
+	fn mul(self, o:Quaternion) -> Quaternion {
+		Quaternion::new(
+			self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+			self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+			self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+			self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::prng::RNG;
+	use crate::matrices::rotation_matrix_y;
+
+	fn random_rotation(rng:&mut RNG) -> Quaternion {
+		let axis = Vec3::new(rng.get_f32_range(-1.0, 1.0), rng.get_f32_range(-1.0, 1.0), rng.get_f32_range(-1.0, 1.0) + 2.0).normalize();
+		Quaternion::from_axis_angle(&axis, rng.get_f32_range(-3.0, 3.0))
+	}
+
+	fn random_vec3(rng:&mut RNG) -> Vec3 {
+		Vec3::new(rng.get_f32_range(-5.0, 5.0), rng.get_f32_range(-5.0, 5.0), rng.get_f32_range(-5.0, 5.0))
+	}
+
+	#[test]
+	fn rotation_agrees_with_the_matrix() {
+		let mut rng = RNG::new(4);
+		for _ in 0..100 {
+			let q = random_rotation(&mut rng);
+			let v = random_vec3(&mut rng);
+			let by_quaternion = q.rotate(&v);
+			let by_matrix = &v * &q.to_matrix();
+			assert!((by_quaternion - by_matrix).len() < 1e-4, "{:?} != {:?}", by_quaternion, by_matrix);
+			assert!((by_quaternion.len() - v.len()).abs() < 1e-4);
+			assert!((q.conjugate().rotate(&by_quaternion) - v).len() < 1e-4);
+		}
+
+		// Same handedness as the matrix builders
+		let q = Quaternion::from_axis_angle(&Vec3::new(0.0, 1.0, 0.0), 0.7);
+		let m = rotation_matrix_y(0.7);
+		let v = Vec3::new(1.0, 2.0, 3.0);
+		assert!((q.rotate(&v) - &v * &m).len() < 1e-5);
+	}
+
+	#[test]
+	fn products_compose_rotations() {
+		let mut rng = RNG::new(5);
+		for _ in 0..100 {
+			let (a, b) = (random_rotation(&mut rng), random_rotation(&mut rng));
+			let v = random_vec3(&mut rng);
+			assert!(((a * b).rotate(&v) - a.rotate(&b.rotate(&v))).len() < 1e-4);
+			assert!((&v * &(b.to_matrix() * a.to_matrix()) - (a * b).rotate(&v)).len() < 1e-4);
+			assert!(((a * b).len() - 1.0).abs() < 1e-5);
+		}
+		assert_eq!(Quaternion::IDENTITY * Quaternion::IDENTITY, Quaternion::IDENTITY);
+	}
+
+	#[test]
+	fn slerp_stays_on_the_shorter_arc() {
+		let mut rng = RNG::new(6);
+		for _ in 0..100 {
+			let (a, b) = (random_rotation(&mut rng), random_rotation(&mut rng));
+			let start = Quaternion::slerp(&a, &b, 0.0);
+			let end = Quaternion::slerp(&a, &b, 1.0);
+			assert!((start.dot(&a) - 1.0).abs() < 1e-4);
+			// `b` and `-b` are the same rotation
+			assert!((end.dot(&b).abs() - 1.0).abs() < 1e-4);
+
+			let t = rng.get_f32();
+			let mid = Quaternion::slerp(&a, &b, t);
+			assert!((mid.len() - 1.0).abs() < 1e-5);
+			// The angle travelled grows linearly with t
+			let total = a.dot(&b).abs().min(1.0).acos();
+			let travelled = a.dot(&mid).abs().min(1.0).acos();
+			assert!((travelled - total * t).abs() < 1e-2, "{} of {} at {}", travelled, total, t);
+		}
+
+		let half = Quaternion::slerp(&Quaternion::IDENTITY, &Quaternion::from_axis_angle(&Vec3::new(1.0, 0.0, 0.0), 2.0), 0.5);
+		let expected = Quaternion::from_axis_angle(&Vec3::new(1.0, 0.0, 0.0), 1.0);
+		assert!((half.dot(&expected) - 1.0).abs() < 1e-6);
+		assert_eq!(Quaternion::new(0.0, 0.0, 0.0, 0.0).normalize(), Quaternion::IDENTITY);
+	}
+}
@@ -0,0 +1,2 @@
+pub const PI:f32 = core::f32::consts::PI;
+pub const TAU:f32 = core::f32::consts::TAU;
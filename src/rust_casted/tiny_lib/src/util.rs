@@ -0,0 +1,105 @@
+use core::ops::{Add, AddAssign, Sub};
+
+pub trait FloatUtil: Copy {
+	fn lerp(from:Self, to:Self, t:Self) -> Self;
+	/// Linear interpolation between `edge0` and `edge1`, clamped to `[0, 1]`.
+	fn linearstep(edge0:Self, edge1:Self, x:Self) -> Self;
+	/// Hermite interpolation between `edge0` and `edge1`, clamped to `[0, 1]`.
+	fn smoothstep(edge0:Self, edge1:Self, x:Self) -> Self;
+	fn sq(self) -> Self;
+}
+
+impl FloatUtil for f32 {
+	#[inline(always)]
+	fn lerp(from:f32, to:f32, t:f32) -> f32 {
+		from + (to - from) * t
+	}
+	#[inline(always)]
+	fn linearstep(edge0:f32, edge1:f32, x:f32) -> f32 {
+		((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0)
+	}
+	#[inline(always)]
+	fn smoothstep(edge0:f32, edge1:f32, x:f32) -> f32 {
+		let t = f32::linearstep(edge0, edge1, x);
+		t * t * (3.0 - 2.0 * t)
+	}
+	#[inline(always)]
+	fn sq(self) -> f32 {
+		self * self
+	}
+}
+
+/// Either `+1` or `-1`, usable as a step direction.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum NonZeroSignum {
+	POS,
+	NEG,
+}
+impl NonZeroSignum {
+	#[inline(always)]
+	pub const fn is_negative(self) -> bool {
+		match self {
+			NonZeroSignum::POS => false,
+			NonZeroSignum::NEG => true,
+		}
+	}
+	#[inline(always)]
+	pub const fn is_positive(self) -> bool {
+		!self.is_negative()
+	}
+	#[inline(always)]
+	pub const fn as_i8(self) -> i8 {
+		match self {
+			NonZeroSignum::POS =>  1,
+			NonZeroSignum::NEG => -1,
+		}
+	}
+}
+
+macro_rules! signum_ops {
+	( $( $t:ty ),+ ) => {
+		$(
+			impl Add<NonZeroSignum> for $t {
+				type Output = $t;
+				#[inline(always)]
+				fn add(self, rhs:NonZeroSignum) -> $t { self + rhs.as_i8() as $t }
+			}
+			impl Sub<NonZeroSignum> for $t {
+				type Output = $t;
+				#[inline(always)]
+				fn sub(self, rhs:NonZeroSignum) -> $t { self - rhs.as_i8() as $t }
+			}
+			impl AddAssign<NonZeroSignum> for $t {
+				#[inline(always)]
+				fn add_assign(&mut self, rhs:NonZeroSignum) { *self += rhs.as_i8() as $t }
+			}
+		)+
+	}
+}
+signum_ops![i8, i16, i32, i64, isize];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn steps_are_clamped() {
+		assert_eq!(f32::linearstep(1.0, 3.0, 0.0), 0.0);
+		assert_eq!(f32::linearstep(1.0, 3.0, 2.0), 0.5);
+		assert_eq!(f32::linearstep(1.0, 3.0, 5.0), 1.0);
+		assert_eq!(f32::smoothstep(1.0, 3.0, 2.0), 0.5);
+		assert!(f32::smoothstep(1.0, 3.0, 1.5) < 0.25);
+		assert_eq!(f32::lerp(2.0, 4.0, 0.25), 2.5);
+		assert_eq!((-3f32).sq(), 9.0);
+	}
+
+	#[test]
+	fn signum_steps_integers() {
+		let mut x = 5i32;
+		x += NonZeroSignum::NEG;
+		assert_eq!(x, 4);
+		assert_eq!(x + NonZeroSignum::POS, 5);
+		assert_eq!(x - NonZeroSignum::POS, 3);
+		assert!(NonZeroSignum::NEG.is_negative() && NonZeroSignum::POS.is_positive());
+	}
+}
@@ -0,0 +1,205 @@
+use core::ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign, Neg};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Vec2 {
+	pub x:f32,
+	pub y:f32,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Vec3 {
+	pub x:f32,
+	pub y:f32,
+	pub z:f32,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Vec4 {
+	pub x:f32,
+	pub y:f32,
+	pub z:f32,
+	pub w:f32,
+}
+
+impl Vec2 {
+	pub const ZERO:Vec2 = Vec2::new(0.0, 0.0);
+	pub const ONE:Vec2 = Vec2::new(1.0, 1.0);
+
+	#[inline(always)]
+	pub const fn new(x:f32, y:f32) -> Vec2 { Vec2 { x, y } }
+	#[inline(always)]
+	pub fn dot(&self, other:&Vec2) -> f32 { self.x * other.x + self.y * other.y }
+	#[inline(always)]
+	pub fn len(&self) -> f32 { self.dot(self).sqrt() }
+	#[inline(always)]
+	pub fn normalize(self) -> Vec2 { self * (1.0 / self.len()) }
+	#[inline(always)]
+	pub fn lerp(from:Vec2, to:Vec2, t:f32) -> Vec2 { from + (to - from) * t }
+}
+
+impl Vec3 {
+	pub const ZERO:Vec3 = Vec3::new(0.0, 0.0, 0.0);
+	pub const ONE:Vec3 = Vec3::new(1.0, 1.0, 1.0);
+
+	#[inline(always)]
+	pub const fn new(x:f32, y:f32, z:f32) -> Vec3 { Vec3 { x, y, z } }
+
+	// Texture coordinate accessors, a Vec3 doubles as a (u, v, w) triple.
+	#[inline(always)] pub fn u(&mut self) -> &mut f32 { &mut self.x }
+	#[inline(always)] pub fn v(&mut self) -> &mut f32 { &mut self.y }
+	#[inline(always)] pub fn w(&mut self) -> &mut f32 { &mut self.z }
+	#[inline(always)] pub const fn us(&self) -> f32 { self.x }
+	#[inline(always)] pub const fn vs(&self) -> f32 { self.y }
+	#[inline(always)] pub const fn ws(&self) -> f32 { self.z }
+
+	#[inline(always)]
+	pub fn dot(&self, other:&Vec3) -> f32 { self.x * other.x + self.y * other.y + self.z * other.z }
+	#[inline(always)]
+	pub fn cross_product(a:&Vec3, b:&Vec3) -> Vec3 {
+		Vec3 {
+			x: a.y * b.z - a.z * b.y,
+			y: a.z * b.x - a.x * b.z,
+			z: a.x * b.y - a.y * b.x,
+		}
+	}
+	#[inline(always)]
+	pub fn len(&self) -> f32 { self.dot(self).sqrt() }
+	#[inline(always)]
+	pub fn normalize(self) -> Vec3 { self * (1.0 / self.len()) }
+	#[inline(always)]
+	pub fn lerp(from:Vec3, to:Vec3, t:f32) -> Vec3 { from + (to - from) * t }
+	#[inline(always)]
+	pub const fn xyz1(self) -> Vec4 { Vec4::new(self.x, self.y, self.z, 1.0) }
+	#[inline(always)]
+	pub const fn xyz0(self) -> Vec4 { Vec4::new(self.x, self.y, self.z, 0.0) }
+}
+
+impl Vec4 {
+	pub const ZERO:Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.0);
+
+	#[inline(always)]
+	pub const fn new(x:f32, y:f32, z:f32, w:f32) -> Vec4 { Vec4 { x, y, z, w } }
+	#[inline(always)]
+	pub const fn xyz1(x:f32, y:f32, z:f32) -> Vec4 { Vec4 { x, y, z, w: 1.0 } }
+	#[inline(always)]
+	pub const fn xyz(&self) -> Vec3 { Vec3::new(self.x, self.y, self.z) }
+	/// Dot product of the `xyz` part, `w` is ignored.
+	#[inline(always)]
+	pub fn dot(a:&Vec4, b:&Vec4) -> f32 { a.x * b.x + a.y * b.y + a.z * b.z }
+	/// Dot product of all four components.
+	#[inline(always)]
+	pub fn dot4(a:&Vec4, b:&Vec4) -> f32 { a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w }
+	/// Length of the `xyz` part.
+	#[inline(always)]
+	pub fn len(&self) -> f32 { Vec4::dot(self, self).sqrt() }
+	/// Inverse length of the `xyz` part.
+	#[inline(always)]
+	pub fn inv_len(&self) -> f32 { 1.0 / self.len() }
+	#[inline(always)]
+	pub fn lerp(from:Vec4, to:Vec4, t:f32) -> Vec4 { from + (to - from) * t }
+}
+
+macro_rules! vector_ops {
+	( $v:ident, $( $c:ident ),+ ) => {
+		impl Add for $v {
+			type Output = $v;
+			#[inline(always)]
+			fn add(self, o:$v) -> $v { $v { $( $c: self.$c + o.$c ),+ } }
+		}
+		impl Sub for $v {
+			type Output = $v;
+			#[inline(always)]
+			fn sub(self, o:$v) -> $v { $v { $( $c: self.$c - o.$c ),+ } }
+		}
+		impl Mul<f32> for $v {
+			type Output = $v;
+			#[inline(always)]
+			fn mul(self, o:f32) -> $v { $v { $( $c: self.$c * o ),+ } }
+		}
+		impl Mul<$v> for f32 {
+			type Output = $v;
+			#[inline(always)]
+			fn mul(self, o:$v) -> $v { o * self }
+		}
+		impl Div<f32> for $v {
+			type Output = $v;
+			#[inline(always)]
+			fn div(self, o:f32) -> $v { $v { $( $c: self.$c / o ),+ } }
+		}
+		impl Neg for $v {
+			type Output = $v;
+			#[inline(always)]
+			fn neg(self) -> $v { $v { $( $c: -self.$c ),+ } }
+		}
+		impl AddAssign for $v {
+			#[inline(always)]
+			fn add_assign(&mut self, o:$v) { $( self.$c += o.$c; )+ }
+		}
+		impl SubAssign for $v {
+			#[inline(always)]
+			fn sub_assign(&mut self, o:$v) { $( self.$c -= o.$c; )+ }
+		}
+		impl MulAssign<f32> for $v {
+			#[inline(always)]
+			fn mul_assign(&mut self, o:f32) { $( self.$c *= o; )+ }
+		}
+		impl DivAssign<f32> for $v {
+			#[inline(always)]
+			fn div_assign(&mut self, o:f32) { $( self.$c /= o; )+ }
+		}
+	}
+}
+
+vector_ops!(Vec2, x, y);
+vector_ops!(Vec3, x, y, z);
+vector_ops!(Vec4, x, y, z, w);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::prng::RNG;
+
+	fn random_vec3(rng:&mut RNG) -> Vec3 {
+		Vec3::new(rng.get_f32_range(-10.0, 10.0), rng.get_f32_range(-10.0, 10.0), rng.get_f32_range(-10.0, 10.0))
+	}
+
+	#[test]
+	fn cross_product_is_perpendicular() {
+		let mut rng = RNG::new(8);
+		for _ in 0..100 {
+			let (a, b) = (random_vec3(&mut rng), random_vec3(&mut rng));
+			let c = Vec3::cross_product(&a, &b);
+			let scale = a.len() * b.len();
+			assert!(c.dot(&a).abs() <= 1e-4 * scale * a.len());
+			assert!(c.dot(&b).abs() <= 1e-4 * scale * b.len());
+			// |a x b|^2 + (a.b)^2 = |a|^2 |b|^2
+			assert!(((c.dot(&c) + a.dot(&b) * a.dot(&b)) / (scale * scale) - 1.0).abs() < 1e-4);
+			assert_eq!(Vec3::cross_product(&b, &a), -c);
+		}
+		let z = Vec3::cross_product(&Vec3::new(1.0, 0.0, 0.0), &Vec3::new(0.0, 1.0, 0.0));
+		assert_eq!(z, Vec3::new(0.0, 0.0, 1.0));
+	}
+
+	#[test]
+	fn arithmetic_is_componentwise() {
+		let mut rng = RNG::new(9);
+		for _ in 0..100 {
+			let (a, b) = (random_vec3(&mut rng), random_vec3(&mut rng));
+			assert!((a.normalize().len() - 1.0).abs() < 1e-5);
+			assert!((a + b - b - a).len() < 1e-4);
+			assert_eq!(Vec3::lerp(a, b, 0.0), a);
+			assert!((Vec3::lerp(a, b, 1.0) - b).len() < 1e-5);
+			let mut c = a;
+			c += b;
+			c *= 2.0;
+			assert_eq!(c, (a + b) * 2.0);
+			assert_eq!(2.0 * a, a * 2.0);
+		}
+
+		let v = Vec4::new(1.0, 2.0, 3.0, 4.0);
+		assert_eq!(Vec4::dot(&v, &v), 14.0);
+		assert_eq!(Vec4::dot4(&v, &v), 30.0);
+		assert_eq!(v.xyz().xyz1(), Vec4::xyz1(1.0, 2.0, 3.0));
+		assert_eq!(Vec2::new(3.0, 4.0).len(), 5.0);
+	}
+}
@@ -4,57 +4,59 @@
 #![crate_type = "cdylib"]
 
 // TODO: watch for update https://github.com/rust-lang/rust/issues/29596
-#![feature(link_args)]
-#![allow(unused_attributes)] // link_args actually is used
 //#![link_args = "--import-memory"]
-#![feature(const_fn)]
-#![feature(const_if_match)]
-#![feature(const_loop)]
-#![feature(const_mut_refs)]
-#![feature(const_extern_fn)]
-#![feature(core_intrinsics)] // likely / unlikely branch predictions
-#![feature(clamp)]
-#![feature(tau_constant)]
-#![feature(test)]
 
 // #[panic_handler]
 // fn panic(_info: &core::panic::PanicInfo) -> ! {
 // 	loop {}
 // }
 
+use core::ptr::addr_of_mut;
 use game_core::*;
 
 static mut STATE:State = State::new();
 
 #[no_mangle]
-pub const extern fn w() -> u16 { SCREEN_WIDTH }
+pub const extern "C" fn w() -> u16 { SCREEN_WIDTH }
 #[no_mangle]
-pub const extern fn h() -> u16 { SCREEN_HEIGHT }
+pub const extern "C" fn h() -> u16 { SCREEN_HEIGHT }
 
 #[no_mangle]
 pub static mut p:[RGBA; SCREEN_SPACE as usize] = [RGBA::zeroed(); SCREEN_SPACE as usize];
 
 #[no_mangle]
-pub unsafe extern fn t(
+pub extern "C" fn t(
 	time_stamp:i32,
 	locked_pointer:u8,
 	abs_x:f32, abs_y:f32,
 	delta_x:f32, delta_y:f32,
 	scale:f32,
-	mouse_down:u8, mouse_up:u8
+	mouse_down:u8, mouse_up:u8,
+	wheel:f32,
+	vk_u:u8, vk_d:u8, vk_l:u8, vk_r:u8, vk_space:u8, vk_tab:u8, vk_view:u8
 ) {
-	STATE.tick(
-		time_stamp,
-		locked_pointer != 0,
+	let input = Input {
+		locked_pointer: locked_pointer != 0,
 		abs_x, abs_y,
 		delta_x, delta_y,
 		scale,
-		mouse_down != 0, mouse_up != 0,
-		&mut p
-	);
+		wheel,
+		lmb: mouse_down != 0,
+		rmb: mouse_up != 0,
+		up: vk_u != 0,
+		down: vk_d != 0,
+		left: vk_l != 0,
+		right: vk_r != 0,
+		space: vk_space != 0,
+		tab: vk_tab != 0,
+		view: vk_view != 0,
+	};
+	let state = unsafe { &mut *addr_of_mut!(STATE) };
+	let image = unsafe { &mut *addr_of_mut!(p) };
+	state.tick(time_stamp, &input, image);
 }
 
 #[no_mangle]
-pub unsafe extern fn i() {
-	STATE.init();
+pub extern "C" fn i() {
+	unsafe { &mut *addr_of_mut!(STATE) }.init();
 }
\ No newline at end of file
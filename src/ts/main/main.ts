@@ -1,6 +1,5 @@
-const width = 128;
-const height = 128;
-
+var width = 1;
+var height = 1;
 var scale = -1;
 
 var instance:WebAssembly.Instance;
@@ -10,54 +9,160 @@ function r() {
 	var dw = document.body.clientWidth;
 	var dh = document.body.clientHeight;
 
-	if((dw * height) / (dh * width) >= 1)
-		scale = Math.max(1, Math.floor(dh / height));
-	else
-		scale = Math.max(1, Math.floor(dw / width));
-	
-	canvas.style.width  = (width*scale)+"px";
-	canvas.style.height = (height*scale)+"px";
+	let ww = width;
+	let hh = height;
+	if ((dw * hh) / (dh * ww) >= 1) {
+		if(hh > dh) {
+			do {
+				hh /= 2;
+				ww /= 2;
+			} while(hh > dh);
+			canvas.classList.remove("p");
+			canvas.classList.add("s");
+		} else {
+			let scale = Math.floor(dh / hh);
+			ww *= scale;
+			hh *= scale;
+			canvas.classList.remove("s");
+			canvas.classList.add("p");
+		}
+	} else {
+		if(ww > dw) {
+			do {
+				hh /= 2;
+				ww /= 2;
+			} while(ww > dw);
+			canvas.classList.remove("p");
+			canvas.classList.add("s");
+		} else {
+			let scale = Math.floor(dw / ww);
+			ww *= scale;
+			hh *= scale;
+			canvas.classList.remove("s");
+			canvas.classList.add("p");
+		}
+	}
+
+	canvas.style.width  = Math.floor(ww) + "px";
+	canvas.style.height = Math.floor(hh) + "px";
 }
 
 var ctx:CanvasRenderingContext2D = canvas.getContext("2d");
 
-var md:(timestamp:number)=>void;
+var tk:(
+	timestamp:number,
+	locked:boolean,
+	mx:number, my:number,
+	rx:number, ry:number,
+	scale:number,
+	md:number, mu:number,
+	wh:number,
+	ku:number, kd:number, kl:number, kr:number, ks:number, kt:number, kv:number
+)=>void;
 
 var img:ImageData;
 
 function animationLoop(timestamp:number) {
-  md(timestamp);
-  ctx.putImageData(img, 0, 0);
-  requestAnimationFrame(animationLoop);
+	tk(timestamp,locked,mx,my,rx,ry,scale,md,mu,wh,ku,kd,kl,kr,ks,kt,kv);
+	rx = ry = md = mu = wh = ks = 0;
+	ctx.putImageData(img, 0, 0);
+	requestAnimationFrame(animationLoop);
 }
 
-r();
 let imports = {
-    env: {
-        memoryBase: 0,
-        tableBase: 0,
-        cos: Math.cos,
-        __table_base: 0,
-        __memory_base: 0,
-        memory: new WebAssembly.Memory({ initial: 256 }),
-        table: new WebAssembly.Table({ initial: 0, element: 'anyfunc' }),
-    }
+	env: {
+		memory: new WebAssembly.Memory({ initial: 256 }),
+	}
 };
-
+let rpl = canvas.requestPointerLock;
 fetch("emc.wasm").then(response =>
-  response.arrayBuffer()
+	response.arrayBuffer()
 ).then(bytes => {
-  return WebAssembly.instantiate(bytes, imports);
+	return WebAssembly.instantiate(bytes, imports);
 }).then(results => {
-  var instance = results.instance;
-  md = instance.exports._t;
+	instance = results.instance;
+	tk = instance.exports.t;
+	width = instance.exports.w();
+	height = instance.exports.h();
+	canvas.width = width;
+	canvas.height = height;
+	var offset = instance.exports.p.value;
+	let byteSize = width * height * 4;
+	var usub = new Uint8ClampedArray(instance.exports.memory.buffer, offset, byteSize);
+	instance.exports.i();
+	img = new ImageData(usub, width, height);
+	r();
+	animationLoop(0);
+}).catch(e => {
+	console.log(e);
+});
 
-  let byteSize = width * height * 4;
-  var offset = instance.exports._p();
+let locked = false;
+function changeCallback() {
+	locked = document.pointerLockElement === canvas;
+}
 
-  var usub = new Uint8ClampedArray(imports.env.memory.buffer, offset, byteSize);
-  img = new ImageData(usub, width, height);
-  animationLoop(0);
-}).catch(e => {
-    console.log(e);
-});
\ No newline at end of file
+let mx = 0, my = 0, rx = 0, ry = 0;
+function moveCallback(e:MouseEvent) {
+	rx += e.movementX || 0;
+	ry += e.movementY || 0;
+
+	let rect = canvas.getBoundingClientRect();
+	mx = e.clientX - rect.left;
+	my = e.clientY - rect.top;
+}
+let md = 0, mu = 0;
+function mouseDown() {
+	md=1;
+	if(!locked) rpl.call(canvas);
+}
+function mouseUp() {
+	mu=1;
+}
+
+// Wheel steps, positive when scrolling up
+let wh = 0;
+function wheelCallback(e:WheelEvent) {
+	wh -= Math.sign(e.deltaY);
+}
+
+// Held keys, space only counts the press
+let ku = 0, kd = 0, kl = 0, kr = 0, ks = 0, kt = 0, kv = 0;
+function key(e:KeyboardEvent, down:number) {
+	switch (e.code) {
+		case "KeyW": ku = down; break;
+		case "KeyS": kd = down; break;
+		case "KeyA": kl = down; break;
+		case "KeyD": kr = down; break;
+		case "Space": if (down && !e.repeat) ks = 1; break;
+		case "Tab": kt = down; break;
+		case "KeyV": kv = down; break;
+		default: return;
+	}
+	e.preventDefault();
+}
+function keyDown(e:KeyboardEvent) {
+	key(e, 1);
+}
+function keyUp(e:KeyboardEvent) {
+	key(e, 0);
+}
+
+function lockError() {
+	changeCallback();
+}
+
+// Hook pointer lock state change events
+document.addEventListener('pointerlockchange', changeCallback, false);
+
+// Hook mouse move events
+document.addEventListener("mousemove", moveCallback, false);
+
+document.addEventListener("mousedown", mouseDown, false);
+document.addEventListener("mouseup", mouseUp, false);
+document.addEventListener("wheel", wheelCallback, false);
+
+document.addEventListener("keydown", keyDown, false);
+document.addEventListener("keyup", keyUp, false);
+
+document.addEventListener('pointerlockerror', lockError, false);
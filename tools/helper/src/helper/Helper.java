@@ -5,16 +5,19 @@ import java.awt.image.*;
 import java.io.*;
 import java.nio.file.*;
 import java.util.*;
+import java.util.function.*;
 
 public class Helper {
 	public static final Path root = Paths.get("../../");
 	public static final Path models = root.resolve("src").resolve("res");
 	public static final Path cCode = root.resolve("src").resolve("c");
-	public static final Path rustRes = root.resolve("src").resolve("rust_casted").resolve("res");
+	public static final Path rustRes = root.resolve("src").resolve("rust_casted").resolve("game_core").resolve("res");
 
 	public static void main(String[] args) throws IOException {
 		// var obj = parseOBJ(readFile(models.resolve("cube.obj"))).serialize();
-		byte[] obj = parseOBJ(readFile(models.resolve("pb/rpan.obj"))).serialize();
+		OBJ rpan = parseOBJ(readFile(models.resolve("pb/rpan.obj")));
+		rpan.rig = redPandaRig();
+		byte[] obj = rpan.serialize();
 		byte[] texture = parsePNG(readFile(models.resolve("pb/rpan_hr.png"))).serialize();
 		byte[] made_with = parsePNG(readFile(models.resolve("made_with.png"))).serialize();
 		// byte[] cArray = toCArray("model_bin", sobj);
@@ -86,6 +89,52 @@ public class Helper {
 		return obj;
 	}
 
+	/**
+	 * The panda stands on the xz plane facing +z, with its head above y 1.5 and its tail reaching back below z 0.
+	 * Its only clip tilts the head, wags the tail and bobs the body, and loops after two seconds.
+	 */
+	private static Rig redPandaRig() {
+		Rig rig = new Rig();
+		int root = rig.bone(Rig.NO_PARENT, 0, 0, 0);
+		int head = rig.bone(root, 0, 1.5f, 0.7f);
+		int tail = rig.bone(root, 0, 1.0f, 0.25f);
+		rig.skinning = v -> {
+			float h = smoothstep(1.35f, 1.6f, v.y);
+			float t = v.y < 1.3f ? smoothstep(0.3f, 0f, v.z) : 0;
+			return Skin.blend(root, head, h, tail, t);
+		};
+
+		Clip idle = new Clip(2);
+		idle.track(head, Track.ROTATION)
+			.key(0, rotation(0, 0, 1, 0))
+			.key(0.5f, rotation(0, 0, 1, 0.25f))
+			.key(1.5f, rotation(0, 0, 1, -0.25f))
+			.key(2, rotation(0, 0, 1, 0));
+		idle.track(tail, Track.ROTATION)
+			.key(0, rotation(0, 1, 0, 0))
+			.key(0.5f, rotation(0, 1, 0, 0.5f))
+			.key(1, rotation(0, 1, 0, 0))
+			.key(1.5f, rotation(0, 1, 0, -0.5f))
+			.key(2, rotation(0, 1, 0, 0));
+		idle.track(root, Track.TRANSLATION)
+			.key(0, 0, 0, 0)
+			.key(1, 0, 0.05f, 0)
+			.key(2, 0, 0, 0);
+		rig.clips.add(idle);
+		return rig;
+	}
+
+	private static float smoothstep(float edge0, float edge1, float x) {
+		float t = Math.max(0, Math.min(1, (x - edge0) / (edge1 - edge0)));
+		return t * t * (3 - 2 * t);
+	}
+
+	/** Quaternion xyzw for `angle` radians around the unit vector xyz. */
+	private static float[] rotation(float x, float y, float z, float angle) {
+		float sin = (float) Math.sin(angle / 2);
+		return new float[] {x * sin, y * sin, z * sin, (float) Math.cos(angle / 2)};
+	}
+
 	private static Texture parsePNG(byte[] binary) throws IOException {
 		BufferedImage img = ImageIO.read(new ByteArrayInputStream(binary));
 		short w = (short)img.getWidth();
@@ -143,10 +192,117 @@ public class Helper {
 		}
 	}
 
+	static class Bone {
+		int parent;
+		float[] translation;
+		float[] rotation = {0, 0, 0, 1};
+		float[] scale = {1, 1, 1};
+
+		public Bone(int parent, float[] translation) {
+			this.parent = parent;
+			this.translation = translation;
+		}
+	}
+
+	/** Up to four bones that move a vertex, the weights add up to 255. */
+	static class Skin {
+		int[] bones = new int[4];
+		int[] weights = new int[4];
+
+		/** `base` takes whatever `a` and `b` leave, `wa + wb` must not go past 1. */
+		static Skin blend(int base, int a, float wa, int b, float wb) {
+			Skin skin = new Skin();
+			skin.bones = new int[] {base, a, b, 0};
+			skin.weights[1] = Math.round(wa * 255);
+			skin.weights[2] = Math.round(wb * 255);
+			skin.weights[0] = 255 - skin.weights[1] - skin.weights[2];
+			return skin;
+		}
+	}
+
+	static class Track {
+		static final int TRANSLATION = 0, ROTATION = 1, SCALE = 2;
+
+		int bone, kind;
+		/** Time followed by the value, three floats or four for rotations. */
+		ArrayList<float[]> keyframes = new ArrayList<>();
+
+		Track key(float time, float... value) {
+			float[] keyframe = new float[1 + value.length];
+			keyframe[0] = time;
+			System.arraycopy(value, 0, keyframe, 1, value.length);
+			keyframes.add(keyframe);
+			return this;
+		}
+	}
+
+	static class Clip {
+		float duration;
+		ArrayList<Track> tracks = new ArrayList<>();
+
+		Clip(float duration) {
+			this.duration = duration;
+		}
+
+		Track track(int bone, int kind) {
+			Track track = new Track();
+			track.bone = bone;
+			track.kind = kind;
+			tracks.add(track);
+			return track;
+		}
+	}
+
+	/** Skeleton and clips of a model, written after the triangles. */
+	static class Rig {
+		static final int NO_PARENT = 0xFF;
+
+		ArrayList<Bone> bones = new ArrayList<>();
+		ArrayList<Clip> clips = new ArrayList<>();
+		Function<Vertex, Skin> skinning;
+
+		/** Adds a bone at `x y z` relative to its parent, parents have to be added first. */
+		int bone(int parent, float x, float y, float z) {
+			if(parent != NO_PARENT && parent >= bones.size())
+				throw new IllegalArgumentException("parent " + parent + " comes after its child");
+			bones.add(new Bone(parent, new float[] {x, y, z}));
+			return bones.size() - 1;
+		}
+
+		void serialize(DataOutputStream dos, List<Vertex> vertices) throws IOException {
+			dos.writeByte(bones.size());
+			for(Bone bone : bones) {
+				dos.writeByte(bone.parent);
+				for(float f : bone.translation) dos.writeFloat(f);
+				for(float f : bone.rotation) dos.writeFloat(f);
+				for(float f : bone.scale) dos.writeFloat(f);
+			}
+			for(Vertex v : vertices) {
+				Skin skin = skinning.apply(v);
+				for(int b : skin.bones) dos.writeByte(b);
+				for(int w : skin.weights) dos.writeByte(w);
+			}
+			dos.writeByte(clips.size());
+			for(Clip clip : clips) {
+				dos.writeFloat(clip.duration);
+				dos.writeShort(clip.tracks.size());
+				for(Track track : clip.tracks) {
+					dos.writeByte(track.bone);
+					dos.writeByte(track.kind);
+					dos.writeShort(track.keyframes.size());
+					for(float[] keyframe : track.keyframes)
+						for(float f : keyframe) dos.writeFloat(f);
+				}
+			}
+		}
+	}
+
 	static class OBJ {
 		ArrayList<Vertex> vertices = new ArrayList<>();
 		ArrayList<Vertex2D> texture_vertices = new ArrayList<>();
 		ArrayList<Triangle> triangles = new ArrayList<>();
+		/** Optional, static models have none. */
+		Rig rig;
 
 		private void optimize() {
 			for(Triangle t : triangles) {
@@ -180,6 +336,8 @@ public class Helper {
 				dos.writeShort(t.texture_vertices[1]);
 				dos.writeShort(t.texture_vertices[2]);
 			}
+			if(rig != null)
+				rig.serialize(dos, this.vertices);
 			dos.flush();
 			out.flush();
 			return out.toByteArray();